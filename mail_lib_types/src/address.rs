@@ -0,0 +1,363 @@
+/*!
+# Address
+
+An [Address] is either a single [MailBox] or a named group of mailboxes.
+An [AddressList] is what appears in the `To`, `Cc`, `Bcc` and `Reply-To` headers.
+
+Defined in [RFC 5322 Section 3.4](https://tools.ietf.org/html/rfc5322#section-3.4)
+ */
+use std::{borrow::Cow, fmt::Display, str::FromStr};
+
+use chumsky::{error::Cheap, Parser};
+use thiserror::Error;
+
+use crate::{
+    mail_box::{write_display_name, MailBox, RawMailBox},
+    parsers::rfcs::rfc5322::{address, address_list},
+};
+/// Used Internally as a temporary structure to build an [Address]
+#[doc(hidden)]
+#[derive(Debug, PartialEq, Eq)]
+pub enum RawAddress<'a> {
+    Mailbox(RawMailBox<'a>),
+    Group {
+        name: Cow<'a, str>,
+        members: Vec<RawMailBox<'a>>,
+    },
+}
+/// An error that occurs when parsing an [Address] or [AddressList]
+#[derive(Debug, Clone, PartialEq, Hash, Error)]
+pub struct InvalidAddress {
+    /// The spans that caused the error
+    pub spans: Vec<Cheap>,
+    /// The context of the error
+    pub ctx: Option<String>,
+}
+impl Display for InvalidAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Invalid Address")?;
+        if let Some(context) = self.ctx.as_ref() {
+            writeln!(f, "Context: {}", context)?;
+            for span in &self.spans {
+                writeln!(f, "    {}", span)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A single [MailBox] or a group of them
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Address {
+    /// `name <local@domain>` or `local@domain`
+    Mailbox(MailBox),
+    /// `name: member, member;`
+    ///
+    /// Groups can be empty. `undisclosed-recipients:;`
+    Group {
+        /// The display name of the group
+        name: String,
+        /// The mailboxes in the group
+        members: Vec<MailBox>,
+    },
+}
+impl Address {
+    /// Creates a group with the given name and members
+    pub fn group(name: impl Into<String>, members: Vec<MailBox>) -> Self {
+        Address::Group {
+            name: name.into(),
+            members,
+        }
+    }
+    /// The mailboxes of this address. A single mailbox or the members of the group
+    pub fn mailboxes(&self) -> &[MailBox] {
+        match self {
+            Address::Mailbox(mailbox) => std::slice::from_ref(mailbox),
+            Address::Group { members, .. } => members,
+        }
+    }
+}
+impl From<MailBox> for Address {
+    fn from(mailbox: MailBox) -> Self {
+        Address::Mailbox(mailbox)
+    }
+}
+impl From<RawAddress<'_>> for Address {
+    fn from(value: RawAddress<'_>) -> Self {
+        match value {
+            RawAddress::Mailbox(mailbox) => Address::Mailbox(mailbox.into()),
+            RawAddress::Group { name, members } => Address::Group {
+                name: name.into_owned(),
+                members: members.into_iter().map(MailBox::from).collect(),
+            },
+        }
+    }
+}
+/// Formats a mailbox as [MailBox] does. A group as `name: member, member;`
+impl Display for Address {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Address::Mailbox(mailbox) => Display::fmt(mailbox, f),
+            Address::Group { name, members } => {
+                write_display_name(f, name)?;
+                f.write_str(":")?;
+                for (index, member) in members.iter().enumerate() {
+                    if index != 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, " {}", member)?;
+                }
+                f.write_str(";")
+            }
+        }
+    }
+}
+impl FromStr for Address {
+    type Err = InvalidAddress;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        address()
+            .padded()
+            .parse(value)
+            .into_result()
+            .map(Address::from)
+            .map_err(|spans| InvalidAddress {
+                spans,
+                ctx: Some(value.to_owned()),
+            })
+    }
+}
+impl TryFrom<&str> for Address {
+    type Error = InvalidAddress;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Address::from_str(value)
+    }
+}
+impl TryFrom<String> for Address {
+    type Error = InvalidAddress;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Address::from_str(&value)
+    }
+}
+
+/// A list of [Address]es
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct AddressList(Vec<Address>);
+impl AddressList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn push(&mut self, address: impl Into<Address>) {
+        self.0.push(address.into());
+    }
+    pub fn iter(&self) -> std::slice::Iter<'_, Address> {
+        self.0.iter()
+    }
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    pub fn as_slice(&self) -> &[Address] {
+        &self.0
+    }
+    /// Every mailbox in the list. Groups are flattened into their members
+    pub fn mailboxes(&self) -> impl Iterator<Item = &MailBox> {
+        self.0.iter().flat_map(Address::mailboxes)
+    }
+    pub fn into_inner(self) -> Vec<Address> {
+        self.0
+    }
+}
+/// Addresses separated by `, `
+impl Display for AddressList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, address) in self.0.iter().enumerate() {
+            if index != 0 {
+                f.write_str(", ")?;
+            }
+            Display::fmt(address, f)?;
+        }
+        Ok(())
+    }
+}
+/// Parses an `address-list`. Requires at least one address
+impl FromStr for AddressList {
+    type Err = InvalidAddress;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        address_list()
+            .parse(value)
+            .into_result()
+            .map(|addresses| addresses.into_iter().map(Address::from).collect())
+            .map_err(|spans| InvalidAddress {
+                spans,
+                ctx: Some(value.to_owned()),
+            })
+    }
+}
+impl TryFrom<&str> for AddressList {
+    type Error = InvalidAddress;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        AddressList::from_str(value)
+    }
+}
+impl TryFrom<String> for AddressList {
+    type Error = InvalidAddress;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        AddressList::from_str(&value)
+    }
+}
+impl From<Vec<Address>> for AddressList {
+    fn from(addresses: Vec<Address>) -> Self {
+        Self(addresses)
+    }
+}
+impl From<Address> for AddressList {
+    fn from(address: Address) -> Self {
+        Self(vec![address])
+    }
+}
+impl From<MailBox> for AddressList {
+    fn from(mailbox: MailBox) -> Self {
+        Self(vec![Address::Mailbox(mailbox)])
+    }
+}
+impl FromIterator<Address> for AddressList {
+    fn from_iter<T: IntoIterator<Item = Address>>(iter: T) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+impl FromIterator<MailBox> for AddressList {
+    fn from_iter<T: IntoIterator<Item = MailBox>>(iter: T) -> Self {
+        Self(iter.into_iter().map(Address::Mailbox).collect())
+    }
+}
+impl IntoIterator for AddressList {
+    type Item = Address;
+    type IntoIter = std::vec::IntoIter<Address>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+impl<'a> IntoIterator for &'a AddressList {
+    type Item = &'a Address;
+    type IntoIter = std::slice::Iter<'a, Address>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+#[cfg(feature = "serde")]
+mod _serde {
+    use serde::{ser::SerializeSeq, Deserialize, Serialize};
+
+    use super::{Address, AddressList};
+    /// Serialized as the formatted address
+    impl Serialize for Address {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            serializer.collect_str(self)
+        }
+    }
+    impl<'de> Deserialize<'de> for Address {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            let value = String::deserialize(deserializer)?;
+            Address::try_from(value).map_err(serde::de::Error::custom)
+        }
+    }
+    /// Serialized as a sequence of addresses
+    impl Serialize for AddressList {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            let mut seq = serializer.serialize_seq(Some(self.len()))?;
+            for address in self {
+                seq.serialize_element(address)?;
+            }
+            seq.end()
+        }
+    }
+    struct AddressListVisitor;
+
+    impl<'de> serde::de::Visitor<'de> for AddressListVisitor {
+        type Value = AddressList;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("an address list or a sequence of addresses")
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+        where
+            E: serde::de::Error,
+        {
+            AddressList::try_from(value).map_err(serde::de::Error::custom)
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: serde::de::SeqAccess<'de>,
+        {
+            let mut addresses = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+            while let Some(address) = seq.next_element::<Address>()? {
+                addresses.push(address);
+            }
+            Ok(AddressList::from(addresses))
+        }
+    }
+    /// Accepts either a single string or a sequence of addresses
+    impl<'de> Deserialize<'de> for AddressList {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            deserializer.deserialize_any(AddressListVisitor)
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{Address, AddressList};
+
+    #[test]
+    fn test_parse() {
+        let list: AddressList =
+            "Mary Smith <mary@x.test>, jdoe@example.org, A Group:Ed Jones <c@a.test>,joe@where.test;"
+                .parse()
+                .unwrap();
+        assert_eq!(list.len(), 3);
+        assert_eq!(list.mailboxes().count(), 4);
+        let Address::Group { name, members } = &list.as_slice()[2] else {
+            panic!("Expected a group");
+        };
+        assert_eq!(name, "A Group");
+        assert_eq!(members[0].get_name(), Some("Ed Jones"));
+        assert_eq!(
+            list.to_string(),
+            "\"Mary Smith\" <mary@x.test>, jdoe@example.org, \"A Group\": \"Ed Jones\" <c@a.test>, joe@where.test;"
+        );
+        assert_eq!(list.to_string().parse::<AddressList>().unwrap(), list);
+
+        let empty: Address = "undisclosed-recipients:;".parse().unwrap();
+        assert_eq!(empty, Address::group("undisclosed-recipients", Vec::new()));
+        assert_eq!(empty.to_string(), "undisclosed-recipients:;");
+
+        assert!("".parse::<AddressList>().is_err());
+        assert!("Group: a@example.com".parse::<AddressList>().is_err());
+    }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let list: AddressList = serde_json::from_str(r#""a@example.com, Team:;""#).unwrap();
+        assert_eq!(list.len(), 2);
+        let json = serde_json::to_string(&list).unwrap();
+        assert_eq!(json, r#"["a@example.com","Team:;"]"#);
+        assert_eq!(serde_json::from_str::<AddressList>(&json).unwrap(), list);
+    }
+}
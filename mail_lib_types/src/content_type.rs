@@ -0,0 +1,793 @@
+/*!
+# Content-Type and Content-Disposition
+
+The MIME header fields that describe a body part.
+
+- [ContentType] is defined in [RFC 2045 Section 5](https://datatracker.ietf.org/doc/html/rfc2045#section-5)
+- [ContentDisposition] is defined in [RFC 2183](https://datatracker.ietf.org/doc/html/rfc2183)
+
+Parameters support [RFC 2231](https://datatracker.ietf.org/doc/html/rfc2231).
+Continuations, charset and language tags are decoded when parsing.
+Values that need it are percent-encoded and split into continuations when formatting
+ */
+use std::{collections::HashMap, fmt::Display, str::FromStr};
+
+use chumsky::{error::Cheap, Parser};
+use thiserror::Error;
+
+use crate::parsers::rfcs::{
+    rfc2045::{content_type, is_token_char},
+    rfc2183::disposition,
+    rfc2231::{extended_initial_value, extended_other_values, is_attribute_char},
+};
+
+/// The longest encoded value a single parameter section will hold before it is split
+const SECTION_LENGTH: usize = 50;
+
+/// Used Internally as a temporary structure to build [Parameters]
+#[doc(hidden)]
+#[derive(Debug, PartialEq, Eq)]
+pub struct RawParameter<'a> {
+    pub(crate) name: &'a str,
+    /// The `*N` of a continuation
+    pub(crate) section: Option<u32>,
+    /// If the name ended with `*`
+    pub(crate) extended: bool,
+    /// Without the quotes. Still escaped
+    pub(crate) value: &'a str,
+    pub(crate) quoted: bool,
+}
+/// An error that occurs when parsing a [ContentType]
+#[derive(Debug, Clone, PartialEq, Hash, Error)]
+pub struct InvalidContentType {
+    /// The spans that caused the error
+    pub spans: Vec<Cheap>,
+    /// The context of the error
+    pub ctx: Option<String>,
+}
+impl Display for InvalidContentType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Invalid Content-Type")?;
+        if let Some(context) = self.ctx.as_ref() {
+            writeln!(f, "Context: {}", context)?;
+            for span in &self.spans {
+                writeln!(f, "    {}", span)?;
+            }
+        }
+        Ok(())
+    }
+}
+/// An error that occurs when parsing a [ContentDisposition]
+#[derive(Debug, Clone, PartialEq, Hash, Error)]
+pub struct InvalidContentDisposition {
+    /// The spans that caused the error
+    pub spans: Vec<Cheap>,
+    /// The context of the error
+    pub ctx: Option<String>,
+}
+impl Display for InvalidContentDisposition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Invalid Content-Disposition")?;
+        if let Some(context) = self.ctx.as_ref() {
+            writeln!(f, "Context: {}", context)?;
+            for span in &self.spans {
+                writeln!(f, "    {}", span)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A single decoded parameter
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Parameter {
+    name: String,
+    value: String,
+    charset: Option<String>,
+    language: Option<String>,
+}
+impl Parameter {
+    /// Creates a parameter. The name is stored in lowercase.
+    ///
+    /// The name must only contain attribute characters. The value can be anything
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into().to_ascii_lowercase(),
+            value: value.into(),
+            charset: None,
+            language: None,
+        }
+    }
+    /// Tags the value with a language. `en-us`
+    pub fn with_language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+    /// The name in lowercase
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// The decoded value
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+    /// The charset the value was tagged with
+    pub fn charset(&self) -> Option<&str> {
+        self.charset.as_deref()
+    }
+    /// The language the value was tagged with
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+    /// The charset used when formatting. The original charset is kept if the value is ASCII
+    fn encoding_charset(&self) -> &str {
+        self.charset
+            .as_deref()
+            .filter(|_| self.value.is_ascii())
+            .unwrap_or("utf-8")
+    }
+}
+/// Formats as `name=value` or `name*=charset'language'value`.
+///
+/// Long values are split into continuations separated by folded `;`s
+impl Display for Parameter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = &self.name;
+        let plain = self.language.is_none()
+            && self
+                .value
+                .chars()
+                .all(|c| c == '\t' || (c.is_ascii() && !c.is_ascii_control()));
+        if plain {
+            if self.value.len() <= SECTION_LENGTH {
+                write!(f, "{}=", name)?;
+                return write_value(f, &self.value);
+            }
+            // ASCII so every chunk is valid UTF-8
+            for (section, chunk) in self.value.as_bytes().chunks(SECTION_LENGTH).enumerate() {
+                if section != 0 {
+                    f.write_str(";\r\n ")?;
+                }
+                write!(f, "{}*{}=", name, section)?;
+                write_value(f, std::str::from_utf8(chunk).unwrap_or_default())?;
+            }
+            return Ok(());
+        }
+        // The charset and language are part of the first section
+        let encoded = format!(
+            "{}'{}'{}",
+            self.encoding_charset(),
+            self.language.as_deref().unwrap_or_default(),
+            percent_encode(&self.value)
+        );
+        if encoded.len() <= SECTION_LENGTH {
+            return write!(f, "{}*={}", name, encoded);
+        }
+        for (section, chunk) in split_encoded(&encoded).into_iter().enumerate() {
+            if section != 0 {
+                f.write_str(";\r\n ")?;
+            }
+            write!(f, "{}*{}*={}", name, section, chunk)?;
+        }
+        Ok(())
+    }
+}
+/// A token if possible. Otherwise a quoted-string
+fn write_value(f: &mut std::fmt::Formatter<'_>, value: &str) -> std::fmt::Result {
+    if !value.is_empty() && value.chars().all(is_token_char) {
+        return f.write_str(value);
+    }
+    f.write_str("\"")?;
+    for c in value.chars() {
+        if matches!(c, '"' | '\\') {
+            f.write_str("\\")?;
+        }
+        write!(f, "{}", c)?;
+    }
+    f.write_str("\"")
+}
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if is_attribute_char(byte as char) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+/// Splits into sections of at most [SECTION_LENGTH]. Never inside an ext-octet
+fn split_encoded(encoded: &str) -> Vec<&str> {
+    let mut sections = Vec::new();
+    let mut rest = encoded;
+    while !rest.is_empty() {
+        let mut end = SECTION_LENGTH.min(rest.len());
+        if let Some(percent) = rest[..end].rfind('%')
+            && percent + 3 > end
+        {
+            end = percent;
+        }
+        sections.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    sections
+}
+/// Removes the quoted-pair escapes and any folding
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            '\r' | '\n' => {}
+            c => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+/// Only ISO-8859-1 is decoded specially.
+///
+/// Everything else is read as UTF-8 with invalid bytes replaced. US-ASCII is a subset of it
+fn decode_charset(octets: &[u8], charset: Option<&str>) -> String {
+    match charset.map(str::to_ascii_lowercase).as_deref() {
+        Some("iso-8859-1" | "iso_8859-1" | "latin1" | "l1") => {
+            octets.iter().map(|byte| char::from(*byte)).collect()
+        }
+        _ => String::from_utf8_lossy(octets).into_owned(),
+    }
+}
+/// Decodes a parameter from its sections. A single parameter is one section
+fn decode_sections(name: &str, sections: &[&RawParameter<'_>]) -> Parameter {
+    let mut octets = Vec::new();
+    let mut charset = None;
+    let mut language = None;
+    for (index, raw) in sections.iter().enumerate() {
+        if !raw.extended {
+            octets.extend_from_slice(unescape(raw.value).as_bytes());
+            continue;
+        }
+        let decoded = if index == 0 {
+            extended_initial_value()
+                .parse(raw.value)
+                .into_result()
+                .ok()
+                .map(|(tagged_charset, tagged_language, decoded)| {
+                    charset = tagged_charset.map(str::to_owned);
+                    language = tagged_language.map(str::to_owned);
+                    decoded
+                })
+        } else {
+            extended_other_values().parse(raw.value).into_result().ok()
+        };
+        // Malformed values are kept as they are
+        octets.extend(decoded.unwrap_or_else(|| raw.value.as_bytes().to_vec()));
+    }
+    Parameter {
+        name: name.to_ascii_lowercase(),
+        value: decode_charset(&octets, charset.as_deref()),
+        charset,
+        language,
+    }
+}
+
+/// The parameters of a [ContentType] or [ContentDisposition]. Kept in the order they appeared
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Parameters(Vec<Parameter>);
+impl Parameters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Combines the raw parameters.
+    ///
+    /// Continuations are used up to the first missing section.
+    /// They take priority over `name*=` which takes priority over `name=`
+    pub(crate) fn from_raw(raw: Vec<RawParameter<'_>>) -> Self {
+        // Grouped by lowercased name. `order` keeps the position each name first appeared at
+        let mut order = Vec::new();
+        let mut grouped: HashMap<String, Vec<&RawParameter<'_>>> = HashMap::new();
+        for raw_parameter in &raw {
+            let name = raw_parameter.name.to_ascii_lowercase();
+            grouped
+                .entry(name)
+                .or_insert_with_key(|name| {
+                    order.push(name.clone());
+                    Vec::new()
+                })
+                .push(raw_parameter);
+        }
+        let mut parameters = Parameters::new();
+        for name in order {
+            let same_name = &grouped[&name];
+            let mut sections: Vec<&RawParameter<'_>> = same_name
+                .iter()
+                .copied()
+                .filter(|other| other.section.is_some())
+                .collect();
+            sections.sort_by_key(|section| section.section);
+            let sections: Vec<&RawParameter<'_>> = sections
+                .into_iter()
+                .enumerate()
+                .take_while(|(index, section)| section.section == Some(*index as u32))
+                .map(|(_, section)| section)
+                .collect();
+            let single = same_name
+                .iter()
+                .copied()
+                .filter(|other| other.section.is_none())
+                .min_by_key(|other| !other.extended);
+            let parameter = if !sections.is_empty() {
+                decode_sections(&name, &sections)
+            } else if let Some(single) = single {
+                decode_sections(&name, &[single])
+            } else {
+                continue;
+            };
+            parameters.0.push(parameter);
+        }
+        parameters
+    }
+    /// The value of a parameter. Name is case-insensitive
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.get_parameter(name).map(Parameter::value)
+    }
+    /// A parameter with its charset and language. Name is case-insensitive
+    pub fn get_parameter(&self, name: &str) -> Option<&Parameter> {
+        self.0
+            .iter()
+            .find(|parameter| parameter.name.eq_ignore_ascii_case(name))
+    }
+    /// Adds a parameter. Replaces one with the same name
+    pub fn insert(&mut self, parameter: Parameter) {
+        match self
+            .0
+            .iter_mut()
+            .find(|existing| existing.name == parameter.name)
+        {
+            Some(existing) => *existing = parameter,
+            None => self.0.push(parameter),
+        }
+    }
+    /// Removes a parameter. Name is case-insensitive
+    pub fn remove(&mut self, name: &str) -> Option<Parameter> {
+        let index = self
+            .0
+            .iter()
+            .position(|parameter| parameter.name.eq_ignore_ascii_case(name))?;
+        Some(self.0.remove(index))
+    }
+    pub fn iter(&self) -> std::slice::Iter<'_, Parameter> {
+        self.0.iter()
+    }
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    pub fn into_inner(self) -> Vec<Parameter> {
+        self.0
+    }
+}
+/// Each parameter preceded by `; `
+impl Display for Parameters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for parameter in &self.0 {
+            write!(f, "; {}", parameter)?;
+        }
+        Ok(())
+    }
+}
+impl FromIterator<Parameter> for Parameters {
+    fn from_iter<T: IntoIterator<Item = Parameter>>(iter: T) -> Self {
+        let mut parameters = Parameters::new();
+        for parameter in iter {
+            parameters.insert(parameter);
+        }
+        parameters
+    }
+}
+impl IntoIterator for Parameters {
+    type Item = Parameter;
+    type IntoIter = std::vec::IntoIter<Parameter>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+impl<'a> IntoIterator for &'a Parameters {
+    type Item = &'a Parameter;
+    type IntoIter = std::slice::Iter<'a, Parameter>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+/// A `Content-Type`. `type/subtype; parameter=value`
+///
+/// The type and subtype are stored in lowercase
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ContentType {
+    main_type: String,
+    subtype: String,
+    parameters: Parameters,
+}
+impl ContentType {
+    /// Creates a [ContentType] without parameters
+    pub fn new(main_type: impl AsRef<str>, subtype: impl AsRef<str>) -> Self {
+        Self {
+            main_type: main_type.as_ref().to_ascii_lowercase(),
+            subtype: subtype.as_ref().to_ascii_lowercase(),
+            parameters: Parameters::new(),
+        }
+    }
+    /// Adds a parameter. Replaces one with the same name
+    pub fn with_parameter(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.parameters.insert(Parameter::new(name, value));
+        self
+    }
+    /// The top-level type. `text` in `text/plain`
+    pub fn main_type(&self) -> &str {
+        &self.main_type
+    }
+    /// `plain` in `text/plain`
+    pub fn subtype(&self) -> &str {
+        &self.subtype
+    }
+    /// `type/subtype` without the parameters
+    pub fn media_type(&self) -> String {
+        format!("{}/{}", self.main_type, self.subtype)
+    }
+    pub fn parameters(&self) -> &Parameters {
+        &self.parameters
+    }
+    pub fn parameters_mut(&mut self) -> &mut Parameters {
+        &mut self.parameters
+    }
+    /// The value of a parameter. Name is case-insensitive
+    pub fn parameter(&self, name: &str) -> Option<&str> {
+        self.parameters.get(name)
+    }
+    /// The `charset` parameter
+    pub fn charset(&self) -> Option<&str> {
+        self.parameter("charset")
+    }
+    /// The `boundary` parameter of a multipart type
+    pub fn boundary(&self) -> Option<&str> {
+        self.parameter("boundary")
+    }
+    pub fn is_multipart(&self) -> bool {
+        self.main_type == "multipart"
+    }
+}
+/// `text/plain; charset=us-ascii` as RFC 2045 specifies
+impl Default for ContentType {
+    fn default() -> Self {
+        ContentType::new("text", "plain").with_parameter("charset", "us-ascii")
+    }
+}
+impl Display for ContentType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}{}", self.main_type, self.subtype, self.parameters)
+    }
+}
+impl FromStr for ContentType {
+    type Err = InvalidContentType;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        content_type()
+            .parse(value)
+            .into_result()
+            .map(|(main_type, subtype, parameters)| ContentType {
+                main_type: main_type.to_ascii_lowercase(),
+                subtype: subtype.to_ascii_lowercase(),
+                parameters: Parameters::from_raw(parameters),
+            })
+            .map_err(|spans| InvalidContentType {
+                spans,
+                ctx: Some(value.to_owned()),
+            })
+    }
+}
+impl TryFrom<&str> for ContentType {
+    type Error = InvalidContentType;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        ContentType::from_str(value)
+    }
+}
+impl TryFrom<String> for ContentType {
+    type Error = InvalidContentType;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        ContentType::from_str(&value)
+    }
+}
+
+/// The `disposition-type` of a [ContentDisposition]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DispositionType {
+    /// Displayed automatically
+    Inline,
+    /// Displayed only when the user asks for it
+    Attachment,
+    /// Any other type. Treated as `attachment` as RFC 2183 requires
+    Other(String),
+}
+impl From<&str> for DispositionType {
+    fn from(value: &str) -> Self {
+        match value.to_ascii_lowercase().as_str() {
+            "inline" => DispositionType::Inline,
+            "attachment" => DispositionType::Attachment,
+            other => DispositionType::Other(other.to_owned()),
+        }
+    }
+}
+impl Display for DispositionType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DispositionType::Inline => f.write_str("inline"),
+            DispositionType::Attachment => f.write_str("attachment"),
+            DispositionType::Other(other) => f.write_str(other),
+        }
+    }
+}
+/// A `Content-Disposition`. `attachment; filename=report.pdf`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ContentDisposition {
+    disposition: DispositionType,
+    parameters: Parameters,
+}
+impl ContentDisposition {
+    /// Creates a [ContentDisposition] without parameters
+    pub fn new(disposition: DispositionType) -> Self {
+        Self {
+            disposition,
+            parameters: Parameters::new(),
+        }
+    }
+    pub fn inline() -> Self {
+        Self::new(DispositionType::Inline)
+    }
+    /// An attachment with the given filename
+    pub fn attachment(filename: impl Into<String>) -> Self {
+        Self::new(DispositionType::Attachment).with_parameter("filename", filename)
+    }
+    /// Adds a parameter. Replaces one with the same name
+    pub fn with_parameter(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.parameters.insert(Parameter::new(name, value));
+        self
+    }
+    pub fn disposition(&self) -> &DispositionType {
+        &self.disposition
+    }
+    pub fn is_inline(&self) -> bool {
+        self.disposition == DispositionType::Inline
+    }
+    /// Unknown disposition types count as attachments
+    pub fn is_attachment(&self) -> bool {
+        !self.is_inline()
+    }
+    pub fn parameters(&self) -> &Parameters {
+        &self.parameters
+    }
+    pub fn parameters_mut(&mut self) -> &mut Parameters {
+        &mut self.parameters
+    }
+    /// The value of a parameter. Name is case-insensitive
+    pub fn parameter(&self, name: &str) -> Option<&str> {
+        self.parameters.get(name)
+    }
+    /// The `filename` parameter. Decoded if it used RFC 2231
+    pub fn filename(&self) -> Option<&str> {
+        self.parameter("filename")
+    }
+}
+impl Display for ContentDisposition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.disposition, self.parameters)
+    }
+}
+impl FromStr for ContentDisposition {
+    type Err = InvalidContentDisposition;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        disposition()
+            .parse(value)
+            .into_result()
+            .map(|(disposition, parameters)| ContentDisposition {
+                disposition: DispositionType::from(disposition),
+                parameters: Parameters::from_raw(parameters),
+            })
+            .map_err(|spans| InvalidContentDisposition {
+                spans,
+                ctx: Some(value.to_owned()),
+            })
+    }
+}
+impl TryFrom<&str> for ContentDisposition {
+    type Error = InvalidContentDisposition;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        ContentDisposition::from_str(value)
+    }
+}
+impl TryFrom<String> for ContentDisposition {
+    type Error = InvalidContentDisposition;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        ContentDisposition::from_str(&value)
+    }
+}
+#[cfg(feature = "serde")]
+mod _serde {
+    use serde::{Deserialize, Serialize};
+
+    use super::{ContentDisposition, ContentType};
+    /// Serialized as the formatted header value
+    impl Serialize for ContentType {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            serializer.collect_str(self)
+        }
+    }
+    impl<'de> Deserialize<'de> for ContentType {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            let value = String::deserialize(deserializer)?;
+            ContentType::try_from(value).map_err(serde::de::Error::custom)
+        }
+    }
+    /// Serialized as the formatted header value
+    impl Serialize for ContentDisposition {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            serializer.collect_str(self)
+        }
+    }
+    impl<'de> Deserialize<'de> for ContentDisposition {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            let value = String::deserialize(deserializer)?;
+            ContentDisposition::try_from(value).map_err(serde::de::Error::custom)
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{ContentDisposition, ContentType, DispositionType, Parameter};
+
+    #[test]
+    fn test_parse() {
+        let content_type: ContentType = "Text/HTML; Charset=\"UTF-8\" (comment)".parse().unwrap();
+        assert_eq!(content_type.media_type(), "text/html");
+        assert_eq!(content_type.charset(), Some("UTF-8"));
+        assert_eq!(content_type.to_string(), "text/html; charset=UTF-8");
+
+        let content_type: ContentType = "multipart/mixed; boundary=\"simple boundary\""
+            .parse()
+            .unwrap();
+        assert!(content_type.is_multipart());
+        assert_eq!(content_type.boundary(), Some("simple boundary"));
+        assert_eq!(
+            content_type.to_string(),
+            "multipart/mixed; boundary=\"simple boundary\""
+        );
+
+        let disposition: ContentDisposition =
+            "attachment; filename=genome.jpeg;\r\n modification-date=\"Wed, 12 Feb 1997 16:29:51 -0500\";"
+                .parse()
+                .unwrap();
+        assert!(disposition.is_attachment());
+        assert_eq!(disposition.filename(), Some("genome.jpeg"));
+        assert_eq!(
+            disposition.parameter("Modification-Date"),
+            Some("Wed, 12 Feb 1997 16:29:51 -0500")
+        );
+        assert_eq!(
+            "X-Unknown"
+                .parse::<ContentDisposition>()
+                .unwrap()
+                .disposition(),
+            &DispositionType::Other("x-unknown".to_owned())
+        );
+
+        assert!("text".parse::<ContentType>().is_err());
+        assert!("text/plain; charset".parse::<ContentType>().is_err());
+    }
+    #[test]
+    fn test_rfc2231() {
+        let content_type: ContentType = "message/external-body; access-type=URL;\r\n URL*0=\"ftp://\";\r\n URL*1=\"cs.utk.edu/pub/moore/bulk-mailer/bulk-mailer.tar\""
+            .parse()
+            .unwrap();
+        assert_eq!(
+            content_type.parameter("url"),
+            Some("ftp://cs.utk.edu/pub/moore/bulk-mailer/bulk-mailer.tar")
+        );
+
+        let content_type: ContentType = "application/x-stuff;\r\n title*0*=us-ascii'en'This%20is%20even%20more%20;\r\n title*2=\"isn't it!\";\r\n title*1*=%2A%2A%2Afun%2A%2A%2A%20"
+            .parse()
+            .unwrap();
+        let title = content_type.parameters().get_parameter("title").unwrap();
+        assert_eq!(title.value(), "This is even more ***fun*** isn't it!");
+        assert_eq!(title.charset(), Some("us-ascii"));
+        assert_eq!(title.language(), Some("en"));
+
+        let disposition: ContentDisposition =
+            "attachment; filename=\"fallback.txt\"; filename*=UTF-8''%E2%82%AC%20rates.txt"
+                .parse()
+                .unwrap();
+        assert_eq!(disposition.filename(), Some("€ rates.txt"));
+
+        let disposition: ContentDisposition = "inline; filename*=iso-8859-1''caf%E9; name*1=x"
+            .parse()
+            .unwrap();
+        assert_eq!(disposition.filename(), Some("café"));
+        // Continuations must start at 0
+        assert_eq!(disposition.parameter("name"), None);
+    }
+    #[test]
+    fn test_format() {
+        let disposition = ContentDisposition::attachment("résumé \"final\".pdf");
+        assert_eq!(
+            disposition.to_string(),
+            "attachment; filename*=utf-8''r%C3%A9sum%C3%A9%20%22final%22.pdf"
+        );
+        assert_eq!(
+            disposition
+                .to_string()
+                .parse::<ContentDisposition>()
+                .unwrap()
+                .filename(),
+            disposition.filename()
+        );
+
+        let mut content_type = ContentType::new("text", "plain");
+        content_type
+            .parameters_mut()
+            .insert(Parameter::new("title", "Hello").with_language("en"));
+        assert_eq!(
+            content_type.to_string(),
+            "text/plain; title*=utf-8'en'Hello"
+        );
+
+        let long_name = "a".repeat(70) + " b";
+        let content_type =
+            ContentType::new("application", "pdf").with_parameter("name", &long_name);
+        assert_eq!(
+            content_type.to_string(),
+            format!(
+                "application/pdf; name*0={};\r\n name*1=\"{} b\"",
+                "a".repeat(50),
+                "a".repeat(20)
+            )
+        );
+        assert_eq!(
+            content_type.to_string().parse::<ContentType>().unwrap(),
+            content_type
+        );
+
+        let long_name = "€".repeat(30);
+        let disposition = ContentDisposition::attachment(&long_name);
+        let formatted = disposition.to_string();
+        assert!(formatted.starts_with("attachment; filename*0*=utf-8''%E2%82%AC"));
+        assert!(formatted.contains(";\r\n filename*1*=%"));
+        assert!(formatted.lines().all(|line| line.len() <= 78));
+        let parsed: ContentDisposition = formatted.parse().unwrap();
+        assert_eq!(parsed.filename(), Some(long_name.as_str()));
+    }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let content_type = ContentType::default();
+        let json = serde_json::to_string(&content_type).unwrap();
+        assert_eq!(json, r#""text/plain; charset=us-ascii""#);
+        assert_eq!(
+            serde_json::from_str::<ContentType>(&json).unwrap(),
+            content_type
+        );
+    }
+}
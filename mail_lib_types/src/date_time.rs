@@ -0,0 +1,453 @@
+/*!
+# DateTime
+
+The `date-time` used in the `Date`, `Resent-Date` and `Received` headers.
+
+Defined in [RFC 5322 Section 3.3](https://datatracker.ietf.org/doc/html/rfc5322#section-3.3)
+
+Parsing accepts the obsolete forms from [Section 4.3](https://datatracker.ietf.org/doc/html/rfc5322#section-4.3).
+Formatting always produces the canonical form `Fri, 21 Nov 1997 09:55:06 -0600`.
+
+Conversions to and from [chrono](https://docs.rs/chrono) and [time](https://docs.rs/time) are available behind the `chrono` and `time` features
+ */
+use std::{fmt::Display, str::FromStr};
+
+use chumsky::Parser;
+use thiserror::Error;
+
+use crate::parsers::rfcs::rfc5322::date_time;
+
+const DAY_NAMES: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTH_NAMES: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+/// Finds the day of the week from its name. Case-insensitive
+pub(crate) fn day_from_name(name: &str) -> Option<u8> {
+    DAY_NAMES
+        .iter()
+        .position(|day| day.eq_ignore_ascii_case(name))
+        .map(|index| index as u8)
+}
+/// Finds the month from its name. Case-insensitive
+pub(crate) fn month_from_name(name: &str) -> Option<u8> {
+    MONTH_NAMES
+        .iter()
+        .position(|month| month.eq_ignore_ascii_case(name))
+        .map(|index| index as u8 + 1)
+}
+/// The offset in minutes of an `obs-zone`.
+///
+/// Military zones are treated as `-0000` as RFC 5322 recommends
+pub(crate) fn zone_from_name(name: &str) -> Option<Option<i16>> {
+    let offset = match name.to_ascii_uppercase().as_str() {
+        "UT" | "GMT" => 0,
+        "EDT" => -4,
+        "EST" | "CDT" => -5,
+        "CST" | "MDT" => -6,
+        "MST" | "PDT" => -7,
+        "PST" => -8,
+        military if military.len() == 1 && military != "J" => return Some(None),
+        _ => return None,
+    };
+    Some(Some(offset * 60))
+}
+fn is_leap_year(year: u16) -> bool {
+    (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400)
+}
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+/// Used Internally as a temporary structure to build a [DateTime]
+#[doc(hidden)]
+#[derive(Debug, PartialEq, Eq)]
+pub struct RawDateTime {
+    pub(crate) day: u8,
+    pub(crate) month: u8,
+    pub(crate) year: u32,
+    pub(crate) hour: u8,
+    pub(crate) minute: u8,
+    pub(crate) second: Option<u8>,
+    pub(crate) offset: Option<i16>,
+}
+/// An error that occurs when parsing or creating a [DateTime]
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum InvalidDateTime {
+    #[error("Invalid date-time {0:?}")]
+    Syntax(String),
+    #[error("The {0} is out of range")]
+    OutOfRange(&'static str),
+}
+
+/// A date and time with an offset from UTC
+///
+/// The day of the week is not stored. It is ignored when parsing and calculated when formatting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DateTime {
+    year: u16,
+    month: u8,
+    day: u8,
+    hour: u8,
+    minute: u8,
+    second: u8,
+    offset: Option<i16>,
+}
+impl DateTime {
+    /// Creates a new [DateTime]. Checks that every field is in range
+    ///
+    /// `offset` is in minutes. `None` is `-0000`, UTC with the local zone unknown.
+    /// A second of `60` is allowed for leap seconds
+    pub fn new(
+        year: u16,
+        month: u8,
+        day: u8,
+        hour: u8,
+        minute: u8,
+        second: u8,
+        offset: Option<i16>,
+    ) -> Result<Self, InvalidDateTime> {
+        if !(1900..=9999).contains(&year) {
+            return Err(InvalidDateTime::OutOfRange("year"));
+        }
+        if !(1..=12).contains(&month) {
+            return Err(InvalidDateTime::OutOfRange("month"));
+        }
+        if day == 0 || day > days_in_month(year, month) {
+            return Err(InvalidDateTime::OutOfRange("day"));
+        }
+        if hour > 23 {
+            return Err(InvalidDateTime::OutOfRange("hour"));
+        }
+        if minute > 59 {
+            return Err(InvalidDateTime::OutOfRange("minute"));
+        }
+        if second > 60 {
+            return Err(InvalidDateTime::OutOfRange("second"));
+        }
+        if offset.is_some_and(|offset| offset.abs() > 99 * 60 + 59) {
+            return Err(InvalidDateTime::OutOfRange("offset"));
+        }
+        Ok(Self {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            offset,
+        })
+    }
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+    /// 1 to 12
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+    pub fn hour(&self) -> u8 {
+        self.hour
+    }
+    pub fn minute(&self) -> u8 {
+        self.minute
+    }
+    pub fn second(&self) -> u8 {
+        self.second
+    }
+    /// The offset from UTC in minutes. `None` is `-0000`
+    pub fn offset(&self) -> Option<i16> {
+        self.offset
+    }
+    /// The offset from UTC in minutes. `-0000` is treated as UTC
+    pub fn offset_minutes(&self) -> i16 {
+        self.offset.unwrap_or_default()
+    }
+    /// The day of the week. 0 is Sunday
+    pub fn day_of_week(&self) -> u8 {
+        const MONTH_OFFSETS: [u16; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+        let year = if self.month < 3 {
+            self.year - 1
+        } else {
+            self.year
+        };
+        let day = year + year / 4 - year / 100
+            + year / 400
+            + MONTH_OFFSETS[self.month as usize - 1]
+            + self.day as u16;
+        (day % 7) as u8
+    }
+}
+impl TryFrom<RawDateTime> for DateTime {
+    type Error = InvalidDateTime;
+    fn try_from(value: RawDateTime) -> Result<Self, Self::Error> {
+        let RawDateTime {
+            day,
+            month,
+            year,
+            hour,
+            minute,
+            second,
+            offset,
+        } = value;
+        let year = u16::try_from(year).map_err(|_| InvalidDateTime::OutOfRange("year"))?;
+        DateTime::new(
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second.unwrap_or_default(),
+            offset,
+        )
+    }
+}
+/// Formats as `day-name, day month year hour:minute:second zone`
+impl Display for DateTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}, {} {} {:04} {:02}:{:02}:{:02} ",
+            DAY_NAMES[self.day_of_week() as usize],
+            self.day,
+            MONTH_NAMES[self.month as usize - 1],
+            self.year,
+            self.hour,
+            self.minute,
+            self.second
+        )?;
+        match self.offset {
+            Some(offset) => {
+                let sign = if offset < 0 { '-' } else { '+' };
+                let offset = offset.unsigned_abs();
+                write!(f, "{}{:02}{:02}", sign, offset / 60, offset % 60)
+            }
+            None => f.write_str("-0000"),
+        }
+    }
+}
+impl FromStr for DateTime {
+    type Err = InvalidDateTime;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let raw = date_time()
+            .parse(value)
+            .into_result()
+            .map_err(|_| InvalidDateTime::Syntax(value.to_owned()))?;
+        DateTime::try_from(raw)
+    }
+}
+impl TryFrom<&str> for DateTime {
+    type Error = InvalidDateTime;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        DateTime::from_str(value)
+    }
+}
+impl TryFrom<String> for DateTime {
+    type Error = InvalidDateTime;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        DateTime::from_str(&value)
+    }
+}
+#[cfg(feature = "serde")]
+mod _serde {
+    use serde::{Deserialize, Serialize};
+
+    use super::DateTime;
+    /// Serialized in the canonical form
+    impl Serialize for DateTime {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            serializer.collect_str(self)
+        }
+    }
+    impl<'de> Deserialize<'de> for DateTime {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            let value = String::deserialize(deserializer)?;
+            DateTime::try_from(value).map_err(serde::de::Error::custom)
+        }
+    }
+}
+#[cfg(feature = "chrono")]
+mod _chrono {
+    use chrono::{Datelike, FixedOffset, NaiveDate, Offset, TimeZone, Timelike};
+
+    use super::{DateTime, InvalidDateTime};
+    /// `-0000` becomes a UTC offset. A leap second is kept as chrono's leap second
+    impl TryFrom<DateTime> for chrono::DateTime<FixedOffset> {
+        type Error = InvalidDateTime;
+        fn try_from(value: DateTime) -> Result<Self, Self::Error> {
+            let offset = FixedOffset::east_opt(i32::from(value.offset_minutes()) * 60)
+                .ok_or(InvalidDateTime::OutOfRange("offset"))?;
+            let (second, milli) = if value.second == 60 {
+                (59, 1_000)
+            } else {
+                (value.second, 0)
+            };
+            let naive = NaiveDate::from_ymd_opt(
+                i32::from(value.year),
+                u32::from(value.month),
+                u32::from(value.day),
+            )
+            .and_then(|date| {
+                date.and_hms_milli_opt(
+                    u32::from(value.hour),
+                    u32::from(value.minute),
+                    u32::from(second),
+                    milli,
+                )
+            })
+            .ok_or(InvalidDateTime::OutOfRange("date"))?;
+            offset
+                .from_local_datetime(&naive)
+                .single()
+                .ok_or(InvalidDateTime::OutOfRange("date"))
+        }
+    }
+    impl<Tz: TimeZone> TryFrom<chrono::DateTime<Tz>> for DateTime {
+        type Error = InvalidDateTime;
+        fn try_from(value: chrono::DateTime<Tz>) -> Result<Self, Self::Error> {
+            let year =
+                u16::try_from(value.year()).map_err(|_| InvalidDateTime::OutOfRange("year"))?;
+            let second = if value.nanosecond() >= 1_000_000_000 {
+                60
+            } else {
+                value.second() as u8
+            };
+            let offset = value.offset().fix().local_minus_utc() / 60;
+            DateTime::new(
+                year,
+                value.month() as u8,
+                value.day() as u8,
+                value.hour() as u8,
+                value.minute() as u8,
+                second,
+                Some(offset as i16),
+            )
+        }
+    }
+}
+#[cfg(feature = "time")]
+mod _time {
+    use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset};
+
+    use super::{DateTime, InvalidDateTime};
+    /// `-0000` becomes a UTC offset. `time` has no leap seconds so they become `59`
+    impl TryFrom<DateTime> for OffsetDateTime {
+        type Error = InvalidDateTime;
+        fn try_from(value: DateTime) -> Result<Self, Self::Error> {
+            let month =
+                Month::try_from(value.month).map_err(|_| InvalidDateTime::OutOfRange("month"))?;
+            let date = Date::from_calendar_date(i32::from(value.year), month, value.day)
+                .map_err(|_| InvalidDateTime::OutOfRange("date"))?;
+            let time = Time::from_hms(value.hour, value.minute, value.second.min(59))
+                .map_err(|_| InvalidDateTime::OutOfRange("time"))?;
+            let offset = UtcOffset::from_whole_seconds(i32::from(value.offset_minutes()) * 60)
+                .map_err(|_| InvalidDateTime::OutOfRange("offset"))?;
+            Ok(PrimitiveDateTime::new(date, time).assume_offset(offset))
+        }
+    }
+    impl TryFrom<OffsetDateTime> for DateTime {
+        type Error = InvalidDateTime;
+        fn try_from(value: OffsetDateTime) -> Result<Self, Self::Error> {
+            let year =
+                u16::try_from(value.year()).map_err(|_| InvalidDateTime::OutOfRange("year"))?;
+            DateTime::new(
+                year,
+                u8::from(value.month()),
+                value.day(),
+                value.hour(),
+                value.minute(),
+                value.second(),
+                Some(value.offset().whole_minutes()),
+            )
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{DateTime, InvalidDateTime};
+
+    fn check(raw: &str, canonical: &str) {
+        let date_time: DateTime = raw
+            .parse()
+            .unwrap_or_else(|error| panic!("{:?}: {}", raw, error));
+        assert_eq!(date_time.to_string(), canonical, "{:?}", raw);
+    }
+    #[test]
+    fn test_parse() {
+        check(
+            "Fri, 21 Nov 1997 09:55:06 -0600",
+            "Fri, 21 Nov 1997 09:55:06 -0600",
+        );
+        check(
+            "1 Jul 2003 10:52:37 +0200",
+            "Tue, 1 Jul 2003 10:52:37 +0200",
+        );
+        check(
+            "Thu,\r\n      13\r\n        Feb\r\n          1969\r\n      23:32\r\n               -0330 (Newfoundland Time)",
+            "Thu, 13 Feb 1969 23:32:00 -0330",
+        );
+        check("21 Nov 97 09:55:06 GMT", "Fri, 21 Nov 1997 09:55:06 +0000");
+        check(
+            "Mon, 1 Jan 01 00:00:00 pdt",
+            "Mon, 1 Jan 2001 00:00:00 -0700",
+        );
+        check(
+            "Sat, 29 Feb 2020 12:00:00 Z",
+            "Sat, 29 Feb 2020 12:00:00 -0000",
+        );
+        check(
+            "Fri, 21 Nov 1997 09:55:06 -0000",
+            "Fri, 21 Nov 1997 09:55:06 -0000",
+        );
+        check(
+            "Sun, 31 Dec 2023 23:59:60 +0000",
+            "Sun, 31 Dec 2023 23:59:60 +0000",
+        );
+    }
+    #[test]
+    fn test_invalid() {
+        assert_eq!(
+            "Fri, 30 Feb 1997 09:55:06 -0600".parse::<DateTime>(),
+            Err(InvalidDateTime::OutOfRange("day"))
+        );
+        assert_eq!(
+            "Fri, 21 Nov 1997 24:55:06 -0600".parse::<DateTime>(),
+            Err(InvalidDateTime::OutOfRange("hour"))
+        );
+        assert!(matches!(
+            "Fri, 21 Foo 1997 09:55:06 -0600".parse::<DateTime>(),
+            Err(InvalidDateTime::Syntax(_))
+        ));
+        assert!("21 Nov 1997 09:55:06 -0675".parse::<DateTime>().is_err());
+        assert!("21 Nov 1997 09:55:06 J".parse::<DateTime>().is_err());
+    }
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_chrono() {
+        let date_time: DateTime = "Fri, 21 Nov 1997 09:55:06 -0600".parse().unwrap();
+        let chrono = chrono::DateTime::<chrono::FixedOffset>::try_from(date_time).unwrap();
+        assert_eq!(chrono.timestamp(), 880127706);
+        assert_eq!(DateTime::try_from(chrono).unwrap(), date_time);
+    }
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_time() {
+        let date_time: DateTime = "Fri, 21 Nov 1997 09:55:06 -0600".parse().unwrap();
+        let time = time::OffsetDateTime::try_from(date_time).unwrap();
+        assert_eq!(time.unix_timestamp(), 880127706);
+        assert_eq!(DateTime::try_from(time).unwrap(), date_time);
+    }
+}
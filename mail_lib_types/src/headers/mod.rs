@@ -0,0 +1,549 @@
+/*!
+# Headers
+
+Typed header fields of an Internet Message.
+
+Defined in [RFC 5322 Section 3.6](https://datatracker.ietf.org/doc/html/rfc5322#section-3.6)
+
+`Content-Type` and `Content-Disposition` are parsed as well.
+Any other header that is not defined by RFC 5322 is kept as [Header::Other]
+ */
+use std::{fmt::Display, str::FromStr};
+
+use chumsky::Parser;
+use thiserror::Error;
+
+use crate::{
+    address::AddressList,
+    content_type::{ContentDisposition, ContentType},
+    date_time::DateTime,
+    mail_box::MailBox,
+    message_id::MessageId,
+    parsers::{rfcs::rfc5322, ErrType},
+    EmailAddress,
+};
+
+/// An error that occurs when parsing a [Header]
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum InvalidHeader {
+    #[error("Header line is missing a `:` {0:?}")]
+    MissingColon(String),
+    #[error("Invalid header name {0:?}")]
+    InvalidName(String),
+    #[error("Invalid value for header {name}: {value:?}")]
+    InvalidValue { name: String, value: String },
+    #[error("Continuation line without a header {0:?}")]
+    UnexpectedContinuation(String),
+}
+
+/// A single header field
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Header {
+    /// `From: mailbox-list`
+    From(Vec<MailBox>),
+    /// `Sender: mailbox`
+    Sender(MailBox),
+    /// `Reply-To: address-list`
+    ReplyTo(AddressList),
+    /// `To: address-list`
+    To(AddressList),
+    /// `Cc: address-list`
+    Cc(AddressList),
+    /// `Bcc: [address-list]`
+    Bcc(AddressList),
+    /// `Date: date-time`
+    Date(DateTime),
+    /// `Message-ID: msg-id`
+    MessageId(MessageId),
+    /// `In-Reply-To: 1*msg-id`
+    InReplyTo(Vec<MessageId>),
+    /// `References: 1*msg-id`
+    References(Vec<MessageId>),
+    /// `Subject: unstructured`
+    Subject(String),
+    /// `Comments: unstructured`
+    Comments(String),
+    /// `Keywords: phrase *("," phrase)`
+    Keywords(Vec<String>),
+    /// `Resent-Date: date-time`
+    ResentDate(DateTime),
+    /// `Resent-From: mailbox-list`
+    ResentFrom(Vec<MailBox>),
+    /// `Resent-Sender: mailbox`
+    ResentSender(MailBox),
+    /// `Resent-To: address-list`
+    ResentTo(AddressList),
+    /// `Resent-Cc: address-list`
+    ResentCc(AddressList),
+    /// `Resent-Bcc: [address-list]`
+    ResentBcc(AddressList),
+    /// `Resent-Message-ID: msg-id`
+    ResentMessageId(MessageId),
+    /// `Return-Path: path`
+    ///
+    /// `None` is the null path `<>`
+    ReturnPath(Option<EmailAddress>),
+    /// `Received: *received-token ";" date-time`
+    Received {
+        /// The received tokens. Kept as the unfolded value
+        tokens: String,
+        /// The date-time after the `;`
+        date: DateTime,
+    },
+    /// `Content-Type: type "/" subtype *(";" parameter)`
+    ContentType(ContentType),
+    /// `Content-Disposition: disposition-type *(";" disposition-parm)`
+    ContentDisposition(ContentDisposition),
+    /// Any header not listed above
+    Other {
+        /// The name as it appeared in the message
+        name: String,
+        /// The unfolded value
+        value: String,
+    },
+}
+impl Header {
+    /// Parses a header from its name and value.
+    ///
+    /// The value can still be folded. The name is matched case-insensitively
+    pub fn parse(name: &str, value: &str) -> Result<Self, InvalidHeader> {
+        if name.is_empty() || !name.bytes().all(|b| matches!(b, 33..=57 | 59..=126)) {
+            return Err(InvalidHeader::InvalidName(name.to_owned()));
+        }
+        let unfolded: String = value.lines().collect();
+        let value = unfolded.trim();
+        let invalid = || InvalidHeader::InvalidValue {
+            name: name.to_owned(),
+            value: value.to_owned(),
+        };
+        let header = match name.to_ascii_lowercase().as_str() {
+            "from" => Header::From(mailbox_list(value).ok_or_else(invalid)?),
+            "sender" => Header::Sender(single_mailbox(value).ok_or_else(invalid)?),
+            "reply-to" => Header::ReplyTo(address_list(value).ok_or_else(invalid)?),
+            "to" => Header::To(address_list(value).ok_or_else(invalid)?),
+            "cc" => Header::Cc(address_list(value).ok_or_else(invalid)?),
+            "bcc" => Header::Bcc(optional_address_list(value).ok_or_else(invalid)?),
+            "date" => Header::Date(value.parse().map_err(|_| invalid())?),
+            "message-id" => Header::MessageId(value.parse().map_err(|_| invalid())?),
+            "in-reply-to" => {
+                Header::InReplyTo(MessageId::parse_list(value).map_err(|_| invalid())?)
+            }
+            "references" => {
+                Header::References(MessageId::parse_list(value).map_err(|_| invalid())?)
+            }
+            "subject" => Header::Subject(value.to_owned()),
+            "comments" => Header::Comments(value.to_owned()),
+            "keywords" => Header::Keywords(
+                parse_with(rfc5322::phrase_list(), value)
+                    .ok_or_else(invalid)?
+                    .into_iter()
+                    .map(|keyword| keyword.to_owned())
+                    .collect(),
+            ),
+            "resent-date" => Header::ResentDate(value.parse().map_err(|_| invalid())?),
+            "resent-from" => Header::ResentFrom(mailbox_list(value).ok_or_else(invalid)?),
+            "resent-sender" => Header::ResentSender(single_mailbox(value).ok_or_else(invalid)?),
+            "resent-to" => Header::ResentTo(address_list(value).ok_or_else(invalid)?),
+            "resent-cc" => Header::ResentCc(address_list(value).ok_or_else(invalid)?),
+            "resent-bcc" => Header::ResentBcc(optional_address_list(value).ok_or_else(invalid)?),
+            "resent-message-id" => Header::ResentMessageId(value.parse().map_err(|_| invalid())?),
+            "return-path" => Header::ReturnPath(
+                parse_with(rfc5322::path(), value)
+                    .ok_or_else(invalid)?
+                    // Safe as long as the parser did its job
+                    .map(|(local, domain)| unsafe {
+                        EmailAddress::new_unchecked_from_parts(local, domain)
+                    }),
+            ),
+            "received" => {
+                let (tokens, date) = value.rsplit_once(';').ok_or_else(invalid)?;
+                Header::Received {
+                    tokens: tokens.trim().to_owned(),
+                    date: date.parse().map_err(|_| invalid())?,
+                }
+            }
+            "content-type" => Header::ContentType(value.parse().map_err(|_| invalid())?),
+            "content-disposition" => {
+                Header::ContentDisposition(value.parse().map_err(|_| invalid())?)
+            }
+            _ => Header::Other {
+                name: name.to_owned(),
+                value: value.to_owned(),
+            },
+        };
+        Ok(header)
+    }
+    /// Parses a header like [Header::parse]. A value that does not parse is kept as [Header::Other].
+    ///
+    /// Only an invalid name is an error. Real messages often have one malformed header
+    pub fn parse_lenient(name: &str, value: &str) -> Result<Self, InvalidHeader> {
+        match Header::parse(name, value) {
+            Err(InvalidHeader::InvalidValue { name, value }) => Ok(Header::Other { name, value }),
+            result => result,
+        }
+    }
+    /// The name of the header.
+    ///
+    /// Known headers use the casing from RFC 5322
+    pub fn name(&self) -> &str {
+        match self {
+            Header::From(_) => "From",
+            Header::Sender(_) => "Sender",
+            Header::ReplyTo(_) => "Reply-To",
+            Header::To(_) => "To",
+            Header::Cc(_) => "Cc",
+            Header::Bcc(_) => "Bcc",
+            Header::Date(_) => "Date",
+            Header::MessageId(_) => "Message-ID",
+            Header::InReplyTo(_) => "In-Reply-To",
+            Header::References(_) => "References",
+            Header::Subject(_) => "Subject",
+            Header::Comments(_) => "Comments",
+            Header::Keywords(_) => "Keywords",
+            Header::ResentDate(_) => "Resent-Date",
+            Header::ResentFrom(_) => "Resent-From",
+            Header::ResentSender(_) => "Resent-Sender",
+            Header::ResentTo(_) => "Resent-To",
+            Header::ResentCc(_) => "Resent-Cc",
+            Header::ResentBcc(_) => "Resent-Bcc",
+            Header::ResentMessageId(_) => "Resent-Message-ID",
+            Header::ReturnPath(_) => "Return-Path",
+            Header::Received { .. } => "Received",
+            Header::ContentType(_) => "Content-Type",
+            Header::ContentDisposition(_) => "Content-Disposition",
+            Header::Other { name, .. } => name,
+        }
+    }
+    /// If the header has the given name. Case-insensitive
+    pub fn is(&self, name: &str) -> bool {
+        self.name().eq_ignore_ascii_case(name)
+    }
+    /// If this is one of the `Resent-*` headers
+    pub fn is_resent(&self) -> bool {
+        matches!(
+            self,
+            Header::ResentDate(_)
+                | Header::ResentFrom(_)
+                | Header::ResentSender(_)
+                | Header::ResentTo(_)
+                | Header::ResentCc(_)
+                | Header::ResentBcc(_)
+                | Header::ResentMessageId(_)
+        )
+    }
+}
+fn parse_with<'a, T>(
+    parser: impl Parser<'a, &'a str, T, ErrType<'a>>,
+    value: &'a str,
+) -> Option<T> {
+    parser.parse(value).into_result().ok()
+}
+fn single_mailbox(value: &str) -> Option<MailBox> {
+    parse_with(rfc5322::mailbox().padded(), value).map(MailBox::from)
+}
+fn mailbox_list(value: &str) -> Option<Vec<MailBox>> {
+    parse_with(rfc5322::mailbox_list(), value)
+        .map(|mailboxes| mailboxes.into_iter().map(MailBox::from).collect())
+}
+fn address_list(value: &str) -> Option<AddressList> {
+    value.parse().ok()
+}
+/// Bcc can be empty
+fn optional_address_list(value: &str) -> Option<AddressList> {
+    if value.is_empty() {
+        return Some(AddressList::new());
+    }
+    address_list(value)
+}
+fn write_list<T: Display>(
+    f: &mut std::fmt::Formatter<'_>,
+    items: &[T],
+    separator: &str,
+) -> std::fmt::Result {
+    for (index, item) in items.iter().enumerate() {
+        if index != 0 {
+            f.write_str(separator)?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+/// Formats as `Name: value` without the trailing CRLF. Long values are not folded
+impl Display for Header {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", self.name())?;
+        match self {
+            Header::From(mailboxes) | Header::ResentFrom(mailboxes) => {
+                write_list(f, mailboxes, ", ")
+            }
+            Header::ReplyTo(addresses)
+            | Header::To(addresses)
+            | Header::Cc(addresses)
+            | Header::Bcc(addresses)
+            | Header::ResentTo(addresses)
+            | Header::ResentCc(addresses)
+            | Header::ResentBcc(addresses) => write!(f, "{}", addresses),
+            Header::Sender(mailbox) | Header::ResentSender(mailbox) => write!(f, "{}", mailbox),
+            Header::MessageId(id) | Header::ResentMessageId(id) => write!(f, "{}", id),
+            Header::InReplyTo(ids) | Header::References(ids) => write_list(f, ids, " "),
+            Header::Keywords(keywords) => write_list(f, keywords, ", "),
+            Header::ReturnPath(Some(path)) => write!(f, "<{}>", path),
+            Header::ReturnPath(None) => f.write_str("<>"),
+            Header::Received { tokens, date } if tokens.is_empty() => write!(f, "; {}", date),
+            Header::Received { tokens, date } => write!(f, "{}; {}", tokens, date),
+            Header::Date(date) | Header::ResentDate(date) => write!(f, "{}", date),
+            Header::ContentType(content_type) => write!(f, "{}", content_type),
+            Header::ContentDisposition(disposition) => write!(f, "{}", disposition),
+            Header::Subject(value) | Header::Comments(value) | Header::Other { value, .. } => {
+                f.write_str(value)
+            }
+        }
+    }
+}
+/// Parses a single header field `Name: value`. The value can be folded
+impl FromStr for Header {
+    type Err = InvalidHeader;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = s
+            .split_once(':')
+            .ok_or_else(|| InvalidHeader::MissingColon(s.to_owned()))?;
+        // obs-optional allows whitespace before the colon
+        Header::parse(name.trim_end(), value)
+    }
+}
+
+/// The header section of a message. Headers are kept in the order they appeared
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Headers(Vec<Header>);
+impl Headers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn push(&mut self, header: Header) {
+        self.0.push(header);
+    }
+    pub fn iter(&self) -> std::slice::Iter<'_, Header> {
+        self.0.iter()
+    }
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    /// The first header with the given name. Case-insensitive
+    pub fn get(&self, name: &str) -> Option<&Header> {
+        self.0.iter().find(|header| header.is(name))
+    }
+    /// All headers with the given name. Case-insensitive
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Header> + 'a {
+        self.0.iter().filter(move |header| header.is(name))
+    }
+    pub fn into_inner(self) -> Vec<Header> {
+        self.0
+    }
+}
+/// Parses the header section. Stops at the first empty line. Accepts CRLF or LF line endings
+///
+/// Headers with an invalid value are kept as [Header::Other]. See [Header::parse_lenient]
+impl FromStr for Headers {
+    type Err = InvalidHeader;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut headers = Vec::new();
+        let mut current: Option<String> = None;
+        for line in s.lines() {
+            if line.is_empty() {
+                break;
+            }
+            if line.starts_with([' ', '\t']) {
+                let Some(field) = current.as_mut() else {
+                    return Err(InvalidHeader::UnexpectedContinuation(line.to_owned()));
+                };
+                field.push_str("\r\n");
+                field.push_str(line);
+            } else if let Some(field) = current.replace(line.to_owned()) {
+                headers.push(parse_field(&field)?);
+            }
+        }
+        if let Some(field) = current {
+            headers.push(parse_field(&field)?);
+        }
+        Ok(Self(headers))
+    }
+}
+fn parse_field(field: &str) -> Result<Header, InvalidHeader> {
+    let (name, value) = field
+        .split_once(':')
+        .ok_or_else(|| InvalidHeader::MissingColon(field.to_owned()))?;
+    Header::parse_lenient(name.trim_end(), value)
+}
+/// Each header followed by a CRLF
+impl Display for Headers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for header in &self.0 {
+            write!(f, "{}\r\n", header)?;
+        }
+        Ok(())
+    }
+}
+impl From<Vec<Header>> for Headers {
+    fn from(headers: Vec<Header>) -> Self {
+        Self(headers)
+    }
+}
+impl FromIterator<Header> for Headers {
+    fn from_iter<T: IntoIterator<Item = Header>>(iter: T) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+impl IntoIterator for Headers {
+    type Item = Header;
+    type IntoIter = std::vec::IntoIter<Header>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+impl<'a> IntoIterator for &'a Headers {
+    type Item = &'a Header;
+    type IntoIter = std::slice::Iter<'a, Header>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{Header, Headers, InvalidHeader};
+    use crate::{address::AddressList, message_id::MessageId};
+
+    #[test]
+    fn test_parse() {
+        let Header::From(from) = "From: John Doe <jdoe@machine.example>".parse().unwrap() else {
+            panic!("Expected From");
+        };
+        assert_eq!(from.len(), 1);
+        assert_eq!(from[0].get_name(), Some("John Doe"));
+        assert_eq!(from[0].email, "jdoe@machine.example");
+
+        let to: Header = "TO: \"Mary Smith\" <mary@example.net>,\r\n jane@example.net"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            to.to_string(),
+            "To: \"Mary Smith\" <mary@example.net>, jane@example.net"
+        );
+        assert_eq!(
+            Header::parse("Bcc", "").unwrap(),
+            Header::Bcc(AddressList::new())
+        );
+        let Header::To(to) = Header::parse("To", "undisclosed-recipients:;").unwrap() else {
+            panic!("Expected To");
+        };
+        assert_eq!(to.mailboxes().count(), 0);
+        assert_eq!(
+            Header::parse("Message-ID", " <1234@local.machine.example>").unwrap(),
+            Header::MessageId(MessageId::from_parts("1234", "local.machine.example").unwrap())
+        );
+        let references =
+            Header::parse("References", "<1@example.net>\r\n\t<2@example.net>").unwrap();
+        assert_eq!(
+            references.to_string(),
+            "References: <1@example.net> <2@example.net>"
+        );
+        assert_eq!(
+            Header::parse("Keywords", "mail, rust").unwrap(),
+            Header::Keywords(vec!["mail".to_owned(), "rust".to_owned()])
+        );
+        assert_eq!(
+            Header::parse("Return-Path", "<>").unwrap(),
+            Header::ReturnPath(None)
+        );
+        assert!(Header::parse("Resent-To", "bob@example.com")
+            .unwrap()
+            .is_resent());
+        assert_eq!(
+            Header::parse("Date", "21 Nov 97 09:55:06 GMT")
+                .unwrap()
+                .to_string(),
+            "Date: Fri, 21 Nov 1997 09:55:06 +0000"
+        );
+        let received = Header::parse(
+            "Received",
+            "from mail.example.com by mx.example.net;\r\n Fri, 21 Nov 1997 09:55:06 -0600",
+        )
+        .unwrap();
+        assert_eq!(
+            received.to_string(),
+            "Received: from mail.example.com by mx.example.net; Fri, 21 Nov 1997 09:55:06 -0600"
+        );
+        assert!(Header::parse("Date", "yesterday").is_err());
+        let Header::ContentType(content_type) =
+            Header::parse("content-type", "text/plain;\r\n charset=utf-8").unwrap()
+        else {
+            panic!("Expected Content-Type");
+        };
+        assert_eq!(content_type.charset(), Some("utf-8"));
+        assert_eq!(
+            Header::ContentType(content_type).to_string(),
+            "Content-Type: text/plain; charset=utf-8"
+        );
+        assert_eq!(
+            Header::parse("X-Mailer", " mail_lib ").unwrap(),
+            Header::Other {
+                name: "X-Mailer".to_owned(),
+                value: "mail_lib".to_owned()
+            }
+        );
+
+        assert!(matches!(
+            Header::parse("From", "not an address"),
+            Err(InvalidHeader::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            Header::parse("Bad Name", "value"),
+            Err(InvalidHeader::InvalidName(_))
+        ));
+        assert!(matches!(
+            "No colon".parse::<Header>(),
+            Err(InvalidHeader::MissingColon(_))
+        ));
+    }
+
+    #[test]
+    fn test_headers() {
+        let raw =
+            "From: jdoe@machine.example\r\nSubject: Saying\r\n Hello\r\nX-Custom: 1\r\n\r\nBody";
+        let headers: Headers = raw.parse().unwrap();
+        assert_eq!(headers.len(), 3);
+        assert_eq!(
+            headers.get("subject"),
+            Some(&Header::Subject("Saying Hello".to_owned()))
+        );
+        assert_eq!(headers.get_all("x-custom").count(), 1);
+        assert_eq!(
+            headers.to_string(),
+            "From: jdoe@machine.example\r\nSubject: Saying Hello\r\nX-Custom: 1\r\n"
+        );
+
+        let headers: Headers = "Subject: LF only\nTo: a@example.com\n".parse().unwrap();
+        assert_eq!(headers.len(), 2);
+
+        let headers: Headers = "Date: yesterday\r\nSubject: Hi\r\n".parse().unwrap();
+        assert_eq!(
+            headers.get("date"),
+            Some(&Header::Other {
+                name: "Date".to_owned(),
+                value: "yesterday".to_owned()
+            })
+        );
+        assert!(matches!(
+            "Subject: Hi\r\nNo colon\r\n".parse::<Headers>(),
+            Err(InvalidHeader::MissingColon(_))
+        ));
+        assert!(matches!(
+            " continued".parse::<Headers>(),
+            Err(InvalidHeader::UnexpectedContinuation(_))
+        ));
+    }
+}
@@ -5,8 +5,14 @@ Types Representing the email standard
 
  */
 
+pub mod address;
+pub mod content_type;
+pub mod date_time;
 pub mod email_address;
+pub mod headers;
 pub mod mail_box;
+pub mod message;
+pub mod message_id;
 pub mod parsers;
 pub use email_address::EmailAddress;
 
@@ -5,7 +5,7 @@ A [MailBox] is a structure that contains a [EmailAddress] and an optional name.
 
 Defined in [RFC 5322 Section 3.4](https://tools.ietf.org/html/rfc5322#section-3.4)
  */
-use std::{fmt::Display, str::FromStr};
+use std::{borrow::Cow, fmt::Display, str::FromStr};
 
 use chumsky::{error::Cheap, Parser};
 use digestible::Digestible;
@@ -16,14 +16,14 @@ use crate::{parsers::rfcs::rfc5322::mailbox, EmailAddress};
 #[doc(hidden)]
 #[derive(Debug, PartialEq, Eq)]
 pub struct RawMailBox<'a> {
-    pub(crate) display_name: Option<&'a str>,
+    pub(crate) display_name: Option<Cow<'a, str>>,
     pub(crate) local: &'a str,
     pub(crate) domain: &'a str,
 }
 impl RawMailBox<'_> {
     #[inline(always)]
     pub(crate) fn new<'a>(
-        display_name: Option<&'a str>,
+        display_name: Option<Cow<'a, str>>,
         local: &'a str,
         domain: &'a str,
     ) -> RawMailBox<'a> {
@@ -59,7 +59,7 @@ impl<'a> TryFrom<&'a str> for RawMailBox<'a> {
 impl Into<(Option<String>, String, String)> for RawMailBox<'_> {
     fn into(self) -> (Option<String>, String, String) {
         (
-            self.display_name.map(|v| v.to_owned()),
+            self.display_name.map(Cow::into_owned),
             self.local.to_owned(),
             self.domain.to_owned(),
         )
@@ -73,15 +73,33 @@ pub struct MailBox {
     /// The email address of the mailbox
     pub email: EmailAddress,
 }
+/// Writes a display name. Quoted unless it is a single atom.
+///
+/// `"` and `\` are escaped as quoted-pairs
+pub fn write_display_name(f: &mut impl std::fmt::Write, name: &str) -> std::fmt::Result {
+    if !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-/=?^_`{|}~".contains(c))
+    {
+        return f.write_str(name);
+    }
+    f.write_char('"')?;
+    for c in name.chars() {
+        if matches!(c, '"' | '\\') {
+            f.write_char('\\')?;
+        }
+        f.write_char(c)?;
+    }
+    f.write_char('"')
+}
+/// Formats as `name <local@domain>` or just `local@domain` when there is no name
 impl Display for MailBox {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self.name.as_deref() {
             Some(name) => {
-                if name.contains(' ') {
-                    write!(f, "\"{}\"", name)
-                } else {
-                    write!(f, "{}", name)
-                }
+                write_display_name(f, name)?;
+                write!(f, " <{}>", self.email)
             }
             None => write!(f, "{}", self.email),
         }
@@ -235,12 +253,12 @@ impl From<RawMailBox<'_>> for MailBox {
         } = value;
         // Safe as long as the parser did its job
         let email = unsafe { EmailAddress::new_unchecked_from_parts(local, domain) };
-        MailBox::new(display_name.map(|v| v.to_owned()), email)
+        MailBox::new(display_name.map(Cow::into_owned), email)
     }
 }
 impl PartialEq<RawMailBox<'_>> for MailBox {
     fn eq(&self, other: &RawMailBox) -> bool {
-        other.display_name == self.name.as_deref()
+        other.display_name.as_deref() == self.name.as_deref()
             && self.email.get_local() == other.local
             && self.email.get_domain() == other.domain
     }
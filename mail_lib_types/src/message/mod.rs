@@ -0,0 +1,365 @@
+/*!
+# Message
+
+A zero-copy parser for an Internet Message. Everything borrows from the raw bytes.
+
+Defined in [RFC 5322 Section 2](https://datatracker.ietf.org/doc/html/rfc5322#section-2)
+
+The parser is lenient.
+- Lines can end with CRLF or a bare LF
+- A line that is not a header field ends the header section
+- Multipart bodies are split into [Message::parts] recursively. Every part is a [Message] of its own
+- `message/rfc822` bodies are parsed into [Message::embedded_message]
+- Nesting stops at [DEFAULT_MAX_DEPTH] to protect against malicious messages
+ */
+mod multipart;
+use std::borrow::Cow;
+
+pub use multipart::{Multipart, MultipartSubtype};
+
+use crate::{
+    content_type::{ContentDisposition, ContentType},
+    headers::{Header, Headers, InvalidHeader},
+};
+
+/// How many levels of multipart and `message/rfc822` nesting [Message::parse] will parse
+pub const DEFAULT_MAX_DEPTH: usize = 16;
+
+/// A header field as it appeared in the message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawHeader<'a> {
+    raw: &'a [u8],
+    name: &'a str,
+    value: &'a [u8],
+}
+impl<'a> RawHeader<'a> {
+    /// The whole field. Including the name and any folding. Without the final line break
+    pub fn raw(&self) -> &'a [u8] {
+        self.raw
+    }
+    /// The name of the field
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+    /// If the field has the given name. Case-insensitive
+    pub fn is(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+    }
+    /// Everything after the `:`. Still folded
+    pub fn raw_value(&self) -> &'a [u8] {
+        self.value
+    }
+    /// The value with folding removed and surrounding whitespace trimmed.
+    ///
+    /// Borrowed if the value was not folded and is valid UTF-8
+    pub fn value(&self) -> Cow<'a, str> {
+        if !self.value.contains(&b'\n') {
+            return match String::from_utf8_lossy(self.value) {
+                Cow::Borrowed(value) => Cow::Borrowed(value.trim()),
+                Cow::Owned(value) => Cow::Owned(value.trim().to_owned()),
+            };
+        }
+        let unfolded: Vec<u8> = self
+            .value
+            .iter()
+            .copied()
+            .filter(|byte| *byte != b'\r' && *byte != b'\n')
+            .collect();
+        Cow::Owned(String::from_utf8_lossy(&unfolded).trim().to_owned())
+    }
+    /// Parses the field into a typed [Header]
+    pub fn parse(&self) -> Result<Header, InvalidHeader> {
+        Header::parse(self.name, &self.value())
+    }
+}
+/// A message or a MIME part of one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message<'a> {
+    raw: &'a [u8],
+    header_section: &'a [u8],
+    headers: Vec<RawHeader<'a>>,
+    body: &'a [u8],
+    /// Parsed once. Most of the accessors need it
+    content_type: Option<ContentType>,
+    default_media_type: &'static str,
+    multipart: Option<Multipart<'a>>,
+    embedded: Option<Box<Message<'a>>>,
+}
+impl<'a> Message<'a> {
+    /// Parses a message.
+    ///
+    /// Never fails. Anything that can not be understood ends up in the body
+    pub fn parse(raw: &'a [u8]) -> Self {
+        Self::parse_with_max_depth(raw, DEFAULT_MAX_DEPTH)
+    }
+    /// Parses a message. Only `max_depth` levels of nested parts are parsed.
+    ///
+    /// Parts below that are left unparsed in their parent's body
+    pub fn parse_with_max_depth(raw: &'a [u8], max_depth: usize) -> Self {
+        Self::parse_part(raw, "text/plain", max_depth)
+    }
+    pub(crate) fn parse_part(
+        raw: &'a [u8],
+        default_media_type: &'static str,
+        depth_left: usize,
+    ) -> Self {
+        let (headers, body_start) = parse_header_section(raw);
+        let content_type = headers
+            .iter()
+            .find(|header| header.is("Content-Type"))
+            .and_then(|header| header.value().parse().ok());
+        let mut message = Message {
+            raw,
+            header_section: &raw[..body_start.min(raw.len())],
+            headers,
+            body: &raw[body_start.min(raw.len())..],
+            content_type,
+            default_media_type,
+            multipart: None,
+            embedded: None,
+        };
+        if depth_left == 0 {
+            return message;
+        }
+        let media_type = message.media_type();
+        if let Some(subtype) = media_type.strip_prefix("multipart/") {
+            if let Some(boundary) = message.content_type_parameter("boundary") {
+                message.multipart = Some(Multipart::parse(
+                    message.body,
+                    MultipartSubtype::from(subtype),
+                    boundary,
+                    depth_left - 1,
+                ));
+            }
+        } else if media_type == "message/rfc822" {
+            message.embedded = Some(Box::new(Message::parse_part(
+                message.body,
+                "text/plain",
+                depth_left - 1,
+            )));
+        }
+        message
+    }
+    /// The bytes this message was parsed from
+    pub fn raw(&self) -> &'a [u8] {
+        self.raw
+    }
+    /// The header section. Including the empty line that ends it
+    pub fn raw_headers(&self) -> &'a [u8] {
+        self.header_section
+    }
+    /// The header fields in the order they appeared
+    pub fn headers(&self) -> &[RawHeader<'a>] {
+        &self.headers
+    }
+    /// The first header with the given name. Case-insensitive
+    pub fn header(&self, name: &str) -> Option<&RawHeader<'a>> {
+        self.headers.iter().find(|header| header.is(name))
+    }
+    /// All headers with the given name. Case-insensitive
+    pub fn header_all<'b>(&'b self, name: &'b str) -> impl Iterator<Item = &'b RawHeader<'a>> {
+        self.headers.iter().filter(move |header| header.is(name))
+    }
+    /// Parses every header into a typed [Header]
+    ///
+    /// Headers with an invalid value are kept as [Header::Other]. See [Header::parse_lenient]
+    pub fn typed_headers(&self) -> Result<Headers, InvalidHeader> {
+        self.headers
+            .iter()
+            .map(|header| Header::parse_lenient(header.name, &header.value()))
+            .collect()
+    }
+    /// Everything after the header section
+    pub fn body(&self) -> &'a [u8] {
+        self.body
+    }
+    /// The parts of a multipart body. Empty if the message is not multipart
+    pub fn parts(&self) -> &[Message<'a>] {
+        self.multipart
+            .as_ref()
+            .map_or(&[], |multipart| multipart.parts())
+    }
+    /// The split multipart body.
+    ///
+    /// `None` if the message is not multipart or the depth limit was reached
+    pub fn multipart(&self) -> Option<&Multipart<'a>> {
+        self.multipart.as_ref()
+    }
+    /// The parsed body of a `message/rfc822`
+    pub fn embedded_message(&self) -> Option<&Message<'a>> {
+        self.embedded.as_deref()
+    }
+    /// If the `Content-Type` is `multipart/*` with a boundary
+    pub fn is_multipart(&self) -> bool {
+        self.media_type().starts_with("multipart/")
+            && self.content_type_parameter("boundary").is_some()
+    }
+    /// The parsed `Content-Type`. `None` if it is missing or invalid
+    pub fn content_type(&self) -> Option<&ContentType> {
+        self.content_type.as_ref()
+    }
+    /// The parsed `Content-Disposition`. `None` if it is missing or invalid
+    pub fn content_disposition(&self) -> Option<ContentDisposition> {
+        self.header("Content-Disposition")?.value().parse().ok()
+    }
+    /// The `type/subtype` of the `Content-Type` in lowercase.
+    ///
+    /// Defaults to `text/plain` as RFC 2045 specifies. Including when the `Content-Type` is invalid.
+    /// Parts of a `multipart/digest` default to `message/rfc822`
+    pub fn media_type(&self) -> String {
+        self.content_type()
+            .map(|content_type| content_type.media_type())
+            .unwrap_or_else(|| self.default_media_type.to_owned())
+    }
+    /// A decoded parameter of the `Content-Type`. Name is case-insensitive
+    pub fn content_type_parameter(&self, name: &str) -> Option<String> {
+        self.content_type()?
+            .parameter(name)
+            .filter(|value| !value.is_empty())
+            .map(str::to_owned)
+    }
+}
+/// The end of the line starting at `start`. Not including the line break. And the start of the next line
+fn next_line(raw: &[u8], start: usize) -> (usize, usize) {
+    match raw[start..].iter().position(|byte| *byte == b'\n') {
+        Some(index) => {
+            let newline = start + index;
+            let end = if newline > start && raw[newline - 1] == b'\r' {
+                newline - 1
+            } else {
+                newline
+            };
+            (end, newline + 1)
+        }
+        None => (raw.len(), raw.len()),
+    }
+}
+fn is_field_name(name: &[u8]) -> bool {
+    !name.is_empty() && name.iter().all(|b| matches!(b, 33..=57 | 59..=126))
+}
+/// Returns the headers and where the body starts
+fn parse_header_section(raw: &[u8]) -> (Vec<RawHeader<'_>>, usize) {
+    let mut headers: Vec<RawHeader<'_>> = Vec::new();
+    // The start of the current field and where its value starts
+    let mut current: Option<(usize, usize, &str)> = None;
+    let mut position = 0;
+    while position < raw.len() {
+        let (end, next) = next_line(raw, position);
+        let line = &raw[position..end];
+        if line.is_empty() {
+            return (headers, next);
+        }
+        if matches!(line[0], b' ' | b'\t') && current.is_some() {
+            if let (Some((start, value_start, name)), Some(last)) = (current, headers.last_mut()) {
+                *last = RawHeader {
+                    raw: &raw[start..end],
+                    name,
+                    value: &raw[value_start..end],
+                };
+            }
+            position = next;
+            continue;
+        }
+        let Some(colon) = line.iter().position(|byte| *byte == b':') else {
+            return (headers, position);
+        };
+        let name = line[..colon].trim_ascii_end();
+        if !is_field_name(name) {
+            return (headers, position);
+        }
+        // is_field_name only allows ASCII
+        let name = std::str::from_utf8(name).unwrap_or_default();
+        let value_start = position + colon + 1;
+        current = Some((position, value_start, name));
+        headers.push(RawHeader {
+            raw: line,
+            name,
+            value: &raw[value_start..end],
+        });
+        position = next;
+    }
+    (headers, raw.len())
+}
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::Message;
+    use crate::headers::Header;
+
+    #[test]
+    fn test_parse() {
+        let raw = b"From: John Doe <jdoe@machine.example>\r\nSubject: Saying\r\n Hello\r\nMessage-ID: <1234@local.machine.example>\r\n\r\nThis is a message just to say hello.\r\n";
+        let message = Message::parse(raw);
+        assert_eq!(message.headers().len(), 3);
+        let subject = message.header("subject").unwrap();
+        assert_eq!(subject.raw(), b"Subject: Saying\r\n Hello");
+        assert_eq!(subject.raw_value(), b" Saying\r\n Hello");
+        assert_eq!(subject.value(), "Saying Hello");
+        assert_eq!(
+            subject.parse().unwrap(),
+            Header::Subject("Saying Hello".to_owned())
+        );
+        assert_eq!(message.body(), b"This is a message just to say hello.\r\n");
+        assert_eq!(message.typed_headers().unwrap().len(), 3);
+        assert!(!message.is_multipart());
+
+        let message = Message::parse(b"From: not an address\r\nSubject: Hi\r\n\r\nBody");
+        let headers = message.typed_headers().unwrap();
+        assert_eq!(
+            headers.get("from"),
+            Some(&Header::Other {
+                name: "From".to_owned(),
+                value: "not an address".to_owned()
+            })
+        );
+        assert_eq!(message.media_type(), "text/plain");
+    }
+    #[test]
+    fn test_bare_lf() {
+        let raw = b"To: a@example.com\nSubject: LF\n\tonly\n\nBody\n";
+        let message = Message::parse(raw);
+        assert_eq!(message.headers().len(), 2);
+        assert_eq!(message.header("Subject").unwrap().value(), "LF\tonly");
+        assert_eq!(message.body(), b"Body\n");
+
+        let message = Message::parse(b"Not a header\r\nSubject: x\r\n\r\n");
+        assert!(message.headers().is_empty());
+        assert_eq!(message.body(), b"Not a header\r\nSubject: x\r\n\r\n");
+    }
+    #[test]
+    fn test_nested_multipart() {
+        let raw = b"Content-Type: multipart/mixed; boundary=\"outer\"\r\n\
+\r\n\
+This is the preamble\r\n\
+--outer\r\n\
+Content-Type: multipart/alternative; boundary=inner\r\n\
+\r\n\
+--inner\r\n\
+Content-Type: text/plain\r\n\
+\r\n\
+Plain\r\n\
+--inner\r\n\
+Content-Type: text/html\r\n\
+\r\n\
+<p>HTML</p>\r\n\
+--inner--\r\n\
+--outer\r\n\
+\r\n\
+No headers\r\n\
+--outer--\r\n\
+Epilogue\r\n";
+        let message = Message::parse(raw);
+        assert!(message.is_multipart());
+        assert_eq!(message.parts().len(), 2);
+        let alternative = &message.parts()[0];
+        assert_eq!(alternative.media_type(), "multipart/alternative");
+        assert_eq!(alternative.parts().len(), 2);
+        assert_eq!(alternative.parts()[0].body(), b"Plain");
+        assert_eq!(alternative.parts()[1].media_type(), "text/html");
+        assert_eq!(alternative.parts()[1].body(), b"<p>HTML</p>");
+        let plain = &message.parts()[1];
+        assert!(plain.headers().is_empty());
+        assert_eq!(plain.body(), b"No headers");
+    }
+}
@@ -0,0 +1,294 @@
+/*!
+# Multipart
+Splitting of `multipart` bodies.
+
+Defined in [RFC 2046 Section 5.1](https://datatracker.ietf.org/doc/html/rfc2046#section-5.1)
+
+- The preamble and epilogue are kept but are not parts
+- Transport padding after a delimiter is ignored
+- A missing final boundary ends the last part at the end of the body. See [Multipart::is_closed]
+ */
+use std::fmt::Display;
+
+use super::{next_line, Message};
+
+/// The subtype of a `multipart` media type
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MultipartSubtype {
+    /// `multipart/mixed` [RFC 2046 Section 5.1.3](https://datatracker.ietf.org/doc/html/rfc2046#section-5.1.3)
+    Mixed,
+    /// `multipart/alternative` [RFC 2046 Section 5.1.4](https://datatracker.ietf.org/doc/html/rfc2046#section-5.1.4)
+    Alternative,
+    /// `multipart/related` [RFC 2387](https://datatracker.ietf.org/doc/html/rfc2387)
+    Related,
+    /// `multipart/digest` [RFC 2046 Section 5.1.5](https://datatracker.ietf.org/doc/html/rfc2046#section-5.1.5)
+    ///
+    /// Parts default to `message/rfc822`
+    Digest,
+    /// `multipart/signed` [RFC 1847 Section 2.1](https://datatracker.ietf.org/doc/html/rfc1847#section-2.1)
+    Signed,
+    /// Any other subtype. Treated as `mixed` as RFC 2046 requires
+    Other(String),
+}
+impl From<&str> for MultipartSubtype {
+    fn from(value: &str) -> Self {
+        match value.to_ascii_lowercase().as_str() {
+            "mixed" => MultipartSubtype::Mixed,
+            "alternative" => MultipartSubtype::Alternative,
+            "related" => MultipartSubtype::Related,
+            "digest" => MultipartSubtype::Digest,
+            "signed" => MultipartSubtype::Signed,
+            other => MultipartSubtype::Other(other.to_owned()),
+        }
+    }
+}
+impl Display for MultipartSubtype {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MultipartSubtype::Mixed => f.write_str("mixed"),
+            MultipartSubtype::Alternative => f.write_str("alternative"),
+            MultipartSubtype::Related => f.write_str("related"),
+            MultipartSubtype::Digest => f.write_str("digest"),
+            MultipartSubtype::Signed => f.write_str("signed"),
+            MultipartSubtype::Other(other) => f.write_str(other),
+        }
+    }
+}
+/// The body of a `multipart/*` entity
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Multipart<'a> {
+    subtype: MultipartSubtype,
+    boundary: String,
+    preamble: &'a [u8],
+    parts: Vec<Message<'a>>,
+    epilogue: &'a [u8],
+    closed: bool,
+}
+impl<'a> Multipart<'a> {
+    /// Splits `body` on `boundary`. Parts are parsed with `depth_left` levels of nesting remaining
+    pub(crate) fn parse(
+        body: &'a [u8],
+        subtype: MultipartSubtype,
+        boundary: String,
+        depth_left: usize,
+    ) -> Self {
+        let default_media_type = if subtype == MultipartSubtype::Digest {
+            "message/rfc822"
+        } else {
+            "text/plain"
+        };
+        let split = split(body, &boundary);
+        Multipart {
+            subtype,
+            boundary,
+            preamble: split.preamble,
+            parts: split
+                .parts
+                .into_iter()
+                .map(|part| Message::parse_part(part, default_media_type, depth_left))
+                .collect(),
+            epilogue: split.epilogue,
+            closed: split.closed,
+        }
+    }
+    pub fn subtype(&self) -> &MultipartSubtype {
+        &self.subtype
+    }
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+    /// Everything before the first delimiter. Should be ignored
+    pub fn preamble(&self) -> &'a [u8] {
+        self.preamble
+    }
+    pub fn parts(&self) -> &[Message<'a>] {
+        &self.parts
+    }
+    /// Everything after the final delimiter. Should be ignored
+    pub fn epilogue(&self) -> &'a [u8] {
+        self.epilogue
+    }
+    /// If the final delimiter `--boundary--` was found
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+    /// The last part of a `multipart/alternative`. The sender's preferred version
+    pub fn preferred(&self) -> Option<&Message<'a>> {
+        self.parts.last()
+    }
+    /// The root of a `multipart/related`.
+    ///
+    /// The part whose `Content-ID` matches the `start` parameter. Otherwise the first part
+    pub fn root(&self, start: Option<&str>) -> Option<&Message<'a>> {
+        start
+            .and_then(|start| {
+                self.parts.iter().find(|part| {
+                    part.header("Content-ID")
+                        .is_some_and(|content_id| content_id.value() == start)
+                })
+            })
+            .or_else(|| self.parts.first())
+    }
+    /// The first part of a `multipart/signed`. The content that was signed
+    pub fn signed_content(&self) -> Option<&Message<'a>> {
+        (self.subtype == MultipartSubtype::Signed)
+            .then(|| self.parts.first())
+            .flatten()
+    }
+    /// The second part of a `multipart/signed`. Its type is the `protocol` parameter
+    pub fn signature(&self) -> Option<&Message<'a>> {
+        (self.subtype == MultipartSubtype::Signed)
+            .then(|| self.parts.get(1))
+            .flatten()
+    }
+}
+struct Split<'a> {
+    preamble: &'a [u8],
+    parts: Vec<&'a [u8]>,
+    epilogue: &'a [u8],
+    closed: bool,
+}
+/// The line break before a delimiter belongs to the delimiter
+fn split<'a>(body: &'a [u8], boundary: &str) -> Split<'a> {
+    let delimiter = format!("--{}", boundary);
+    let mut split = Split {
+        preamble: body,
+        parts: Vec::new(),
+        epilogue: &[],
+        closed: false,
+    };
+    let mut part_start = None;
+    let mut position = 0;
+    while position < body.len() {
+        let (end, next) = next_line(body, position);
+        let line = &body[position..end];
+        if let Some(rest) = line.strip_prefix(delimiter.as_bytes()) {
+            let (closing, padding) = match rest.strip_prefix(b"--") {
+                Some(padding) => (true, padding),
+                None => (false, rest),
+            };
+            if padding.iter().all(|byte| matches!(byte, b' ' | b'\t')) {
+                let content_end = line_break_start(body, position);
+                match part_start {
+                    Some(start) => split.parts.push(&body[start..content_end.max(start)]),
+                    None => split.preamble = &body[..content_end],
+                }
+                if closing {
+                    split.epilogue = &body[next..];
+                    split.closed = true;
+                    return split;
+                }
+                part_start = Some(next);
+            }
+        }
+        position = next;
+    }
+    if let Some(start) = part_start {
+        split.parts.push(&body[start.min(body.len())..]);
+    }
+    split
+}
+/// Where the line break before `line_start` starts
+fn line_break_start(raw: &[u8], line_start: usize) -> usize {
+    let mut start = line_start;
+    if start > 0 && raw[start - 1] == b'\n' {
+        start -= 1;
+        if start > 0 && raw[start - 1] == b'\r' {
+            start -= 1;
+        }
+    }
+    start
+}
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::MultipartSubtype;
+    use crate::message::Message;
+
+    #[test]
+    fn test_split() {
+        let raw = b"Content-Type: multipart/mixed; boundary=\"simple boundary\"\r\n\
+\r\n\
+This is the preamble.\r\n\
+--simple boundary  \r\n\
+\r\n\
+Implicitly typed plain US-ASCII text.\r\n\
+--simple boundary\r\n\
+Content-type: text/plain; charset=us-ascii\r\n\
+\r\n\
+Explicitly typed.\r\n\
+\r\n\
+--simple boundary-- \r\n\
+This is the epilogue.\r\n";
+        let message = Message::parse(raw);
+        let multipart = message.multipart().unwrap();
+        assert_eq!(multipart.subtype(), &MultipartSubtype::Mixed);
+        assert_eq!(multipart.boundary(), "simple boundary");
+        assert_eq!(multipart.preamble(), b"This is the preamble.");
+        assert_eq!(multipart.epilogue(), b"This is the epilogue.\r\n");
+        assert!(multipart.is_closed());
+        assert_eq!(multipart.parts().len(), 2);
+        assert_eq!(
+            multipart.parts()[0].body(),
+            b"Implicitly typed plain US-ASCII text."
+        );
+        assert_eq!(multipart.parts()[1].body(), b"Explicitly typed.\r\n");
+    }
+    #[test]
+    fn test_missing_final_boundary() {
+        let raw = b"Content-Type: multipart/alternative; boundary=b\n\n--b\n\nfirst\n--b\nContent-Type: text/html\n\n<p>second</p>\n";
+        let message = Message::parse(raw);
+        let multipart = message.multipart().unwrap();
+        assert!(!multipart.is_closed());
+        assert!(multipart.preamble().is_empty());
+        assert_eq!(multipart.parts().len(), 2);
+        assert_eq!(multipart.parts()[1].body(), b"<p>second</p>\n");
+        assert_eq!(multipart.preferred().unwrap().media_type(), "text/html");
+    }
+    #[test]
+    fn test_digest_and_signed() {
+        let raw = b"Content-Type: multipart/digest; boundary=d\r\n\r\n--d\r\n\r\nSubject: first\r\n\r\nbody\r\n--d--\r\n";
+        let message = Message::parse(raw);
+        let part = &message.parts()[0];
+        assert_eq!(part.media_type(), "message/rfc822");
+        let embedded = part.embedded_message().unwrap();
+        assert_eq!(embedded.header("Subject").unwrap().value(), "first");
+        assert_eq!(embedded.body(), b"body");
+
+        let raw = b"Content-Type: multipart/signed; protocol=\"application/pgp-signature\";\r\n micalg=pgp-sha256; boundary=s\r\n\r\n--s\r\n\r\nsigned\r\n--s\r\nContent-Type: application/pgp-signature\r\n\r\nsignature\r\n--s--\r\n";
+        let message = Message::parse(raw);
+        assert_eq!(
+            message.content_type_parameter("protocol").as_deref(),
+            Some("application/pgp-signature")
+        );
+        let multipart = message.multipart().unwrap();
+        assert_eq!(multipart.signed_content().unwrap().body(), b"signed");
+        assert_eq!(
+            multipart.signature().unwrap().media_type(),
+            "application/pgp-signature"
+        );
+    }
+    #[test]
+    fn test_depth_limit() {
+        let mut raw = Vec::new();
+        for depth in 0..100 {
+            raw.extend_from_slice(
+                format!(
+                    "Content-Type: multipart/mixed; boundary={}\r\n\r\n--{}\r\n",
+                    depth, depth
+                )
+                .as_bytes(),
+            );
+        }
+        let mut message = Message::parse_with_max_depth(&raw, 3);
+        let mut depth = 0;
+        while let Some(part) = message.parts().first() {
+            message = part.clone();
+            depth += 1;
+        }
+        assert_eq!(depth, 3);
+        assert!(message.is_multipart());
+        assert!(message.multipart().is_none());
+    }
+}
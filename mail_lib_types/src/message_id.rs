@@ -0,0 +1,209 @@
+/*!
+# MessageId
+
+The `msg-id` used in the `Message-ID`, `In-Reply-To` and `References` headers.
+
+Defined in [RFC 5322 Section 3.6.4](https://datatracker.ietf.org/doc/html/rfc5322#section-3.6.4)
+ */
+#[cfg(feature = "rand")]
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{fmt::Display, str::FromStr};
+
+use chumsky::{error::Cheap, Parser};
+use thiserror::Error;
+
+use crate::parsers::rfcs::rfc5322::{msg_id, msg_id_list};
+
+/// An error that occurs when parsing a [MessageId]
+#[derive(Debug, Clone, PartialEq, Hash, Error)]
+pub struct InvalidMessageId {
+    /// The spans that caused the error
+    pub spans: Vec<Cheap>,
+    /// The context of the error
+    pub ctx: Option<String>,
+}
+impl Display for InvalidMessageId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Invalid Message-ID")?;
+        if let Some(context) = self.ctx.as_ref() {
+            writeln!(f, "Context: {}", context)?;
+            for span in &self.spans {
+                writeln!(f, "    {}", span)?;
+            }
+        }
+        Ok(())
+    }
+}
+/// A Message-ID. `<id-left@id-right>`
+///
+/// Stored without the `<` and `>`. [Display] adds them back
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MessageId {
+    id: String,
+    at_index: usize,
+}
+impl MessageId {
+    /// Creates a [MessageId] from its parts. Checks that they are valid
+    pub fn from_parts(
+        id_left: impl AsRef<str>,
+        id_right: impl AsRef<str>,
+    ) -> Result<Self, InvalidMessageId> {
+        format!("<{}@{}>", id_left.as_ref(), id_right.as_ref()).parse()
+    }
+    /// Generates a new globally unique [MessageId] for the given domain
+    ///
+    /// The id-left is the current time in milliseconds followed by 64 random bits.
+    /// `<18c4b5d1e2f.9a0b1c2d3e4f5a6b@example.com>`
+    #[cfg(feature = "rand")]
+    pub fn generate(domain: impl AsRef<str>) -> Result<Self, InvalidMessageId> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let id_left = format!("{:x}.{:016x}", timestamp, rand::random::<u64>());
+        MessageId::from_parts(id_left, domain)
+    }
+    /// Parses one or more [MessageId]s separated by whitespace or comments.
+    ///
+    /// Used by `In-Reply-To` and `References`
+    pub fn parse_list(value: &str) -> Result<Vec<Self>, InvalidMessageId> {
+        msg_id_list()
+            .parse(value)
+            .into_result()
+            .map(|ids| {
+                ids.into_iter()
+                    .map(|(id_left, id_right)| MessageId::new_from_parts(id_left, id_right))
+                    .collect()
+            })
+            .map_err(|spans| InvalidMessageId {
+                spans,
+                ctx: Some(value.to_owned()),
+            })
+    }
+    fn new_from_parts(id_left: &str, id_right: &str) -> Self {
+        MessageId {
+            id: format!("{}@{}", id_left, id_right),
+            at_index: id_left.len(),
+        }
+    }
+    /// The part before the `@`
+    pub fn id_left(&self) -> &str {
+        &self.id[..self.at_index]
+    }
+    /// The part after the `@`
+    pub fn id_right(&self) -> &str {
+        &self.id[self.at_index + 1..]
+    }
+    /// The id without the `<` and `>`
+    pub fn as_str(&self) -> &str {
+        &self.id
+    }
+}
+impl AsRef<str> for MessageId {
+    fn as_ref(&self) -> &str {
+        &self.id
+    }
+}
+/// Formats as `<id-left@id-right>`
+impl Display for MessageId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<{}>", self.id)
+    }
+}
+/// Parses a `msg-id`. The `<` and `>` are required
+impl FromStr for MessageId {
+    type Err = InvalidMessageId;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        msg_id()
+            .parse(value)
+            .into_result()
+            .map(|(id_left, id_right)| MessageId::new_from_parts(id_left, id_right))
+            .map_err(|spans| InvalidMessageId {
+                spans,
+                ctx: Some(value.to_owned()),
+            })
+    }
+}
+impl TryFrom<&str> for MessageId {
+    type Error = InvalidMessageId;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        MessageId::from_str(value)
+    }
+}
+impl TryFrom<String> for MessageId {
+    type Error = InvalidMessageId;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        MessageId::from_str(&value)
+    }
+}
+#[cfg(feature = "serde")]
+mod _serde {
+    use serde::{Deserialize, Serialize};
+
+    use super::MessageId;
+    /// Serialized as `<id-left@id-right>`
+    impl Serialize for MessageId {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            serializer.collect_str(self)
+        }
+    }
+    impl<'de> Deserialize<'de> for MessageId {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            let value = String::deserialize(deserializer)?;
+            MessageId::try_from(value).map_err(serde::de::Error::custom)
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::MessageId;
+
+    #[test]
+    fn test_parse() {
+        let id: MessageId = "<1234@local.machine.example>".parse().unwrap();
+        assert_eq!(id.id_left(), "1234");
+        assert_eq!(id.id_right(), "local.machine.example");
+        assert_eq!(id.to_string(), "<1234@local.machine.example>");
+
+        let id: MessageId = "<abcd.1234@[127.0.0.1]>".parse().unwrap();
+        assert_eq!(id.id_right(), "[127.0.0.1]");
+
+        // obs-id-left and obs-id-right
+        let id: MessageId = " <\"old style\"@example.com> (comment)".parse().unwrap();
+        assert_eq!(id.id_left(), "\"old style\"");
+
+        assert!("1234@local.machine.example".parse::<MessageId>().is_err());
+        assert!("<1234>".parse::<MessageId>().is_err());
+        assert!("<a@b> <c@d>".parse::<MessageId>().is_err());
+
+        let ids = MessageId::parse_list("<1@example.net>\r\n <2@example.net>").unwrap();
+        assert_eq!(ids.len(), 2);
+        assert_eq!(ids[1].as_str(), "2@example.net");
+    }
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_generate() {
+        let first = MessageId::generate("example.com").unwrap();
+        let second = MessageId::generate("example.com").unwrap();
+        assert_ne!(first, second);
+        assert_eq!(first.id_right(), "example.com");
+        assert_eq!(first.to_string().parse::<MessageId>().unwrap(), first);
+        assert!(MessageId::generate("not a domain").is_err());
+    }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let id: MessageId = "<1234@example.com>".parse().unwrap();
+        let json = serde_json::to_string(&id).unwrap();
+        assert_eq!(json, r#""<1234@example.com>""#);
+        assert_eq!(serde_json::from_str::<MessageId>(&json).unwrap(), id);
+    }
+}
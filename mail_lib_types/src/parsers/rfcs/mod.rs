@@ -2,6 +2,9 @@
  * Parsers for Email RFCs. Using Chumsky
  */
 #![allow(dead_code)]
+pub mod rfc2045;
+pub mod rfc2183;
+pub mod rfc2231;
 pub mod rfc2234;
 pub mod rfc2822;
 pub mod rfc5234;
@@ -0,0 +1,110 @@
+/*!
+ * Parsers for [RFC 2045](https://datatracker.ietf.org/doc/html/rfc2045)
+ */
+use chumsky::prelude::*;
+
+use super::{rfc2231::parameter, rfc5322::cfws};
+use crate::{content_type::RawParameter, parsers::ErrType};
+
+/// ```ebnf
+/// tspecials :=  "(" / ")" / "<" / ">" / "@" /
+///               "," / ";" / ":" / "\" / <">
+///               "/" / "[" / "]" / "?" / "="
+/// ```
+pub const TSPECIALS: &str = "()<>@,;:\\\"/[]?=";
+
+pub(crate) fn is_token_char(c: char) -> bool {
+    c.is_ascii_graphic() && !TSPECIALS.contains(c)
+}
+/// ```ebnf
+/// token := 1*<any (US-ASCII) CHAR except SPACE, CTLs,
+///             or tspecials>
+/// ```
+pub fn token<'a>() -> impl Parser<'a, &'a str, &'a str, ErrType<'a>> + Clone {
+    any()
+        .filter(|c: &char| is_token_char(*c))
+        .repeated()
+        .at_least(1)
+        .to_slice()
+}
+/// The inside of a quoted-string. Still escaped.
+///
+/// Non-ASCII is allowed as [RFC 6532](https://datatracker.ietf.org/doc/html/rfc6532#section-3.2) does
+pub fn quoted_value<'a>() -> impl Parser<'a, &'a str, &'a str, ErrType<'a>> + Clone {
+    choice((
+        just('\\').then(any()).to_slice(),
+        none_of("\"\\").to_slice(),
+    ))
+    .repeated()
+    .to_slice()
+    .delimited_by(just('"'), just('"'))
+}
+/// ```ebnf
+/// value := token / quoted-string
+/// ```
+///
+/// Returns the value and if it was quoted
+pub fn value<'a>() -> impl Parser<'a, &'a str, (&'a str, bool), ErrType<'a>> + Clone {
+    choice((
+        quoted_value().map(|value| (value, true)),
+        token().map(|value| (value, false)),
+    ))
+}
+/// `*(";" parameter)`. A trailing `;` is allowed
+pub fn parameters<'a>() -> impl Parser<'a, &'a str, Vec<RawParameter<'a>>, ErrType<'a>> {
+    just(';')
+        .padded_by(cfws().or_not())
+        .ignore_then(parameter())
+        .repeated()
+        .collect()
+        .then_ignore(just(';').padded_by(cfws().or_not()).or_not())
+}
+/// ```ebnf
+/// content := "Content-Type" ":" type "/" subtype
+///            *(";" parameter)
+/// ```
+///
+/// Returns the type, subtype and parameters
+#[allow(clippy::type_complexity)]
+pub fn content_type<'a>(
+) -> impl Parser<'a, &'a str, (&'a str, &'a str, Vec<RawParameter<'a>>), ErrType<'a>> {
+    token()
+        .padded_by(cfws().or_not())
+        .then_ignore(just('/'))
+        .then(token().padded_by(cfws().or_not()))
+        .then(parameters())
+        .map(|((main_type, subtype), parameters)| (main_type, subtype, parameters))
+}
+
+#[cfg(test)]
+mod tests {
+    use chumsky::Parser;
+    use pretty_assertions::assert_eq;
+
+    use super::content_type;
+
+    #[test]
+    pub fn test_content_type() {
+        let (main_type, subtype, parameters) = content_type()
+            .parse("text/plain; charset=\"us-ascii\" (Plain text)")
+            .into_result()
+            .unwrap();
+        assert_eq!((main_type, subtype), ("text", "plain"));
+        assert_eq!(parameters.len(), 1);
+        assert_eq!(parameters[0].name, "charset");
+        assert_eq!(parameters[0].value, "us-ascii");
+        assert!(parameters[0].quoted);
+
+        let (_, _, parameters) = content_type()
+            .parse("multipart/mixed ; boundary=\"simple \\\"boundary\\\"\";")
+            .into_result()
+            .unwrap();
+        assert_eq!(parameters[0].value, "simple \\\"boundary\\\"");
+
+        assert!(content_type().parse("text").into_result().is_err());
+        assert!(content_type()
+            .parse("text/plain; =x")
+            .into_result()
+            .is_err());
+    }
+}
@@ -0,0 +1,25 @@
+/*!
+ * Parsers for [RFC 2183](https://datatracker.ietf.org/doc/html/rfc2183)
+ */
+use chumsky::prelude::*;
+
+use super::{
+    rfc2045::{parameters, token},
+    rfc5322::cfws,
+};
+use crate::{content_type::RawParameter, parsers::ErrType};
+
+/// ```ebnf
+/// disposition := "Content-Disposition" ":"
+///                disposition-type
+///                *(";" disposition-parm)
+/// disposition-type := "inline"
+///                   / "attachment"
+///                   / extension-token
+/// ```
+///
+/// Returns the disposition type and parameters
+pub fn disposition<'a>() -> impl Parser<'a, &'a str, (&'a str, Vec<RawParameter<'a>>), ErrType<'a>>
+{
+    token().padded_by(cfws().or_not()).then(parameters())
+}
@@ -0,0 +1,166 @@
+/*!
+ * Parsers for [RFC 2231](https://datatracker.ietf.org/doc/html/rfc2231)
+ */
+use chumsky::prelude::*;
+
+use super::{
+    rfc2045::{is_token_char, value},
+    rfc5322::cfws,
+};
+use crate::{content_type::RawParameter, parsers::ErrType};
+
+pub(crate) fn is_attribute_char(c: char) -> bool {
+    is_token_char(c) && !matches!(c, '*' | '\'' | '%')
+}
+/// ```ebnf
+/// attribute := 1*attribute-char
+/// attribute-char := <any (US-ASCII) CHAR except SPACE, CTLs,
+///                   "*", "'", "%", or tspecials>
+/// ```
+pub fn attribute<'a>() -> impl Parser<'a, &'a str, &'a str, ErrType<'a>> + Clone {
+    any()
+        .filter(|c: &char| is_attribute_char(*c))
+        .repeated()
+        .at_least(1)
+        .to_slice()
+}
+/// ```ebnf
+/// section := initial-section / other-sections
+/// initial-section := "*0"
+/// other-sections := "*" ("1" / "2" / "3" / "4" / "5" /
+///                        "6" / "7" / "8" / "9") *DIGIT)
+/// ```
+pub fn section<'a>() -> impl Parser<'a, &'a str, u32, ErrType<'a>> + Clone {
+    just('*').ignore_then(
+        one_of('0'..='9')
+            .repeated()
+            .at_least(1)
+            .to_slice()
+            .filter(|digits: &&str| digits.parse::<u32>().is_ok())
+            .map(|digits: &str| digits.parse().unwrap_or_default()),
+    )
+}
+/// ```ebnf
+/// parameter := regular-parameter / extended-parameter
+///
+/// regular-parameter := regular-parameter-name "=" value
+/// regular-parameter-name := attribute [section]
+///
+/// extended-parameter := (extended-initial-name "="
+///                        extended-initial-value) /
+///                       (extended-other-names "="
+///                        extended-other-values)
+/// extended-initial-name := attribute [initial-section] "*"
+/// extended-other-names := attribute other-sections "*"
+/// ```
+///
+/// Extended values are not decoded. See [extended_initial_value] and [extended_other_values]
+pub fn parameter<'a>() -> impl Parser<'a, &'a str, RawParameter<'a>, ErrType<'a>> {
+    attribute()
+        .then(section().or_not())
+        .then(just('*').or_not().map(|star| star.is_some()))
+        .then_ignore(just('=').padded_by(cfws().or_not()))
+        .then(value())
+        .then_ignore(cfws().or_not())
+        .map(
+            |(((name, section), extended), (value, quoted))| RawParameter {
+                name,
+                section,
+                extended,
+                value,
+                quoted,
+            },
+        )
+}
+/// ```ebnf
+/// ext-octet := "%" 2(DIGIT / "A" / "B" / "C" / "D" / "E" / "F")
+/// ```
+///
+/// Lowercase hex digits are accepted
+pub fn ext_octet<'a>() -> impl Parser<'a, &'a str, u8, ErrType<'a>> + Clone {
+    just('%').ignore_then(
+        one_of("0123456789ABCDEFabcdef")
+            .repeated()
+            .exactly(2)
+            .to_slice()
+            .map(|hex: &str| u8::from_str_radix(hex, 16).unwrap_or_default()),
+    )
+}
+/// ```ebnf
+/// extended-other-values := *(ext-octet / attribute-char)
+/// ```
+///
+/// Returns the percent-decoded bytes
+pub fn extended_other_values<'a>() -> impl Parser<'a, &'a str, Vec<u8>, ErrType<'a>> + Clone {
+    choice((
+        ext_octet().map(|octet| vec![octet]),
+        any()
+            .filter(|c: &char| is_attribute_char(*c))
+            .map(|c: char| vec![c as u8]),
+    ))
+    .repeated()
+    .collect::<Vec<_>>()
+    .map(|octets| octets.concat())
+}
+/// ```ebnf
+/// extended-initial-value := [charset] "'" [language] "'"
+///                           extended-other-values
+/// ```
+///
+/// Returns the charset, language and percent-decoded bytes
+#[allow(clippy::type_complexity)]
+pub fn extended_initial_value<'a>(
+) -> impl Parser<'a, &'a str, (Option<&'a str>, Option<&'a str>, Vec<u8>), ErrType<'a>> {
+    let tag = none_of("'").repeated().to_slice();
+    tag.then_ignore(just('\''))
+        .then(tag)
+        .then_ignore(just('\''))
+        .then(extended_other_values())
+        .map(|((charset, language), octets): ((&str, &str), Vec<u8>)| {
+            (
+                Some(charset).filter(|charset| !charset.is_empty()),
+                Some(language).filter(|language| !language.is_empty()),
+                octets,
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use chumsky::Parser;
+    use pretty_assertions::assert_eq;
+
+    use super::{extended_initial_value, extended_other_values, parameter};
+
+    #[test]
+    pub fn test_parameter() {
+        let raw = parameter()
+            .parse("title*0*=us-ascii'en'This%20is%20even%20more%20")
+            .into_result()
+            .unwrap();
+        assert_eq!(raw.name, "title");
+        assert_eq!(raw.section, Some(0));
+        assert!(raw.extended);
+        assert_eq!(
+            extended_initial_value().parse(raw.value).into_result(),
+            Ok((Some("us-ascii"), Some("en"), b"This is even more ".to_vec()))
+        );
+
+        let raw = parameter()
+            .parse("title*2=\"isn't it!\"")
+            .into_result()
+            .unwrap();
+        assert_eq!((raw.section, raw.extended), (Some(2), false));
+        assert_eq!(raw.value, "isn't it!");
+
+        assert_eq!(
+            extended_other_values().parse("%2A%2a").into_result(),
+            Ok(b"**".to_vec())
+        );
+        assert_eq!(
+            extended_initial_value().parse("''a").into_result(),
+            Ok((None, None, b"a".to_vec()))
+        );
+        assert!(extended_other_values().parse("%2").into_result().is_err());
+    }
+}
@@ -39,7 +39,7 @@ pub fn lf<'a>() -> impl Parser<'a, &'a str, char, ErrType<'a>> {
 /// ```ebnf
 /// CRLF =  CR LF ; Internet standard newline
 /// ```
-pub fn crlf<'a>() -> impl Parser<'a, &'a str, (char, char), ErrType<'a>> {
+pub fn crlf<'a>() -> impl Parser<'a, &'a str, (char, char), ErrType<'a>> + Clone {
     just('\r').then(just('\n'))
 }
 
@@ -86,7 +86,7 @@ pub fn htab<'a>() -> impl Parser<'a, &'a str, char, ErrType<'a>> {
 /// ```ebnf
 /// WSP =  SP / HTAB ; white space
 /// ```
-pub fn wsp<'a>() -> impl Parser<'a, &'a str, char, ErrType<'a>> {
+pub fn wsp<'a>() -> impl Parser<'a, &'a str, char, ErrType<'a>> + Clone {
     one_of([
         '\x20', // SP
         '\x09', // HTAB
@@ -2,6 +2,8 @@
  * Parsers for [RFC 2822](https://datatracker.ietf.org/doc/html/rfc2822)
  */
 
+use std::borrow::Cow;
+
 use chumsky::prelude::*;
 
 use super::rfc2234::{self, alpha, crlf, digit, dquote, wsp};
@@ -11,7 +13,7 @@ use crate::{mail_box::RawMailBox, parsers::ErrType};
 /// ```ebnf
 ///    FWS             =   ([*WSP CRLF] 1*WSP) /  obs-FWS
 /// ```
-pub fn fws<'a>() -> impl Parser<'a, &'a str, &'a str, ErrType<'a>> {
+pub fn fws<'a>() -> impl Parser<'a, &'a str, &'a str, ErrType<'a>> + Clone {
     let rfc2822_fws = {
         let wsp_then_crlf = wsp().repeated().then(crlf()).to_slice().or_not();
         wsp_then_crlf
@@ -266,7 +268,9 @@ pub fn name_addr<'a>() -> impl Parser<'a, &'a str, RawMailBox<'a>, ErrType<'a>>
         .or_not()
         .padded()
         .then(angle_addr())
-        .map(|(display_name, (local, domain))| RawMailBox::new(display_name, local, domain))
+        .map(|(display_name, (local, domain))| {
+            RawMailBox::new(display_name.map(Cow::Borrowed), local, domain)
+        })
 }
 /// ```ebnf
 /// mailbox         =       name-addr / addr-spec
@@ -5,7 +5,7 @@ use crate::parsers::ErrType;
 /// ```ebnf
 /// vchar           =  %x21-7E ; visible (printing) characters
 /// ```
-pub fn vchar<'a>() -> impl Parser<'a, &'a str, char, ErrType<'a>> {
+pub fn vchar<'a>() -> impl Parser<'a, &'a str, char, ErrType<'a>> + Clone {
     one_of('\x21'..='\x7E')
 }
 
@@ -1,7 +1,14 @@
+use std::borrow::Cow;
+
 use chumsky::prelude::*;
 
 use super::rfc5234::*;
-use crate::{mail_box::RawMailBox, parsers::ErrType};
+use crate::{
+    address::RawAddress,
+    date_time::{day_from_name, month_from_name, zone_from_name, RawDateTime},
+    mail_box::RawMailBox,
+    parsers::ErrType,
+};
 
 ///
 /// ```ebnf
@@ -21,7 +28,7 @@ pub fn qtext<'a>() -> impl Parser<'a, &'a str, &'a str, ErrType<'a>> {
 /// ```ebnf
 /// quoted-pair     =       ("\" (VCHAR / WSP)) / obs-qp
 /// ```
-pub fn quoted_pair<'a>() -> impl Parser<'a, &'a str, &'a str, ErrType<'a>> {
+pub fn quoted_pair<'a>() -> impl Parser<'a, &'a str, &'a str, ErrType<'a>> + Clone {
     just('\\').then(vchar()).to_slice()
 }
 /// ```ebnf
@@ -72,9 +79,54 @@ pub fn quoted_string_strip_quotes<'a>() -> impl Parser<'a, &'a str, &'a str, Err
 }
 // These are the same as in rfc2822
 #[doc(inline)]
-pub use super::rfc2822::{
-    atext, atom, ccontent, cfws, comment, ctext, dot_atom, dot_atom_text, fws,
-};
+pub use super::rfc2822::{atext, atom, dot_atom, dot_atom_text, fws};
+/// ```ebnf
+/// ctext           =   %d33-39 /          ; Printable US-ASCII
+///                     %d42-91 /          ;  characters not including
+///                     %d93-126 /         ;  "(", ")", or "\"
+///                     obs-ctext
+/// ```
+pub fn ctext<'a>() -> impl Parser<'a, &'a str, &'a str, ErrType<'a>> + Clone {
+    choice((
+        one_of('\x21'..='\x27'), // ASCII 33-39
+        one_of('\x2A'..='\x5B'), // ASCII 42-91
+        one_of('\x5D'..='\x7E'), // ASCII 93-126
+    ))
+    .to_slice()
+}
+/// ```ebnf
+/// ccontent        =   ctext / quoted-pair / comment
+/// comment         =   "(" *([FWS] ccontent) [FWS] ")"
+/// ```
+///
+/// Comments can be nested
+pub fn comment<'a>() -> impl Parser<'a, &'a str, &'a str, ErrType<'a>> + Clone {
+    recursive(|comment| {
+        let ccontent = choice((ctext(), quoted_pair(), comment));
+        fws()
+            .or_not()
+            .then(ccontent)
+            .repeated()
+            .then(fws().or_not())
+            .delimited_by(just('('), just(')'))
+            .to_slice()
+    })
+}
+/// ```ebnf
+/// CFWS            =   (1*([FWS] comment) [FWS]) / FWS
+/// ```
+pub fn cfws<'a>() -> impl Parser<'a, &'a str, &'a str, ErrType<'a>> {
+    choice((
+        fws()
+            .or_not()
+            .then(comment())
+            .repeated()
+            .at_least(1)
+            .then(fws().or_not())
+            .to_slice(),
+        fws(),
+    ))
+}
 /// ```ebnf
 /// word            =       atom / quoted-string
 /// ```
@@ -90,16 +142,72 @@ pub fn word_strip_quotes<'a>() -> impl Parser<'a, &'a str, &'a str, ErrType<'a>>
 /// ```
 pub fn pharse<'a>() -> impl Parser<'a, &'a str, &'a str, ErrType<'a>> {
     // TODO: obs-phrase
-    word()
+    word().repeated().at_least(1).to_slice().map(str::trim)
 }
 
 /// ```ebnf
 /// display-name    =       phrase
 /// ```
 ///
-/// This does not call `phrase` because I want the `"` to be stripped
-pub fn display_name<'a>() -> impl Parser<'a, &'a str, &'a str, ErrType<'a>> {
-    word_strip_quotes()
+/// Returns the decoded phrase. See [decode_phrase]
+pub fn display_name<'a>() -> impl Parser<'a, &'a str, Cow<'a, str>, ErrType<'a>> {
+    word().repeated().at_least(1).to_slice().map(decode_phrase)
+}
+/// Decodes an already parsed phrase.
+///
+/// The `"` and the `\` of quoted-pairs are removed and CFWS is dropped.
+/// The words are joined with a single space. Borrowed if the phrase is only atoms separated by single spaces
+pub fn decode_phrase(phrase: &str) -> Cow<'_, str> {
+    let trimmed = phrase.trim();
+    if !trimmed.contains(['"', '\\', '(', '\r', '\n', '\t']) && !trimmed.contains("  ") {
+        return Cow::Borrowed(trimmed);
+    }
+    let mut words: Vec<String> = Vec::new();
+    // If the last word is an atom that is still being read
+    let mut in_atom = false;
+    let mut chars = phrase.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                let mut word = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => word.extend(chars.next()),
+                        // FWS is unfolded
+                        '\r' | '\n' => {}
+                        c => word.push(c),
+                    }
+                }
+                words.push(word);
+                in_atom = false;
+            }
+            '(' => {
+                let mut depth = 1;
+                while depth > 0 {
+                    match chars.next() {
+                        Some('(') => depth += 1,
+                        Some(')') => depth -= 1,
+                        Some('\\') => {
+                            chars.next();
+                        }
+                        Some(_) => {}
+                        None => break,
+                    }
+                }
+                in_atom = false;
+            }
+            c if c.is_whitespace() => in_atom = false,
+            c => match words.last_mut() {
+                Some(word) if in_atom => word.push(c),
+                _ => {
+                    words.push(c.to_string());
+                    in_atom = true;
+                }
+            },
+        }
+    }
+    Cow::Owned(words.join(" "))
 }
 
 /// ```ebnf
@@ -164,14 +272,239 @@ pub fn mailbox<'a>() -> impl Parser<'a, &'a str, RawMailBox<'a>, ErrType<'a>> {
         addr_spec().map(|(local, domain)| RawMailBox::new_no_name(local, domain)),
     ))
 }
+/// ```ebnf
+/// mailbox-list    =   (mailbox *("," mailbox)) / obs-mbox-list
+/// ```
+pub fn mailbox_list<'a>() -> impl Parser<'a, &'a str, Vec<RawMailBox<'a>>, ErrType<'a>> {
+    mailbox()
+        .padded()
+        .separated_by(just(','))
+        .at_least(1)
+        .collect()
+}
+/// ```ebnf
+/// group           =   display-name ":" [group-list] ";" [CFWS]
+/// group-list      =   mailbox-list / CFWS / obs-group-list
+/// ```
+pub fn group<'a>() -> impl Parser<'a, &'a str, RawAddress<'a>, ErrType<'a>> {
+    display_name()
+        .padded()
+        .then_ignore(just(':'))
+        .then(mailbox_list().or_not())
+        .then_ignore(just(';').padded())
+        .map(|(name, members)| RawAddress::Group {
+            name,
+            members: members.unwrap_or_default(),
+        })
+}
+/// ```ebnf
+/// address         =   mailbox / group
+/// ```
+pub fn address<'a>() -> impl Parser<'a, &'a str, RawAddress<'a>, ErrType<'a>> {
+    choice((mailbox().map(RawAddress::Mailbox), group()))
+}
+/// ```ebnf
+/// address-list    =   (address *("," address)) / obs-addr-list
+/// ```
+pub fn address_list<'a>() -> impl Parser<'a, &'a str, Vec<RawAddress<'a>>, ErrType<'a>> {
+    address()
+        .padded()
+        .separated_by(just(','))
+        .at_least(1)
+        .collect()
+}
+/// ```ebnf
+/// path            =   angle-addr / ([CFWS] "<" [CFWS] ">" [CFWS])
+/// ```
+///
+/// The null path `<>` is returned as `None`
+pub fn path<'a>() -> impl Parser<'a, &'a str, Option<(&'a str, &'a str)>, ErrType<'a>> {
+    choice((
+        angle_addr().map(Some),
+        just('<').then(cfws().or_not()).then(just('>')).to(None),
+    ))
+    .padded()
+}
+/// ```ebnf
+/// no-fold-literal =   "[" *dtext "]"
+/// ```
+pub fn no_fold_literal<'a>() -> impl Parser<'a, &'a str, &'a str, ErrType<'a>> {
+    just('[')
+        .then(dtext().repeated())
+        .then(just(']'))
+        .to_slice()
+}
+/// ```ebnf
+/// msg-id          =   [CFWS] "<" id-left "@" id-right ">" [CFWS]
+/// id-left         =   dot-atom-text / obs-id-left
+/// id-right        =   dot-atom-text / no-fold-literal / obs-id-right
+/// obs-id-left     =   local-part
+/// obs-id-right    =   domain
+/// ```
+pub fn msg_id<'a>() -> impl Parser<'a, &'a str, (&'a str, &'a str), ErrType<'a>> {
+    local_part()
+        .then_ignore(just('@'))
+        .then(choice((no_fold_literal(), domain())))
+        .delimited_by(just('<'), just('>'))
+        .padded_by(cfws().or_not())
+}
+/// One or more [msg_id]s. Used by `In-Reply-To` and `References`
+///
+/// ```ebnf
+/// in-reply-to     =   "In-Reply-To:" 1*msg-id CRLF
+/// references      =   "References:" 1*msg-id CRLF
+/// ```
+pub fn msg_id_list<'a>() -> impl Parser<'a, &'a str, Vec<(&'a str, &'a str)>, ErrType<'a>> {
+    msg_id().repeated().at_least(1).collect()
+}
+/// A number of `min` to `max` digits
+fn number<'a, T: std::str::FromStr + Default>(
+    min: usize,
+    max: usize,
+) -> impl Parser<'a, &'a str, T, ErrType<'a>> {
+    one_of('0'..='9')
+        .repeated()
+        .at_least(min)
+        .at_most(max)
+        .to_slice()
+        .map(|digits: &str| digits.parse().unwrap_or_default())
+}
+/// ```ebnf
+/// day-name        =   "Mon" / "Tue" / "Wed" / "Thu" /
+///                     "Fri" / "Sat" / "Sun"
+/// ```
+pub fn day_name<'a>() -> impl Parser<'a, &'a str, u8, ErrType<'a>> {
+    one_of('a'..='z')
+        .or(one_of('A'..='Z'))
+        .repeated()
+        .exactly(3)
+        .to_slice()
+        .filter(|name: &&str| day_from_name(name).is_some())
+        .map(|name: &str| day_from_name(name).unwrap_or_default())
+}
+/// ```ebnf
+/// month           =   "Jan" / "Feb" / "Mar" / "Apr" /
+///                     "May" / "Jun" / "Jul" / "Aug" /
+///                     "Sep" / "Oct" / "Nov" / "Dec"
+/// ```
+pub fn month<'a>() -> impl Parser<'a, &'a str, u8, ErrType<'a>> {
+    one_of('a'..='z')
+        .or(one_of('A'..='Z'))
+        .repeated()
+        .exactly(3)
+        .to_slice()
+        .filter(|name: &&str| month_from_name(name).is_some())
+        .map(|name: &str| month_from_name(name).unwrap_or_default())
+}
+/// ```ebnf
+/// zone            =   (FWS ( "+" / "-" ) 4DIGIT) / obs-zone
+/// obs-zone        =   "UT" / "GMT" /     ; Universal Time
+///                     "EST" / "EDT" /    ; Eastern:  - 5/ - 4
+///                     "CST" / "CDT" /    ; Central:  - 6/ - 5
+///                     "MST" / "MDT" /    ; Mountain: - 7/ - 6
+///                     "PST" / "PDT" /    ; Pacific:  - 8/ - 7
+///                     %d65-73 /          ; Military zones - "A"
+///                     %d75-90 /          ; through "I" and "K"
+///                     %d97-105 /         ; through "Z", both
+///                     %d107-122          ; upper and lower case
+/// ```
+///
+/// The offset in minutes. `None` is `-0000` and the military zones
+pub fn zone<'a>() -> impl Parser<'a, &'a str, Option<i16>, ErrType<'a>> {
+    let numeric = one_of("+-")
+        .then(number::<i16>(2, 2))
+        .then(number::<i16>(2, 2))
+        .filter(|(_, minutes)| *minutes < 60)
+        .map(
+            |((sign, hours), minutes)| match (sign, hours * 60 + minutes) {
+                ('-', 0) => None,
+                ('-', offset) => Some(-offset),
+                (_, offset) => Some(offset),
+            },
+        );
+    let named = one_of('a'..='z')
+        .or(one_of('A'..='Z'))
+        .repeated()
+        .at_least(1)
+        .at_most(3)
+        .to_slice()
+        .filter(|name: &&str| zone_from_name(name).is_some())
+        .map(|name: &str| zone_from_name(name).flatten());
+    choice((numeric, named))
+}
+/// ```ebnf
+/// date-time       =   [ day-of-week "," ] date time [CFWS]
+/// day-of-week     =   ([FWS] day-name) / obs-day-of-week
+/// date            =   day month year
+/// day             =   ([FWS] 1*2DIGIT FWS) / obs-day
+/// year            =   (FWS 4*DIGIT FWS) / obs-year
+/// time            =   time-of-day zone
+/// time-of-day     =   hour ":" minute [ ":" second ]
+/// ```
+///
+/// Accepts the obsolete forms. Comments and folding are allowed between every token.
+/// Two digit years below 50 are in the 2000s. Other two and three digit years have 1900 added
+pub fn date_time<'a>() -> impl Parser<'a, &'a str, RawDateTime, ErrType<'a>> {
+    let day_of_week = day_name().padded_by(cfws().or_not()).then_ignore(just(','));
+    let year = one_of('0'..='9')
+        .repeated()
+        .at_least(2)
+        .at_most(9)
+        .to_slice()
+        .map(|digits: &str| {
+            let year: u32 = digits.parse().unwrap_or_default();
+            match digits.len() {
+                2 if year < 50 => year + 2000,
+                2 | 3 => year + 1900,
+                _ => year,
+            }
+        });
+    let second = just(':')
+        .ignore_then(number::<u8>(2, 2).padded_by(cfws().or_not()))
+        .or_not();
+    day_of_week
+        .or_not()
+        .ignore_then(number::<u8>(1, 2).padded_by(cfws().or_not()))
+        .then(month().padded_by(cfws().or_not()))
+        .then(year.padded_by(cfws().or_not()))
+        .then(number::<u8>(1, 2).padded_by(cfws().or_not()))
+        .then_ignore(just(':'))
+        .then(number::<u8>(2, 2).padded_by(cfws().or_not()))
+        .then(second)
+        .then(zone().padded_by(cfws().or_not()))
+        .map(
+            |((((((day, month), year), hour), minute), second), offset)| RawDateTime {
+                day,
+                month,
+                year,
+                hour,
+                minute,
+                second,
+                offset,
+            },
+        )
+}
+/// ```ebnf
+/// keywords        =   "Keywords:" phrase *("," phrase) CRLF
+/// ```
+pub fn phrase_list<'a>() -> impl Parser<'a, &'a str, Vec<&'a str>, ErrType<'a>> {
+    pharse()
+        .padded()
+        .separated_by(just(','))
+        .at_least(1)
+        .collect()
+}
 
 #[cfg(test)]
 mod tests {
     use chumsky::Parser;
     use pretty_assertions::assert_eq;
 
-    use super::{display_name, mailbox};
+    use super::{
+        address_list, display_name, mailbox, mailbox_list, msg_id_list, path, phrase_list,
+    };
     use super::{domain, domain_literal, quoted_string};
+    use crate::address::RawAddress;
     #[test]
     pub fn test_mailbox() {
         let v = mailbox()
@@ -189,10 +522,68 @@ mod tests {
 
     #[test]
     pub fn test_display_name() {
-        assert_eq!(display_name().parse("John").into_result(), Ok("John"));
+        assert_eq!(
+            display_name().parse("John").into_result(),
+            Ok("John".into())
+        );
         assert_eq!(
             display_name().parse(r#""Darth Vader""#).into_result(),
-            Ok(r#"Darth Vader"#)
+            Ok(r#"Darth Vader"#.into())
+        );
+        assert_eq!(
+            display_name().parse("John Smith").into_result(),
+            Ok("John Smith".into())
+        );
+        assert_eq!(
+            display_name()
+                .parse(r#""John" (The) Smith  "\"Q\"""#)
+                .into_result(),
+            Ok(r#"John Smith "Q""#.into())
+        );
+    }
+    #[test]
+    pub fn test_lists() {
+        let mailboxes = mailbox_list()
+            .parse("John Smith <john@example.com>, jane@example.com")
+            .into_result()
+            .unwrap();
+        assert_eq!(mailboxes.len(), 2);
+        assert_eq!(mailboxes[0].display_name, Some("John Smith".into()));
+        assert_eq!(mailboxes[1].local, "jane");
+        assert!(mailbox_list().parse("").into_result().is_err());
+
+        let addresses = address_list()
+            .parse("undisclosed-recipients:;, Team: a@example.com, b@example.com;, c@example.com")
+            .into_result()
+            .unwrap();
+        assert_eq!(addresses.len(), 3);
+        assert!(matches!(
+            &addresses[0],
+            RawAddress::Group { name, members } if name == "undisclosed-recipients" && members.is_empty()
+        ));
+        assert!(matches!(
+            &addresses[1],
+            RawAddress::Group { name, members } if name == "Team" && members.len() == 2
+        ));
+        assert!(matches!(&addresses[2], RawAddress::Mailbox(_)));
+
+        assert_eq!(
+            msg_id_list()
+                .parse("<1234@local.machine.example>\r\n <5678@[127.0.0.1]>")
+                .into_result(),
+            Ok(vec![
+                ("1234", "local.machine.example"),
+                ("5678", "[127.0.0.1]")
+            ])
+        );
+        assert_eq!(
+            phrase_list().parse("mail, \"rust lang\"").into_result(),
+            Ok(vec!["mail", "\"rust lang\""])
+        );
+        assert_eq!(path().parse("<>").into_result(), Ok(None));
+        assert_eq!(
+            path().parse(" <bounce@example.com> ").into_result(),
+            Ok(Some(("bounce", "example.com")))
         );
     }
     #[test]
@@ -59,3 +59,30 @@ pub fn invalid_tests() {
         }
     }
 }
+#[test]
+pub fn display_name_round_trip() {
+    for (raw, name) in [
+        (r#""John" Smith <j@example.com>"#, "John Smith"),
+        (r#""Smith, John" <j@example.com>"#, "Smith, John"),
+        (
+            r#"John (Johnny) "Q." Smith <j@example.com>"#,
+            "John Q. Smith",
+        ),
+        ("John   Smith <j@example.com>", "John Smith"),
+        (
+            r#""John \"Q\" \\ Smith" <j@example.com>"#,
+            r#"John "Q" \ Smith"#,
+        ),
+    ] {
+        let mailbox = MailBox::try_from(raw).unwrap();
+        assert_eq!(mailbox.get_name(), Some(name));
+        let formatted = mailbox.to_string();
+        assert_eq!(
+            MailBox::try_from(formatted.as_str()).unwrap(),
+            mailbox,
+            "{formatted}"
+        );
+    }
+    let mailbox = MailBox::try_from(r#""a\"b" <j@example.com>"#).unwrap();
+    assert_eq!(mailbox.to_string(), r#""a\"b" <j@example.com>"#);
+}
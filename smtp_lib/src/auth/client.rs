@@ -0,0 +1,248 @@
+/*!
+The client side of the SASL mechanisms
+
+Each mechanism produces the raw responses. The base64 encoding is done by the caller.
+ */
+use std::fmt::Debug;
+
+use super::{cram_md5_digest, AuthError, AuthMechanism};
+
+/// The client side of a SASL mechanism
+pub trait SaslClient: Send {
+    fn mechanism(&self) -> AuthMechanism;
+    /// The response sent with the AUTH command.
+    ///
+    /// None if the mechanism waits for the first challenge
+    fn initial_response(&mut self) -> Option<Vec<u8>>;
+    /// Creates the response to a `334` challenge
+    fn respond(&mut self, challenge: &[u8]) -> Result<Vec<u8>, AuthError>;
+    /// If the exchange is finished on the client side.
+    ///
+    /// A `235` before this is true is treated as a failure.
+    /// Mechanisms that verify the server must only return true once they did
+    fn is_complete(&self) -> bool {
+        true
+    }
+}
+
+/// PLAIN. `[authzid] NUL authcid NUL passwd`
+#[derive(Clone)]
+pub struct PlainClient {
+    authorization_id: Option<String>,
+    username: String,
+    password: String,
+    sent: bool,
+}
+impl PlainClient {
+    pub fn new(username: impl Into<String>, password: impl Into<String>) -> Self {
+        Self {
+            authorization_id: None,
+            username: username.into(),
+            password: password.into(),
+            sent: false,
+        }
+    }
+    /// Act as a different identity than the username
+    pub fn with_authorization_id(mut self, authorization_id: impl Into<String>) -> Self {
+        self.authorization_id = Some(authorization_id.into());
+        self
+    }
+    fn message(&mut self) -> Vec<u8> {
+        self.sent = true;
+        let mut message = Vec::new();
+        if let Some(authorization_id) = &self.authorization_id {
+            message.extend_from_slice(authorization_id.as_bytes());
+        }
+        message.push(0);
+        message.extend_from_slice(self.username.as_bytes());
+        message.push(0);
+        message.extend_from_slice(self.password.as_bytes());
+        message
+    }
+}
+/// Does not print the password
+impl Debug for PlainClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PlainClient")
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+impl SaslClient for PlainClient {
+    fn mechanism(&self) -> AuthMechanism {
+        AuthMechanism::Plain
+    }
+
+    fn initial_response(&mut self) -> Option<Vec<u8>> {
+        Some(self.message())
+    }
+
+    fn respond(&mut self, _: &[u8]) -> Result<Vec<u8>, AuthError> {
+        // The server did not accept the initial response and asked again
+        if self.sent {
+            return Err(AuthError::Malformed("Unexpected challenge"));
+        }
+        Ok(self.message())
+    }
+}
+
+/// LOGIN. Sends the username and the password as responses to two challenges
+#[derive(Clone)]
+pub struct LoginClient {
+    username: String,
+    password: String,
+    step: usize,
+}
+impl LoginClient {
+    pub fn new(username: impl Into<String>, password: impl Into<String>) -> Self {
+        Self {
+            username: username.into(),
+            password: password.into(),
+            step: 0,
+        }
+    }
+}
+/// Does not print the password
+impl Debug for LoginClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LoginClient")
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+impl SaslClient for LoginClient {
+    fn mechanism(&self) -> AuthMechanism {
+        AuthMechanism::Login
+    }
+
+    fn initial_response(&mut self) -> Option<Vec<u8>> {
+        None
+    }
+
+    fn respond(&mut self, challenge: &[u8]) -> Result<Vec<u8>, AuthError> {
+        // The challenges are usually `Username:` and `Password:`. Not every server sends them
+        let is_password = if challenge.eq_ignore_ascii_case(b"Username:") {
+            false
+        } else if challenge.eq_ignore_ascii_case(b"Password:") {
+            true
+        } else {
+            self.step > 0
+        };
+        self.step += 1;
+        match (is_password, self.step) {
+            (_, 3..) => Err(AuthError::Malformed("Unexpected challenge")),
+            (false, _) => Ok(self.username.as_bytes().to_vec()),
+            (true, _) => Ok(self.password.as_bytes().to_vec()),
+        }
+    }
+}
+
+/// CRAM-MD5. Responds with the username and the HMAC-MD5 of the challenge
+#[derive(Clone)]
+pub struct CramMd5Client {
+    username: String,
+    password: String,
+}
+impl CramMd5Client {
+    pub fn new(username: impl Into<String>, password: impl Into<String>) -> Self {
+        Self {
+            username: username.into(),
+            password: password.into(),
+        }
+    }
+}
+/// Does not print the password
+impl Debug for CramMd5Client {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CramMd5Client")
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+impl SaslClient for CramMd5Client {
+    fn mechanism(&self) -> AuthMechanism {
+        AuthMechanism::CramMd5
+    }
+
+    fn initial_response(&mut self) -> Option<Vec<u8>> {
+        None
+    }
+
+    fn respond(&mut self, challenge: &[u8]) -> Result<Vec<u8>, AuthError> {
+        let digest = cram_md5_digest(&self.password, challenge);
+        Ok(format!("{} {}", self.username, digest).into_bytes())
+    }
+}
+
+/// XOAUTH2. Sends an OAuth 2.0 bearer token
+#[derive(Clone)]
+pub struct XOAuth2Client {
+    username: String,
+    token: String,
+}
+impl XOAuth2Client {
+    pub fn new(username: impl Into<String>, token: impl Into<String>) -> Self {
+        Self {
+            username: username.into(),
+            token: token.into(),
+        }
+    }
+}
+/// Does not print the token
+impl Debug for XOAuth2Client {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("XOAuth2Client")
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+impl SaslClient for XOAuth2Client {
+    fn mechanism(&self) -> AuthMechanism {
+        AuthMechanism::XOAuth2
+    }
+
+    fn initial_response(&mut self) -> Option<Vec<u8>> {
+        Some(
+            format!(
+                "user={}\x01auth=Bearer {}\x01\x01",
+                self.username, self.token
+            )
+            .into_bytes(),
+        )
+    }
+
+    fn respond(&mut self, _: &[u8]) -> Result<Vec<u8>, AuthError> {
+        // The challenge is a JSON error. An empty response makes the server send the final error reply
+        Ok(Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CramMd5Client, LoginClient, PlainClient, SaslClient};
+
+    #[test]
+    fn test_plain() {
+        let mut client = PlainClient::new("tim", "tanstaaf");
+        assert_eq!(client.initial_response().unwrap(), b"\0tim\0tanstaaf");
+        let mut client = PlainClient::new("tim", "tanstaaf").with_authorization_id("admin");
+        assert_eq!(client.initial_response().unwrap(), b"admin\0tim\0tanstaaf");
+    }
+    #[test]
+    fn test_login() {
+        let mut client = LoginClient::new("tim", "tanstaaf");
+        assert!(client.initial_response().is_none());
+        assert_eq!(client.respond(b"Username:").unwrap(), b"tim");
+        assert_eq!(client.respond(b"Password:").unwrap(), b"tanstaaf");
+        assert!(client.respond(b"Password:").is_err());
+    }
+    #[test]
+    fn test_cram_md5() {
+        // RFC 2195 Section 2
+        let mut client = CramMd5Client::new("tim", "tanstaaftanstaaf");
+        let response = client
+            .respond(b"<1896.697170952@postoffice.reston.mci.net>")
+            .unwrap();
+        assert_eq!(response, b"tim b913a602c7eda7a495b4e6e7334d3890");
+    }
+}
@@ -0,0 +1,333 @@
+/*!
+# SMTP Authentication
+
+[RFC 4954](https://datatracker.ietf.org/doc/html/rfc4954)
+
+The client sends `AUTH <mechanism> [initial-response]`.
+The server replies `334 <base64 challenge>` until the exchange is done and then `235` or an error.
+The client can cancel the exchange by replying `*`.
+
+- [client] The mechanisms used by a client. See [SaslClient](client::SaslClient)
+- [server] The server side state machine. Credentials are checked by an [Authenticator]
+- [scram] SCRAM-SHA-1 and SCRAM-SHA-256. The server only stores [ScramCredentials](scram::ScramCredentials)
+ */
+pub mod client;
+pub mod scram;
+pub mod server;
+
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hmac::{Hmac, Mac};
+use md5::Md5;
+use thiserror::Error;
+
+use self::scram::{ScramCredentials, ScramHash};
+use crate::{
+    enhanced_status_code::{EnhancedStatusCode, StatusClass},
+    server_response::{MultilineServerResponse, ResponseCode, ServerResponseLine},
+};
+
+/// The longest AUTH command or `334` response a server has to accept. Including the CRLF
+///
+/// [RFC 4954 Section 4](https://datatracker.ietf.org/doc/html/rfc4954#section-4)
+pub const MAX_AUTH_LINE_LENGTH: usize = 12288;
+
+/// A SASL mechanism
+///
+/// [IANA SASL Mechanisms](https://www.iana.org/assignments/sasl-mechanisms/sasl-mechanisms.xhtml)
+#[derive(Debug, Clone, PartialEq, Eq, Hash, strum::EnumString, strum::Display, strum::AsRefStr)]
+#[strum(ascii_case_insensitive)]
+pub enum AuthMechanism {
+    /// [RFC 4616](https://datatracker.ietf.org/doc/html/rfc4616)
+    #[strum(serialize = "PLAIN")]
+    Plain,
+    /// [draft-murchison-sasl-login](https://datatracker.ietf.org/doc/html/draft-murchison-sasl-login-00)
+    #[strum(serialize = "LOGIN")]
+    Login,
+    /// [RFC 2195](https://datatracker.ietf.org/doc/html/rfc2195)
+    #[strum(serialize = "CRAM-MD5")]
+    CramMd5,
+    /// [Google XOAUTH2](https://developers.google.com/gmail/imap/xoauth2-protocol)
+    #[strum(serialize = "XOAUTH2")]
+    XOAuth2,
+    /// [RFC 5802](https://datatracker.ietf.org/doc/html/rfc5802)
+    #[strum(serialize = "SCRAM-SHA-1")]
+    ScramSha1,
+    /// SCRAM-SHA-1 with channel binding
+    #[strum(serialize = "SCRAM-SHA-1-PLUS")]
+    ScramSha1Plus,
+    /// [RFC 7677](https://datatracker.ietf.org/doc/html/rfc7677)
+    #[strum(serialize = "SCRAM-SHA-256")]
+    ScramSha256,
+    /// SCRAM-SHA-256 with channel binding
+    #[strum(serialize = "SCRAM-SHA-256-PLUS")]
+    ScramSha256Plus,
+    #[strum(default)]
+    Other(String),
+}
+impl AuthMechanism {
+    /// Parses a space separated list of mechanisms. As sent in the EHLO response
+    pub fn parse_list(value: &str) -> Vec<Self> {
+        value
+            .split_ascii_whitespace()
+            .filter_map(|mechanism| mechanism.parse().ok())
+            .collect()
+    }
+    /// Formats the mechanisms as a space separated list
+    pub fn format_list<'a>(mechanisms: impl IntoIterator<Item = &'a Self>) -> String {
+        mechanisms
+            .into_iter()
+            .map(AuthMechanism::to_string)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+    /// If the password is sent in a form that can be read by anyone listening.
+    ///
+    /// These mechanisms should only be used over TLS
+    pub fn is_plain_text(&self) -> bool {
+        matches!(
+            self,
+            AuthMechanism::Plain | AuthMechanism::Login | AuthMechanism::XOAuth2
+        )
+    }
+    /// If the mechanism binds the exchange to the TLS connection. Only usable over TLS
+    pub fn is_channel_binding(&self) -> bool {
+        matches!(
+            self,
+            AuthMechanism::ScramSha1Plus | AuthMechanism::ScramSha256Plus
+        )
+    }
+}
+
+/// An error during authentication
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum AuthError {
+    #[error("Authentication credentials invalid")]
+    InvalidCredentials,
+    #[error("Temporary authentication failure")]
+    TemporaryFailure,
+    #[error("Unrecognized authentication type: {0}")]
+    UnsupportedMechanism(AuthMechanism),
+    #[error("Encryption required for requested authentication mechanism")]
+    EncryptionRequired,
+    #[error("Authentication cancelled")]
+    Cancelled,
+    #[error("Invalid base64")]
+    InvalidBase64,
+    #[error("Malformed authentication data: {0}")]
+    Malformed(&'static str),
+}
+impl AuthError {
+    /// The reply code that should be sent to the client
+    pub fn code(&self) -> ResponseCode {
+        match self {
+            AuthError::InvalidCredentials => ResponseCode::AuthenticationCredentialsInvalid,
+            AuthError::TemporaryFailure => ResponseCode::TemporaryAuthenticationFailure,
+            AuthError::UnsupportedMechanism(_) => ResponseCode::ParameterNotImplemented,
+            AuthError::EncryptionRequired => ResponseCode::from(538),
+            AuthError::Cancelled | AuthError::InvalidBase64 | AuthError::Malformed(_) => {
+                ResponseCode::SyntaxErrorInParameters
+            }
+        }
+    }
+    pub fn enhanced_status_code(&self) -> EnhancedStatusCode {
+        match self {
+            AuthError::InvalidCredentials => {
+                EnhancedStatusCode::new(StatusClass::PermanentFailure, 7, 8)
+            }
+            AuthError::TemporaryFailure => {
+                EnhancedStatusCode::new(StatusClass::PersistentTransientFailure, 7, 0)
+            }
+            AuthError::UnsupportedMechanism(_) => {
+                EnhancedStatusCode::new(StatusClass::PermanentFailure, 5, 4)
+            }
+            AuthError::EncryptionRequired => {
+                EnhancedStatusCode::new(StatusClass::PermanentFailure, 7, 11)
+            }
+            AuthError::Cancelled => EnhancedStatusCode::new(StatusClass::PermanentFailure, 0, 0),
+            AuthError::InvalidBase64 | AuthError::Malformed(_) => EnhancedStatusCode::SYNTAX_ERROR,
+        }
+    }
+    /// The reply that should be sent to the client
+    pub fn to_response(&self) -> MultilineServerResponse {
+        MultilineServerResponse::new(vec![ServerResponseLine::new_with_enhanced_status_code(
+            self.code(),
+            self.enhanced_status_code(),
+            Some(self.to_string()),
+        )])
+    }
+}
+
+/// Encodes a challenge or response for the `334` exchange
+///
+/// An empty value is sent as an empty line
+pub fn encode_sasl(data: &[u8]) -> String {
+    STANDARD.encode(data)
+}
+/// Decodes a line of the `334` exchange. The line ending is removed.
+///
+/// Returns [AuthError::Cancelled] if the line is `*`
+pub fn decode_sasl(line: &str) -> Result<Vec<u8>, AuthError> {
+    let line = line.trim_end_matches(['\r', '\n']);
+    if line == "*" {
+        return Err(AuthError::Cancelled);
+    }
+    STANDARD
+        .decode(line.trim())
+        .map_err(|_| AuthError::InvalidBase64)
+}
+/// The hex encoded HMAC-MD5 of the challenge with the password as the key
+pub(crate) fn cram_md5_digest(password: &str, challenge: &[u8]) -> String {
+    // HMAC accepts any key length
+    let mut mac = Hmac::<Md5>::new_from_slice(password.as_bytes()).expect("Any key length");
+    mac.update(challenge);
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+/// Compares without returning early. So the time taken does not depend on where the values differ
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |result, (a, b)| result | (a ^ b)) == 0
+}
+
+/// The credentials sent by a client. Passed to an [Authenticator]
+#[derive(Clone, PartialEq, Eq)]
+pub enum AuthCredentials {
+    /// Sent with PLAIN or LOGIN
+    Password {
+        /// The identity to act as. None if it is the same as the username
+        authorization_id: Option<String>,
+        username: String,
+        password: String,
+    },
+    /// Sent with CRAM-MD5. The password is never sent
+    CramMd5 {
+        username: String,
+        challenge: String,
+        /// The lowercase hex encoded HMAC-MD5 of the challenge
+        digest: String,
+    },
+    /// Sent with XOAUTH2
+    XOAuth2 { username: String, token: String },
+}
+impl AuthCredentials {
+    pub fn username(&self) -> &str {
+        match self {
+            AuthCredentials::Password { username, .. }
+            | AuthCredentials::CramMd5 { username, .. }
+            | AuthCredentials::XOAuth2 { username, .. } => username,
+        }
+    }
+    /// Checks the credentials against the stored password.
+    ///
+    /// Always false for [AuthCredentials::XOAuth2]
+    pub fn verify_password(&self, stored_password: &str) -> bool {
+        match self {
+            AuthCredentials::Password { password, .. } => {
+                constant_time_eq(password.as_bytes(), stored_password.as_bytes())
+            }
+            AuthCredentials::CramMd5 {
+                challenge, digest, ..
+            } => {
+                let expected = cram_md5_digest(stored_password, challenge.as_bytes());
+                constant_time_eq(expected.as_bytes(), digest.to_ascii_lowercase().as_bytes())
+            }
+            AuthCredentials::XOAuth2 { .. } => false,
+        }
+    }
+}
+/// Does not print the password or token
+impl std::fmt::Debug for AuthCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthCredentials::Password {
+                authorization_id,
+                username,
+                ..
+            } => f
+                .debug_struct("Password")
+                .field("authorization_id", authorization_id)
+                .field("username", username)
+                .finish_non_exhaustive(),
+            AuthCredentials::CramMd5 { username, .. } => f
+                .debug_struct("CramMd5")
+                .field("username", username)
+                .finish_non_exhaustive(),
+            AuthCredentials::XOAuth2 { username, .. } => f
+                .debug_struct("XOAuth2")
+                .field("username", username)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
+/// Checks the credentials sent by a client against a credential store
+#[async_trait]
+pub trait Authenticator: Send + Sync + 'static {
+    /// The mechanisms advertised in the EHLO response
+    fn mechanisms(&self) -> Vec<AuthMechanism> {
+        vec![AuthMechanism::Plain, AuthMechanism::Login]
+    }
+    /// Returns the authenticated identity.
+    ///
+    /// Return [AuthError::InvalidCredentials] if the credentials are wrong
+    /// and [AuthError::TemporaryFailure] if the credential store is unavailable
+    async fn authenticate(&self, credentials: AuthCredentials) -> Result<String, AuthError>;
+    /// The stored SCRAM credentials of the user. Required for the SCRAM mechanisms.
+    ///
+    /// Return [AuthError::InvalidCredentials] if the user does not exist
+    async fn scram_credentials(
+        &self,
+        hash: ScramHash,
+        _username: &str,
+    ) -> Result<ScramCredentials, AuthError> {
+        Err(AuthError::UnsupportedMechanism(hash.mechanism(false)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_sasl, encode_sasl, AuthCredentials, AuthError, AuthMechanism};
+
+    #[test]
+    fn test_mechanism() {
+        let mechanisms =
+            AuthMechanism::parse_list("PLAIN login CRAM-MD5 XOAUTH2 SCRAM-SHA-256-PLUS GSSAPI");
+        assert_eq!(
+            mechanisms,
+            vec![
+                AuthMechanism::Plain,
+                AuthMechanism::Login,
+                AuthMechanism::CramMd5,
+                AuthMechanism::XOAuth2,
+                AuthMechanism::ScramSha256Plus,
+                AuthMechanism::Other("GSSAPI".to_owned()),
+            ]
+        );
+        assert_eq!(
+            AuthMechanism::format_list(&mechanisms),
+            "PLAIN LOGIN CRAM-MD5 XOAUTH2 SCRAM-SHA-256-PLUS GSSAPI"
+        );
+    }
+    #[test]
+    fn test_sasl_encoding() {
+        assert_eq!(encode_sasl(b"Username:"), "VXNlcm5hbWU6");
+        assert_eq!(decode_sasl("VXNlcm5hbWU6\r\n").unwrap(), b"Username:");
+        assert_eq!(decode_sasl("\r\n").unwrap(), b"");
+        assert_eq!(decode_sasl("*\r\n"), Err(AuthError::Cancelled));
+        assert_eq!(decode_sasl("not base64!"), Err(AuthError::InvalidBase64));
+    }
+    #[test]
+    fn test_cram_md5() {
+        // RFC 2195 Section 2
+        let credentials = AuthCredentials::CramMd5 {
+            username: "tim".to_owned(),
+            challenge: "<1896.697170952@postoffice.reston.mci.net>".to_owned(),
+            digest: "b913a602c7eda7a495b4e6e7334d3890".to_owned(),
+        };
+        assert!(credentials.verify_password("tanstaaftanstaaf"));
+        assert!(!credentials.verify_password("tanstaaf"));
+    }
+}
@@ -0,0 +1,742 @@
+/*!
+SCRAM-SHA-1 and SCRAM-SHA-256
+
+[RFC 5802](https://datatracker.ietf.org/doc/html/rfc5802) and [RFC 7677](https://datatracker.ietf.org/doc/html/rfc7677)
+
+The password is never sent. The server only stores [ScramCredentials] and both sides prove that they know the password.
+
+The `-PLUS` variants bind the exchange to the TLS connection with a [ChannelBinding].
+So the exchange can not be relayed through a man in the middle.
+
+In SMTP the server sends the final message as a `334` challenge. The client answers with an empty line and the server replies `235`
+ */
+use std::{fmt::Display, str::FromStr};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use super::{client::SaslClient, constant_time_eq, AuthError, AuthMechanism};
+
+/// The default iteration count for [ScramCredentials::generate]. The minimum recommended by RFC 7677
+pub const DEFAULT_ITERATIONS: u32 = 4096;
+/// The largest iteration count [ScramClient] accepts from a server. A higher count would let the server use up the client's CPU
+pub const MAX_ITERATIONS: u32 = 1_000_000;
+
+/// The hash function of a SCRAM mechanism
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScramHash {
+    Sha1,
+    Sha256,
+}
+impl ScramHash {
+    /// Returns the hash and if the mechanism uses channel binding
+    pub fn from_mechanism(mechanism: &AuthMechanism) -> Option<(Self, bool)> {
+        match mechanism {
+            AuthMechanism::ScramSha1 => Some((ScramHash::Sha1, false)),
+            AuthMechanism::ScramSha1Plus => Some((ScramHash::Sha1, true)),
+            AuthMechanism::ScramSha256 => Some((ScramHash::Sha256, false)),
+            AuthMechanism::ScramSha256Plus => Some((ScramHash::Sha256, true)),
+            _ => None,
+        }
+    }
+    pub fn mechanism(&self, channel_binding: bool) -> AuthMechanism {
+        match (self, channel_binding) {
+            (ScramHash::Sha1, false) => AuthMechanism::ScramSha1,
+            (ScramHash::Sha1, true) => AuthMechanism::ScramSha1Plus,
+            (ScramHash::Sha256, false) => AuthMechanism::ScramSha256,
+            (ScramHash::Sha256, true) => AuthMechanism::ScramSha256Plus,
+        }
+    }
+    fn hash(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            ScramHash::Sha1 => Sha1::digest(data).to_vec(),
+            ScramHash::Sha256 => Sha256::digest(data).to_vec(),
+        }
+    }
+    fn hmac(&self, key: &[u8], data: &[u8]) -> Vec<u8> {
+        // HMAC accepts any key length
+        match self {
+            ScramHash::Sha1 => {
+                let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("Any key length");
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+            ScramHash::Sha256 => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("Any key length");
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+        }
+    }
+    /// `Hi()` in RFC 5802. PBKDF2 with HMAC
+    fn salted_password(&self, password: &str, salt: &[u8], iterations: u32) -> Vec<u8> {
+        match self {
+            ScramHash::Sha1 => {
+                let mut output = [0; 20];
+                pbkdf2::pbkdf2_hmac::<Sha1>(password.as_bytes(), salt, iterations, &mut output);
+                output.to_vec()
+            }
+            ScramHash::Sha256 => {
+                let mut output = [0; 32];
+                pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, iterations, &mut output);
+                output.to_vec()
+            }
+        }
+    }
+}
+
+/// Data that identifies the TLS connection. Used by the `-PLUS` mechanisms
+#[derive(Clone, PartialEq, Eq)]
+pub enum ChannelBinding {
+    /// [RFC 9266](https://datatracker.ietf.org/doc/html/rfc9266). 32 bytes exported with the label `EXPORTER-Channel-Binding`
+    TlsExporter(Vec<u8>),
+    /// [RFC 5929](https://datatracker.ietf.org/doc/html/rfc5929#section-3). TLS 1.2 and earlier only
+    TlsUnique(Vec<u8>),
+}
+impl ChannelBinding {
+    /// The label used to export the keying material for [ChannelBinding::TlsExporter]
+    pub const EXPORTER_LABEL: &'static [u8] = b"EXPORTER-Channel-Binding";
+
+    /// The channel binding type sent in the GS2 header
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChannelBinding::TlsExporter(_) => "tls-exporter",
+            ChannelBinding::TlsUnique(_) => "tls-unique",
+        }
+    }
+    pub fn data(&self) -> &[u8] {
+        match self {
+            ChannelBinding::TlsExporter(data) | ChannelBinding::TlsUnique(data) => data,
+        }
+    }
+}
+/// Does not print the data
+impl std::fmt::Debug for ChannelBinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ChannelBinding").field(&self.name()).finish()
+    }
+}
+
+/// What the server stores instead of the password
+///
+/// Stored in the format of [RFC 5803](https://datatracker.ietf.org/doc/html/rfc5803#section-3).
+/// `SCRAM-SHA-256$<iterations>:<salt>$<StoredKey>:<ServerKey>`
+#[derive(Clone, PartialEq, Eq)]
+pub struct ScramCredentials {
+    pub hash: ScramHash,
+    pub salt: Vec<u8>,
+    pub iterations: u32,
+    pub stored_key: Vec<u8>,
+    pub server_key: Vec<u8>,
+}
+impl ScramCredentials {
+    /// Derives the keys from the password
+    pub fn new(hash: ScramHash, password: &str, salt: Vec<u8>, iterations: u32) -> Self {
+        let salted_password = hash.salted_password(&saslprep(password), &salt, iterations);
+        let client_key = hash.hmac(&salted_password, b"Client Key");
+        Self {
+            stored_key: hash.hash(&client_key),
+            server_key: hash.hmac(&salted_password, b"Server Key"),
+            hash,
+            salt,
+            iterations,
+        }
+    }
+    /// Derives the keys with a random salt and [DEFAULT_ITERATIONS]
+    pub fn generate(hash: ScramHash, password: &str) -> Self {
+        let salt = rand::random::<[u8; 16]>().to_vec();
+        Self::new(hash, password, salt, DEFAULT_ITERATIONS)
+    }
+}
+impl Display for ScramCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}${}:{}${}:{}",
+            self.hash.mechanism(false),
+            self.iterations,
+            STANDARD.encode(&self.salt),
+            STANDARD.encode(&self.stored_key),
+            STANDARD.encode(&self.server_key)
+        )
+    }
+}
+impl FromStr for ScramCredentials {
+    type Err = AuthError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = AuthError::Malformed("Invalid SCRAM credentials");
+        let mut parts = s.split('$');
+        let (Some(mechanism), Some(salt), Some(keys), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid);
+        };
+        let (hash, false) = mechanism
+            .parse()
+            .ok()
+            .as_ref()
+            .and_then(ScramHash::from_mechanism)
+            .ok_or(invalid.clone())?
+        else {
+            return Err(invalid);
+        };
+        let (iterations, salt) = salt.split_once(':').ok_or(invalid.clone())?;
+        let (stored_key, server_key) = keys.split_once(':').ok_or(invalid.clone())?;
+        let decode = |value: &str| STANDARD.decode(value).map_err(|_| AuthError::InvalidBase64);
+        Ok(Self {
+            hash,
+            salt: decode(salt)?,
+            iterations: iterations.parse().map_err(|_| invalid)?,
+            stored_key: decode(stored_key)?,
+            server_key: decode(server_key)?,
+        })
+    }
+}
+/// Does not print the keys
+impl std::fmt::Debug for ScramCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScramCredentials")
+            .field("hash", &self.hash)
+            .field("iterations", &self.iterations)
+            .finish_non_exhaustive()
+    }
+}
+
+/// SASLprep. [RFC 4013](https://datatracker.ietf.org/doc/html/rfc4013)
+///
+/// Passwords that can not be prepared are used as is
+fn saslprep(value: &str) -> std::borrow::Cow<'_, str> {
+    stringprep::saslprep(value).unwrap_or(std::borrow::Cow::Borrowed(value))
+}
+/// Escapes `,` and `=` in a username
+fn encode_name(name: &str) -> String {
+    name.replace('=', "=3D").replace(',', "=2C")
+}
+fn decode_name(name: &str) -> Result<String, AuthError> {
+    let mut decoded = String::with_capacity(name.len());
+    let mut rest = name;
+    while let Some(index) = rest.find('=') {
+        decoded.push_str(&rest[..index]);
+        match rest.get(index..index + 3) {
+            Some("=2C") => decoded.push(','),
+            Some("=3D") => decoded.push('='),
+            _ => return Err(AuthError::Malformed("Invalid escape in username")),
+        }
+        rest = &rest[index + 3..];
+    }
+    decoded.push_str(rest);
+    Ok(decoded)
+}
+/// A printable nonce without `,`
+fn generate_nonce() -> String {
+    STANDARD.encode(rand::random::<[u8; 18]>())
+}
+fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter().zip(b).map(|(a, b)| a ^ b).collect()
+}
+/// Finds the value of `key=` in a message
+fn attribute(message: &str, key: char) -> Option<&str> {
+    message.split(',').find_map(|attribute| {
+        attribute
+            .strip_prefix(key)
+            .and_then(|value| value.strip_prefix('='))
+    })
+}
+fn to_str(message: &[u8]) -> Result<&str, AuthError> {
+    std::str::from_utf8(message).map_err(|_| AuthError::Malformed("Invalid UTF-8"))
+}
+
+#[derive(Debug)]
+enum ClientState {
+    Start,
+    /// The client-first-message was sent
+    ClientFirst {
+        client_first_bare: String,
+    },
+    /// The client-final-message was sent
+    ClientFinal {
+        server_signature: Vec<u8>,
+    },
+    Done,
+}
+/// The client side of SCRAM
+pub struct ScramClient {
+    hash: ScramHash,
+    username: String,
+    password: String,
+    authorization_id: Option<String>,
+    channel_binding: Option<ChannelBinding>,
+    nonce: String,
+    state: ClientState,
+}
+impl ScramClient {
+    pub fn new(hash: ScramHash, username: impl Into<String>, password: impl Into<String>) -> Self {
+        Self {
+            hash,
+            username: username.into(),
+            password: password.into(),
+            authorization_id: None,
+            channel_binding: None,
+            nonce: generate_nonce(),
+            state: ClientState::Start,
+        }
+    }
+    /// Act as a different identity than the username
+    pub fn with_authorization_id(mut self, authorization_id: impl Into<String>) -> Self {
+        self.authorization_id = Some(authorization_id.into());
+        self
+    }
+    /// Uses the `-PLUS` variant of the mechanism
+    pub fn with_channel_binding(mut self, channel_binding: ChannelBinding) -> Self {
+        self.channel_binding = Some(channel_binding);
+        self
+    }
+    /// `gs2-header`
+    fn gs2_header(&self) -> String {
+        let flag = match &self.channel_binding {
+            Some(channel_binding) => format!("p={}", channel_binding.name()),
+            None => "n".to_owned(),
+        };
+        match &self.authorization_id {
+            Some(authorization_id) => format!("{},a={},", flag, encode_name(authorization_id)),
+            None => format!("{},,", flag),
+        }
+    }
+    fn client_first(&mut self) -> Vec<u8> {
+        let client_first_bare = format!(
+            "n={},r={}",
+            encode_name(&saslprep(&self.username)),
+            self.nonce
+        );
+        let message = format!("{}{}", self.gs2_header(), client_first_bare);
+        self.state = ClientState::ClientFirst { client_first_bare };
+        message.into_bytes()
+    }
+    fn client_final(
+        &mut self,
+        client_first_bare: &str,
+        server_first: &str,
+    ) -> Result<Vec<u8>, AuthError> {
+        if let Some(error) = attribute(server_first, 'e') {
+            return Err(server_error(error));
+        }
+        let nonce = attribute(server_first, 'r').ok_or(AuthError::Malformed("Missing nonce"))?;
+        if !nonce.starts_with(&self.nonce) || nonce.len() == self.nonce.len() {
+            return Err(AuthError::Malformed("Invalid server nonce"));
+        }
+        let salt = attribute(server_first, 's')
+            .ok_or(AuthError::Malformed("Missing salt"))
+            .and_then(|salt| STANDARD.decode(salt).map_err(|_| AuthError::InvalidBase64))?;
+        let iterations = attribute(server_first, 'i')
+            .and_then(|iterations| iterations.parse::<u32>().ok())
+            .filter(|iterations| *iterations > 0)
+            .ok_or(AuthError::Malformed("Invalid iteration count"))?;
+        if iterations > MAX_ITERATIONS {
+            return Err(AuthError::Malformed("Iteration count too large"));
+        }
+
+        let mut channel_binding = self.gs2_header().into_bytes();
+        if let Some(data) = &self.channel_binding {
+            channel_binding.extend_from_slice(data.data());
+        }
+        let client_final_without_proof =
+            format!("c={},r={}", STANDARD.encode(channel_binding), nonce);
+        let auth_message = format!(
+            "{},{},{}",
+            client_first_bare, server_first, client_final_without_proof
+        );
+
+        let hash = self.hash;
+        let salted_password = hash.salted_password(&saslprep(&self.password), &salt, iterations);
+        let client_key = hash.hmac(&salted_password, b"Client Key");
+        let stored_key = hash.hash(&client_key);
+        let client_signature = hash.hmac(&stored_key, auth_message.as_bytes());
+        let server_key = hash.hmac(&salted_password, b"Server Key");
+        self.state = ClientState::ClientFinal {
+            server_signature: hash.hmac(&server_key, auth_message.as_bytes()),
+        };
+        let proof = xor(&client_key, &client_signature);
+        Ok(format!(
+            "{},p={}",
+            client_final_without_proof,
+            STANDARD.encode(proof)
+        )
+        .into_bytes())
+    }
+}
+impl SaslClient for ScramClient {
+    fn mechanism(&self) -> AuthMechanism {
+        self.hash.mechanism(self.channel_binding.is_some())
+    }
+
+    fn initial_response(&mut self) -> Option<Vec<u8>> {
+        Some(self.client_first())
+    }
+
+    fn respond(&mut self, challenge: &[u8]) -> Result<Vec<u8>, AuthError> {
+        let challenge = to_str(challenge)?;
+        match std::mem::replace(&mut self.state, ClientState::Done) {
+            // The server did not accept the initial response and asked for it
+            ClientState::Start if challenge.is_empty() => Ok(self.client_first()),
+            ClientState::ClientFirst { client_first_bare } => {
+                self.client_final(&client_first_bare, challenge)
+            }
+            ClientState::ClientFinal { server_signature } => {
+                if let Some(error) = attribute(challenge, 'e') {
+                    return Err(server_error(error));
+                }
+                let verifier = attribute(challenge, 'v')
+                    .ok_or(AuthError::Malformed("Missing server signature"))
+                    .and_then(|v| STANDARD.decode(v).map_err(|_| AuthError::InvalidBase64))?;
+                if !constant_time_eq(&verifier, &server_signature) {
+                    return Err(AuthError::Malformed("Invalid server signature"));
+                }
+                Ok(Vec::new())
+            }
+            _ => Err(AuthError::Malformed("Unexpected challenge")),
+        }
+    }
+    /// Only once the server signature was verified
+    fn is_complete(&self) -> bool {
+        matches!(self.state, ClientState::Done)
+    }
+}
+/// Does not print the password
+impl std::fmt::Debug for ScramClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScramClient")
+            .field("hash", &self.hash)
+            .field("username", &self.username)
+            .field("channel_binding", &self.channel_binding)
+            .finish_non_exhaustive()
+    }
+}
+fn server_error(error: &str) -> AuthError {
+    match error {
+        "invalid-proof" | "unknown-user" => AuthError::InvalidCredentials,
+        _ => AuthError::Malformed("The server rejected the exchange"),
+    }
+}
+
+/// The parts of the client-first-message needed for the rest of the exchange
+#[derive(Debug)]
+struct ClientFirst {
+    gs2_header: String,
+    client_first_bare: String,
+    nonce: String,
+}
+#[derive(Debug)]
+enum ServerState {
+    Start,
+    ClientFirst(ClientFirst),
+    ServerFirst {
+        client_final_prefix: String,
+        gs2_header: String,
+        nonce: String,
+        credentials: ScramCredentials,
+    },
+    Done,
+}
+/// The server side of SCRAM
+///
+/// 1. [ScramServer::client_first] parses the first message of the client and returns the username
+/// 2. [ScramServer::server_first] creates the challenge from the stored credentials of the user
+/// 3. [ScramServer::client_final] checks the proof and returns the final message
+#[derive(Debug)]
+pub struct ScramServer {
+    hash: ScramHash,
+    plus: bool,
+    channel_binding: Option<ChannelBinding>,
+    username: String,
+    server_nonce: String,
+    state: ServerState,
+}
+impl ScramServer {
+    /// `channel_binding` is the binding of the TLS connection if the server offers the `-PLUS` mechanisms.
+    ///
+    /// Fails with [AuthError::EncryptionRequired] if the mechanism uses channel binding and there is none
+    pub fn new(
+        mechanism: &AuthMechanism,
+        channel_binding: Option<ChannelBinding>,
+    ) -> Result<Self, AuthError> {
+        let (hash, plus) = ScramHash::from_mechanism(mechanism)
+            .ok_or_else(|| AuthError::UnsupportedMechanism(mechanism.clone()))?;
+        if plus && channel_binding.is_none() {
+            return Err(AuthError::EncryptionRequired);
+        }
+        Ok(Self {
+            hash,
+            plus,
+            channel_binding,
+            username: String::new(),
+            server_nonce: generate_nonce(),
+            state: ServerState::Start,
+        })
+    }
+    pub fn hash(&self) -> ScramHash {
+        self.hash
+    }
+    /// The username sent by the client. Empty before [ScramServer::client_first]
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+    /// Parses the client-first-message and returns the username
+    pub fn client_first(&mut self, message: &[u8]) -> Result<&str, AuthError> {
+        if !matches!(self.state, ServerState::Start) {
+            return Err(AuthError::Malformed("Unexpected message"));
+        }
+        let message = to_str(message)?;
+        let mut parts = message.splitn(3, ',');
+        let (Some(flag), Some(authorization_id), Some(client_first_bare)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(AuthError::Malformed("Expected a GS2 header"));
+        };
+        match (flag, self.plus) {
+            ("n", false) => {}
+            // The client supports channel binding but thinks the server does not. A downgrade attack if we do
+            ("y", false) if self.channel_binding.is_none() => {}
+            ("y", false) => return Err(AuthError::InvalidCredentials),
+            (flag, true) => {
+                let name = flag.strip_prefix("p=");
+                let expected = self.channel_binding.as_ref().map(ChannelBinding::name);
+                if name.is_none() || name != expected {
+                    return Err(AuthError::Malformed("Unsupported channel binding type"));
+                }
+            }
+            _ => return Err(AuthError::Malformed("Invalid channel binding flag")),
+        }
+        let username = attribute(client_first_bare, 'n')
+            .filter(|_| client_first_bare.starts_with("n="))
+            .ok_or(AuthError::Malformed("Missing username"))?;
+        let username = decode_name(username)?;
+        if let Some(authorization_id) = authorization_id.strip_prefix("a=") {
+            // Acting as a different user is not supported
+            if decode_name(authorization_id)? != username {
+                return Err(AuthError::InvalidCredentials);
+            }
+        }
+        let nonce = attribute(client_first_bare, 'r')
+            .filter(|nonce| !nonce.is_empty())
+            .ok_or(AuthError::Malformed("Missing nonce"))?;
+        let gs2_header = format!("{},{},", flag, authorization_id);
+        self.state = ServerState::ClientFirst(ClientFirst {
+            gs2_header,
+            client_first_bare: client_first_bare.to_owned(),
+            nonce: nonce.to_owned(),
+        });
+        self.username = username;
+        Ok(&self.username)
+    }
+    /// Creates the server-first-message from the stored credentials of the user
+    pub fn server_first(&mut self, credentials: ScramCredentials) -> Result<Vec<u8>, AuthError> {
+        let ServerState::ClientFirst(client_first) =
+            std::mem::replace(&mut self.state, ServerState::Done)
+        else {
+            return Err(AuthError::Malformed("Unexpected message"));
+        };
+        if credentials.hash != self.hash {
+            return Err(AuthError::TemporaryFailure);
+        }
+        let nonce = format!("{}{}", client_first.nonce, self.server_nonce);
+        let server_first = format!(
+            "r={},s={},i={}",
+            nonce,
+            STANDARD.encode(&credentials.salt),
+            credentials.iterations
+        );
+        self.state = ServerState::ServerFirst {
+            client_final_prefix: format!("{},{}", client_first.client_first_bare, server_first),
+            gs2_header: client_first.gs2_header,
+            nonce,
+            credentials,
+        };
+        Ok(server_first.into_bytes())
+    }
+    /// Checks the proof in the client-final-message and returns the server-final-message
+    pub fn client_final(&mut self, message: &[u8]) -> Result<Vec<u8>, AuthError> {
+        let ServerState::ServerFirst {
+            client_final_prefix,
+            gs2_header,
+            nonce,
+            credentials,
+        } = std::mem::replace(&mut self.state, ServerState::Done)
+        else {
+            return Err(AuthError::Malformed("Unexpected message"));
+        };
+        let message = to_str(message)?;
+        let (without_proof, proof) = message
+            .rsplit_once(",p=")
+            .ok_or(AuthError::Malformed("Missing proof"))?;
+        let proof = STANDARD
+            .decode(proof)
+            .map_err(|_| AuthError::InvalidBase64)?;
+        if attribute(without_proof, 'r') != Some(nonce.as_str()) {
+            return Err(AuthError::Malformed("Invalid nonce"));
+        }
+        let mut expected_binding = gs2_header.into_bytes();
+        if let Some(channel_binding) = self.channel_binding.as_ref().filter(|_| self.plus) {
+            expected_binding.extend_from_slice(channel_binding.data());
+        }
+        let channel_binding = attribute(without_proof, 'c')
+            .ok_or(AuthError::Malformed("Missing channel binding"))
+            .and_then(|c| STANDARD.decode(c).map_err(|_| AuthError::InvalidBase64))?;
+        if !constant_time_eq(&channel_binding, &expected_binding) {
+            return Err(AuthError::InvalidCredentials);
+        }
+
+        let hash = self.hash;
+        let auth_message = format!("{},{}", client_final_prefix, without_proof);
+        let client_signature = hash.hmac(&credentials.stored_key, auth_message.as_bytes());
+        if proof.len() != client_signature.len() {
+            return Err(AuthError::InvalidCredentials);
+        }
+        let client_key = xor(&proof, &client_signature);
+        if !constant_time_eq(&hash.hash(&client_key), &credentials.stored_key) {
+            return Err(AuthError::InvalidCredentials);
+        }
+        let server_signature = hash.hmac(&credentials.server_key, auth_message.as_bytes());
+        Ok(format!("v={}", STANDARD.encode(server_signature)).into_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ChannelBinding, ScramClient, ScramCredentials, ScramHash, ScramServer, MAX_ITERATIONS,
+    };
+    use crate::auth::{client::SaslClient, AuthError, AuthMechanism};
+
+    /// Runs the exchange with fixed nonces. Returns every message
+    fn run(
+        mut client: ScramClient,
+        mut server: ScramServer,
+        credentials: ScramCredentials,
+    ) -> Result<Vec<String>, AuthError> {
+        let mut messages = Vec::new();
+        let client_first = client.initial_response().unwrap();
+        server.client_first(&client_first)?;
+        let server_first = server.server_first(credentials)?;
+        let client_final = client.respond(&server_first)?;
+        let server_final = server.client_final(&client_final)?;
+        assert!(client.respond(&server_final)?.is_empty());
+        for message in [client_first, server_first, client_final, server_final] {
+            messages.push(String::from_utf8(message).unwrap());
+        }
+        Ok(messages)
+    }
+    #[test]
+    fn test_sha1() {
+        // RFC 5802 Section 5
+        let mut client = ScramClient::new(ScramHash::Sha1, "user", "pencil");
+        client.nonce = "fyko+d2lbbFgONRv9qkxdawL".to_owned();
+        let mut server = ScramServer::new(&AuthMechanism::ScramSha1, None).unwrap();
+        server.server_nonce = "3rfcNHYJY1ZVvWVs7j".to_owned();
+        let salt = base64::Engine::decode(
+            &base64::engine::general_purpose::STANDARD,
+            "QSXCR+Q6sek8bf92",
+        )
+        .unwrap();
+        let credentials = ScramCredentials::new(ScramHash::Sha1, "pencil", salt, 4096);
+
+        let messages = run(client, server, credentials).unwrap();
+        assert_eq!(
+            messages,
+            [
+                "n,,n=user,r=fyko+d2lbbFgONRv9qkxdawL",
+                "r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,s=QSXCR+Q6sek8bf92,i=4096",
+                "c=biws,r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,p=v0X8v3Bz2T0CJGbJQyF0X+HI4Ts=",
+                "v=rmF9pqV8S7suAoZWja4dJRkFsKQ=",
+            ]
+        );
+    }
+    #[test]
+    fn test_sha256() {
+        // RFC 7677 Section 3
+        let mut client = ScramClient::new(ScramHash::Sha256, "user", "pencil");
+        client.nonce = "rOprNGfwEbeRWgbNEkqO".to_owned();
+        let mut server = ScramServer::new(&AuthMechanism::ScramSha256, None).unwrap();
+        server.server_nonce = "%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0".to_owned();
+        let credentials: ScramCredentials =
+            "SCRAM-SHA-256$4096:W22ZaJ0SNY7soEsUEjb6gQ==$WG5d8oPm3OtcPnkdi4Uo7BkeZkBFzpcXkuLmtbsT4qY=:wfPLwcE6nTWhTAmQ7tl2KeoiWGPlZqQxSrmfPwDl2dU="
+                .parse()
+                .unwrap();
+
+        let messages = run(client, server, credentials).unwrap();
+        assert_eq!(
+            messages[2],
+            "c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ="
+        );
+        assert_eq!(
+            messages[3],
+            "v=6rriTRBi23WpRR/wtup+mMhUZUn/dB5nLTJRsjl95G4="
+        );
+    }
+    #[test]
+    fn test_credentials() {
+        let credentials = ScramCredentials::generate(ScramHash::Sha256, "pencil");
+        let stored = credentials.to_string();
+        assert!(stored.starts_with("SCRAM-SHA-256$4096:"));
+        assert_eq!(stored.parse::<ScramCredentials>().unwrap(), credentials);
+        assert!("SCRAM-SHA-256$4096".parse::<ScramCredentials>().is_err());
+
+        let client = ScramClient::new(ScramHash::Sha256, "user", "wrong");
+        let server = ScramServer::new(&AuthMechanism::ScramSha256, None).unwrap();
+        assert_eq!(
+            run(client, server, credentials),
+            Err(AuthError::InvalidCredentials)
+        );
+    }
+    #[test]
+    fn test_max_iterations() {
+        let mut client = ScramClient::new(ScramHash::Sha256, "user", "pencil");
+        client.nonce = "rOprNGfwEbeRWgbNEkqO".to_owned();
+        client.initial_response().unwrap();
+        let server_first = format!(
+            "r=rOprNGfwEbeRWgbNEkqOserver,s=W22ZaJ0SNY7soEsUEjb6gQ==,i={}",
+            MAX_ITERATIONS + 1
+        );
+        assert_eq!(
+            client.respond(server_first.as_bytes()),
+            Err(AuthError::Malformed("Iteration count too large"))
+        );
+    }
+    #[test]
+    fn test_channel_binding() {
+        let binding = ChannelBinding::TlsExporter(vec![7; 32]);
+        let credentials = ScramCredentials::generate(ScramHash::Sha256, "pencil");
+
+        let client = ScramClient::new(ScramHash::Sha256, "user", "pencil")
+            .with_channel_binding(binding.clone());
+        assert_eq!(client.mechanism(), AuthMechanism::ScramSha256Plus);
+        let server =
+            ScramServer::new(&AuthMechanism::ScramSha256Plus, Some(binding.clone())).unwrap();
+        let messages = run(client, server, credentials.clone()).unwrap();
+        assert!(messages[0].starts_with("p=tls-exporter,,n=user,r="));
+
+        // A different TLS connection. Such as a man in the middle
+        let client = ScramClient::new(ScramHash::Sha256, "user", "pencil")
+            .with_channel_binding(ChannelBinding::TlsExporter(vec![8; 32]));
+        let server = ScramServer::new(&AuthMechanism::ScramSha256Plus, Some(binding)).unwrap();
+        assert_eq!(
+            run(client, server, credentials),
+            Err(AuthError::InvalidCredentials)
+        );
+
+        assert_eq!(
+            ScramServer::new(&AuthMechanism::ScramSha256Plus, None).unwrap_err(),
+            AuthError::EncryptionRequired
+        );
+    }
+    #[test]
+    fn test_username_escaping() {
+        let mut client = ScramClient::new(ScramHash::Sha256, "a,b=c", "pencil");
+        let mut server = ScramServer::new(&AuthMechanism::ScramSha256, None).unwrap();
+        let client_first = client.initial_response().unwrap();
+        assert!(client_first.starts_with(b"n,,n=a=2Cb=3Dc,r="));
+        assert_eq!(server.client_first(&client_first).unwrap(), "a,b=c");
+    }
+}
@@ -0,0 +1,386 @@
+/*!
+The server side of the SASL mechanisms
+
+[SaslServer] turns the responses of the client into [AuthCredentials] and passes them to an [Authenticator]
+
+The SCRAM mechanisms ask the [Authenticator] for the stored [ScramCredentials](super::scram::ScramCredentials) instead
+ */
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{
+    scram::{ChannelBinding, ScramServer},
+    AuthCredentials, AuthError, AuthMechanism, Authenticator,
+};
+
+/// The result of a step of the exchange
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerStep {
+    /// Send `334` with the challenge and wait for the next response
+    Challenge(Vec<u8>),
+    /// Authentication succeeded with the identity. Send `235`
+    Success(String),
+}
+#[derive(Debug)]
+enum State {
+    Start,
+    /// The empty challenge was sent because there was no initial response
+    WaitingForResponse,
+    LoginPassword {
+        username: String,
+    },
+    CramMd5 {
+        challenge: String,
+    },
+    /// The error challenge was sent. The exchange fails after the client responds
+    XOAuth2Failed(AuthError),
+    /// The server-first-message was sent
+    Scram(Box<ScramServer>),
+    /// The server-final-message was sent. The client responds with an empty line
+    ScramFinished {
+        identity: String,
+    },
+    Done,
+}
+/// The server side of one AUTH exchange
+#[derive(Debug)]
+pub struct SaslServer {
+    mechanism: AuthMechanism,
+    hostname: String,
+    channel_binding: Option<ChannelBinding>,
+    state: State,
+}
+impl SaslServer {
+    /// Returns [AuthError::UnsupportedMechanism] if the mechanism is not implemented
+    pub fn new(mechanism: AuthMechanism, hostname: impl Into<String>) -> Result<Self, AuthError> {
+        match mechanism {
+            AuthMechanism::Plain
+            | AuthMechanism::Login
+            | AuthMechanism::CramMd5
+            | AuthMechanism::XOAuth2
+            | AuthMechanism::ScramSha1
+            | AuthMechanism::ScramSha1Plus
+            | AuthMechanism::ScramSha256
+            | AuthMechanism::ScramSha256Plus => Ok(Self {
+                mechanism,
+                hostname: hostname.into(),
+                channel_binding: None,
+                state: State::Start,
+            }),
+            other => Err(AuthError::UnsupportedMechanism(other)),
+        }
+    }
+    /// The channel binding of the TLS connection. Required for the `-PLUS` mechanisms
+    pub fn with_channel_binding(mut self, channel_binding: Option<ChannelBinding>) -> Self {
+        self.channel_binding = channel_binding;
+        self
+    }
+    pub fn mechanism(&self) -> &AuthMechanism {
+        &self.mechanism
+    }
+    /// Handles the next response of the client.
+    ///
+    /// The first call passes the initial response sent with the AUTH command. None if there was none
+    pub async fn step<A: Authenticator + ?Sized>(
+        &mut self,
+        authenticator: &A,
+        response: Option<&[u8]>,
+    ) -> Result<ServerStep, AuthError> {
+        let state = std::mem::replace(&mut self.state, State::Done);
+        let credentials = match (state, response) {
+            (State::Done, _) => return Err(AuthError::Malformed("Authentication is finished")),
+            (State::XOAuth2Failed(error), _) => return Err(error),
+            (State::Start, None) => return Ok(self.first_challenge()),
+            (_, None) => return Err(AuthError::Malformed("Expected a response")),
+            (State::Scram(mut server), Some(response)) => {
+                let server_final = server.client_final(response)?;
+                self.state = State::ScramFinished {
+                    identity: server.username().to_owned(),
+                };
+                return Ok(ServerStep::Challenge(server_final));
+            }
+            (State::ScramFinished { identity }, Some(response)) => {
+                if !response.is_empty() {
+                    return Err(AuthError::Malformed("Expected an empty response"));
+                }
+                return Ok(ServerStep::Success(identity));
+            }
+            (State::LoginPassword { username }, Some(response)) => AuthCredentials::Password {
+                authorization_id: None,
+                username,
+                password: to_string(response)?,
+            },
+            (State::CramMd5 { challenge }, Some(response)) => {
+                let response = to_string(response)?;
+                let (username, digest) = response
+                    .rsplit_once(' ')
+                    .ok_or(AuthError::Malformed("Expected `username digest`"))?;
+                AuthCredentials::CramMd5 {
+                    username: username.to_owned(),
+                    challenge,
+                    digest: digest.to_owned(),
+                }
+            }
+            (State::Start | State::WaitingForResponse, Some(response)) => match self.mechanism {
+                AuthMechanism::Plain => parse_plain(response)?,
+                AuthMechanism::Login => {
+                    self.state = State::LoginPassword {
+                        username: to_string(response)?,
+                    };
+                    return Ok(ServerStep::Challenge(b"Password:".to_vec()));
+                }
+                AuthMechanism::XOAuth2 => parse_xoauth2(response)?,
+                AuthMechanism::ScramSha1
+                | AuthMechanism::ScramSha1Plus
+                | AuthMechanism::ScramSha256
+                | AuthMechanism::ScramSha256Plus => {
+                    return self.scram_client_first(authenticator, response).await;
+                }
+                _ => {
+                    return Err(AuthError::Malformed(
+                        "The mechanism does not take an initial response",
+                    ));
+                }
+            },
+        };
+        match authenticator.authenticate(credentials).await {
+            Ok(identity) => Ok(ServerStep::Success(identity)),
+            // The error is sent as a challenge and the client has to respond before the final reply
+            Err(error) if self.mechanism == AuthMechanism::XOAuth2 => {
+                self.state = State::XOAuth2Failed(error);
+                Ok(ServerStep::Challenge(
+                    br#"{"status":"401","schemes":"bearer"}"#.to_vec(),
+                ))
+            }
+            Err(error) => Err(error),
+        }
+    }
+    /// Looks up the credentials of the user and sends the server-first-message
+    async fn scram_client_first<A: Authenticator + ?Sized>(
+        &mut self,
+        authenticator: &A,
+        response: &[u8],
+    ) -> Result<ServerStep, AuthError> {
+        let mut server = ScramServer::new(&self.mechanism, self.channel_binding.clone())?;
+        server.client_first(response)?;
+        let credentials = authenticator
+            .scram_credentials(server.hash(), server.username())
+            .await?;
+        let server_first = server.server_first(credentials)?;
+        self.state = State::Scram(Box::new(server));
+        Ok(ServerStep::Challenge(server_first))
+    }
+    fn first_challenge(&mut self) -> ServerStep {
+        match self.mechanism {
+            AuthMechanism::Login => {
+                // The username is the response to this challenge
+                self.state = State::WaitingForResponse;
+                ServerStep::Challenge(b"Username:".to_vec())
+            }
+            AuthMechanism::CramMd5 => {
+                let timestamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                let challenge = format!(
+                    "<{}.{}@{}>",
+                    rand::random::<u32>(),
+                    timestamp,
+                    self.hostname
+                );
+                let step = ServerStep::Challenge(challenge.as_bytes().to_vec());
+                self.state = State::CramMd5 { challenge };
+                step
+            }
+            _ => {
+                self.state = State::WaitingForResponse;
+                ServerStep::Challenge(Vec::new())
+            }
+        }
+    }
+}
+fn to_string(value: &[u8]) -> Result<String, AuthError> {
+    String::from_utf8(value.to_vec()).map_err(|_| AuthError::Malformed("Invalid UTF-8"))
+}
+/// `[authzid] NUL authcid NUL passwd`
+fn parse_plain(response: &[u8]) -> Result<AuthCredentials, AuthError> {
+    let mut parts = response.split(|byte| *byte == 0);
+    let (Some(authorization_id), Some(username), Some(password), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(AuthError::Malformed(
+            "Expected `[authzid] NUL authcid NUL passwd`",
+        ));
+    };
+    if username.is_empty() {
+        return Err(AuthError::Malformed("Missing username"));
+    }
+    Ok(AuthCredentials::Password {
+        authorization_id: (!authorization_id.is_empty())
+            .then(|| to_string(authorization_id))
+            .transpose()?,
+        username: to_string(username)?,
+        password: to_string(password)?,
+    })
+}
+/// `user=<username>^Aauth=Bearer <token>^A^A`
+fn parse_xoauth2(response: &[u8]) -> Result<AuthCredentials, AuthError> {
+    let response = to_string(response)?;
+    let mut username = None;
+    let mut token = None;
+    for field in response.split('\x01') {
+        if let Some(value) = field.strip_prefix("user=") {
+            username = Some(value);
+        } else if let Some(value) = field.strip_prefix("auth=") {
+            token = value
+                .get(..7)
+                .filter(|scheme| scheme.eq_ignore_ascii_case("Bearer "))
+                .map(|_| &value[7..]);
+        }
+    }
+    match (username, token) {
+        (Some(username), Some(token)) => Ok(AuthCredentials::XOAuth2 {
+            username: username.to_owned(),
+            token: token.to_owned(),
+        }),
+        _ => Err(AuthError::Malformed("Expected `user=` and `auth=Bearer`")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use futures::executor::block_on;
+
+    use super::{SaslServer, ServerStep};
+    use crate::auth::{
+        client::{CramMd5Client, LoginClient, PlainClient, SaslClient, XOAuth2Client},
+        scram::{ChannelBinding, ScramClient, ScramCredentials, ScramHash},
+        AuthCredentials, AuthError, AuthMechanism, Authenticator,
+    };
+
+    struct TestAuthenticator;
+    #[async_trait]
+    impl Authenticator for TestAuthenticator {
+        async fn authenticate(&self, credentials: AuthCredentials) -> Result<String, AuthError> {
+            let valid = match &credentials {
+                AuthCredentials::XOAuth2 { token, .. } => token == "token",
+                credentials => credentials.verify_password("secret"),
+            };
+            if credentials.username() == "tim" && valid {
+                Ok(credentials.username().to_owned())
+            } else {
+                Err(AuthError::InvalidCredentials)
+            }
+        }
+        async fn scram_credentials(
+            &self,
+            hash: ScramHash,
+            username: &str,
+        ) -> Result<ScramCredentials, AuthError> {
+            if username == "tim" {
+                Ok(ScramCredentials::new(
+                    hash,
+                    "secret",
+                    b"salt".to_vec(),
+                    4096,
+                ))
+            } else {
+                Err(AuthError::InvalidCredentials)
+            }
+        }
+    }
+    /// Runs the exchange the same way the server does
+    fn run(client: &mut dyn SaslClient) -> Result<String, AuthError> {
+        run_with_channel_binding(client, None)
+    }
+    fn run_with_channel_binding(
+        client: &mut dyn SaslClient,
+        channel_binding: Option<ChannelBinding>,
+    ) -> Result<String, AuthError> {
+        let mut server = SaslServer::new(client.mechanism(), "mail.example.com")?
+            .with_channel_binding(channel_binding);
+        let mut response = client.initial_response();
+        loop {
+            match block_on(server.step(&TestAuthenticator, response.as_deref()))? {
+                ServerStep::Success(identity) => return Ok(identity),
+                ServerStep::Challenge(challenge) => {
+                    response = Some(client.respond(&challenge)?);
+                }
+            }
+        }
+    }
+    #[test]
+    fn test_mechanisms() {
+        assert_eq!(run(&mut PlainClient::new("tim", "secret")).unwrap(), "tim");
+        assert_eq!(run(&mut LoginClient::new("tim", "secret")).unwrap(), "tim");
+        assert_eq!(
+            run(&mut CramMd5Client::new("tim", "secret")).unwrap(),
+            "tim"
+        );
+        assert_eq!(run(&mut XOAuth2Client::new("tim", "token")).unwrap(), "tim");
+        assert_eq!(
+            run(&mut ScramClient::new(ScramHash::Sha1, "tim", "secret")).unwrap(),
+            "tim"
+        );
+        assert_eq!(
+            run(&mut ScramClient::new(ScramHash::Sha256, "tim", "secret")).unwrap(),
+            "tim"
+        );
+
+        assert_eq!(
+            run(&mut PlainClient::new("tim", "wrong")),
+            Err(AuthError::InvalidCredentials)
+        );
+        assert_eq!(
+            run(&mut CramMd5Client::new("tim", "wrong")),
+            Err(AuthError::InvalidCredentials)
+        );
+        assert_eq!(
+            run(&mut XOAuth2Client::new("tim", "wrong")),
+            Err(AuthError::InvalidCredentials)
+        );
+        assert_eq!(
+            run(&mut ScramClient::new(ScramHash::Sha256, "tim", "wrong")),
+            Err(AuthError::InvalidCredentials)
+        );
+        assert_eq!(
+            run(&mut ScramClient::new(ScramHash::Sha256, "bob", "secret")),
+            Err(AuthError::InvalidCredentials)
+        );
+    }
+    #[test]
+    fn test_scram_channel_binding() {
+        let binding = ChannelBinding::TlsExporter(vec![1; 32]);
+        let mut client = ScramClient::new(ScramHash::Sha256, "tim", "secret")
+            .with_channel_binding(binding.clone());
+        assert_eq!(
+            run_with_channel_binding(&mut client, Some(binding.clone())).unwrap(),
+            "tim"
+        );
+        // Without TLS there is nothing to bind to
+        let mut client =
+            ScramClient::new(ScramHash::Sha1, "tim", "secret").with_channel_binding(binding);
+        assert_eq!(
+            run_with_channel_binding(&mut client, None),
+            Err(AuthError::EncryptionRequired)
+        );
+    }
+    #[test]
+    fn test_plain_without_initial_response() {
+        let mut server = SaslServer::new(AuthMechanism::Plain, "mail.example.com").unwrap();
+        let step = block_on(server.step(&TestAuthenticator, None)).unwrap();
+        assert_eq!(step, ServerStep::Challenge(Vec::new()));
+        let step = block_on(server.step(&TestAuthenticator, Some(b"\0tim\0secret"))).unwrap();
+        assert_eq!(step, ServerStep::Success("tim".to_owned()));
+    }
+    #[test]
+    fn test_malformed() {
+        let mut server = SaslServer::new(AuthMechanism::Plain, "mail.example.com").unwrap();
+        let result = block_on(server.step(&TestAuthenticator, Some(b"tim\0secret")));
+        assert!(matches!(result, Err(AuthError::Malformed(_))));
+        assert_eq!(
+            SaslServer::new(AuthMechanism::Other("GSSAPI".to_owned()), "").unwrap_err(),
+            AuthError::UnsupportedMechanism(AuthMechanism::Other("GSSAPI".to_owned()))
+        );
+    }
+}
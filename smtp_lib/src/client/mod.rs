@@ -0,0 +1,1085 @@
+/*!
+# Tokio SMTP Client
+
+Sends messages to an SMTP server. Requires the `client` feature.
+
+```rust,no_run
+use mail_lib_types::EmailAddress;
+use smtp_lib::{
+    client::Client,
+    commands::command_impls::{MailFromCommandData, RcptToCommandData},
+    session::Envelope,
+};
+# async fn run() -> smtp_lib::Result<()> {
+let mut client = Client::connect("mail.example.com:25", "client.example.com").await?;
+
+let mut envelope = Envelope::new(MailFromCommandData::new(Some(
+    EmailAddress::new("sender@example.com").unwrap(),
+)));
+envelope.recipients.push(RcptToCommandData::new(
+    EmailAddress::new("user@example.com").unwrap(),
+));
+let results = client
+    .send(&envelope, b"Subject: Hello\r\n\r\nHello World\r\n")
+    .await?;
+assert!(results.iter().all(|result| result.is_delivered()));
+client.quit().await?;
+# Ok(())
+# }
+```
+ */
+#[cfg(feature = "rustls")]
+use std::sync::Arc;
+use std::{fmt::Debug, io, time::Duration};
+
+use bytes::BytesMut;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    net::{TcpStream, ToSocketAddrs},
+};
+#[cfg(feature = "rustls")]
+use tokio_rustls::rustls::{pki_types::ServerName, ClientConfig};
+use tracing::debug;
+
+use crate::{
+    auth::{
+        client::SaslClient, decode_sasl, encode_sasl, scram::ChannelBinding, AuthError,
+        AuthMechanism,
+    },
+    commands::{
+        async_traits::AsyncSMTPCommand,
+        command_impls::{
+            AuthCommand, AuthCommandData, BdatCommand, BdatCommandData, BodyType, DataCommand,
+            EhloCommand, EhloCommandData, EhloResponse, ForwardPath, HeloCommand, LhloCommand,
+            MailFromCommand, MailFromCommandData, MailParameter, QuitCommand, RcptToCommand,
+            RcptToCommandData, RsetCommand,
+        },
+    },
+    data::{DataDecoder, DataEncoder, DecodeStatus},
+    error::SMTPError,
+    server_response::{MultilineServerResponse, ResponseCode},
+    session::Envelope,
+    smtp_client::{async_traits::AsyncSMTPClient, SMTPClient},
+    smtp_server::{Capabilities, SMTPServerExtension},
+    statement::MultiLineStatement,
+    tls::MaybeTlsStream,
+    BoxSendFuture, CRLF, END_OF_MULTILINE_COMMAND,
+};
+#[cfg(feature = "rustls")]
+use crate::{
+    commands::command_impls::StartTlsCommand, smtp_client::async_traits::AsyncStartTlsClient,
+};
+
+/// The result of sending a message to one recipient
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecipientResult {
+    pub forward_path: ForwardPath,
+    /// The reply to RCPT TO
+    pub rcpt_response: MultilineServerResponse,
+    /// The reply after the message was sent. With LMTP every recipient gets its own reply.
+    ///
+    /// None if the recipient was rejected
+    pub data_response: Option<MultilineServerResponse>,
+}
+impl RecipientResult {
+    /// If the server accepted the recipient
+    pub fn is_accepted(&self) -> bool {
+        is_positive(&self.rcpt_response)
+    }
+    /// If the server accepted the message for this recipient
+    pub fn is_delivered(&self) -> bool {
+        self.data_response.as_ref().is_some_and(is_positive)
+    }
+}
+fn is_positive(response: &MultilineServerResponse) -> bool {
+    response
+        .code()
+        .is_some_and(|code| code.is_positive_completion())
+}
+/// Fails with [SMTPError::Rejected] if the response is not a positive completion
+fn expect_positive(response: MultilineServerResponse) -> crate::Result<MultilineServerResponse> {
+    if is_positive(&response) {
+        Ok(response)
+    } else {
+        Err(SMTPError::Rejected(response))
+    }
+}
+
+/// The default size of a BDAT chunk
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// A connection to an SMTP server
+pub struct Client<S> {
+    stream: BufReader<MaybeTlsStream<S>>,
+    hostname: String,
+    greeting: MultilineServerResponse,
+    extended: bool,
+    lmtp: bool,
+    capabilities: Capabilities,
+    timeout: Duration,
+    chunk_size: usize,
+    #[cfg(feature = "rustls")]
+    tls: Option<(Arc<ClientConfig>, ServerName<'static>)>,
+}
+impl Client<TcpStream> {
+    /// Connects to the server. Reads the greeting and sends EHLO
+    pub async fn connect(
+        addr: impl ToSocketAddrs,
+        hostname: impl Into<String>,
+    ) -> crate::Result<Self> {
+        let stream = TcpStream::connect(addr).await?;
+        Self::new(stream, hostname).await
+    }
+    /// Connects to an LMTP server. Reads the greeting and sends LHLO
+    pub async fn connect_lmtp(
+        addr: impl ToSocketAddrs,
+        hostname: impl Into<String>,
+    ) -> crate::Result<Self> {
+        let stream = TcpStream::connect(addr).await?;
+        Self::new_lmtp(stream, hostname).await
+    }
+}
+impl<S> Client<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    /// Reads the greeting and sends EHLO. Falls back to HELO if the server does not support EHLO.
+    ///
+    /// `hostname` is the name of this client sent with EHLO
+    pub async fn new(stream: S, hostname: impl Into<String>) -> crate::Result<Self> {
+        Self::start(stream, hostname.into(), false).await
+    }
+    /// Reads the greeting and sends LHLO.
+    ///
+    /// The client speaks LMTP. Every accepted recipient gets its own reply to the message.
+    /// [RFC 2033](https://datatracker.ietf.org/doc/html/rfc2033)
+    pub async fn new_lmtp(stream: S, hostname: impl Into<String>) -> crate::Result<Self> {
+        Self::start(stream, hostname.into(), true).await
+    }
+    async fn start(stream: S, hostname: String, lmtp: bool) -> crate::Result<Self> {
+        let mut client = Self {
+            stream: BufReader::new(MaybeTlsStream::Plain(stream)),
+            hostname,
+            greeting: MultilineServerResponse::new(Vec::new()),
+            extended: false,
+            lmtp,
+            capabilities: Capabilities::default(),
+            // RFC 5321 Section 4.5.3.2.1
+            timeout: Duration::from_secs(5 * 60),
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            #[cfg(feature = "rustls")]
+            tls: None,
+        };
+        let greeting = client.read_response().await?;
+        if greeting.code() != Some(ResponseCode::ServiceReady) {
+            return Err(SMTPError::Rejected(greeting));
+        }
+        client.greeting = greeting;
+        client.ehlo().await?;
+        Ok(client)
+    }
+    /// How long to wait for a reply. Defaults to 5 minutes
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+    /// The size of each BDAT chunk. Defaults to [DEFAULT_CHUNK_SIZE]
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+    /// The TLS config used by [Client::start_tls]. `server_name` is used to verify the certificate of the server
+    #[cfg(feature = "rustls")]
+    pub fn with_tls(mut self, config: Arc<ClientConfig>, server_name: ServerName<'static>) -> Self {
+        self.tls = Some((config, server_name));
+        self
+    }
+    /// If the connection is encrypted
+    pub fn is_tls(&self) -> bool {
+        self.stream.get_ref().is_tls()
+    }
+    /// The channel binding of the TLS connection. Pass it to [ScramClient::with_channel_binding](crate::auth::scram::ScramClient::with_channel_binding)
+    /// to use the `-PLUS` mechanisms
+    pub fn channel_binding(&self) -> Option<ChannelBinding> {
+        self.stream.get_ref().channel_binding()
+    }
+    /// The `220` greeting sent by the server
+    pub fn greeting(&self) -> &MultilineServerResponse {
+        &self.greeting
+    }
+    /// If the server accepted EHLO. False if the client fell back to HELO
+    pub fn is_extended(&self) -> bool {
+        self.extended
+    }
+    /// If the client speaks LMTP
+    pub fn is_lmtp(&self) -> bool {
+        self.lmtp
+    }
+    /// The extensions the server sent in the EHLO response
+    pub fn extensions(&self) -> &[SMTPServerExtension] {
+        self.capabilities.as_slice()
+    }
+    /// The extensions with lookups such as [Capabilities::supports_dsn]
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+    /// The value of the `SIZE` extension
+    pub fn max_message_size(&self) -> Option<usize> {
+        self.capabilities.max_size()
+    }
+    /// If the server accepts groups of commands without waiting for each reply
+    ///
+    /// [RFC 2920](https://datatracker.ietf.org/doc/html/rfc2920)
+    pub fn supports_pipelining(&self) -> bool {
+        self.capabilities.supports_pipelining()
+    }
+    /// If the server accepts BDAT. The message is then sent with BDAT instead of DATA
+    ///
+    /// [RFC 3030](https://datatracker.ietf.org/doc/html/rfc3030)
+    pub fn supports_chunking(&self) -> bool {
+        self.capabilities.supports_chunking()
+    }
+    /// The mechanisms of the `AUTH` extension
+    pub fn auth_mechanisms(&self) -> &[AuthMechanism] {
+        self.capabilities.auth_mechanisms()
+    }
+    /// Sends EHLO and stores the extensions. Falls back to HELO if the server does not support EHLO
+    ///
+    /// LMTP sends LHLO without a fall back
+    pub async fn ehlo(&mut self) -> crate::Result<()> {
+        let hostname = EhloCommandData::from(self.hostname.clone());
+        let mut response = if self.lmtp {
+            LhloCommand::client_send(hostname.clone(), self).await?;
+            LhloCommand::client_read(self).await?
+        } else {
+            EhloCommand::client_send(hostname.clone(), self).await?;
+            EhloCommand::client_read(self).await?
+        };
+        self.extended = true;
+        if !self.lmtp && response.should_fall_back_to_helo() {
+            debug!("Server does not support EHLO. Falling back to HELO");
+            HeloCommand::client_send(hostname, self).await?;
+            response = HeloCommand::client_read(self).await?;
+            self.extended = false;
+        }
+        match response {
+            EhloResponse::Success { extensions, .. } => {
+                self.capabilities = extensions.into();
+                Ok(())
+            }
+            EhloResponse::Error { code, message } => Err(SMTPError::Rejected(
+                MultilineServerResponse::new_with_code(code, Some(message), 0),
+            )),
+        }
+    }
+    /// Sends STARTTLS, performs the TLS handshake and sends EHLO again.
+    ///
+    /// Requires [Client::with_tls]
+    #[cfg(feature = "rustls")]
+    pub async fn start_tls(&mut self) -> crate::Result<()> {
+        if self.is_tls() {
+            return Err(SMTPError::StartTls("TLS already active"));
+        }
+        if !self.capabilities.supports_start_tls() {
+            return Err(SMTPError::StartTls("The server does not support STARTTLS"));
+        }
+        StartTlsCommand::client_send((), self).await?;
+        let response = StartTlsCommand::client_read(self).await?;
+        if response.code() != Some(ResponseCode::ServiceReady) {
+            return Err(SMTPError::Rejected(response));
+        }
+        self.upgrade().await?;
+        self.ehlo().await
+    }
+    #[cfg(feature = "rustls")]
+    async fn upgrade(&mut self) -> crate::Result<()> {
+        let Some((config, server_name)) = self.tls.clone() else {
+            return Err(SMTPError::StartTls("No TLS config"));
+        };
+        // Anything sent before the handshake could have been injected by an attacker
+        if !self.stream.buffer().is_empty() {
+            return Err(SMTPError::StartTls(
+                "The server sent data after the 220 reply",
+            ));
+        }
+        self.stream
+            .get_mut()
+            .upgrade_client(config, server_name)
+            .await?;
+        self.extended = false;
+        self.capabilities.clear();
+        debug!("TLS handshake finished");
+        Ok(())
+    }
+    /// Sends AUTH and runs the `334` exchange with the mechanism.
+    ///
+    /// Fails with [SMTPError::Rejected] if the server rejects the credentials.
+    /// If the mechanism can not respond to a challenge the exchange is cancelled with `*`.
+    /// A `235` before the mechanism is complete fails. SCRAM has not verified the server at that point
+    pub async fn authenticate(&mut self, sasl: &mut dyn SaslClient) -> crate::Result<()> {
+        let mechanism = sasl.mechanism();
+        if !self.auth_mechanisms().contains(&mechanism) {
+            return Err(AuthError::UnsupportedMechanism(mechanism).into());
+        }
+        let command = AuthCommandData::new(mechanism, sasl.initial_response());
+        AuthCommand::client_send(command, self).await?;
+        loop {
+            let response = AuthCommand::client_read(self).await?;
+            match response.code() {
+                Some(ResponseCode::AuthenticationSucceeded) => {
+                    if !sasl.is_complete() {
+                        return Err(AuthError::Malformed(
+                            "The server accepted before the exchange was complete",
+                        )
+                        .into());
+                    }
+                    debug!("Authenticated");
+                    return Ok(());
+                }
+                Some(ResponseCode::ServerChallenge) => {
+                    let challenge = response
+                        .0
+                        .first()
+                        .and_then(|line| line.message())
+                        .unwrap_or_default();
+                    match decode_sasl(challenge).and_then(|challenge| sasl.respond(&challenge)) {
+                        Ok(reply) => {
+                            let reply = format!("{}{}", encode_sasl(&reply), CRLF);
+                            self.write_bytes(reply.as_bytes()).await?;
+                        }
+                        Err(error) => {
+                            self.write_bytes(b"*\r\n").await?;
+                            // The server confirms the cancellation with 501
+                            AuthCommand::client_read(self).await?;
+                            return Err(error.into());
+                        }
+                    }
+                }
+                _ => return Err(SMTPError::Rejected(response)),
+            }
+        }
+    }
+    /// Sends a message.
+    ///
+    /// MAIL FROM and the RCPT TO commands are sent in one write if the server supports PIPELINING.
+    /// The message is sent with BDAT if the server supports CHUNKING.
+    /// `BODY=BINARYMIME` fails with [SMTPError::MissingExtension] if the server does not support it
+    ///
+    /// Returns an error if the server rejects MAIL FROM.
+    /// Recipients rejected by the server are returned with [RecipientResult::is_accepted] false.
+    /// If no recipients are accepted the transaction is reset and the message is not sent.
+    /// With LMTP the reply to the message is read for every accepted recipient
+    pub async fn send(
+        &mut self,
+        envelope: &Envelope,
+        message: &[u8],
+    ) -> crate::Result<Vec<RecipientResult>> {
+        let mut mail_from = envelope.mail_from.clone();
+        if mail_from.body() == Some(BodyType::BinaryMime)
+            && !self.capabilities.supports_binary_mime()
+        {
+            return Err(SMTPError::MissingExtension("BINARYMIME"));
+        }
+        if let Some(max_size) = self.max_message_size() {
+            if message.len() > max_size {
+                return Err(SMTPError::MessageTooLarge(max_size));
+            }
+            if mail_from.size().is_none() {
+                mail_from
+                    .parameters
+                    .push(MailParameter::Size(message.len()));
+            }
+        }
+        let mut results = if self.supports_pipelining() {
+            self.send_envelope_pipelined(mail_from, &envelope.recipients)
+                .await?
+        } else {
+            self.send_envelope(mail_from, &envelope.recipients).await?
+        };
+        let accepted = results.iter().filter(|result| result.is_accepted()).count();
+        if accepted == 0 {
+            self.reset().await?;
+            return Ok(results);
+        }
+
+        let data_responses = if self.supports_chunking() {
+            self.send_chunks(message, accepted).await?
+        } else {
+            DataCommand::client_send((), self).await?;
+            match DataCommand::client_read(self).await? {
+                response if response.is_start_mail_input() => {
+                    let mut encoder = DataEncoder::new();
+                    let mut body = BytesMut::with_capacity(message.len() + 5);
+                    encoder.encode(message, &mut body);
+                    encoder.finish(&mut body);
+                    self.write_bytes(&body).await?;
+                    self.read_message_responses(accepted).await?
+                }
+                response => vec![response.into_inner()],
+            }
+        };
+        // A single reply applies to every recipient
+        for (index, result) in results
+            .iter_mut()
+            .filter(|result| result.is_accepted())
+            .enumerate()
+        {
+            result.data_response = data_responses
+                .get(index)
+                .or(data_responses.first())
+                .cloned();
+        }
+        Ok(results)
+    }
+    /// Sends MAIL FROM and waits for the reply before each RCPT TO
+    async fn send_envelope(
+        &mut self,
+        mail_from: MailFromCommandData,
+        recipients: &[RcptToCommandData],
+    ) -> crate::Result<Vec<RecipientResult>> {
+        MailFromCommand::client_send(mail_from, self).await?;
+        expect_positive(MailFromCommand::client_read(self).await?)?;
+
+        let mut results = Vec::with_capacity(recipients.len());
+        for recipient in recipients {
+            RcptToCommand::client_send(recipient.clone(), self).await?;
+            let response = RcptToCommand::client_read(self).await?;
+            results.push(RecipientResult {
+                forward_path: recipient.forward_path.clone(),
+                rcpt_response: response,
+                data_response: None,
+            });
+        }
+        Ok(results)
+    }
+    /// Sends MAIL FROM and every RCPT TO in one write. Then reads the replies in the order the commands were sent
+    ///
+    /// [RFC 2920 Section 3.1](https://datatracker.ietf.org/doc/html/rfc2920#section-3.1)
+    async fn send_envelope_pipelined(
+        &mut self,
+        mail_from: MailFromCommandData,
+        recipients: &[RcptToCommandData],
+    ) -> crate::Result<Vec<RecipientResult>> {
+        let mut commands = format!("MAIL FROM:{}{}", mail_from, CRLF);
+        for recipient in recipients {
+            commands.push_str(&format!("RCPT TO:{}{}", recipient, CRLF));
+        }
+        self.write_string(commands).await?;
+        debug!(recipients = recipients.len(), "Pipelined envelope");
+
+        // Every reply is read. Even if MAIL FROM was rejected. Otherwise the replies would be matched to the wrong commands
+        let mail_from_response = MailFromCommand::client_read(self).await?;
+        let mut results = Vec::with_capacity(recipients.len());
+        for recipient in recipients {
+            let response = RcptToCommand::client_read(self).await?;
+            results.push(RecipientResult {
+                forward_path: recipient.forward_path.clone(),
+                rcpt_response: response,
+                data_response: None,
+            });
+        }
+        expect_positive(mail_from_response)?;
+        Ok(results)
+    }
+    /// Sends the message with BDAT in chunks of [Client::with_chunk_size].
+    ///
+    /// Returns the replies to the last chunk or the reply to the first chunk the server rejected
+    async fn send_chunks(
+        &mut self,
+        message: &[u8],
+        accepted: usize,
+    ) -> crate::Result<Vec<MultilineServerResponse>> {
+        let mut start = 0;
+        loop {
+            let end = (start + self.chunk_size).min(message.len());
+            let last = end == message.len();
+            let chunk = &message[start..end];
+            BdatCommand::client_send(BdatCommandData::new(chunk.len(), last), self).await?;
+            self.write_bytes(chunk).await?;
+            if last {
+                return self.read_message_responses(accepted).await;
+            }
+            let response = BdatCommand::client_read(self).await?;
+            if !is_positive(&response) {
+                // The server might still consider the transaction open
+                self.reset().await?;
+                return Ok(vec![response]);
+            }
+            start = end;
+        }
+    }
+    /// Reads the replies after the entire message was sent. LMTP sends one for every accepted recipient
+    ///
+    /// [RFC 2033 Section 4.2](https://datatracker.ietf.org/doc/html/rfc2033#section-4.2)
+    async fn read_message_responses(
+        &mut self,
+        accepted: usize,
+    ) -> crate::Result<Vec<MultilineServerResponse>> {
+        let replies = if self.lmtp { accepted } else { 1 };
+        let mut responses = Vec::with_capacity(replies);
+        for _ in 0..replies {
+            responses.push(self.read_response().await?);
+        }
+        Ok(responses)
+    }
+    /// Sends RSET. Aborts the current mail transaction
+    pub async fn reset(&mut self) -> crate::Result<()> {
+        RsetCommand::client_send((), self).await?;
+        expect_positive(RsetCommand::client_read(self).await?)?;
+        Ok(())
+    }
+    /// Sends QUIT and closes the connection
+    pub async fn quit(mut self) -> crate::Result<()> {
+        QuitCommand::client_send((), &mut self).await?;
+        QuitCommand::client_read(&mut self).await?;
+        self.stream.get_mut().shutdown().await?;
+        Ok(())
+    }
+    /// Returns the underlying stream
+    pub fn into_inner(self) -> MaybeTlsStream<S> {
+        self.stream.into_inner()
+    }
+    async fn write_bytes(&mut self, bytes: &[u8]) -> crate::Result<()> {
+        let stream = self.stream.get_mut();
+        stream.write_all(bytes).await?;
+        stream.flush().await?;
+        Ok(())
+    }
+    async fn read_response(&mut self) -> crate::Result<MultilineServerResponse> {
+        let lines = self.read_lines().await?;
+        Ok(MultilineServerResponse::from_lines(lines)?)
+    }
+    /// Reads a line without the CRLF
+    async fn next_line(&mut self) -> crate::Result<String> {
+        let mut line = String::new();
+        let read = tokio::time::timeout(self.timeout, self.stream.read_line(&mut line))
+            .await
+            .map_err(|_| io::Error::from(io::ErrorKind::TimedOut))??;
+        if read == 0 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        line.truncate(line.trim_end_matches(['\r', '\n']).len());
+        Ok(line)
+    }
+    /// Reads lines until a line without a hyphen after the code
+    async fn read_lines(&mut self) -> crate::Result<Vec<String>> {
+        let mut lines = Vec::new();
+        loop {
+            let line = self.next_line().await?;
+            let last = line.as_bytes().get(3) != Some(&b'-');
+            lines.push(line);
+            if last {
+                return Ok(lines);
+            }
+        }
+    }
+}
+impl<S> Debug for Client<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Client")
+            .field("hostname", &self.hostname)
+            .field("extended", &self.extended)
+            .field("lmtp", &self.lmtp)
+            .field("capabilities", &self.capabilities)
+            .finish()
+    }
+}
+impl<S> SMTPClient for Client<S> {
+    fn get_hostname(&self) -> &str {
+        &self.hostname
+    }
+
+    fn get_end_of_multiline_command(&self) -> &str {
+        END_OF_MULTILINE_COMMAND
+    }
+}
+impl<'a, S> AsyncSMTPClient<'a> for Client<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'a,
+{
+    type ReadLineFuture = BoxSendFuture<'a, crate::Result<String>>;
+    type WriteFuture = BoxSendFuture<'a, crate::Result<()>>;
+    type ReadTilEndFuture = BoxSendFuture<'a, crate::Result<String>>;
+    type ReadTilNonHyphenatedLine = BoxSendFuture<'a, crate::Result<Vec<String>>>;
+
+    fn read_line(&'a mut self) -> Self::ReadLineFuture {
+        Box::pin(self.next_line())
+    }
+
+    fn write_string(&'a mut self, command: String) -> Self::WriteFuture {
+        Box::pin(async move { self.write_bytes(command.as_bytes()).await })
+    }
+
+    fn read_til_non_hyphenated_line(&'a mut self) -> Self::ReadTilNonHyphenatedLine {
+        Box::pin(self.read_lines())
+    }
+
+    /// Reads a dot-terminated body. The dot-stuffing and the terminator are removed
+    ///
+    /// Invalid UTF-8 is replaced with `U+FFFD` rather than failing
+    fn read_til_end(&'a mut self) -> Self::ReadTilEndFuture {
+        Box::pin(async move {
+            let mut decoder = DataDecoder::new();
+            let mut content = BytesMut::new();
+            let mut line = Vec::new();
+            loop {
+                line.clear();
+                // Read a line at a time so nothing after the terminator is consumed
+                let read =
+                    tokio::time::timeout(self.timeout, self.stream.read_until(b'\n', &mut line))
+                        .await
+                        .map_err(|_| io::Error::from(io::ErrorKind::TimedOut))??;
+                if read == 0 {
+                    return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+                }
+                if let DecodeStatus::Finished { .. } = decoder.decode(&line, &mut content) {
+                    return Ok(String::from_utf8_lossy(&content).into_owned());
+                }
+            }
+        })
+    }
+}
+#[cfg(feature = "rustls")]
+impl<'a, S> AsyncStartTlsClient<'a> for Client<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'a,
+{
+    type UpgradeFuture = BoxSendFuture<'a, crate::Result<()>>;
+
+    fn is_tls(&self) -> bool {
+        Client::is_tls(self)
+    }
+
+    fn upgrade_to_tls(&'a mut self) -> Self::UpgradeFuture {
+        Box::pin(self.upgrade())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use mail_lib_types::EmailAddress;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, DuplexStream};
+
+    use super::Client;
+    use crate::{
+        auth::{
+            client::LoginClient,
+            decode_sasl, encode_sasl,
+            scram::{ScramClient, ScramHash},
+            AuthError, AuthMechanism,
+        },
+        commands::command_impls::{
+            BdatCommandData, BodyType, MailFromCommandData, MailParameter, RcptToCommandData,
+        },
+        error::SMTPError,
+        server_response::ResponseCode,
+        session::Envelope,
+        smtp_client::async_traits::AsyncSMTPClient,
+        smtp_server::SMTPServerExtension,
+    };
+
+    /// Answers each command with the next reply. Returns everything the client sent
+    async fn scripted_server(stream: DuplexStream, replies: &[&str]) -> String {
+        let mut stream = BufReader::new(stream);
+        let mut received = String::new();
+        let mut replies = replies.iter();
+        stream
+            .get_mut()
+            .write_all(replies.next().unwrap().as_bytes())
+            .await
+            .unwrap();
+        for reply in replies {
+            let start = received.len();
+            loop {
+                stream.read_line(&mut received).await.unwrap();
+                // The message body is answered once the terminator is read
+                if !received[start..].starts_with("Subject") || received.ends_with("\r\n.\r\n") {
+                    break;
+                }
+            }
+            stream.get_mut().write_all(reply.as_bytes()).await.unwrap();
+        }
+        stream.read_to_string(&mut received).await.unwrap();
+        received
+    }
+    #[tokio::test]
+    async fn test_send() {
+        let (client_stream, server_stream) = tokio::io::duplex(1024);
+        let server = tokio::spawn(async move {
+            scripted_server(
+                server_stream,
+                &[
+                    "220 mail.example.com ESMTP\r\n",
+                    "250-mail.example.com\r\n250-SIZE 1000\r\n250 ENHANCEDSTATUSCODES\r\n",
+                    "250 2.1.0 OK\r\n",
+                    "250 2.1.5 OK\r\n",
+                    "550 5.1.1 No such user\r\n",
+                    "354 Start mail input\r\n",
+                    "250 2.6.0 Queued\r\n",
+                    "221 2.0.0 Bye\r\n",
+                ],
+            )
+            .await
+        });
+        let mut client = Client::new(client_stream, "client.example.com")
+            .await
+            .unwrap();
+        assert!(client.is_extended());
+        assert_eq!(client.max_message_size(), Some(1000));
+        assert!(client
+            .extensions()
+            .contains(&SMTPServerExtension::EnhancedStatusCodes));
+
+        let mut envelope = Envelope::new(MailFromCommandData::new(Some(
+            EmailAddress::new("sender@example.com").unwrap(),
+        )));
+        for recipient in ["user@example.com", "unknown@example.com"] {
+            envelope.recipients.push(RcptToCommandData::new(
+                EmailAddress::new(recipient).unwrap(),
+            ));
+        }
+        let results = client
+            .send(&envelope, b"Subject: Test\r\n\r\n.Hello")
+            .await
+            .unwrap();
+        assert!(results[0].is_delivered());
+        assert!(!results[1].is_accepted());
+        assert!(!results[1].is_delivered());
+        client.quit().await.unwrap();
+
+        let received = server.await.unwrap();
+        assert_eq!(
+            received,
+            "EHLO client.example.com\r\n\
+            MAIL FROM:<sender@example.com> SIZE=23\r\n\
+            RCPT TO:<user@example.com>\r\n\
+            RCPT TO:<unknown@example.com>\r\n\
+            DATA\r\n\
+            Subject: Test\r\n\r\n..Hello\r\n.\r\n\
+            QUIT\r\n"
+        );
+    }
+    #[tokio::test]
+    async fn test_lmtp() {
+        let (client_stream, server_stream) = tokio::io::duplex(1024);
+        let server = tokio::spawn(async move {
+            scripted_server(
+                server_stream,
+                &[
+                    "220 mail.example.com LMTP\r\n",
+                    "250-mail.example.com\r\n250 ENHANCEDSTATUSCODES\r\n",
+                    "250 2.1.0 OK\r\n",
+                    "250 2.1.5 OK\r\n",
+                    "550 5.1.1 No such user\r\n",
+                    "250 2.1.5 OK\r\n",
+                    "354 Start mail input\r\n",
+                    "250 2.6.0 <user@example.com> Delivered\r\n452 4.2.2 <full@example.com> Mailbox full\r\n",
+                    "221 2.0.0 Bye\r\n",
+                ],
+            )
+            .await
+        });
+        let mut client = Client::new_lmtp(client_stream, "client.example.com")
+            .await
+            .unwrap();
+        assert!(client.is_lmtp());
+        assert!(client.capabilities().supports_enhanced_status_codes());
+
+        let mut envelope = Envelope::new(MailFromCommandData::new(Some(
+            EmailAddress::new("sender@example.com").unwrap(),
+        )));
+        for recipient in [
+            "user@example.com",
+            "unknown@example.com",
+            "full@example.com",
+        ] {
+            envelope.recipients.push(RcptToCommandData::new(
+                EmailAddress::new(recipient).unwrap(),
+            ));
+        }
+        let results = client
+            .send(&envelope, b"Subject: Test\r\n\r\nHello\r\n")
+            .await
+            .unwrap();
+        assert!(results[0].is_delivered());
+        assert!(!results[1].is_accepted());
+        assert!(results[2].is_accepted());
+        assert!(!results[2].is_delivered());
+        assert_eq!(
+            results[2].data_response.as_ref().unwrap().code(),
+            Some(ResponseCode::InsufficientStorage)
+        );
+        client.quit().await.unwrap();
+
+        let received = server.await.unwrap();
+        assert!(received.starts_with("LHLO client.example.com\r\n"));
+    }
+    #[tokio::test]
+    async fn test_pipelining() {
+        let (client_stream, server_stream) = tokio::io::duplex(1024);
+        let server = tokio::spawn(async move {
+            // The replies to a group are only sent after the last command of the group
+            scripted_server(
+                server_stream,
+                &[
+                    "220 mail.example.com\r\n",
+                    "250-mail.example.com\r\n250 PIPELINING\r\n",
+                    "",
+                    "",
+                    "250 2.1.0 OK\r\n550 5.1.1 No such user\r\n250 2.1.5 OK\r\n",
+                    "354 Start mail input\r\n",
+                    "250 2.6.0 Queued\r\n",
+                    "",
+                    "550 5.7.1 Rejected\r\n503 5.5.1 Bad sequence of commands\r\n",
+                    "250 2.0.0 OK\r\n",
+                ],
+            )
+            .await
+        });
+        let mut client = Client::new(client_stream, "client.example.com")
+            .await
+            .unwrap()
+            .with_timeout(Duration::from_secs(5));
+        assert!(client.supports_pipelining());
+
+        let mut envelope = Envelope::new(MailFromCommandData::new(Some(
+            EmailAddress::new("sender@example.com").unwrap(),
+        )));
+        for recipient in ["unknown@example.com", "user@example.com"] {
+            envelope.recipients.push(RcptToCommandData::new(
+                EmailAddress::new(recipient).unwrap(),
+            ));
+        }
+        let results = client
+            .send(&envelope, b"Subject: Test\r\n\r\nHello")
+            .await
+            .unwrap();
+        assert!(!results[0].is_accepted());
+        assert!(results[1].is_delivered());
+
+        envelope.recipients.truncate(1);
+        let error = client
+            .send(&envelope, b"Subject: Test\r\n\r\nHello")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, SMTPError::Rejected(_)));
+        // The reply to RCPT TO was read. So the next reply belongs to RSET
+        client.reset().await.unwrap();
+        drop(client);
+
+        assert_eq!(
+            server.await.unwrap(),
+            "EHLO client.example.com\r\n\
+            MAIL FROM:<sender@example.com>\r\n\
+            RCPT TO:<unknown@example.com>\r\n\
+            RCPT TO:<user@example.com>\r\n\
+            DATA\r\n\
+            Subject: Test\r\n\r\nHello\r\n.\r\n\
+            MAIL FROM:<sender@example.com>\r\n\
+            RCPT TO:<unknown@example.com>\r\n\
+            RSET\r\n"
+        );
+    }
+    #[tokio::test]
+    async fn test_chunking() {
+        let (client_stream, server_stream) = tokio::io::duplex(1024);
+        let server = tokio::spawn(async move {
+            let mut stream = BufReader::new(server_stream);
+            let mut received = Vec::new();
+            let mut line = String::new();
+            for reply in [
+                "220 mail.example.com\r\n",
+                "250-mail.example.com\r\n250-CHUNKING\r\n250 BINARYMIME\r\n",
+                "250 2.1.0 OK\r\n",
+            ] {
+                stream.get_mut().write_all(reply.as_bytes()).await.unwrap();
+                line.clear();
+                stream.read_line(&mut line).await.unwrap();
+                received.extend_from_slice(line.as_bytes());
+            }
+            stream
+                .get_mut()
+                .write_all(b"250 2.1.5 OK\r\n")
+                .await
+                .unwrap();
+            loop {
+                line.clear();
+                stream.read_line(&mut line).await.unwrap();
+                received.extend_from_slice(line.as_bytes());
+                let data = BdatCommandData::from_command_line(&line).unwrap();
+                let mut chunk = vec![0; data.size];
+                stream.read_exact(&mut chunk).await.unwrap();
+                received.extend_from_slice(&chunk);
+                stream
+                    .get_mut()
+                    .write_all(b"250 2.0.0 OK\r\n")
+                    .await
+                    .unwrap();
+                if data.last {
+                    break;
+                }
+            }
+            stream.read_to_end(&mut received).await.unwrap();
+            received
+        });
+        let mut client = Client::new(client_stream, "client.example.com")
+            .await
+            .unwrap()
+            .with_chunk_size(5);
+        assert!(client.supports_chunking());
+
+        let mut envelope = Envelope::new(
+            MailFromCommandData::new(Some(EmailAddress::new("sender@example.com").unwrap()))
+                .with_parameter(MailParameter::Body(BodyType::BinaryMime)),
+        );
+        envelope.recipients.push(RcptToCommandData::new(
+            EmailAddress::new("user@example.com").unwrap(),
+        ));
+        let results = client
+            .send(&envelope, b"\x00\x01\r\n.\r\nbinary")
+            .await
+            .unwrap();
+        assert!(results[0].is_delivered());
+        drop(client);
+
+        assert_eq!(
+            server.await.unwrap(),
+            b"EHLO client.example.com\r\n\
+            MAIL FROM:<sender@example.com> BODY=BINARYMIME\r\n\
+            RCPT TO:<user@example.com>\r\n\
+            BDAT 5\r\n\x00\x01\r\n.\
+            BDAT 5\r\n\r\nbin\
+            BDAT 3 LAST\r\nary"
+        );
+    }
+    #[tokio::test]
+    async fn test_helo_fall_back() {
+        let (client_stream, server_stream) = tokio::io::duplex(1024);
+        let server = tokio::spawn(async move {
+            scripted_server(
+                server_stream,
+                &[
+                    "220 mail.example.com\r\n",
+                    "502 Command not implemented\r\n",
+                    "250 mail.example.com\r\n",
+                ],
+            )
+            .await
+        });
+        let client = Client::new(client_stream, "client.example.com")
+            .await
+            .unwrap();
+        assert!(!client.is_extended());
+        assert!(client.extensions().is_empty());
+        drop(client);
+        assert_eq!(
+            server.await.unwrap(),
+            "EHLO client.example.com\r\nHELO client.example.com\r\n"
+        );
+    }
+    #[tokio::test]
+    async fn test_read_til_end() {
+        let (client_stream, server_stream) = tokio::io::duplex(1024);
+        let server = tokio::spawn(async move {
+            scripted_server(
+                server_stream,
+                &[
+                    "220 mail.example.com\r\n",
+                    "250 mail.example.com\r\n.\r\nLine\r\n..\r\n..Dot\r\n.\r\n",
+                ],
+            )
+            .await
+        });
+        let mut client = Client::new(client_stream, "client.example.com")
+            .await
+            .unwrap();
+        // A lone dot on the first line is an empty body
+        assert_eq!(client.read_til_end().await.unwrap(), "");
+        assert_eq!(
+            client.read_til_end().await.unwrap(),
+            "Line\r\n.\r\n.Dot\r\n"
+        );
+        drop(client);
+        server.await.unwrap();
+    }
+    #[tokio::test]
+    async fn test_authenticate() {
+        let (client_stream, server_stream) = tokio::io::duplex(1024);
+        let server = tokio::spawn(async move {
+            scripted_server(
+                server_stream,
+                &[
+                    "220 mail.example.com\r\n",
+                    "250-mail.example.com\r\n250 AUTH PLAIN LOGIN\r\n",
+                    "334 VXNlcm5hbWU6\r\n",
+                    "334 UGFzc3dvcmQ6\r\n",
+                    "235 2.7.0 Authentication successful\r\n",
+                ],
+            )
+            .await
+        });
+        let mut client = Client::new(client_stream, "client.example.com")
+            .await
+            .unwrap();
+        assert_eq!(
+            client.auth_mechanisms(),
+            &[AuthMechanism::Plain, AuthMechanism::Login]
+        );
+        client
+            .authenticate(&mut LoginClient::new("tim", "secret"))
+            .await
+            .unwrap();
+        drop(client);
+        assert_eq!(
+            server.await.unwrap(),
+            "EHLO client.example.com\r\nAUTH LOGIN\r\ndGlt\r\nc2VjcmV0\r\n"
+        );
+    }
+    #[tokio::test]
+    async fn test_authenticate_early_success() {
+        let (client_stream, server_stream) = tokio::io::duplex(1024);
+        let server = tokio::spawn(async move {
+            let mut stream = BufReader::new(server_stream);
+            let mut line = String::new();
+            for reply in [
+                "220 mail.example.com\r\n",
+                "250-mail.example.com\r\n250 AUTH SCRAM-SHA-256\r\n",
+            ] {
+                stream.get_mut().write_all(reply.as_bytes()).await.unwrap();
+                line.clear();
+                stream.read_line(&mut line).await.unwrap();
+            }
+            let client_first = decode_sasl(line.rsplit(' ').next().unwrap()).unwrap();
+            let client_first = String::from_utf8(client_first).unwrap();
+            let nonce = client_first.split("r=").nth(1).unwrap();
+            let server_first = format!("r={}server,s={},i=4096", nonce, encode_sasl(b"salt"));
+            let challenge = format!("334 {}\r\n", encode_sasl(server_first.as_bytes()));
+            stream
+                .get_mut()
+                .write_all(challenge.as_bytes())
+                .await
+                .unwrap();
+            line.clear();
+            stream.read_line(&mut line).await.unwrap();
+            // Skips the server-final-message that proves the server knows the password
+            stream
+                .get_mut()
+                .write_all(b"235 2.7.0 Authentication successful\r\n")
+                .await
+                .unwrap();
+            let mut rest = String::new();
+            stream.read_to_string(&mut rest).await.unwrap();
+        });
+        let mut client = Client::new(client_stream, "client.example.com")
+            .await
+            .unwrap();
+        let error = client
+            .authenticate(&mut ScramClient::new(ScramHash::Sha256, "tim", "secret"))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, SMTPError::Auth(AuthError::Malformed(_))));
+        drop(client);
+        server.await.unwrap();
+    }
+}
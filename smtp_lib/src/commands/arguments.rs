@@ -0,0 +1,253 @@
+/*!
+Helpers for parsing the arguments of SMTP commands.
+
+Paths are defined in [RFC 5321 Section 4.1.2](https://datatracker.ietf.org/doc/html/rfc5321#section-4.1.2)
+and xtext in [RFC 3461 Section 4](https://datatracker.ietf.org/doc/html/rfc3461#section-4)
+ */
+use std::{fmt::Write, str::FromStr};
+
+use mail_lib_types::EmailAddress;
+
+use crate::commands::CommandParseError;
+
+/// Strips the command keyword off the start of the line.
+///
+/// The keyword is matched case insensitive. The trailing CRLF is removed.
+pub fn strip_command<'a>(line: &'a str, command: &str) -> Result<&'a str, CommandParseError> {
+    let line = line.trim_end_matches(['\r', '\n']);
+    match line.get(..command.len()) {
+        Some(start) if start.eq_ignore_ascii_case(command) => Ok(&line[command.len()..]),
+        _ => Err(CommandParseError::InvalidCommand(line.to_owned())),
+    }
+}
+
+/// Strips the command keyword and returns the argument after it.
+///
+/// Returns None if there is no argument. The argument must be separated from the keyword by a space
+pub fn command_argument<'a>(
+    line: &'a str,
+    command: &str,
+) -> Result<Option<&'a str>, CommandParseError> {
+    let arguments = strip_command(line, command)?;
+    if arguments.is_empty() {
+        return Ok(None);
+    }
+    if !arguments.starts_with(' ') {
+        return Err(CommandParseError::InvalidCommand(
+            line.trim_end().to_owned(),
+        ));
+    }
+    let arguments = arguments.trim();
+    Ok((!arguments.is_empty()).then_some(arguments))
+}
+/// For commands that do not take any arguments. Such as `RSET` or `DATA`
+pub fn no_arguments(line: &str, command: &str) -> Result<(), CommandParseError> {
+    match command_argument(line, command)? {
+        None => Ok(()),
+        Some(argument) => Err(CommandParseError::InvalidParameter(argument.to_owned())),
+    }
+}
+
+/// Splits `<path> [SP parameters]` into the path and the parameters.
+pub fn split_path(arguments: &str) -> Result<(&str, &str), CommandParseError> {
+    // Some clients send `MAIL FROM: <address>`
+    let arguments = arguments.trim_start();
+    if !arguments.starts_with('<') {
+        return Err(CommandParseError::InvalidPath(arguments.to_owned()));
+    }
+    let end = arguments
+        .find('>')
+        .ok_or_else(|| CommandParseError::InvalidPath(arguments.to_owned()))?;
+    let (path, parameters) = arguments.split_at(end + 1);
+    if !parameters.is_empty() && !parameters.starts_with(' ') {
+        return Err(CommandParseError::InvalidPath(arguments.to_owned()));
+    }
+    Ok((path, parameters.trim()))
+}
+
+/// Parses a path. `"<" [ A-d-l ":" ] Mailbox ">"` or `"<>"`
+///
+/// The obsolete source route (`A-d-l`) is accepted and discarded.
+///
+/// Returns None if the path is the null path `<>`
+pub fn parse_path(path: &str) -> Result<Option<EmailAddress>, CommandParseError> {
+    let inner = path
+        .strip_prefix('<')
+        .and_then(|path| path.strip_suffix('>'))
+        .ok_or_else(|| CommandParseError::InvalidPath(path.to_owned()))?;
+    if inner.is_empty() {
+        return Ok(None);
+    }
+    let mailbox = if inner.starts_with('@') {
+        // A-d-l = At-domain *( "," At-domain )
+        let (route, mailbox) = inner
+            .split_once(':')
+            .ok_or_else(|| CommandParseError::InvalidPath(path.to_owned()))?;
+        let valid_route = route.split(',').all(|at_domain| {
+            at_domain
+                .strip_prefix('@')
+                .is_some_and(|domain| !domain.is_empty())
+        });
+        if !valid_route {
+            return Err(CommandParseError::InvalidPath(path.to_owned()));
+        }
+        mailbox
+    } else {
+        inner
+    };
+    EmailAddress::new(mailbox)
+        .map(Some)
+        .map_err(CommandParseError::from)
+}
+
+/// Formats a path. `None` becomes the null path `<>`
+pub fn format_path(address: Option<&EmailAddress>) -> String {
+    match address {
+        Some(address) => format!("<{}>", address),
+        None => "<>".to_owned(),
+    }
+}
+
+/// Splits an `esmtp-param` into its keyword and value.
+///
+/// ```ebnf
+/// esmtp-param    = esmtp-keyword ["=" esmtp-value]
+/// ```
+pub fn split_parameter(parameter: &str) -> Result<(&str, Option<&str>), CommandParseError> {
+    let (keyword, value) = match parameter.split_once('=') {
+        Some((keyword, value)) => (keyword, Some(value)),
+        None => (parameter, None),
+    };
+    let valid_keyword = keyword
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphanumeric())
+        && keyword
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-');
+    if !valid_keyword || value.is_some_and(str::is_empty) {
+        return Err(CommandParseError::InvalidParameter(parameter.to_owned()));
+    }
+    Ok((keyword, value))
+}
+
+/// Parses the space separated `Mail-parameters` or `Rcpt-parameters`
+pub fn parse_parameters<P>(parameters: &str) -> Result<Vec<P>, CommandParseError>
+where
+    P: FromStr<Err = CommandParseError>,
+{
+    parameters
+        .split(' ')
+        .filter(|parameter| !parameter.is_empty())
+        .map(P::from_str)
+        .collect()
+}
+
+/// Encodes a value as xtext.
+///
+/// Any byte outside of `!`-`~` and the characters `+` and `=` are encoded as `+XX`
+pub fn xtext_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if (b'!'..=b'~').contains(&byte) && byte != b'+' && byte != b'=' {
+            encoded.push(byte as char);
+        } else {
+            // Infallible
+            let _ = write!(encoded, "+{:02X}", byte);
+        }
+    }
+    encoded
+}
+
+/// Decodes a xtext value.
+///
+/// The hex digits after `+` must be uppercase as `hexchar` requires
+pub fn xtext_decode(value: &str) -> Result<String, CommandParseError> {
+    let invalid = || CommandParseError::InvalidXText(value.to_owned());
+    let mut decoded = Vec::with_capacity(value.len());
+    let mut bytes = value.bytes();
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b'+' => {
+                let (Some(high), Some(low)) = (bytes.next(), bytes.next()) else {
+                    return Err(invalid());
+                };
+                if !is_hex_char(high) || !is_hex_char(low) {
+                    return Err(invalid());
+                }
+                let hex = [high, low];
+                let hex = std::str::from_utf8(&hex).map_err(|_| invalid())?;
+                decoded.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+            }
+            b'!'..=b'~' if byte != b'=' => decoded.push(byte),
+            _ => return Err(invalid()),
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| invalid())
+}
+/// A digit of `hexchar`. Only `0-9` and `A-F` are allowed
+fn is_hex_char(byte: u8) -> bool {
+    matches!(byte, b'0'..=b'9' | b'A'..=b'F')
+}
+#[cfg(test)]
+mod tests {
+    use super::{
+        command_argument, no_arguments, parse_path, split_path, strip_command, xtext_decode,
+        xtext_encode,
+    };
+
+    #[test]
+    fn test_strip_command() {
+        assert_eq!(
+            strip_command("mail from:<a@example.com>\r\n", "MAIL FROM:"),
+            Ok("<a@example.com>")
+        );
+        assert!(strip_command("MAIL <a@example.com>", "MAIL FROM:").is_err());
+    }
+    #[test]
+    fn test_command_argument() {
+        assert_eq!(command_argument("HELP\r\n", "HELP"), Ok(None));
+        assert_eq!(
+            command_argument("help  MAIL \r\n", "HELP"),
+            Ok(Some("MAIL"))
+        );
+        assert!(command_argument("HELPER", "HELP").is_err());
+        assert!(no_arguments("RSET", "RSET").is_ok());
+        assert!(no_arguments("RSET now", "RSET").is_err());
+    }
+    #[test]
+    fn test_paths() {
+        assert_eq!(parse_path("<>"), Ok(None));
+        let address = parse_path("<user@example.com>").unwrap().unwrap();
+        assert_eq!(address, "user@example.com");
+        let address = parse_path("<@relay.one,@relay.two:user@example.com>")
+            .unwrap()
+            .unwrap();
+        assert_eq!(address, "user@example.com");
+
+        assert!(parse_path("user@example.com").is_err());
+        assert!(parse_path("<user>").is_err());
+        assert!(parse_path("<@:user@example.com>").is_err());
+        assert!(parse_path("<user{at}example.com>").is_err());
+
+        assert_eq!(
+            split_path(" <user@example.com> SIZE=100"),
+            Ok(("<user@example.com>", "SIZE=100"))
+        );
+        assert!(split_path("<user@example.com>SIZE=100").is_err());
+    }
+    #[test]
+    fn test_xtext() {
+        let encoded = xtext_encode("rfc822;user+tag=1@example.com");
+        assert_eq!(encoded, "rfc822;user+2Btag+3D1@example.com");
+        assert_eq!(
+            xtext_decode(&encoded).unwrap(),
+            "rfc822;user+tag=1@example.com"
+        );
+        assert!(xtext_decode("bad+2").is_err());
+        assert!(xtext_decode("++1").is_err());
+        assert!(xtext_decode("+2b").is_err());
+        assert!(xtext_decode("+-1").is_err());
+        assert!(xtext_decode("bad value").is_err());
+    }
+}
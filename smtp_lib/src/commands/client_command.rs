@@ -0,0 +1,199 @@
+/*!
+Parses any command sent by a client.
+
+```rust
+use smtp_lib::commands::ClientCommand;
+
+let command = ClientCommand::parse("mail from:<sender@example.com> SIZE=100\r\n").unwrap();
+assert!(matches!(command, ClientCommand::MailFrom(_)));
+```
+ */
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+use crate::{
+    commands::{
+        command_impls::{
+            AuthCommandData, BdatCommandData, DataCommand, EhloCommandData, ExpnCommandData,
+            HeloCommand, HelpCommandData, LhloCommand, MailFromCommandData, NoopCommandData,
+            QuitCommand, RcptToCommandData, RsetCommand, StartTlsCommand, VrfyCommandData,
+        },
+        CommandParseError,
+    },
+    CRLF,
+};
+
+/// The maximum length of a command line. Including the CRLF
+///
+/// [RFC 5321 Section 4.5.3.1.4](https://datatracker.ietf.org/doc/html/rfc5321#section-4.5.3.1.4)
+pub const MAX_COMMAND_LINE_LENGTH: usize = 512;
+
+/// Every command a client can send
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientCommand {
+    Ehlo(EhloCommandData),
+    Helo(EhloCommandData),
+    /// LMTP only. [RFC 2033](https://datatracker.ietf.org/doc/html/rfc2033#section-4.1)
+    Lhlo(EhloCommandData),
+    MailFrom(MailFromCommandData),
+    RcptTo(RcptToCommandData),
+    Data,
+    Rset,
+    Noop(NoopCommandData),
+    Quit,
+    Vrfy(VrfyCommandData),
+    Expn(ExpnCommandData),
+    Help(HelpCommandData),
+    StartTls,
+    Auth(AuthCommandData),
+    /// The chunk is read separately. See [BdatCommand](crate::commands::command_impls::BdatCommand)
+    Bdat(BdatCommandData),
+}
+impl ClientCommand {
+    /// Parses a command line with the [MAX_COMMAND_LINE_LENGTH] limit
+    pub fn parse(line: &str) -> Result<Self, CommandParseError> {
+        Self::parse_with_limit(line, MAX_COMMAND_LINE_LENGTH)
+    }
+    /// Parses a command line.
+    ///
+    /// The command is matched case insensitive.
+    /// Unknown commands return [CommandParseError::UnknownCommand]
+    pub fn parse_with_limit(line: &str, max_length: usize) -> Result<Self, CommandParseError> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        let length = line.len() + CRLF.len();
+        if length > max_length {
+            return Err(CommandParseError::LineTooLong(length));
+        }
+        let verb = line.split(' ').next().unwrap_or_default();
+        let command = match verb.to_ascii_uppercase().as_str() {
+            "EHLO" => Self::Ehlo(EhloCommandData::from_command_line(line)?),
+            "HELO" => Self::Helo(HeloCommand::from_command_line(line)?),
+            "LHLO" => Self::Lhlo(LhloCommand::from_command_line(line)?),
+            "MAIL" => Self::MailFrom(MailFromCommandData::from_command_line(line)?),
+            "RCPT" => Self::RcptTo(RcptToCommandData::from_command_line(line)?),
+            "DATA" => {
+                DataCommand::from_command_line(line)?;
+                Self::Data
+            }
+            "RSET" => {
+                RsetCommand::from_command_line(line)?;
+                Self::Rset
+            }
+            "NOOP" => Self::Noop(NoopCommandData::from_command_line(line)?),
+            "QUIT" => {
+                QuitCommand::from_command_line(line)?;
+                Self::Quit
+            }
+            "VRFY" => Self::Vrfy(VrfyCommandData::from_command_line(line)?),
+            "EXPN" => Self::Expn(ExpnCommandData::from_command_line(line)?),
+            "HELP" => Self::Help(HelpCommandData::from_command_line(line)?),
+            "STARTTLS" => {
+                StartTlsCommand::from_command_line(line)?;
+                Self::StartTls
+            }
+            "AUTH" => Self::Auth(AuthCommandData::from_command_line(line)?),
+            "BDAT" => Self::Bdat(BdatCommandData::from_command_line(line)?),
+            _ => return Err(CommandParseError::UnknownCommand(verb.to_owned())),
+        };
+        Ok(command)
+    }
+}
+impl FromStr for ClientCommand {
+    type Err = CommandParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+/// Formats the command line. Without the CRLF
+impl Display for ClientCommand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientCommand::Ehlo(data) => write!(f, "EHLO {}", data.client_hostname),
+            ClientCommand::Helo(data) => write!(f, "HELO {}", data.client_hostname),
+            ClientCommand::Lhlo(data) => write!(f, "LHLO {}", data.client_hostname),
+            ClientCommand::MailFrom(data) => write!(f, "MAIL FROM:{}", data),
+            ClientCommand::RcptTo(data) => write!(f, "RCPT TO:{}", data),
+            ClientCommand::Data => write!(f, "DATA"),
+            ClientCommand::Rset => write!(f, "RSET"),
+            ClientCommand::Noop(NoopCommandData { argument: None }) => write!(f, "NOOP"),
+            ClientCommand::Noop(NoopCommandData {
+                argument: Some(argument),
+            }) => write!(f, "NOOP {}", argument),
+            ClientCommand::Quit => write!(f, "QUIT"),
+            ClientCommand::Vrfy(data) => write!(f, "VRFY {}", data.argument),
+            ClientCommand::Expn(data) => write!(f, "EXPN {}", data.mailing_list),
+            ClientCommand::Help(HelpCommandData { topic: None }) => write!(f, "HELP"),
+            ClientCommand::Help(HelpCommandData { topic: Some(topic) }) => {
+                write!(f, "HELP {}", topic)
+            }
+            ClientCommand::StartTls => write!(f, "STARTTLS"),
+            ClientCommand::Auth(data) => write!(f, "AUTH {}", data),
+            ClientCommand::Bdat(data) => write!(f, "BDAT {}", data),
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::{ClientCommand, MAX_COMMAND_LINE_LENGTH};
+    use crate::{
+        commands::{command_impls::EhloCommand, CommandParseError, SMTPCommand},
+        error::SMTPError,
+    };
+
+    #[test]
+    fn test_parse() {
+        let lines = [
+            "EHLO client.example.com",
+            "HELO client.example.com",
+            "LHLO client.example.com",
+            "MAIL FROM:<sender@example.com> SIZE=100",
+            "RCPT TO:<user@example.com> NOTIFY=NEVER",
+            "DATA",
+            "RSET",
+            "NOOP",
+            "QUIT",
+            "VRFY Smith",
+            "EXPN Example-People",
+            "HELP MAIL",
+            "STARTTLS",
+            "AUTH CRAM-MD5",
+            "BDAT 100 LAST",
+        ];
+        for line in lines {
+            let command = ClientCommand::parse(&format!("{}\r\n", line)).unwrap();
+            assert_eq!(command.to_string(), line);
+            let lower = ClientCommand::parse(&line.to_ascii_lowercase()).unwrap();
+            assert_eq!(
+                lower.to_string().to_ascii_lowercase(),
+                line.to_ascii_lowercase()
+            );
+        }
+    }
+    #[test]
+    fn test_errors() {
+        let error = ClientCommand::parse("EHLOX client.example.com").unwrap_err();
+        assert_eq!(error, CommandParseError::UnknownCommand("EHLOX".to_owned()));
+        assert_eq!(SMTPError::from(error).get_error_code(), 500);
+
+        let error = ClientCommand::parse("MAIL FROM:<not an address>").unwrap_err();
+        assert_eq!(SMTPError::from(error).get_error_code(), 501);
+
+        let error = ClientCommand::parse("RSET now").unwrap_err();
+        assert_eq!(SMTPError::from(error).get_error_code(), 501);
+
+        let long = format!("NOOP {}\r\n", "a".repeat(MAX_COMMAND_LINE_LENGTH));
+        let error = ClientCommand::parse(&long).unwrap_err();
+        assert!(matches!(error, CommandParseError::LineTooLong(_)));
+        assert_eq!(SMTPError::from(error).get_error_code(), 500);
+        assert!(ClientCommand::parse_with_limit(&long, 1024).is_ok());
+    }
+    #[test]
+    fn test_can_handle() {
+        assert!(EhloCommand::can_handle("ehlo client.example.com"));
+        assert!(EhloCommand::can_handle("EHLO"));
+        assert!(!EhloCommand::can_handle("EHLOX client.example.com"));
+    }
+}
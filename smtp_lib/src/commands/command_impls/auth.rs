@@ -0,0 +1,179 @@
+use std::fmt::Display;
+
+use crate::{
+    auth::{decode_sasl, encode_sasl, AuthMechanism},
+    commands::{arguments::command_argument, CommandParseError, SMTPCommand},
+    server_response::MultilineServerResponse,
+};
+
+/// The data that is in the AUTH command
+///
+/// ```ebnf
+/// auth = "AUTH" SP sasl-mech [SP initial-response] CRLF
+/// initial-response = base64 / "="
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthCommandData {
+    pub mechanism: AuthMechanism,
+    /// The decoded initial response. `=` is an empty response
+    pub initial_response: Option<Vec<u8>>,
+}
+impl AuthCommandData {
+    pub fn new(mechanism: AuthMechanism, initial_response: Option<Vec<u8>>) -> Self {
+        Self {
+            mechanism,
+            initial_response,
+        }
+    }
+    /// Parses the entire command line. Including the `AUTH`
+    pub fn from_command_line(line: &str) -> Result<Self, CommandParseError> {
+        let argument = command_argument(line, AuthCommand::command())?
+            .ok_or(CommandParseError::MissingArgument("mechanism"))?;
+        let (mechanism, initial_response) = match argument.split_once(' ') {
+            Some((mechanism, initial_response)) => (mechanism, Some(initial_response.trim())),
+            None => (argument, None),
+        };
+        let mechanism = mechanism
+            .parse()
+            .map_err(|_| CommandParseError::InvalidParameter(mechanism.to_owned()))?;
+        let initial_response = match initial_response {
+            None => None,
+            Some("=") => Some(Vec::new()),
+            Some(initial_response) => Some(decode_sasl(initial_response).map_err(|error| {
+                CommandParseError::InvalidParameter(format!("Initial response: {}", error))
+            })?),
+        };
+        Ok(Self {
+            mechanism,
+            initial_response,
+        })
+    }
+}
+/// Formats `mechanism [initial-response]`
+impl Display for AuthCommandData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.initial_response {
+            None => write!(f, "{}", self.mechanism),
+            Some(response) if response.is_empty() => write!(f, "{} =", self.mechanism),
+            Some(response) => write!(f, "{} {}", self.mechanism, encode_sasl(response)),
+        }
+    }
+}
+
+/// The AUTH Command as specified [here](https://datatracker.ietf.org/doc/html/rfc4954#section-4)
+///
+/// This only sends the command and reads the first reply.
+/// The `334` exchange is done by [SaslClient](crate::auth::client::SaslClient) and [SaslServer](crate::auth::server::SaslServer)
+pub struct AuthCommand;
+impl SMTPCommand for AuthCommand {
+    type ClientCommand = AuthCommandData;
+    type ServerResponse = MultilineServerResponse;
+
+    fn command() -> &'static str
+    where
+        Self: Sized,
+    {
+        "AUTH"
+    }
+}
+
+#[cfg(feature = "async")]
+mod async_impl {
+    use futures::future::{ready, BoxFuture, Ready};
+
+    use super::{AuthCommand, AuthCommandData};
+    use crate::{
+        commands::async_traits::AsyncSMTPCommand, error::SMTPError,
+        server_response::MultilineServerResponse, smtp_client::async_traits::AsyncSMTPClient,
+        smtp_server::async_traits::AsyncSMTPConnection,
+        statement::async_statement::AsyncMultilineStatement, CRLF,
+    };
+
+    impl<'a> AsyncSMTPCommand<'a> for AuthCommand {
+        type ServerHandleRead = Ready<crate::Result<Self::ClientCommand>>;
+        type SendFuture = BoxFuture<'a, crate::Result<()>>;
+        type HandleServerResponse = BoxFuture<'a, crate::Result<MultilineServerResponse>>;
+
+        fn server_read<'b, C: AsyncSMTPConnection<'b>>(
+            _: &mut C,
+            line: String,
+        ) -> Self::ServerHandleRead
+        where
+            Self: Sized,
+        {
+            ready(AuthCommandData::from_command_line(&line).map_err(SMTPError::from))
+        }
+
+        fn server_send<C: AsyncSMTPConnection<'a>>(
+            response: Self::ServerResponse,
+            client: &'a mut C,
+        ) -> Self::SendFuture {
+            Box::pin(async move {
+                client.write_statement(response).await?;
+                Ok(())
+            })
+        }
+
+        fn client_send<C: AsyncSMTPClient<'a>>(
+            command: Self::ClientCommand,
+            client: &'a mut C,
+        ) -> Self::SendFuture {
+            Box::pin(async move {
+                client
+                    .write_string(format!("AUTH {}{}", command, CRLF))
+                    .await?;
+                Ok(())
+            })
+        }
+
+        fn client_read<C: AsyncSMTPClient<'a>>(client: &'a mut C) -> Self::HandleServerResponse {
+            MultilineServerResponse::read_til_non_hyphenated_line(client)
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::AuthCommandData;
+    use crate::{
+        auth::AuthMechanism, commands::CommandParseError, smtp_server::SMTPServerExtension,
+    };
+
+    #[test]
+    fn test_parse() {
+        let data =
+            AuthCommandData::from_command_line("AUTH PLAIN AHRpbQB0YW5zdGFhZg==\r\n").unwrap();
+        assert_eq!(data.mechanism, AuthMechanism::Plain);
+        assert_eq!(
+            data.initial_response.as_deref(),
+            Some(&b"\0tim\0tanstaaf"[..])
+        );
+        assert_eq!(data.to_string(), "PLAIN AHRpbQB0YW5zdGFhZg==");
+
+        let data = AuthCommandData::from_command_line("auth cram-md5\r\n").unwrap();
+        assert_eq!(data, AuthCommandData::new(AuthMechanism::CramMd5, None));
+
+        let data = AuthCommandData::from_command_line("AUTH PLAIN =\r\n").unwrap();
+        assert_eq!(data.initial_response, Some(Vec::new()));
+        assert_eq!(data.to_string(), "PLAIN =");
+
+        assert_eq!(
+            AuthCommandData::from_command_line("AUTH\r\n"),
+            Err(CommandParseError::MissingArgument("mechanism"))
+        );
+        assert!(AuthCommandData::from_command_line("AUTH PLAIN !!!\r\n").is_err());
+    }
+    #[test]
+    fn test_extension() {
+        let extension =
+            SMTPServerExtension::try_from("AUTH PLAIN LOGIN CRAM-MD5".to_owned()).unwrap();
+        assert_eq!(
+            extension,
+            SMTPServerExtension::Auth(vec![
+                AuthMechanism::Plain,
+                AuthMechanism::Login,
+                AuthMechanism::CramMd5
+            ])
+        );
+        assert_eq!(extension.to_string(), "AUTH PLAIN LOGIN CRAM-MD5");
+    }
+}
@@ -0,0 +1,156 @@
+use std::fmt::Display;
+
+use crate::{
+    commands::{arguments::command_argument, CommandParseError, SMTPCommand},
+    server_response::MultilineServerResponse,
+};
+
+/// The data that is in the BDAT command
+///
+/// ```ebnf
+/// bdat-cmd = "BDAT" SP chunk-size [ SP end-marker ] CRLF
+/// chunk-size = 1*DIGIT
+/// end-marker = "LAST"
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BdatCommandData {
+    /// The number of octets sent after the command line
+    pub size: usize,
+    /// If this is the last chunk of the message
+    pub last: bool,
+}
+impl BdatCommandData {
+    pub fn new(size: usize, last: bool) -> Self {
+        Self { size, last }
+    }
+    /// Parses the entire command line. Including the `BDAT`
+    pub fn from_command_line(line: &str) -> Result<Self, CommandParseError> {
+        let argument = command_argument(line, BdatCommand::command())?
+            .ok_or(CommandParseError::MissingArgument("chunk-size"))?;
+        let mut arguments = argument.split(' ').filter(|argument| !argument.is_empty());
+        let size = arguments.next().unwrap_or_default();
+        if !size.bytes().all(|byte| byte.is_ascii_digit()) {
+            return Err(CommandParseError::InvalidParameter(size.to_owned()));
+        }
+        let size = size
+            .parse()
+            .map_err(|_| CommandParseError::InvalidParameter(size.to_owned()))?;
+        let last = match arguments.next() {
+            None => false,
+            Some(marker) if marker.eq_ignore_ascii_case("LAST") => true,
+            Some(other) => return Err(CommandParseError::InvalidParameter(other.to_owned())),
+        };
+        if let Some(other) = arguments.next() {
+            return Err(CommandParseError::InvalidParameter(other.to_owned()));
+        }
+        Ok(Self { size, last })
+    }
+}
+/// Formats `chunk-size [LAST]`
+impl Display for BdatCommandData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.last {
+            write!(f, "{} LAST", self.size)
+        } else {
+            write!(f, "{}", self.size)
+        }
+    }
+}
+
+/// The BDAT Command as specified [here](https://datatracker.ietf.org/doc/html/rfc3030#section-2)
+///
+/// The chunk is sent right after the command line without any dot-stuffing.
+/// The command only handles the `BDAT` line. The client writes the chunk after [AsyncSMTPCommand::client_send](crate::commands::async_traits::AsyncSMTPCommand::client_send).
+///
+/// The server has to read the chunk even if it rejects the command
+pub struct BdatCommand;
+impl SMTPCommand for BdatCommand {
+    type ClientCommand = BdatCommandData;
+    type ServerResponse = MultilineServerResponse;
+
+    fn command() -> &'static str
+    where
+        Self: Sized,
+    {
+        "BDAT"
+    }
+}
+
+#[cfg(feature = "async")]
+mod async_impl {
+    use futures::future::{ready, BoxFuture, Ready};
+
+    use super::{BdatCommand, BdatCommandData};
+    use crate::{
+        commands::async_traits::AsyncSMTPCommand, error::SMTPError,
+        server_response::MultilineServerResponse, smtp_client::async_traits::AsyncSMTPClient,
+        smtp_server::async_traits::AsyncSMTPConnection,
+        statement::async_statement::AsyncMultilineStatement, CRLF,
+    };
+
+    impl<'a> AsyncSMTPCommand<'a> for BdatCommand {
+        type ServerHandleRead = Ready<crate::Result<Self::ClientCommand>>;
+        type SendFuture = BoxFuture<'a, crate::Result<()>>;
+        type HandleServerResponse = BoxFuture<'a, crate::Result<MultilineServerResponse>>;
+
+        fn server_read<'b, C: AsyncSMTPConnection<'b>>(
+            _: &mut C,
+            line: String,
+        ) -> Self::ServerHandleRead
+        where
+            Self: Sized,
+        {
+            ready(BdatCommandData::from_command_line(&line).map_err(SMTPError::from))
+        }
+
+        fn server_send<C: AsyncSMTPConnection<'a>>(
+            response: Self::ServerResponse,
+            client: &'a mut C,
+        ) -> Self::SendFuture {
+            Box::pin(async move {
+                client.write_statement(response).await?;
+                Ok(())
+            })
+        }
+
+        fn client_send<C: AsyncSMTPClient<'a>>(
+            command: Self::ClientCommand,
+            client: &'a mut C,
+        ) -> Self::SendFuture {
+            Box::pin(async move {
+                client
+                    .write_string(format!("BDAT {}{}", command, CRLF))
+                    .await?;
+                Ok(())
+            })
+        }
+
+        fn client_read<C: AsyncSMTPClient<'a>>(client: &'a mut C) -> Self::HandleServerResponse {
+            MultilineServerResponse::read_til_non_hyphenated_line(client)
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::BdatCommandData;
+    use crate::commands::CommandParseError;
+
+    #[test]
+    fn test_parse() {
+        let data = BdatCommandData::from_command_line("BDAT 1000\r\n").unwrap();
+        assert_eq!(data, BdatCommandData::new(1000, false));
+        assert_eq!(data.to_string(), "1000");
+
+        let data = BdatCommandData::from_command_line("bdat 0 last\r\n").unwrap();
+        assert_eq!(data, BdatCommandData::new(0, true));
+        assert_eq!(data.to_string(), "0 LAST");
+
+        assert_eq!(
+            BdatCommandData::from_command_line("BDAT\r\n"),
+            Err(CommandParseError::MissingArgument("chunk-size"))
+        );
+        assert!(BdatCommandData::from_command_line("BDAT -1\r\n").is_err());
+        assert!(BdatCommandData::from_command_line("BDAT 10 FIRST\r\n").is_err());
+        assert!(BdatCommandData::from_command_line("BDAT 10 LAST 5\r\n").is_err());
+    }
+}
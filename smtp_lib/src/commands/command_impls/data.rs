@@ -0,0 +1,146 @@
+use crate::{
+    commands::{arguments::no_arguments, CommandParseError, SMTPCommand},
+    server_response::{MultilineServerResponse, ResponseCode},
+};
+
+/// The reply to the DATA command. Not the reply sent after the message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DataResponse {
+    /// `354` The client can start sending the message
+    StartMailInput(MultilineServerResponse),
+    /// The server will not accept the message
+    Rejected(MultilineServerResponse),
+}
+impl DataResponse {
+    /// `354 Start mail input; end with <CRLF>.<CRLF>`
+    pub fn start_mail_input() -> Self {
+        DataResponse::StartMailInput(MultilineServerResponse::new_with_code(
+            ResponseCode::StartMailInput,
+            Some("Start mail input; end with <CRLF>.<CRLF>".to_owned()),
+            0,
+        ))
+    }
+    /// If the client should send the message
+    pub fn is_start_mail_input(&self) -> bool {
+        matches!(self, DataResponse::StartMailInput(_))
+    }
+    pub fn into_inner(self) -> MultilineServerResponse {
+        match self {
+            DataResponse::StartMailInput(response) | DataResponse::Rejected(response) => response,
+        }
+    }
+}
+impl From<MultilineServerResponse> for DataResponse {
+    fn from(value: MultilineServerResponse) -> Self {
+        if value.code() == Some(ResponseCode::StartMailInput) {
+            DataResponse::StartMailInput(value)
+        } else {
+            DataResponse::Rejected(value)
+        }
+    }
+}
+
+/// The DATA Command as specified [here](https://datatracker.ietf.org/doc/html/rfc5321#section-4.1.1.4)
+///
+/// The command only handles the `DATA` line and the `354` reply.
+///
+/// After a [DataResponse::StartMailInput] the client writes the message with [DataBodyWriter](crate::data::async_data::DataBodyWriter)
+/// and the server reads it with [DataBodyStream](crate::data::async_data::DataBodyStream).
+/// The reply to the message is then a normal [MultilineServerResponse]
+pub struct DataCommand;
+impl DataCommand {
+    /// Parses the command line. DATA does not take any arguments
+    pub fn from_command_line(line: &str) -> Result<(), CommandParseError> {
+        no_arguments(line, Self::command())
+    }
+}
+impl SMTPCommand for DataCommand {
+    type ClientCommand = ();
+    type ServerResponse = DataResponse;
+
+    fn command() -> &'static str
+    where
+        Self: Sized,
+    {
+        "DATA"
+    }
+}
+
+#[cfg(feature = "async")]
+mod async_impl {
+    use futures::future::{ready, BoxFuture, Ready};
+
+    use super::{DataCommand, DataResponse};
+    use crate::{
+        commands::async_traits::AsyncSMTPCommand, error::SMTPError,
+        server_response::MultilineServerResponse, smtp_client::async_traits::AsyncSMTPClient,
+        smtp_server::async_traits::AsyncSMTPConnection,
+        statement::async_statement::AsyncMultilineStatement, CRLF,
+    };
+
+    impl<'a> AsyncSMTPCommand<'a> for DataCommand {
+        type ServerHandleRead = Ready<crate::Result<Self::ClientCommand>>;
+        type SendFuture = BoxFuture<'a, crate::Result<()>>;
+        type HandleServerResponse = BoxFuture<'a, crate::Result<DataResponse>>;
+
+        fn server_read<'b, C: AsyncSMTPConnection<'b>>(
+            _: &mut C,
+            line: String,
+        ) -> Self::ServerHandleRead
+        where
+            Self: Sized,
+        {
+            ready(DataCommand::from_command_line(&line).map_err(SMTPError::from))
+        }
+
+        fn server_send<C: AsyncSMTPConnection<'a>>(
+            response: Self::ServerResponse,
+            client: &'a mut C,
+        ) -> Self::SendFuture {
+            Box::pin(async move {
+                client.write_statement(response.into_inner()).await?;
+                Ok(())
+            })
+        }
+
+        fn client_send<C: AsyncSMTPClient<'a>>(
+            _: Self::ClientCommand,
+            client: &'a mut C,
+        ) -> Self::SendFuture {
+            Box::pin(async move {
+                client.write_string(format!("DATA{}", CRLF)).await?;
+                Ok(())
+            })
+        }
+
+        fn client_read<C: AsyncSMTPClient<'a>>(client: &'a mut C) -> Self::HandleServerResponse {
+            Box::pin(async {
+                let response =
+                    MultilineServerResponse::read_til_non_hyphenated_line(client).await?;
+                Ok(DataResponse::from(response))
+            })
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::{DataCommand, DataResponse};
+    use crate::server_response::MultilineServerResponse;
+
+    #[test]
+    fn test_parse() {
+        assert!(DataCommand::from_command_line("DATA\r\n").is_ok());
+        assert!(DataCommand::from_command_line("data").is_ok());
+        assert!(DataCommand::from_command_line("DATA now").is_err());
+    }
+    #[test]
+    fn test_response() {
+        let response = MultilineServerResponse::from_str("354 Go ahead\r\n").unwrap();
+        assert!(DataResponse::from(response).is_start_mail_input());
+        let response = MultilineServerResponse::from_str("554 No valid recipients\r\n").unwrap();
+        assert!(!DataResponse::from(response).is_start_mail_input());
+        assert!(DataResponse::start_mail_input().is_start_mail_input());
+    }
+}
@@ -1,7 +1,7 @@
 use std::ops::Deref;
 
 use crate::{
-    commands::SMTPCommand,
+    commands::{arguments::command_argument, CommandParseError, SMTPCommand},
     error::SMTPError,
     server_response::{MultilineServerResponse, ResponseCode},
     smtp_server::SMTPServerExtension,
@@ -19,6 +19,14 @@ impl Deref for EhloCommandData {
         &self.client_hostname
     }
 }
+impl EhloCommandData {
+    /// Parses the entire command line. Including the `EHLO`
+    pub fn from_command_line(line: &str) -> Result<Self, CommandParseError> {
+        command_argument(line, EhloCommand::command())?
+            .map(|domain| EhloCommandData::from(domain.to_owned()))
+            .ok_or(CommandParseError::MissingArgument("domain"))
+    }
+}
 impl From<String> for EhloCommandData {
     fn from(value: String) -> Self {
         EhloCommandData {
@@ -43,6 +51,23 @@ pub enum EhloResponse {
         extensions: Vec<SMTPServerExtension>,
     },
 }
+impl EhloResponse {
+    /// If the server does not support EHLO and the client should send HELO instead
+    ///
+    /// [RFC 5321 Section 3.2](https://datatracker.ietf.org/doc/html/rfc5321#section-3.2)
+    pub fn should_fall_back_to_helo(&self) -> bool {
+        matches!(
+            self,
+            EhloResponse::Error {
+                code: ResponseCode::SyntaxError
+                    | ResponseCode::CommandNotImplemented
+                    | ResponseCode::ParameterNotImplemented
+                    | ResponseCode::MailboxUnavailable,
+                ..
+            }
+        )
+    }
+}
 impl TryFrom<MultilineServerResponse> for EhloResponse {
     type Error = SMTPError;
 
@@ -69,7 +94,7 @@ impl TryFrom<MultilineServerResponse> for EhloResponse {
             let Some(message) = line.message else {
                 return Err(SMTPError::InvalidResponse("Expected extension, got none"));
             };
-            let extension = SMTPServerExtension::try_from(message)?;
+            let extension = SMTPServerExtension::parse_lenient(message)?;
             extensions.push(extension);
         }
         let Some(hello_message) = hello.message else {
@@ -105,6 +130,7 @@ mod async_impl {
     use super::{EhloCommand, EhloCommandData, EhloResponse};
     use crate::{
         commands::async_traits::AsyncSMTPCommand,
+        error::SMTPError,
         server_response::{MultilineServerResponse, ResponseCode, ServerResponseLine},
         smtp_client::async_traits::AsyncSMTPClient,
         smtp_server::async_traits::AsyncSMTPConnection,
@@ -124,9 +150,7 @@ mod async_impl {
         where
             Self: Sized,
         {
-            let (_, host_name) = line.split_at(5);
-
-            ready(Ok(EhloCommandData::from(host_name.to_string())))
+            ready(EhloCommandData::from_command_line(&line).map_err(SMTPError::from))
         }
 
         fn server_send<C: AsyncSMTPConnection<'a>>(
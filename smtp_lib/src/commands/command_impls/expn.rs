@@ -0,0 +1,234 @@
+use mail_lib_types::mail_box::MailBox;
+
+use super::vrfy::{format_mailbox, parse_mailbox};
+use crate::{
+    commands::{arguments::command_argument, CommandParseError, SMTPCommand},
+    error::SMTPError,
+    server_response::{MultilineServerResponse, ResponseCode},
+};
+
+/// The reply text when the mailing list has no members
+const EMPTY_LIST_MESSAGE: &str = "OK";
+
+/// The data that is in the EXPN command
+///
+/// ```ebnf
+/// expn = "EXPN" SP String CRLF
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpnCommandData {
+    /// The mailing list to expand
+    pub mailing_list: String,
+}
+impl ExpnCommandData {
+    pub fn new(mailing_list: impl Into<String>) -> Self {
+        Self {
+            mailing_list: mailing_list.into(),
+        }
+    }
+    /// Parses the entire command line. Including the `EXPN`
+    pub fn from_command_line(line: &str) -> Result<Self, CommandParseError> {
+        command_argument(line, ExpnCommand::command())?
+            .map(Self::new)
+            .ok_or(CommandParseError::MissingArgument("mailing list"))
+    }
+}
+/// The response to the EXPN command
+///
+/// [RFC 5321 Section 3.5.3](https://datatracker.ietf.org/doc/html/rfc5321#section-3.5.3)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExpnResponse {
+    /// `250` The members of the mailing list. One per line
+    ///
+    /// An empty list is sent as a single `250 OK`
+    Expanded(Vec<MailBox>),
+    Error {
+        code: ResponseCode,
+        message: Option<String>,
+    },
+}
+impl TryFrom<MultilineServerResponse> for ExpnResponse {
+    type Error = SMTPError;
+
+    fn try_from(value: MultilineServerResponse) -> Result<Self, SMTPError> {
+        match value.code() {
+            None => Err(SMTPError::InvalidResponse("No response lines")),
+            Some(ResponseCode::Ok) if is_empty_list(&value) => {
+                Ok(ExpnResponse::Expanded(Vec::new()))
+            }
+            Some(ResponseCode::Ok) => {
+                let mut mailboxes = Vec::with_capacity(value.0.len());
+                for line in value.0 {
+                    mailboxes.push(parse_mailbox(line.message.as_deref())?);
+                }
+                Ok(ExpnResponse::Expanded(mailboxes))
+            }
+            Some(code) => Ok(ExpnResponse::Error {
+                code,
+                message: value.0.into_iter().next().and_then(|line| line.message),
+            }),
+        }
+    }
+}
+/// A single `250` line without a mailbox. Sent for a list without members
+fn is_empty_list(response: &MultilineServerResponse) -> bool {
+    match response.0.as_slice() {
+        [line] => line
+            .message
+            .as_deref()
+            .is_none_or(|message| message.trim() == EMPTY_LIST_MESSAGE),
+        _ => false,
+    }
+}
+impl From<ExpnResponse> for MultilineServerResponse {
+    fn from(value: ExpnResponse) -> Self {
+        match value {
+            ExpnResponse::Expanded(mailboxes) if mailboxes.is_empty() => {
+                MultilineServerResponse::new_with_code(
+                    ResponseCode::Ok,
+                    Some(EMPTY_LIST_MESSAGE.to_owned()),
+                    0,
+                )
+            }
+            ExpnResponse::Expanded(mailboxes) => {
+                let mut response =
+                    MultilineServerResponse::new(Vec::with_capacity(mailboxes.len()));
+                for mailbox in mailboxes {
+                    response.add_line(ResponseCode::Ok, Some(format_mailbox(&mailbox)));
+                }
+                response
+            }
+            ExpnResponse::Error { code, message } => {
+                MultilineServerResponse::new_with_code(code, message, 0)
+            }
+        }
+    }
+}
+
+/// The EXPN Command as specified [here](https://datatracker.ietf.org/doc/html/rfc5321#section-4.1.1.7)
+pub struct ExpnCommand;
+impl SMTPCommand for ExpnCommand {
+    type ClientCommand = ExpnCommandData;
+    type ServerResponse = ExpnResponse;
+
+    fn command() -> &'static str
+    where
+        Self: Sized,
+    {
+        "EXPN"
+    }
+}
+
+#[cfg(feature = "async")]
+mod async_impl {
+    use futures::future::{ready, BoxFuture, Ready};
+
+    use super::{ExpnCommand, ExpnCommandData, ExpnResponse};
+    use crate::{
+        commands::async_traits::AsyncSMTPCommand, error::SMTPError,
+        server_response::MultilineServerResponse, smtp_client::async_traits::AsyncSMTPClient,
+        smtp_server::async_traits::AsyncSMTPConnection,
+        statement::async_statement::AsyncMultilineStatement, CRLF,
+    };
+
+    impl<'a> AsyncSMTPCommand<'a> for ExpnCommand {
+        type ServerHandleRead = Ready<crate::Result<Self::ClientCommand>>;
+        type SendFuture = BoxFuture<'a, crate::Result<()>>;
+        type HandleServerResponse = BoxFuture<'a, crate::Result<ExpnResponse>>;
+
+        fn server_read<'b, C: AsyncSMTPConnection<'b>>(
+            _: &mut C,
+            line: String,
+        ) -> Self::ServerHandleRead
+        where
+            Self: Sized,
+        {
+            ready(ExpnCommandData::from_command_line(&line).map_err(SMTPError::from))
+        }
+
+        fn server_send<C: AsyncSMTPConnection<'a>>(
+            response: Self::ServerResponse,
+            client: &'a mut C,
+        ) -> Self::SendFuture {
+            Box::pin(async move {
+                client
+                    .write_statement(MultilineServerResponse::from(response))
+                    .await?;
+                Ok(())
+            })
+        }
+
+        fn client_send<C: AsyncSMTPClient<'a>>(
+            command: Self::ClientCommand,
+            client: &'a mut C,
+        ) -> Self::SendFuture {
+            Box::pin(async move {
+                let command = format!("EXPN {}{}", command.mailing_list, CRLF);
+                client.write_string(command).await?;
+                Ok(())
+            })
+        }
+
+        fn client_read<C: AsyncSMTPClient<'a>>(client: &'a mut C) -> Self::HandleServerResponse {
+            Box::pin(async {
+                let response =
+                    MultilineServerResponse::read_til_non_hyphenated_line(client).await?;
+                ExpnResponse::try_from(response)
+            })
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::{ExpnCommandData, ExpnResponse};
+    use crate::{
+        server_response::{MultilineServerResponse, ResponseCode},
+        statement::Statement,
+    };
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            ExpnCommandData::from_command_line("EXPN Example-People\r\n"),
+            Ok(ExpnCommandData::new("Example-People"))
+        );
+        assert!(ExpnCommandData::from_command_line("EXPN").is_err());
+    }
+    #[test]
+    fn test_response() {
+        let response = MultilineServerResponse::from_str(
+            "250-\"Jon Postel\" <Postel@isi.edu>\r\n250-Fonebone <Fonebone@physics.foo-u.edu>\r\n250 <Magic@isi.edu>\r\n",
+        )
+        .unwrap();
+        let expanded = ExpnResponse::try_from(response).unwrap();
+        let ExpnResponse::Expanded(mailboxes) = &expanded else {
+            panic!("Expected expanded");
+        };
+        assert_eq!(mailboxes.len(), 3);
+        assert_eq!(mailboxes[0].get_name(), Some("Jon Postel"));
+        assert_eq!(
+            ExpnResponse::try_from(MultilineServerResponse::from(expanded.clone())).unwrap(),
+            expanded
+        );
+
+        let response = MultilineServerResponse::from_str("550 Access Denied\r\n").unwrap();
+        assert!(matches!(
+            ExpnResponse::try_from(response).unwrap(),
+            ExpnResponse::Error {
+                code: ResponseCode::MailboxUnavailable,
+                ..
+            }
+        ));
+    }
+    #[test]
+    fn test_empty_list() {
+        let response = MultilineServerResponse::from(ExpnResponse::Expanded(vec![]));
+        assert_eq!(response.to_bytes(), "250 OK\r\n");
+        assert_eq!(
+            ExpnResponse::try_from(response).unwrap(),
+            ExpnResponse::Expanded(vec![])
+        );
+    }
+}
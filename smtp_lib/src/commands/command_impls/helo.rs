@@ -0,0 +1,142 @@
+use super::{EhloCommandData, EhloResponse};
+use crate::commands::{arguments::command_argument, CommandParseError, SMTPCommand};
+
+/// The HELO Command as specified [here](https://datatracker.ietf.org/doc/html/rfc5321#section-4.1.1.1)
+///
+/// Uses the same data as [EhloCommand](super::EhloCommand). The response will never contain any extensions
+pub struct HeloCommand;
+impl HeloCommand {
+    /// Parses the entire command line. Including the `HELO`
+    pub fn from_command_line(line: &str) -> Result<EhloCommandData, CommandParseError> {
+        command_argument(line, Self::command())?
+            .map(|domain| EhloCommandData::from(domain.to_owned()))
+            .ok_or(CommandParseError::MissingArgument("domain"))
+    }
+}
+impl SMTPCommand for HeloCommand {
+    type ClientCommand = EhloCommandData;
+    type ServerResponse = EhloResponse;
+
+    fn command() -> &'static str
+    where
+        Self: Sized,
+    {
+        "HELO"
+    }
+}
+
+#[cfg(feature = "async")]
+mod async_impl {
+    use futures::future::{ready, BoxFuture, Ready};
+
+    use super::HeloCommand;
+    use crate::{
+        commands::{
+            async_traits::AsyncSMTPCommand,
+            command_impls::{EhloCommand, EhloCommandData, EhloResponse},
+        },
+        error::SMTPError,
+        server_response::{MultilineServerResponse, ServerResponseLine},
+        smtp_client::async_traits::AsyncSMTPClient,
+        smtp_server::async_traits::AsyncSMTPConnection,
+        statement::async_statement::AsyncMultilineStatement,
+        CRLF,
+    };
+
+    impl<'a> AsyncSMTPCommand<'a> for HeloCommand {
+        type ServerHandleRead = Ready<crate::Result<Self::ClientCommand>>;
+        type SendFuture = BoxFuture<'a, crate::Result<()>>;
+        type HandleServerResponse = BoxFuture<'a, crate::Result<EhloResponse>>;
+
+        fn server_read<'b, C: AsyncSMTPConnection<'b>>(
+            _: &mut C,
+            line: String,
+        ) -> Self::ServerHandleRead
+        where
+            Self: Sized,
+        {
+            ready(HeloCommand::from_command_line(&line).map_err(SMTPError::from))
+        }
+
+        fn server_send<C: AsyncSMTPConnection<'a>>(
+            response: Self::ServerResponse,
+            client: &'a mut C,
+        ) -> Self::SendFuture {
+            Box::pin(async move {
+                let statement = match response {
+                    EhloResponse::Error { code, message } => {
+                        ServerResponseLine::new(code, Some(message))
+                    }
+                    EhloResponse::Success { hello, .. } => {
+                        ServerResponseLine::new(hello.0, Some(hello.1))
+                    }
+                };
+                client.write_statement(statement).await?;
+                Ok(())
+            })
+        }
+
+        fn client_send<C: AsyncSMTPClient<'a>>(
+            command: Self::ClientCommand,
+            client: &'a mut C,
+        ) -> Self::SendFuture {
+            Box::pin(async move {
+                let command = format!("HELO {}{}", command.client_hostname, CRLF);
+                client.write_string(command).await?;
+                Ok(())
+            })
+        }
+
+        fn client_read<C: AsyncSMTPClient<'a>>(client: &'a mut C) -> Self::HandleServerResponse {
+            Box::pin(async {
+                let multiline =
+                    MultilineServerResponse::read_til_non_hyphenated_line(client).await?;
+                EhloResponse::try_from(multiline)
+            })
+        }
+    }
+    /// Sends EHLO and falls back to HELO if the server does not support EHLO
+    pub async fn ehlo_or_helo<C>(
+        client: &mut C,
+        hostname: EhloCommandData,
+    ) -> crate::Result<EhloResponse>
+    where
+        C: for<'b> AsyncSMTPClient<'b>,
+    {
+        EhloCommand::client_send(hostname.clone(), client).await?;
+        let response = EhloCommand::client_read(client).await?;
+        if !response.should_fall_back_to_helo() {
+            return Ok(response);
+        }
+        HeloCommand::client_send(hostname, client).await?;
+        HeloCommand::client_read(client).await
+    }
+}
+#[cfg(feature = "async")]
+pub use async_impl::ehlo_or_helo;
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::HeloCommand;
+    use crate::{commands::command_impls::EhloResponse, server_response::MultilineServerResponse};
+
+    #[test]
+    fn test_parse() {
+        let data = HeloCommand::from_command_line("HELO client.example.com\r\n").unwrap();
+        assert_eq!(data.client_hostname, "client.example.com");
+        assert!(HeloCommand::from_command_line("HELO").is_err());
+    }
+    #[test]
+    fn test_fall_back() {
+        let response =
+            MultilineServerResponse::from_str("502 Command not implemented\r\n").unwrap();
+        assert!(EhloResponse::try_from(response)
+            .unwrap()
+            .should_fall_back_to_helo());
+        let response = MultilineServerResponse::from_str("250 mail.example.com\r\n").unwrap();
+        assert!(!EhloResponse::try_from(response)
+            .unwrap()
+            .should_fall_back_to_helo());
+    }
+}
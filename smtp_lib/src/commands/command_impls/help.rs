@@ -0,0 +1,203 @@
+use crate::{
+    commands::{arguments::command_argument, CommandParseError, SMTPCommand},
+    server_response::{MultilineServerResponse, ResponseCode},
+};
+
+/// The data that is in the HELP command
+///
+/// ```ebnf
+/// help = "HELP" [ SP String ] CRLF
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HelpCommandData {
+    /// The command or topic the client wants help with
+    pub topic: Option<String>,
+}
+impl HelpCommandData {
+    pub fn new(topic: impl Into<String>) -> Self {
+        Self {
+            topic: Some(topic.into()),
+        }
+    }
+    /// Parses the entire command line. Including the `HELP`
+    pub fn from_command_line(line: &str) -> Result<Self, CommandParseError> {
+        let topic = command_argument(line, HelpCommand::command())?;
+        Ok(Self {
+            topic: topic.map(|topic| topic.to_owned()),
+        })
+    }
+}
+/// The response to the HELP command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HelpResponse {
+    /// `214` The lines of help text
+    ///
+    /// Without any lines a single `214 End of HELP info` is sent
+    Help(Vec<String>),
+    Error {
+        code: ResponseCode,
+        message: Option<String>,
+    },
+}
+impl From<MultilineServerResponse> for HelpResponse {
+    fn from(value: MultilineServerResponse) -> Self {
+        match value.code() {
+            Some(ResponseCode::HelpMessage | ResponseCode::SystemStatus) => HelpResponse::Help(
+                value
+                    .0
+                    .into_iter()
+                    .map(|line| line.message.unwrap_or_default())
+                    .collect(),
+            ),
+            code => HelpResponse::Error {
+                code: code.unwrap_or(ResponseCode::SyntaxError),
+                message: value.0.into_iter().next().and_then(|line| line.message),
+            },
+        }
+    }
+}
+impl From<HelpResponse> for MultilineServerResponse {
+    fn from(value: HelpResponse) -> Self {
+        match value {
+            HelpResponse::Help(lines) if lines.is_empty() => {
+                MultilineServerResponse::new_with_code(
+                    ResponseCode::HelpMessage,
+                    Some("End of HELP info".to_owned()),
+                    0,
+                )
+            }
+            HelpResponse::Help(lines) => {
+                let mut response = MultilineServerResponse::new(Vec::with_capacity(lines.len()));
+                for line in lines {
+                    response.add_line(ResponseCode::HelpMessage, Some(line));
+                }
+                response
+            }
+            HelpResponse::Error { code, message } => {
+                MultilineServerResponse::new_with_code(code, message, 0)
+            }
+        }
+    }
+}
+
+/// The HELP Command as specified [here](https://datatracker.ietf.org/doc/html/rfc5321#section-4.1.1.8)
+pub struct HelpCommand;
+impl SMTPCommand for HelpCommand {
+    type ClientCommand = HelpCommandData;
+    type ServerResponse = HelpResponse;
+
+    fn command() -> &'static str
+    where
+        Self: Sized,
+    {
+        "HELP"
+    }
+}
+
+#[cfg(feature = "async")]
+mod async_impl {
+    use futures::future::{ready, BoxFuture, Ready};
+
+    use super::{HelpCommand, HelpCommandData, HelpResponse};
+    use crate::{
+        commands::async_traits::AsyncSMTPCommand, error::SMTPError,
+        server_response::MultilineServerResponse, smtp_client::async_traits::AsyncSMTPClient,
+        smtp_server::async_traits::AsyncSMTPConnection,
+        statement::async_statement::AsyncMultilineStatement, CRLF,
+    };
+
+    impl<'a> AsyncSMTPCommand<'a> for HelpCommand {
+        type ServerHandleRead = Ready<crate::Result<Self::ClientCommand>>;
+        type SendFuture = BoxFuture<'a, crate::Result<()>>;
+        type HandleServerResponse = BoxFuture<'a, crate::Result<HelpResponse>>;
+
+        fn server_read<'b, C: AsyncSMTPConnection<'b>>(
+            _: &mut C,
+            line: String,
+        ) -> Self::ServerHandleRead
+        where
+            Self: Sized,
+        {
+            ready(HelpCommandData::from_command_line(&line).map_err(SMTPError::from))
+        }
+
+        fn server_send<C: AsyncSMTPConnection<'a>>(
+            response: Self::ServerResponse,
+            client: &'a mut C,
+        ) -> Self::SendFuture {
+            Box::pin(async move {
+                client
+                    .write_statement(MultilineServerResponse::from(response))
+                    .await?;
+                Ok(())
+            })
+        }
+
+        fn client_send<C: AsyncSMTPClient<'a>>(
+            command: Self::ClientCommand,
+            client: &'a mut C,
+        ) -> Self::SendFuture {
+            Box::pin(async move {
+                let command = match command.topic {
+                    Some(topic) => format!("HELP {}{}", topic, CRLF),
+                    None => format!("HELP{}", CRLF),
+                };
+                client.write_string(command).await?;
+                Ok(())
+            })
+        }
+
+        fn client_read<C: AsyncSMTPClient<'a>>(client: &'a mut C) -> Self::HandleServerResponse {
+            Box::pin(async {
+                let response =
+                    MultilineServerResponse::read_til_non_hyphenated_line(client).await?;
+                Ok(HelpResponse::from(response))
+            })
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::{HelpCommandData, HelpResponse};
+    use crate::{
+        server_response::{MultilineServerResponse, ResponseCode},
+        statement::Statement,
+    };
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            HelpCommandData::from_command_line("HELP\r\n"),
+            Ok(HelpCommandData::default())
+        );
+        assert_eq!(
+            HelpCommandData::from_command_line("HELP MAIL\r\n"),
+            Ok(HelpCommandData::new("MAIL"))
+        );
+    }
+    #[test]
+    fn test_response() {
+        let response =
+            MultilineServerResponse::from_str("214-Commands:\r\n214 HELO EHLO MAIL\r\n").unwrap();
+        let help = HelpResponse::from(response.clone());
+        assert_eq!(
+            help,
+            HelpResponse::Help(vec!["Commands:".to_owned(), "HELO EHLO MAIL".to_owned()])
+        );
+        assert_eq!(MultilineServerResponse::from(help), response);
+
+        let response = MultilineServerResponse::from_str("502 Not implemented\r\n").unwrap();
+        assert!(matches!(
+            HelpResponse::from(response),
+            HelpResponse::Error {
+                code: ResponseCode::CommandNotImplemented,
+                ..
+            }
+        ));
+
+        let response = MultilineServerResponse::from(HelpResponse::Help(vec![]));
+        assert_eq!(response.to_bytes(), "214 End of HELP info\r\n");
+    }
+}
@@ -0,0 +1,94 @@
+use super::{EhloCommandData, EhloResponse};
+use crate::commands::{arguments::command_argument, CommandParseError, SMTPCommand};
+
+/// The LHLO Command as specified [here](https://datatracker.ietf.org/doc/html/rfc2033#section-4.1)
+///
+/// Replaces EHLO in LMTP. Uses the same data and response as [EhloCommand](super::EhloCommand).
+/// LMTP has no HELO to fall back to
+pub struct LhloCommand;
+impl LhloCommand {
+    /// Parses the entire command line. Including the `LHLO`
+    pub fn from_command_line(line: &str) -> Result<EhloCommandData, CommandParseError> {
+        command_argument(line, Self::command())?
+            .map(|domain| EhloCommandData::from(domain.to_owned()))
+            .ok_or(CommandParseError::MissingArgument("domain"))
+    }
+}
+impl SMTPCommand for LhloCommand {
+    type ClientCommand = EhloCommandData;
+    type ServerResponse = EhloResponse;
+
+    fn command() -> &'static str
+    where
+        Self: Sized,
+    {
+        "LHLO"
+    }
+}
+
+#[cfg(feature = "async")]
+mod async_impl {
+    use futures::future::{ready, BoxFuture, Ready};
+
+    use super::LhloCommand;
+    use crate::{
+        commands::{
+            async_traits::AsyncSMTPCommand,
+            command_impls::{EhloCommand, EhloResponse},
+        },
+        error::SMTPError,
+        smtp_client::async_traits::AsyncSMTPClient,
+        smtp_server::async_traits::AsyncSMTPConnection,
+        CRLF,
+    };
+
+    impl<'a> AsyncSMTPCommand<'a> for LhloCommand {
+        type ServerHandleRead = Ready<crate::Result<Self::ClientCommand>>;
+        type SendFuture = BoxFuture<'a, crate::Result<()>>;
+        type HandleServerResponse = BoxFuture<'a, crate::Result<EhloResponse>>;
+
+        fn server_read<'b, C: AsyncSMTPConnection<'b>>(
+            _: &mut C,
+            line: String,
+        ) -> Self::ServerHandleRead
+        where
+            Self: Sized,
+        {
+            ready(LhloCommand::from_command_line(&line).map_err(SMTPError::from))
+        }
+
+        fn server_send<C: AsyncSMTPConnection<'a>>(
+            response: Self::ServerResponse,
+            client: &'a mut C,
+        ) -> Self::SendFuture {
+            EhloCommand::server_send(response, client)
+        }
+
+        fn client_send<C: AsyncSMTPClient<'a>>(
+            command: Self::ClientCommand,
+            client: &'a mut C,
+        ) -> Self::SendFuture {
+            Box::pin(async move {
+                let command = format!("LHLO {}{}", command.client_hostname, CRLF);
+                client.write_string(command).await?;
+                Ok(())
+            })
+        }
+
+        fn client_read<C: AsyncSMTPClient<'a>>(client: &'a mut C) -> Self::HandleServerResponse {
+            EhloCommand::client_read(client)
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::LhloCommand;
+
+    #[test]
+    fn test_parse() {
+        let data = LhloCommand::from_command_line("LHLO client.example.com\r\n").unwrap();
+        assert_eq!(data.client_hostname, "client.example.com");
+        assert!(LhloCommand::from_command_line("lhlo foo.example.com").is_ok());
+        assert!(LhloCommand::from_command_line("LHLO").is_err());
+    }
+}
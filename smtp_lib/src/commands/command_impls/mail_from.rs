@@ -0,0 +1,327 @@
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+use mail_lib_types::EmailAddress;
+
+use crate::{
+    commands::{
+        arguments::{
+            format_path, parse_parameters, parse_path, split_parameter, split_path, strip_command,
+            xtext_decode, xtext_encode,
+        },
+        CommandParseError, SMTPCommand,
+    },
+    server_response::MultilineServerResponse,
+};
+
+/// The `BODY=` parameter
+///
+/// [RFC 6152](https://datatracker.ietf.org/doc/html/rfc6152) and [RFC 3030](https://datatracker.ietf.org/doc/html/rfc3030)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::EnumString, strum::Display)]
+#[strum(ascii_case_insensitive)]
+pub enum BodyType {
+    #[strum(serialize = "7BIT")]
+    SevenBit,
+    #[strum(serialize = "8BITMIME")]
+    EightBitMime,
+    #[strum(serialize = "BINARYMIME")]
+    BinaryMime,
+}
+/// The `RET=` parameter. What should be returned in a Delivery Status Notification
+///
+/// [RFC 3461 Section 4.3](https://datatracker.ietf.org/doc/html/rfc3461#section-4.3)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::EnumString, strum::Display)]
+#[strum(serialize_all = "UPPERCASE", ascii_case_insensitive)]
+pub enum DsnReturn {
+    /// Return the full message
+    Full,
+    /// Only return the headers
+    Hdrs,
+}
+/// The parameters that can be sent with the MAIL FROM command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MailParameter {
+    /// `SIZE=` [RFC 1870](https://datatracker.ietf.org/doc/html/rfc1870)
+    Size(usize),
+    /// `BODY=`
+    Body(BodyType),
+    /// `SMTPUTF8` [RFC 6531](https://datatracker.ietf.org/doc/html/rfc6531)
+    SMTPUTF8,
+    /// `RET=` [RFC 3461](https://datatracker.ietf.org/doc/html/rfc3461)
+    Ret(DsnReturn),
+    /// `ENVID=` [RFC 3461](https://datatracker.ietf.org/doc/html/rfc3461). Stored decoded
+    EnvId(String),
+    /// `AUTH=` [RFC 4954](https://datatracker.ietf.org/doc/html/rfc4954#section-5). Stored decoded
+    ///
+    /// None is `AUTH=<>`
+    Auth(Option<String>),
+    /// Any other parameter
+    Other {
+        keyword: String,
+        value: Option<String>,
+    },
+}
+impl Display for MailParameter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MailParameter::Size(size) => write!(f, "SIZE={}", size),
+            MailParameter::Body(body) => write!(f, "BODY={}", body),
+            MailParameter::SMTPUTF8 => write!(f, "SMTPUTF8"),
+            MailParameter::Ret(ret) => write!(f, "RET={}", ret),
+            MailParameter::EnvId(id) => write!(f, "ENVID={}", xtext_encode(id)),
+            MailParameter::Auth(Some(mailbox)) => write!(f, "AUTH={}", xtext_encode(mailbox)),
+            MailParameter::Auth(None) => write!(f, "AUTH=<>"),
+            MailParameter::Other { keyword, value } => {
+                if let Some(value) = value {
+                    write!(f, "{}={}", keyword, value)
+                } else {
+                    write!(f, "{}", keyword)
+                }
+            }
+        }
+    }
+}
+impl FromStr for MailParameter {
+    type Err = CommandParseError;
+
+    fn from_str(parameter: &str) -> Result<Self, Self::Err> {
+        let invalid = || CommandParseError::InvalidParameter(parameter.to_owned());
+        let (keyword, value) = split_parameter(parameter)?;
+        let parameter = match (keyword.to_ascii_uppercase().as_str(), value) {
+            ("SIZE", Some(value)) => Self::Size(usize::from_str(value).map_err(|_| invalid())?),
+            ("BODY", Some(value)) => Self::Body(BodyType::from_str(value).map_err(|_| invalid())?),
+            ("SMTPUTF8", None) => Self::SMTPUTF8,
+            ("RET", Some(value)) => Self::Ret(DsnReturn::from_str(value).map_err(|_| invalid())?),
+            ("ENVID", Some(value)) => Self::EnvId(xtext_decode(value)?),
+            ("AUTH", Some("<>")) => Self::Auth(None),
+            ("AUTH", Some(value)) => Self::Auth(Some(xtext_decode(value)?)),
+            ("SIZE" | "BODY" | "SMTPUTF8" | "RET" | "ENVID" | "AUTH", _) => return Err(invalid()),
+            _ => Self::Other {
+                keyword: keyword.to_owned(),
+                value: value.map(|value| value.to_owned()),
+            },
+        };
+        Ok(parameter)
+    }
+}
+/// The data that is in the MAIL FROM command
+///
+/// ```ebnf
+/// mail = "MAIL FROM:" Reverse-path [SP Mail-parameters] CRLF
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MailFromCommandData {
+    /// None is the null sender `<>`. Used for bounces
+    pub reverse_path: Option<EmailAddress>,
+    pub parameters: Vec<MailParameter>,
+}
+impl MailFromCommandData {
+    pub fn new(reverse_path: Option<EmailAddress>) -> Self {
+        Self {
+            reverse_path,
+            parameters: Vec::new(),
+        }
+    }
+    /// Adds a parameter
+    pub fn with_parameter(mut self, parameter: MailParameter) -> Self {
+        self.parameters.push(parameter);
+        self
+    }
+    /// Parses the entire command line. Including the `MAIL FROM:`
+    pub fn from_command_line(line: &str) -> Result<Self, CommandParseError> {
+        Self::from_str(strip_command(line, MailFromCommand::command())?)
+    }
+    /// The value of the `SIZE=` parameter
+    pub fn size(&self) -> Option<usize> {
+        self.parameters
+            .iter()
+            .find_map(|parameter| match parameter {
+                MailParameter::Size(size) => Some(*size),
+                _ => None,
+            })
+    }
+    /// The value of the `BODY=` parameter
+    pub fn body(&self) -> Option<BodyType> {
+        self.parameters
+            .iter()
+            .find_map(|parameter| match parameter {
+                MailParameter::Body(body) => Some(*body),
+                _ => None,
+            })
+    }
+    /// The value of the `RET=` parameter
+    pub fn ret(&self) -> Option<DsnReturn> {
+        self.parameters
+            .iter()
+            .find_map(|parameter| match parameter {
+                MailParameter::Ret(ret) => Some(*ret),
+                _ => None,
+            })
+    }
+    /// The decoded value of the `ENVID=` parameter
+    pub fn env_id(&self) -> Option<&str> {
+        self.parameters
+            .iter()
+            .find_map(|parameter| match parameter {
+                MailParameter::EnvId(id) => Some(id.as_str()),
+                _ => None,
+            })
+    }
+    /// If the `SMTPUTF8` parameter was sent
+    pub fn is_smtp_utf8(&self) -> bool {
+        self.parameters.contains(&MailParameter::SMTPUTF8)
+    }
+}
+/// Formats the arguments of the command. `<reverse-path> [parameters]`
+impl Display for MailFromCommandData {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", format_path(self.reverse_path.as_ref()))?;
+        for parameter in &self.parameters {
+            write!(f, " {}", parameter)?;
+        }
+        Ok(())
+    }
+}
+/// Parses the arguments of the command. `<reverse-path> [parameters]`
+impl FromStr for MailFromCommandData {
+    type Err = CommandParseError;
+
+    fn from_str(arguments: &str) -> Result<Self, Self::Err> {
+        let (path, parameters) = split_path(arguments)?;
+        let reverse_path = parse_path(path)?;
+        Ok(Self {
+            reverse_path,
+            parameters: parse_parameters(parameters)?,
+        })
+    }
+}
+
+/// The MAIL FROM Command as specified [here](https://datatracker.ietf.org/doc/html/rfc5321#section-4.1.1.2)
+pub struct MailFromCommand;
+impl SMTPCommand for MailFromCommand {
+    type ClientCommand = MailFromCommandData;
+    type ServerResponse = MultilineServerResponse;
+
+    fn command() -> &'static str
+    where
+        Self: Sized,
+    {
+        "MAIL FROM:"
+    }
+}
+
+#[cfg(feature = "async")]
+mod async_impl {
+    use futures::future::{ready, BoxFuture, Ready};
+
+    use super::{MailFromCommand, MailFromCommandData};
+    use crate::{
+        commands::async_traits::AsyncSMTPCommand, error::SMTPError,
+        server_response::MultilineServerResponse, smtp_client::async_traits::AsyncSMTPClient,
+        smtp_server::async_traits::AsyncSMTPConnection,
+        statement::async_statement::AsyncMultilineStatement, CRLF,
+    };
+
+    impl<'a> AsyncSMTPCommand<'a> for MailFromCommand {
+        type ServerHandleRead = Ready<crate::Result<Self::ClientCommand>>;
+        type SendFuture = BoxFuture<'a, crate::Result<()>>;
+        type HandleServerResponse = BoxFuture<'a, crate::Result<MultilineServerResponse>>;
+
+        fn server_read<'b, C: AsyncSMTPConnection<'b>>(
+            _: &mut C,
+            line: String,
+        ) -> Self::ServerHandleRead
+        where
+            Self: Sized,
+        {
+            ready(MailFromCommandData::from_command_line(&line).map_err(SMTPError::from))
+        }
+
+        fn server_send<C: AsyncSMTPConnection<'a>>(
+            response: Self::ServerResponse,
+            client: &'a mut C,
+        ) -> Self::SendFuture {
+            Box::pin(async move {
+                client.write_statement(response).await?;
+                Ok(())
+            })
+        }
+
+        fn client_send<C: AsyncSMTPClient<'a>>(
+            command: Self::ClientCommand,
+            client: &'a mut C,
+        ) -> Self::SendFuture {
+            Box::pin(async move {
+                let command = format!("MAIL FROM:{}{}", command, CRLF);
+                client.write_string(command).await?;
+                Ok(())
+            })
+        }
+
+        fn client_read<C: AsyncSMTPClient<'a>>(client: &'a mut C) -> Self::HandleServerResponse {
+            MultilineServerResponse::read_til_non_hyphenated_line(client)
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::{BodyType, DsnReturn, MailFromCommandData, MailParameter};
+    use crate::commands::CommandParseError;
+
+    #[test]
+    fn test_parse() {
+        let data = MailFromCommandData::from_command_line(
+            "MAIL FROM:<sender@example.com> SIZE=1024 BODY=8bitmime SMTPUTF8 RET=HDRS ENVID=QQ314159 AUTH=<>\r\n",
+        )
+        .unwrap();
+        assert_eq!(data.reverse_path.as_ref().unwrap(), &"sender@example.com");
+        assert_eq!(data.size(), Some(1024));
+        assert_eq!(data.body(), Some(BodyType::EightBitMime));
+        assert!(data.is_smtp_utf8());
+        assert!(data
+            .parameters
+            .contains(&MailParameter::Ret(DsnReturn::Hdrs)));
+        assert!(data
+            .parameters
+            .contains(&MailParameter::EnvId("QQ314159".to_owned())));
+        assert!(data.parameters.contains(&MailParameter::Auth(None)));
+    }
+    #[test]
+    fn test_null_sender() {
+        let data = MailFromCommandData::from_command_line("mail from:<>").unwrap();
+        assert_eq!(data.reverse_path, None);
+        assert_eq!(data.to_string(), "<>");
+    }
+    #[test]
+    fn test_round_trip() {
+        let data = MailFromCommandData::from_str(
+            "<sender@example.com> SIZE=10 BODY=BINARYMIME ENVID=a+2Bb X-CUSTOM=yes",
+        )
+        .unwrap();
+        assert!(data
+            .parameters
+            .contains(&MailParameter::EnvId("a+b".to_owned())));
+        assert_eq!(
+            data.to_string(),
+            "<sender@example.com> SIZE=10 BODY=BINARYMIME ENVID=a+2Bb X-CUSTOM=yes"
+        );
+        assert_eq!(MailFromCommandData::from_str(&data.to_string()), Ok(data));
+    }
+    #[test]
+    fn test_invalid() {
+        assert!(matches!(
+            MailFromCommandData::from_command_line("MAIL FROM:<not an address>"),
+            Err(CommandParseError::InvalidAddress(_))
+        ));
+        assert!(MailFromCommandData::from_command_line("MAIL FROM:sender@example.com").is_err());
+        assert!(matches!(
+            MailFromCommandData::from_command_line("MAIL FROM:<sender@example.com> SIZE=big"),
+            Err(CommandParseError::InvalidParameter(_))
+        ));
+        assert!(MailFromCommandData::from_command_line("RCPT TO:<sender@example.com>").is_err());
+    }
+}
@@ -1,3 +1,31 @@
+pub mod auth;
+pub mod bdat;
+pub mod data;
 pub mod ehlo;
+pub mod expn;
+pub mod helo;
+pub mod help;
+pub mod lhlo;
+pub mod mail_from;
+pub mod noop;
+pub mod quit;
+pub mod rcpt_to;
+pub mod rset;
+pub mod starttls;
+pub mod vrfy;
 
+pub use auth::*;
+pub use bdat::*;
+pub use data::*;
 pub use ehlo::*;
+pub use expn::*;
+pub use helo::*;
+pub use help::*;
+pub use lhlo::*;
+pub use mail_from::*;
+pub use noop::*;
+pub use quit::*;
+pub use rcpt_to::*;
+pub use rset::*;
+pub use starttls::*;
+pub use vrfy::*;
@@ -0,0 +1,95 @@
+use crate::{
+    commands::{arguments::command_argument, CommandParseError, SMTPCommand},
+    server_response::MultilineServerResponse,
+};
+
+/// The data that is in the NOOP command
+///
+/// ```ebnf
+/// noop = "NOOP" [ SP String ] CRLF
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NoopCommandData {
+    /// The optional argument. Servers must ignore it
+    pub argument: Option<String>,
+}
+impl NoopCommandData {
+    /// Parses the entire command line. Including the `NOOP`
+    pub fn from_command_line(line: &str) -> Result<Self, CommandParseError> {
+        let argument = command_argument(line, NoopCommand::command())?;
+        Ok(Self {
+            argument: argument.map(|argument| argument.to_owned()),
+        })
+    }
+}
+
+/// The NOOP Command as specified [here](https://datatracker.ietf.org/doc/html/rfc5321#section-4.1.1.9)
+pub struct NoopCommand;
+impl SMTPCommand for NoopCommand {
+    type ClientCommand = NoopCommandData;
+    type ServerResponse = MultilineServerResponse;
+
+    fn command() -> &'static str
+    where
+        Self: Sized,
+    {
+        "NOOP"
+    }
+}
+
+#[cfg(feature = "async")]
+mod async_impl {
+    use futures::future::{ready, BoxFuture, Ready};
+
+    use super::{NoopCommand, NoopCommandData};
+    use crate::{
+        commands::async_traits::AsyncSMTPCommand, error::SMTPError,
+        server_response::MultilineServerResponse, smtp_client::async_traits::AsyncSMTPClient,
+        smtp_server::async_traits::AsyncSMTPConnection,
+        statement::async_statement::AsyncMultilineStatement, CRLF,
+    };
+
+    impl<'a> AsyncSMTPCommand<'a> for NoopCommand {
+        type ServerHandleRead = Ready<crate::Result<Self::ClientCommand>>;
+        type SendFuture = BoxFuture<'a, crate::Result<()>>;
+        type HandleServerResponse = BoxFuture<'a, crate::Result<MultilineServerResponse>>;
+
+        fn server_read<'b, C: AsyncSMTPConnection<'b>>(
+            _: &mut C,
+            line: String,
+        ) -> Self::ServerHandleRead
+        where
+            Self: Sized,
+        {
+            ready(NoopCommandData::from_command_line(&line).map_err(SMTPError::from))
+        }
+
+        fn server_send<C: AsyncSMTPConnection<'a>>(
+            response: Self::ServerResponse,
+            client: &'a mut C,
+        ) -> Self::SendFuture {
+            Box::pin(async move {
+                client.write_statement(response).await?;
+                Ok(())
+            })
+        }
+
+        fn client_send<C: AsyncSMTPClient<'a>>(
+            command: Self::ClientCommand,
+            client: &'a mut C,
+        ) -> Self::SendFuture {
+            Box::pin(async move {
+                let command = match command.argument {
+                    Some(argument) => format!("NOOP {}{}", argument, CRLF),
+                    None => format!("NOOP{}", CRLF),
+                };
+                client.write_string(command).await?;
+                Ok(())
+            })
+        }
+
+        fn client_read<C: AsyncSMTPClient<'a>>(client: &'a mut C) -> Self::HandleServerResponse {
+            MultilineServerResponse::read_til_non_hyphenated_line(client)
+        }
+    }
+}
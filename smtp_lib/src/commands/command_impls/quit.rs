@@ -0,0 +1,79 @@
+use crate::{
+    commands::{arguments::no_arguments, CommandParseError, SMTPCommand},
+    server_response::MultilineServerResponse,
+};
+
+/// The QUIT Command as specified [here](https://datatracker.ietf.org/doc/html/rfc5321#section-4.1.1.10)
+///
+/// Asks the server to close the connection
+pub struct QuitCommand;
+impl QuitCommand {
+    /// Parses the command line. QUIT does not take any arguments
+    pub fn from_command_line(line: &str) -> Result<(), CommandParseError> {
+        no_arguments(line, Self::command())
+    }
+}
+impl SMTPCommand for QuitCommand {
+    type ClientCommand = ();
+    type ServerResponse = MultilineServerResponse;
+
+    fn command() -> &'static str
+    where
+        Self: Sized,
+    {
+        "QUIT"
+    }
+}
+
+#[cfg(feature = "async")]
+mod async_impl {
+    use futures::future::{ready, BoxFuture, Ready};
+
+    use super::QuitCommand;
+    use crate::{
+        commands::async_traits::AsyncSMTPCommand, error::SMTPError,
+        server_response::MultilineServerResponse, smtp_client::async_traits::AsyncSMTPClient,
+        smtp_server::async_traits::AsyncSMTPConnection,
+        statement::async_statement::AsyncMultilineStatement, CRLF,
+    };
+
+    impl<'a> AsyncSMTPCommand<'a> for QuitCommand {
+        type ServerHandleRead = Ready<crate::Result<Self::ClientCommand>>;
+        type SendFuture = BoxFuture<'a, crate::Result<()>>;
+        type HandleServerResponse = BoxFuture<'a, crate::Result<MultilineServerResponse>>;
+
+        fn server_read<'b, C: AsyncSMTPConnection<'b>>(
+            _: &mut C,
+            line: String,
+        ) -> Self::ServerHandleRead
+        where
+            Self: Sized,
+        {
+            ready(QuitCommand::from_command_line(&line).map_err(SMTPError::from))
+        }
+
+        fn server_send<C: AsyncSMTPConnection<'a>>(
+            response: Self::ServerResponse,
+            client: &'a mut C,
+        ) -> Self::SendFuture {
+            Box::pin(async move {
+                client.write_statement(response).await?;
+                Ok(())
+            })
+        }
+
+        fn client_send<C: AsyncSMTPClient<'a>>(
+            _: Self::ClientCommand,
+            client: &'a mut C,
+        ) -> Self::SendFuture {
+            Box::pin(async move {
+                client.write_string(format!("QUIT{}", CRLF)).await?;
+                Ok(())
+            })
+        }
+
+        fn client_read<C: AsyncSMTPClient<'a>>(client: &'a mut C) -> Self::HandleServerResponse {
+            MultilineServerResponse::read_til_non_hyphenated_line(client)
+        }
+    }
+}
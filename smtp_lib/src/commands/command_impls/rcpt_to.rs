@@ -0,0 +1,434 @@
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+use mail_lib_types::EmailAddress;
+
+use crate::{
+    commands::{
+        arguments::{
+            parse_parameters, parse_path, split_parameter, split_path, strip_command, xtext_decode,
+            xtext_encode,
+        },
+        CommandParseError, SMTPCommand,
+    },
+    server_response::MultilineServerResponse,
+};
+
+/// A condition in the `NOTIFY=` parameter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::EnumString, strum::Display)]
+#[strum(serialize_all = "UPPERCASE", ascii_case_insensitive)]
+pub enum NotifyCondition {
+    Success,
+    Failure,
+    Delay,
+}
+/// The `NOTIFY=` parameter. When a Delivery Status Notification should be sent
+///
+/// [RFC 3461 Section 4.1](https://datatracker.ietf.org/doc/html/rfc3461#section-4.1)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DsnNotify {
+    /// `NEVER`
+    Never,
+    /// One or more of `SUCCESS`, `FAILURE`, `DELAY`
+    On(Vec<NotifyCondition>),
+}
+impl DsnNotify {
+    /// Checks if a notification should be sent for the condition
+    pub fn notify_on(&self, condition: NotifyCondition) -> bool {
+        match self {
+            DsnNotify::Never => false,
+            DsnNotify::On(conditions) => conditions.contains(&condition),
+        }
+    }
+}
+impl Display for DsnNotify {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DsnNotify::Never => write!(f, "NEVER"),
+            DsnNotify::On(conditions) => {
+                for (index, condition) in conditions.iter().enumerate() {
+                    if index != 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", condition)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+impl FromStr for DsnNotify {
+    type Err = CommandParseError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || CommandParseError::InvalidParameter(format!("NOTIFY={}", value));
+        if value.eq_ignore_ascii_case("NEVER") {
+            return Ok(DsnNotify::Never);
+        }
+        let mut conditions = Vec::with_capacity(3);
+        for condition in value.split(',') {
+            let condition = NotifyCondition::from_str(condition).map_err(|_| invalid())?;
+            if conditions.contains(&condition) {
+                return Err(invalid());
+            }
+            conditions.push(condition);
+        }
+        Ok(DsnNotify::On(conditions))
+    }
+}
+/// The `ORCPT=` parameter. The original recipient of the message
+///
+/// [RFC 3461 Section 4.2](https://datatracker.ietf.org/doc/html/rfc3461#section-4.2)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OriginalRecipient {
+    /// Usually `rfc822`
+    pub address_type: String,
+    /// The address. Stored decoded
+    pub address: String,
+}
+impl OriginalRecipient {
+    /// Creates an `rfc822` original recipient
+    pub fn rfc822(address: impl Into<String>) -> Self {
+        Self {
+            address_type: "rfc822".to_owned(),
+            address: address.into(),
+        }
+    }
+}
+impl Display for OriginalRecipient {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{};{}", self.address_type, xtext_encode(&self.address))
+    }
+}
+impl FromStr for OriginalRecipient {
+    type Err = CommandParseError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (address_type, address) = value
+            .split_once(';')
+            .filter(|(address_type, _)| !address_type.is_empty())
+            .ok_or_else(|| CommandParseError::InvalidParameter(format!("ORCPT={}", value)))?;
+        Ok(Self {
+            address_type: address_type.to_owned(),
+            address: xtext_decode(address)?,
+        })
+    }
+}
+/// The recipient of the RCPT TO command
+///
+/// [RFC 5321 Section 4.5.1](https://datatracker.ietf.org/doc/html/rfc5321#section-4.5.1) requires the bare `<Postmaster>`
+/// to be accepted. It has no domain so it can not be an [EmailAddress]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForwardPath {
+    /// `<Postmaster>`. The postmaster of the server receiving the command
+    Postmaster,
+    Address(EmailAddress),
+}
+impl ForwardPath {
+    /// None for the bare `<Postmaster>`
+    pub fn address(&self) -> Option<&EmailAddress> {
+        match self {
+            ForwardPath::Postmaster => None,
+            ForwardPath::Address(address) => Some(address),
+        }
+    }
+    /// If the recipient is `<Postmaster>` or `<Postmaster@domain>`. The local part is case-insensitive
+    pub fn is_postmaster(&self) -> bool {
+        match self {
+            ForwardPath::Postmaster => true,
+            ForwardPath::Address(address) => address.get_local().eq_ignore_ascii_case("Postmaster"),
+        }
+    }
+}
+impl From<EmailAddress> for ForwardPath {
+    fn from(address: EmailAddress) -> Self {
+        ForwardPath::Address(address)
+    }
+}
+/// Formats the path without the angle brackets
+impl Display for ForwardPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ForwardPath::Postmaster => write!(f, "Postmaster"),
+            ForwardPath::Address(address) => write!(f, "{}", address),
+        }
+    }
+}
+/// The parameters that can be sent with the RCPT TO command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RcptParameter {
+    /// `NOTIFY=` [RFC 3461](https://datatracker.ietf.org/doc/html/rfc3461)
+    Notify(DsnNotify),
+    /// `ORCPT=` [RFC 3461](https://datatracker.ietf.org/doc/html/rfc3461)
+    OriginalRecipient(OriginalRecipient),
+    /// Any other parameter
+    Other {
+        keyword: String,
+        value: Option<String>,
+    },
+}
+impl Display for RcptParameter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RcptParameter::Notify(notify) => write!(f, "NOTIFY={}", notify),
+            RcptParameter::OriginalRecipient(original) => write!(f, "ORCPT={}", original),
+            RcptParameter::Other { keyword, value } => {
+                if let Some(value) = value {
+                    write!(f, "{}={}", keyword, value)
+                } else {
+                    write!(f, "{}", keyword)
+                }
+            }
+        }
+    }
+}
+impl FromStr for RcptParameter {
+    type Err = CommandParseError;
+
+    fn from_str(parameter: &str) -> Result<Self, Self::Err> {
+        let (keyword, value) = split_parameter(parameter)?;
+        let parameter = match (keyword.to_ascii_uppercase().as_str(), value) {
+            ("NOTIFY", Some(value)) => Self::Notify(DsnNotify::from_str(value)?),
+            ("ORCPT", Some(value)) => Self::OriginalRecipient(OriginalRecipient::from_str(value)?),
+            ("NOTIFY" | "ORCPT", None) => {
+                return Err(CommandParseError::InvalidParameter(parameter.to_owned()))
+            }
+            _ => Self::Other {
+                keyword: keyword.to_owned(),
+                value: value.map(|value| value.to_owned()),
+            },
+        };
+        Ok(parameter)
+    }
+}
+/// The data that is in the RCPT TO command
+///
+/// ```ebnf
+/// rcpt = "RCPT TO:" ( "<Postmaster@" Domain ">" / "<Postmaster>" /
+///             Forward-path ) [SP Rcpt-parameters] CRLF
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RcptToCommandData {
+    pub forward_path: ForwardPath,
+    pub parameters: Vec<RcptParameter>,
+}
+impl RcptToCommandData {
+    pub fn new(forward_path: impl Into<ForwardPath>) -> Self {
+        Self {
+            forward_path: forward_path.into(),
+            parameters: Vec::new(),
+        }
+    }
+    /// Adds a parameter
+    pub fn with_parameter(mut self, parameter: RcptParameter) -> Self {
+        self.parameters.push(parameter);
+        self
+    }
+    /// Parses the entire command line. Including the `RCPT TO:`
+    pub fn from_command_line(line: &str) -> Result<Self, CommandParseError> {
+        Self::from_str(strip_command(line, RcptToCommand::command())?)
+    }
+    /// The value of the `NOTIFY=` parameter
+    pub fn notify(&self) -> Option<&DsnNotify> {
+        self.parameters
+            .iter()
+            .find_map(|parameter| match parameter {
+                RcptParameter::Notify(notify) => Some(notify),
+                _ => None,
+            })
+    }
+    /// The value of the `ORCPT=` parameter
+    pub fn original_recipient(&self) -> Option<&OriginalRecipient> {
+        self.parameters
+            .iter()
+            .find_map(|parameter| match parameter {
+                RcptParameter::OriginalRecipient(original) => Some(original),
+                _ => None,
+            })
+    }
+}
+/// Formats the arguments of the command. `<forward-path> [parameters]`
+impl Display for RcptToCommandData {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<{}>", self.forward_path)?;
+        for parameter in &self.parameters {
+            write!(f, " {}", parameter)?;
+        }
+        Ok(())
+    }
+}
+/// Parses the arguments of the command. `<forward-path> [parameters]`
+///
+/// A source route such as `<@relay.example:user@example.com>` is accepted and discarded
+impl FromStr for RcptToCommandData {
+    type Err = CommandParseError;
+
+    fn from_str(arguments: &str) -> Result<Self, Self::Err> {
+        let (path, parameters) = split_path(arguments)?;
+        let forward_path = if path.eq_ignore_ascii_case("<Postmaster>") {
+            ForwardPath::Postmaster
+        } else {
+            parse_path(path)?
+                .map(ForwardPath::Address)
+                .ok_or_else(|| CommandParseError::InvalidPath(path.to_owned()))?
+        };
+        Ok(Self {
+            forward_path,
+            parameters: parse_parameters(parameters)?,
+        })
+    }
+}
+
+/// The RCPT TO Command as specified [here](https://datatracker.ietf.org/doc/html/rfc5321#section-4.1.1.3)
+pub struct RcptToCommand;
+impl SMTPCommand for RcptToCommand {
+    type ClientCommand = RcptToCommandData;
+    type ServerResponse = MultilineServerResponse;
+
+    fn command() -> &'static str
+    where
+        Self: Sized,
+    {
+        "RCPT TO:"
+    }
+}
+
+#[cfg(feature = "async")]
+mod async_impl {
+    use futures::future::{ready, BoxFuture, Ready};
+
+    use super::{RcptToCommand, RcptToCommandData};
+    use crate::{
+        commands::async_traits::AsyncSMTPCommand, error::SMTPError,
+        server_response::MultilineServerResponse, smtp_client::async_traits::AsyncSMTPClient,
+        smtp_server::async_traits::AsyncSMTPConnection,
+        statement::async_statement::AsyncMultilineStatement, CRLF,
+    };
+
+    impl<'a> AsyncSMTPCommand<'a> for RcptToCommand {
+        type ServerHandleRead = Ready<crate::Result<Self::ClientCommand>>;
+        type SendFuture = BoxFuture<'a, crate::Result<()>>;
+        type HandleServerResponse = BoxFuture<'a, crate::Result<MultilineServerResponse>>;
+
+        fn server_read<'b, C: AsyncSMTPConnection<'b>>(
+            _: &mut C,
+            line: String,
+        ) -> Self::ServerHandleRead
+        where
+            Self: Sized,
+        {
+            ready(RcptToCommandData::from_command_line(&line).map_err(SMTPError::from))
+        }
+
+        fn server_send<C: AsyncSMTPConnection<'a>>(
+            response: Self::ServerResponse,
+            client: &'a mut C,
+        ) -> Self::SendFuture {
+            Box::pin(async move {
+                client.write_statement(response).await?;
+                Ok(())
+            })
+        }
+
+        fn client_send<C: AsyncSMTPClient<'a>>(
+            command: Self::ClientCommand,
+            client: &'a mut C,
+        ) -> Self::SendFuture {
+            Box::pin(async move {
+                let command = format!("RCPT TO:{}{}", command, CRLF);
+                client.write_string(command).await?;
+                Ok(())
+            })
+        }
+
+        fn client_read<C: AsyncSMTPClient<'a>>(client: &'a mut C) -> Self::HandleServerResponse {
+            MultilineServerResponse::read_til_non_hyphenated_line(client)
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::{
+        DsnNotify, ForwardPath, NotifyCondition, OriginalRecipient, RcptParameter,
+        RcptToCommandData,
+    };
+
+    #[test]
+    fn test_parse() {
+        let data = RcptToCommandData::from_command_line(
+            "RCPT TO:<user@example.com> NOTIFY=SUCCESS,delay ORCPT=rfc822;user+2Btag@example.com\r\n",
+        )
+        .unwrap();
+        assert_eq!(data.forward_path.to_string(), "user@example.com");
+        assert_eq!(
+            data.notify(),
+            Some(&DsnNotify::On(vec![
+                NotifyCondition::Success,
+                NotifyCondition::Delay
+            ]))
+        );
+        assert!(data.notify().unwrap().notify_on(NotifyCondition::Delay));
+        assert!(!data.notify().unwrap().notify_on(NotifyCondition::Failure));
+        assert_eq!(
+            data.original_recipient(),
+            Some(&OriginalRecipient::rfc822("user+tag@example.com"))
+        );
+    }
+    #[test]
+    fn test_source_route() {
+        let data =
+            RcptToCommandData::from_command_line("rcpt to:<@hosta.int,@jkl.org:userc@d.bar.org>")
+                .unwrap();
+        assert_eq!(data.forward_path.to_string(), "userc@d.bar.org");
+        assert_eq!(data.to_string(), "<userc@d.bar.org>");
+    }
+    #[test]
+    fn test_postmaster() {
+        let data =
+            RcptToCommandData::from_command_line("RCPT TO:<postmaster> NOTIFY=NEVER\r\n").unwrap();
+        assert_eq!(data.forward_path, ForwardPath::Postmaster);
+        assert!(data.forward_path.address().is_none());
+        assert_eq!(data.to_string(), "<Postmaster> NOTIFY=NEVER");
+
+        let data =
+            RcptToCommandData::from_command_line("RCPT TO:<Postmaster@example.com>").unwrap();
+        assert!(data.forward_path.is_postmaster());
+        assert!(data.forward_path.address().is_some());
+    }
+    #[test]
+    fn test_round_trip() {
+        let data =
+            RcptToCommandData::new(mail_lib_types::EmailAddress::new("user@example.com").unwrap())
+                .with_parameter(RcptParameter::Notify(DsnNotify::Never))
+                .with_parameter(RcptParameter::OriginalRecipient(OriginalRecipient::rfc822(
+                    "user=1@example.com",
+                )));
+        let line = data.to_string();
+        assert_eq!(
+            line,
+            "<user@example.com> NOTIFY=NEVER ORCPT=rfc822;user+3D1@example.com"
+        );
+        assert_eq!(RcptToCommandData::from_str(&line), Ok(data));
+    }
+    #[test]
+    fn test_invalid() {
+        assert!(RcptToCommandData::from_command_line("RCPT TO:<>").is_err());
+        assert!(RcptToCommandData::from_command_line(
+            "RCPT TO:<user@example.com> NOTIFY=NEVER,SUCCESS"
+        )
+        .is_err());
+        assert!(RcptToCommandData::from_command_line(
+            "RCPT TO:<user@example.com> NOTIFY=SOMETIMES"
+        )
+        .is_err());
+        assert!(RcptToCommandData::from_command_line(
+            "RCPT TO:<user@example.com> ORCPT=missing-type"
+        )
+        .is_err());
+        assert!(RcptToCommandData::from_command_line("RCPT TO:user@example.com").is_err());
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{
+    commands::{arguments::no_arguments, CommandParseError, SMTPCommand},
+    server_response::MultilineServerResponse,
+};
+
+/// The STARTTLS Command as specified [here](https://datatracker.ietf.org/doc/html/rfc3207#section-4)
+///
+/// After the server replies `220` both sides start the TLS handshake.
+/// Once the handshake is done everything learned before is discarded and the client must send EHLO again.
+///
+/// See [AsyncStartTlsClient](crate::smtp_client::async_traits::AsyncStartTlsClient)
+/// and [AsyncStartTlsConnection](crate::smtp_server::async_traits::AsyncStartTlsConnection) for swapping the stream
+pub struct StartTlsCommand;
+impl StartTlsCommand {
+    /// Parses the command line. STARTTLS does not take any arguments
+    pub fn from_command_line(line: &str) -> Result<(), CommandParseError> {
+        no_arguments(line, Self::command())
+    }
+}
+impl SMTPCommand for StartTlsCommand {
+    type ClientCommand = ();
+    type ServerResponse = MultilineServerResponse;
+
+    fn command() -> &'static str
+    where
+        Self: Sized,
+    {
+        "STARTTLS"
+    }
+}
+
+#[cfg(feature = "async")]
+mod async_impl {
+    use futures::future::{ready, BoxFuture, Ready};
+
+    use super::StartTlsCommand;
+    use crate::{
+        commands::async_traits::AsyncSMTPCommand, error::SMTPError,
+        server_response::MultilineServerResponse, smtp_client::async_traits::AsyncSMTPClient,
+        smtp_server::async_traits::AsyncSMTPConnection,
+        statement::async_statement::AsyncMultilineStatement, CRLF,
+    };
+
+    impl<'a> AsyncSMTPCommand<'a> for StartTlsCommand {
+        type ServerHandleRead = Ready<crate::Result<Self::ClientCommand>>;
+        type SendFuture = BoxFuture<'a, crate::Result<()>>;
+        type HandleServerResponse = BoxFuture<'a, crate::Result<MultilineServerResponse>>;
+
+        fn server_read<'b, C: AsyncSMTPConnection<'b>>(
+            _: &mut C,
+            line: String,
+        ) -> Self::ServerHandleRead
+        where
+            Self: Sized,
+        {
+            ready(StartTlsCommand::from_command_line(&line).map_err(SMTPError::from))
+        }
+
+        fn server_send<C: AsyncSMTPConnection<'a>>(
+            response: Self::ServerResponse,
+            client: &'a mut C,
+        ) -> Self::SendFuture {
+            Box::pin(async move {
+                client.write_statement(response).await?;
+                Ok(())
+            })
+        }
+
+        fn client_send<C: AsyncSMTPClient<'a>>(
+            _: Self::ClientCommand,
+            client: &'a mut C,
+        ) -> Self::SendFuture {
+            Box::pin(async move {
+                client.write_string(format!("STARTTLS{}", CRLF)).await?;
+                Ok(())
+            })
+        }
+
+        fn client_read<C: AsyncSMTPClient<'a>>(client: &'a mut C) -> Self::HandleServerResponse {
+            MultilineServerResponse::read_til_non_hyphenated_line(client)
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::StartTlsCommand;
+    use crate::smtp_server::SMTPServerExtension;
+
+    #[test]
+    fn test_parse() {
+        assert!(StartTlsCommand::from_command_line("STARTTLS\r\n").is_ok());
+        assert!(StartTlsCommand::from_command_line("STARTTLS now\r\n").is_err());
+    }
+    #[test]
+    fn test_extension() {
+        let extension = SMTPServerExtension::try_from("STARTTLS".to_owned()).unwrap();
+        assert_eq!(extension, SMTPServerExtension::StartTLS);
+        assert_eq!(extension.to_string(), "STARTTLS");
+    }
+}
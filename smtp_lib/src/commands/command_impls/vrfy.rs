@@ -0,0 +1,309 @@
+use std::{fmt::Write, str::FromStr};
+
+use mail_lib_types::{
+    mail_box::{write_display_name, MailBox},
+    EmailAddress,
+};
+
+use crate::{
+    commands::{
+        arguments::{command_argument, parse_path},
+        CommandParseError, SMTPCommand,
+    },
+    error::SMTPError,
+    server_response::{MultilineServerResponse, ResponseCode},
+};
+
+/// Formats a mailbox the way it is sent in a VRFY or EXPN reply. `[Full Name] <local@domain>`
+pub(crate) fn format_mailbox(mailbox: &MailBox) -> String {
+    let mut formatted = String::new();
+    // Infallible
+    if let Some(name) = mailbox.get_name() {
+        let _ = write_display_name(&mut formatted, name);
+        formatted.push(' ');
+    }
+    let _ = write!(formatted, "<{}>", mailbox.email);
+    formatted
+}
+/// Parses a mailbox from a VRFY or EXPN reply line
+pub(crate) fn parse_mailbox(message: Option<&str>) -> crate::Result<MailBox> {
+    message
+        .and_then(|message| MailBox::from_str(message.trim()).ok())
+        .ok_or(SMTPError::InvalidResponse("Expected a mailbox"))
+}
+/// Finds the `<forward-path>` in a 251 or 551 reply
+fn find_forward_path(message: Option<&str>) -> Option<EmailAddress> {
+    let message = message?;
+    let start = message.find('<')?;
+    let end = start + message[start..].find('>')?;
+    parse_path(&message[start..=end]).ok().flatten()
+}
+
+/// The data that is in the VRFY command
+///
+/// ```ebnf
+/// vrfy = "VRFY" SP String CRLF
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VrfyCommandData {
+    /// A user name or mailbox
+    pub argument: String,
+}
+impl VrfyCommandData {
+    pub fn new(argument: impl Into<String>) -> Self {
+        Self {
+            argument: argument.into(),
+        }
+    }
+    /// Parses the entire command line. Including the `VRFY`
+    pub fn from_command_line(line: &str) -> Result<Self, CommandParseError> {
+        command_argument(line, VrfyCommand::command())?
+            .map(Self::new)
+            .ok_or(CommandParseError::MissingArgument("user name or mailbox"))
+    }
+}
+/// The response to the VRFY command
+///
+/// [RFC 5321 Section 3.5.3](https://datatracker.ietf.org/doc/html/rfc5321#section-3.5.3)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VrfyResponse {
+    /// `250` The mailbox of the user
+    Verified(MailBox),
+    /// `251` User not local; will forward to `<forward-path>`
+    WillForward(Option<EmailAddress>),
+    /// `252` The user can not be verified. But the server will attempt delivery
+    CannotVerify(Option<String>),
+    /// `551` User not local; please try `<forward-path>`
+    UserNotLocal(Option<EmailAddress>),
+    /// `553` The argument matches more than one mailbox
+    Ambiguous(Vec<MailBox>),
+    Error {
+        code: ResponseCode,
+        message: Option<String>,
+    },
+}
+impl TryFrom<MultilineServerResponse> for VrfyResponse {
+    type Error = SMTPError;
+
+    fn try_from(value: MultilineServerResponse) -> Result<Self, SMTPError> {
+        let Some(code) = value.code() else {
+            return Err(SMTPError::InvalidResponse("No response lines"));
+        };
+        let mut lines = value.0.into_iter();
+        let response = match code {
+            ResponseCode::Ok => {
+                let message = lines.next().and_then(|line| line.message);
+                VrfyResponse::Verified(parse_mailbox(message.as_deref())?)
+            }
+            ResponseCode::UserNotLocalWillForward => {
+                let message = lines.next().and_then(|line| line.message);
+                VrfyResponse::WillForward(find_forward_path(message.as_deref()))
+            }
+            ResponseCode::CannotVerifyUser => {
+                VrfyResponse::CannotVerify(lines.next().and_then(|line| line.message))
+            }
+            ResponseCode::UserNotLocal => {
+                let message = lines.next().and_then(|line| line.message);
+                VrfyResponse::UserNotLocal(find_forward_path(message.as_deref()))
+            }
+            ResponseCode::MailboxNameNotAllowed => VrfyResponse::Ambiguous(
+                lines
+                    .filter_map(|line| parse_mailbox(line.message.as_deref()).ok())
+                    .collect(),
+            ),
+            code => VrfyResponse::Error {
+                code,
+                message: lines.next().and_then(|line| line.message),
+            },
+        };
+        Ok(response)
+    }
+}
+impl From<VrfyResponse> for MultilineServerResponse {
+    fn from(value: VrfyResponse) -> Self {
+        let forward = |address: Option<EmailAddress>| {
+            address
+                .map(|address| format!("<{}>", address))
+                .unwrap_or_default()
+        };
+        match value {
+            VrfyResponse::Verified(mailbox) => MultilineServerResponse::new_with_code(
+                ResponseCode::Ok,
+                Some(format_mailbox(&mailbox)),
+                0,
+            ),
+            VrfyResponse::WillForward(address) => MultilineServerResponse::new_with_code(
+                ResponseCode::UserNotLocalWillForward,
+                Some(format!(
+                    "User not local; will forward to {}",
+                    forward(address)
+                )),
+                0,
+            ),
+            VrfyResponse::CannotVerify(message) => {
+                MultilineServerResponse::new_with_code(ResponseCode::CannotVerifyUser, message, 0)
+            }
+            VrfyResponse::UserNotLocal(address) => MultilineServerResponse::new_with_code(
+                ResponseCode::UserNotLocal,
+                Some(format!("User not local; please try {}", forward(address))),
+                0,
+            ),
+            VrfyResponse::Ambiguous(mailboxes) => {
+                let mut response = MultilineServerResponse::new_with_code(
+                    ResponseCode::MailboxNameNotAllowed,
+                    Some("User ambiguous; Possibilities are".to_owned()),
+                    mailboxes.len(),
+                );
+                for mailbox in mailboxes {
+                    response.add_line(
+                        ResponseCode::MailboxNameNotAllowed,
+                        Some(format_mailbox(&mailbox)),
+                    );
+                }
+                response
+            }
+            VrfyResponse::Error { code, message } => {
+                MultilineServerResponse::new_with_code(code, message, 0)
+            }
+        }
+    }
+}
+
+/// The VRFY Command as specified [here](https://datatracker.ietf.org/doc/html/rfc5321#section-4.1.1.6)
+pub struct VrfyCommand;
+impl SMTPCommand for VrfyCommand {
+    type ClientCommand = VrfyCommandData;
+    type ServerResponse = VrfyResponse;
+
+    fn command() -> &'static str
+    where
+        Self: Sized,
+    {
+        "VRFY"
+    }
+}
+
+#[cfg(feature = "async")]
+mod async_impl {
+    use futures::future::{ready, BoxFuture, Ready};
+
+    use super::{VrfyCommand, VrfyCommandData, VrfyResponse};
+    use crate::{
+        commands::async_traits::AsyncSMTPCommand, error::SMTPError,
+        server_response::MultilineServerResponse, smtp_client::async_traits::AsyncSMTPClient,
+        smtp_server::async_traits::AsyncSMTPConnection,
+        statement::async_statement::AsyncMultilineStatement, CRLF,
+    };
+
+    impl<'a> AsyncSMTPCommand<'a> for VrfyCommand {
+        type ServerHandleRead = Ready<crate::Result<Self::ClientCommand>>;
+        type SendFuture = BoxFuture<'a, crate::Result<()>>;
+        type HandleServerResponse = BoxFuture<'a, crate::Result<VrfyResponse>>;
+
+        fn server_read<'b, C: AsyncSMTPConnection<'b>>(
+            _: &mut C,
+            line: String,
+        ) -> Self::ServerHandleRead
+        where
+            Self: Sized,
+        {
+            ready(VrfyCommandData::from_command_line(&line).map_err(SMTPError::from))
+        }
+
+        fn server_send<C: AsyncSMTPConnection<'a>>(
+            response: Self::ServerResponse,
+            client: &'a mut C,
+        ) -> Self::SendFuture {
+            Box::pin(async move {
+                client
+                    .write_statement(MultilineServerResponse::from(response))
+                    .await?;
+                Ok(())
+            })
+        }
+
+        fn client_send<C: AsyncSMTPClient<'a>>(
+            command: Self::ClientCommand,
+            client: &'a mut C,
+        ) -> Self::SendFuture {
+            Box::pin(async move {
+                let command = format!("VRFY {}{}", command.argument, CRLF);
+                client.write_string(command).await?;
+                Ok(())
+            })
+        }
+
+        fn client_read<C: AsyncSMTPClient<'a>>(client: &'a mut C) -> Self::HandleServerResponse {
+            Box::pin(async {
+                let response =
+                    MultilineServerResponse::read_til_non_hyphenated_line(client).await?;
+                VrfyResponse::try_from(response)
+            })
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use mail_lib_types::{mail_box::MailBox, EmailAddress};
+
+    use super::{VrfyCommandData, VrfyResponse};
+    use crate::{commands::CommandParseError, server_response::MultilineServerResponse};
+
+    fn parse(response: &str) -> VrfyResponse {
+        VrfyResponse::try_from(MultilineServerResponse::from_str(response).unwrap()).unwrap()
+    }
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            VrfyCommandData::from_command_line("VRFY Smith\r\n"),
+            Ok(VrfyCommandData::new("Smith"))
+        );
+        assert_eq!(
+            VrfyCommandData::from_command_line("VRFY\r\n"),
+            Err(CommandParseError::MissingArgument("user name or mailbox"))
+        );
+    }
+    #[test]
+    fn test_responses() {
+        let VrfyResponse::Verified(mailbox) = parse("250 \"Fred Smith\" <Smith@example.com>\r\n")
+        else {
+            panic!("Expected a verified mailbox");
+        };
+        assert_eq!(mailbox.get_name(), Some("Fred Smith"));
+        assert_eq!(mailbox.email, "Smith@example.com");
+
+        assert_eq!(
+            parse("251 User not local; will forward to <Jones@example.org>\r\n"),
+            VrfyResponse::WillForward(Some(EmailAddress::new("Jones@example.org").unwrap()))
+        );
+        assert!(matches!(
+            parse("252 Cannot VRFY user\r\n"),
+            VrfyResponse::CannotVerify(Some(_))
+        ));
+        let VrfyResponse::Ambiguous(mailboxes) = parse(
+            "553-User ambiguous; Possibilities are\r\n553-Joe <jsmith@foo.com>\r\n553 <dweep@foo.com>\r\n",
+        ) else {
+            panic!("Expected ambiguous");
+        };
+        assert_eq!(mailboxes.len(), 2);
+    }
+    #[test]
+    fn test_round_trip() {
+        let responses = [
+            VrfyResponse::Verified(
+                MailBox::from_str("\"Smith, Fred\" <fred@example.com>").unwrap(),
+            ),
+            VrfyResponse::Verified(MailBox::new(
+                None,
+                EmailAddress::new("fred@example.com").unwrap(),
+            )),
+            VrfyResponse::UserNotLocal(Some(EmailAddress::new("fred@example.org").unwrap())),
+        ];
+        for response in responses {
+            let multiline = MultilineServerResponse::from(response.clone());
+            assert_eq!(VrfyResponse::try_from(multiline).unwrap(), response);
+        }
+    }
+}
@@ -1,5 +1,42 @@
+use mail_lib_types::email_address::InvalidEmailAddress;
+use thiserror::Error;
 
+pub mod arguments;
+pub mod client_command;
 pub mod command_impls;
+
+pub use client_command::ClientCommand;
+/// An error that occurs when parsing a command sent by a client
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum CommandParseError {
+    #[error("Command unrecognized: {0}")]
+    UnknownCommand(String),
+    #[error("Line too long. {0} octets")]
+    LineTooLong(usize),
+    #[error("Invalid command: {0}")]
+    InvalidCommand(String),
+    #[error("Missing argument: {0}")]
+    MissingArgument(&'static str),
+    #[error("Invalid path: {0}")]
+    InvalidPath(String),
+    #[error("Invalid address: {0}")]
+    InvalidAddress(#[from] InvalidEmailAddress),
+    #[error("Invalid parameter: {0}")]
+    InvalidParameter(String),
+    #[error("Invalid xtext: {0}")]
+    InvalidXText(String),
+}
+impl CommandParseError {
+    /// The reply code that should be sent to the client.
+    ///
+    /// `500` if the command was not recognized or the line was too long. Otherwise `501`
+    pub fn error_code(&self) -> u16 {
+        match self {
+            CommandParseError::UnknownCommand(_) | CommandParseError::LineTooLong(_) => 500,
+            _ => 501,
+        }
+    }
+}
 pub trait SMTPCommand: Sized {
     /// What type of data a Server would get from a Client
     type ClientCommand;
@@ -9,11 +46,23 @@ pub trait SMTPCommand: Sized {
     fn command() -> &'static str
     where
         Self: Sized;
+    /// If the line starts with this command. The command is matched case insensitive.
     fn can_handle(command: &str) -> bool
     where
         Self: Sized,
     {
-        command.starts_with(Self::command())
+        let keyword = Self::command();
+        match command.get(..keyword.len()) {
+            Some(start) if start.eq_ignore_ascii_case(keyword) => {
+                // `MAIL FROM:` is followed directly by the path
+                keyword.ends_with(':')
+                    || command[keyword.len()..]
+                        .chars()
+                        .next()
+                        .is_none_or(|next| matches!(next, ' ' | '\r' | '\n'))
+            }
+            _ => false,
+        }
     }
 }
 
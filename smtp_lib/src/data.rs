@@ -0,0 +1,456 @@
+/*!
+# Message Data Transfer
+
+Handles the transparency procedure (dot-stuffing) used by the DATA command as defined in
+[RFC 5321 Section 4.5.2](https://datatracker.ietf.org/doc/html/rfc5321#section-4.5.2).
+
+[DataEncoder] and [DataDecoder] do not do any IO. So they can be used with any transport.
+The async wrappers are found in [async_data]
+
+Only `<CRLF>.<CRLF>` will end the data. A bare LF is never treated as a line ending when looking for the end of the data.
+ */
+use bytes::{BufMut, BytesMut};
+
+/// The sequence that ends the data. The first CRLF is part of the message
+pub static END_OF_DATA: &[u8] = b"\r\n.\r\n";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EncoderState {
+    LineStart,
+    Middle,
+    CarriageReturn,
+}
+/// Dot-stuffs a message body so it can be sent after the DATA command.
+///
+/// Bare CR and LF characters are converted into CRLF
+#[derive(Debug, Clone)]
+pub struct DataEncoder {
+    state: EncoderState,
+}
+impl Default for DataEncoder {
+    fn default() -> Self {
+        Self {
+            state: EncoderState::LineStart,
+        }
+    }
+}
+impl DataEncoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Encodes a chunk of the body. The chunk can end anywhere. Including in the middle of a CRLF
+    pub fn encode(&mut self, input: &[u8], output: &mut BytesMut) {
+        output.reserve(input.len() + 2);
+        for &byte in input {
+            if self.state == EncoderState::CarriageReturn {
+                output.put_u8(b'\n');
+                self.state = EncoderState::LineStart;
+                if byte == b'\n' {
+                    continue;
+                }
+            }
+            match byte {
+                b'.' if self.state == EncoderState::LineStart => {
+                    output.put_slice(b"..");
+                    self.state = EncoderState::Middle;
+                }
+                b'\r' => {
+                    output.put_u8(b'\r');
+                    self.state = EncoderState::CarriageReturn;
+                }
+                b'\n' => {
+                    output.put_slice(b"\r\n");
+                    self.state = EncoderState::LineStart;
+                }
+                other => {
+                    output.put_u8(other);
+                    self.state = EncoderState::Middle;
+                }
+            }
+        }
+    }
+    /// Finishes the body. Adds a CRLF if the body does not end with one and then the `.<CRLF>`
+    pub fn finish(&mut self, output: &mut BytesMut) {
+        match self.state {
+            EncoderState::LineStart => {}
+            EncoderState::Middle => output.put_slice(b"\r\n"),
+            EncoderState::CarriageReturn => output.put_u8(b'\n'),
+        }
+        output.put_slice(b".\r\n");
+        self.state = EncoderState::LineStart;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DecoderState {
+    LineStart,
+    Middle,
+    CarriageReturn,
+    /// A `.` at the start of a line
+    Dot,
+    /// A `.<CR>` at the start of a line
+    DotCarriageReturn,
+    Finished,
+}
+/// The result of [DataDecoder::decode]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeStatus {
+    /// The end of the data has not been found. All of the input was consumed
+    NeedMore,
+    /// The end of the data was found.
+    ///
+    /// `consumed` is the number of bytes of the input that belonged to the data. Including the terminator.
+    /// Anything after that is the next command
+    Finished { consumed: usize },
+}
+/// Removes the dot-stuffing from a message body and finds the end of the data.
+///
+/// The data is expected to start at the beginning of a line. The CRLF before the terminating `.` is kept as part of the message
+#[derive(Debug, Clone)]
+pub struct DataDecoder {
+    state: DecoderState,
+}
+impl Default for DataDecoder {
+    fn default() -> Self {
+        Self {
+            state: DecoderState::LineStart,
+        }
+    }
+}
+impl DataDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// If the end of the data has been found
+    pub fn is_finished(&self) -> bool {
+        self.state == DecoderState::Finished
+    }
+    /// Decodes a chunk of data. The chunk can end anywhere. Including in the middle of the terminator
+    pub fn decode(&mut self, input: &[u8], output: &mut BytesMut) -> DecodeStatus {
+        output.reserve(input.len());
+        for (index, &byte) in input.iter().enumerate() {
+            match self.state {
+                DecoderState::Finished => return DecodeStatus::Finished { consumed: index },
+                DecoderState::Dot => {
+                    // The leading dot is removed
+                    if byte == b'\r' {
+                        self.state = DecoderState::DotCarriageReturn;
+                    } else {
+                        self.middle(byte, output);
+                    }
+                }
+                DecoderState::DotCarriageReturn => {
+                    if byte == b'\n' {
+                        self.state = DecoderState::Finished;
+                        return DecodeStatus::Finished {
+                            consumed: index + 1,
+                        };
+                    }
+                    output.put_u8(b'\r');
+                    self.state = DecoderState::CarriageReturn;
+                    self.carriage_return(byte, output);
+                }
+                DecoderState::LineStart if byte == b'.' => {
+                    self.state = DecoderState::Dot;
+                }
+                DecoderState::CarriageReturn => self.carriage_return(byte, output),
+                DecoderState::LineStart | DecoderState::Middle => self.middle(byte, output),
+            }
+        }
+        if self.is_finished() {
+            DecodeStatus::Finished { consumed: 0 }
+        } else {
+            DecodeStatus::NeedMore
+        }
+    }
+    fn middle(&mut self, byte: u8, output: &mut BytesMut) {
+        output.put_u8(byte);
+        self.state = if byte == b'\r' {
+            DecoderState::CarriageReturn
+        } else {
+            DecoderState::Middle
+        };
+    }
+    fn carriage_return(&mut self, byte: u8, output: &mut BytesMut) {
+        if byte == b'\n' {
+            output.put_u8(byte);
+            self.state = DecoderState::LineStart;
+        } else {
+            self.middle(byte, output);
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+pub mod async_data {
+    use std::{
+        io,
+        pin::Pin,
+        task::{ready, Context, Poll},
+    };
+
+    use bytes::{Bytes, BytesMut};
+    use futures::{
+        io::{AsyncBufRead, AsyncWrite, AsyncWriteExt},
+        Stream,
+    };
+
+    use super::{DataDecoder, DataEncoder, DecodeStatus};
+    use crate::error::SMTPError;
+
+    /// Reads the body of the DATA command as a stream of bytes.
+    ///
+    /// The dot-stuffing is removed and the stream ends once `<CRLF>.<CRLF>` is read.
+    /// Any bytes after the terminator are left in the reader.
+    #[derive(Debug)]
+    pub struct DataBodyStream<R> {
+        reader: R,
+        decoder: DataDecoder,
+        max_size: Option<usize>,
+        size: usize,
+        too_large: bool,
+        done: bool,
+    }
+    impl<R> DataBodyStream<R>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        pub fn new(reader: R) -> Self {
+            Self {
+                reader,
+                decoder: DataDecoder::new(),
+                max_size: None,
+                size: 0,
+                too_large: false,
+                done: false,
+            }
+        }
+        /// The stream will return [SMTPError::MessageTooLarge] once the body is larger than `max_size`.
+        ///
+        /// Polling the stream again discards the rest of the body. So the connection can continue after the reply
+        pub fn with_max_size(mut self, max_size: usize) -> Self {
+            self.max_size = Some(max_size);
+            self
+        }
+        /// The number of bytes of the body that have been read
+        pub fn size(&self) -> usize {
+            self.size
+        }
+        /// If the end of the data was found
+        pub fn is_finished(&self) -> bool {
+            self.decoder.is_finished()
+        }
+        /// If the body was larger than the max size
+        pub fn is_too_large(&self) -> bool {
+            self.too_large
+        }
+        /// Returns the reader.
+        pub fn into_inner(self) -> R {
+            self.reader
+        }
+    }
+    impl<R> Stream for DataBodyStream<R>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        type Item = crate::Result<Bytes>;
+
+        fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            let this = self.get_mut();
+            loop {
+                if this.done {
+                    return Poll::Ready(None);
+                }
+                let buffer = match ready!(Pin::new(&mut this.reader).poll_fill_buf(cx)) {
+                    Ok(buffer) => buffer,
+                    Err(error) => {
+                        this.done = true;
+                        return Poll::Ready(Some(Err(error.into())));
+                    }
+                };
+                if buffer.is_empty() {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "Connection closed before the end of the data",
+                    )
+                    .into())));
+                }
+                let mut output = BytesMut::with_capacity(buffer.len());
+                let consumed = match this.decoder.decode(buffer, &mut output) {
+                    DecodeStatus::NeedMore => buffer.len(),
+                    DecodeStatus::Finished { consumed } => {
+                        this.done = true;
+                        consumed
+                    }
+                };
+                Pin::new(&mut this.reader).consume(consumed);
+                if this.too_large {
+                    continue;
+                }
+
+                this.size += output.len();
+                if let Some(max_size) = this.max_size.filter(|max_size| this.size > *max_size) {
+                    this.too_large = true;
+                    return Poll::Ready(Some(Err(SMTPError::MessageTooLarge(max_size))));
+                }
+                if !output.is_empty() {
+                    return Poll::Ready(Some(Ok(output.freeze())));
+                }
+            }
+        }
+    }
+
+    /// Writes a message body after the DATA command.
+    ///
+    /// Dot-stuffing is applied to everything written. [DataBodyWriter::finish] must be called to send the terminator
+    #[derive(Debug)]
+    pub struct DataBodyWriter<W> {
+        writer: W,
+        encoder: DataEncoder,
+        buffer: BytesMut,
+    }
+    impl<W> DataBodyWriter<W>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        pub fn new(writer: W) -> Self {
+            Self {
+                writer,
+                encoder: DataEncoder::new(),
+                buffer: BytesMut::new(),
+            }
+        }
+        /// Writes a chunk of the body
+        pub async fn write_chunk(&mut self, chunk: &[u8]) -> crate::Result<()> {
+            self.buffer.clear();
+            self.encoder.encode(chunk, &mut self.buffer);
+            self.writer.write_all(&self.buffer).await?;
+            Ok(())
+        }
+        /// Writes the terminator and flushes the writer.
+        pub async fn finish(mut self) -> crate::Result<W> {
+            self.buffer.clear();
+            self.encoder.finish(&mut self.buffer);
+            self.writer.write_all(&self.buffer).await?;
+            self.writer.flush().await?;
+            Ok(self.writer)
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+
+    use super::{DataDecoder, DataEncoder, DecodeStatus};
+
+    fn encode(chunks: &[&[u8]]) -> Vec<u8> {
+        let mut encoder = DataEncoder::new();
+        let mut output = BytesMut::new();
+        for chunk in chunks {
+            encoder.encode(chunk, &mut output);
+        }
+        encoder.finish(&mut output);
+        output.to_vec()
+    }
+    /// Decodes one byte at a time to test every boundary
+    fn decode_byte_by_byte(input: &[u8]) -> (Vec<u8>, Option<usize>) {
+        let mut decoder = DataDecoder::new();
+        let mut output = BytesMut::new();
+        for (index, byte) in input.iter().enumerate() {
+            if let DecodeStatus::Finished { consumed } =
+                decoder.decode(std::slice::from_ref(byte), &mut output)
+            {
+                assert_eq!(consumed, 1);
+                return (output.to_vec(), Some(index + 1));
+            }
+        }
+        (output.to_vec(), None)
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(
+            encode(&[b"Hello\r\n.World\r\n"]),
+            b"Hello\r\n..World\r\n.\r\n"
+        );
+        assert_eq!(
+            encode(&[b"Hello\r", b"\n.", b"World"]),
+            b"Hello\r\n..World\r\n.\r\n"
+        );
+        assert_eq!(encode(&[b".\nbare\rcr"]), b"..\r\nbare\r\ncr\r\n.\r\n");
+        assert_eq!(encode(&[]), b".\r\n");
+    }
+    #[test]
+    fn test_decode() {
+        let input = b"Hello\r\n..World\r\n.\r\nQUIT\r\n";
+        let mut decoder = DataDecoder::new();
+        let mut output = BytesMut::new();
+        let status = decoder.decode(input, &mut output);
+        assert_eq!(status, DecodeStatus::Finished { consumed: 19 });
+        assert_eq!(&input[19..], b"QUIT\r\n");
+        assert_eq!(output.as_ref(), b"Hello\r\n.World\r\n");
+
+        let (output, end) = decode_byte_by_byte(input);
+        assert_eq!(end, Some(19));
+        assert_eq!(output, b"Hello\r\n.World\r\n");
+    }
+    #[test]
+    fn test_decode_only_crlf_terminates() {
+        let (output, end) = decode_byte_by_byte(b"Hello\n.\nWorld\r\n.\r");
+        assert_eq!(end, None);
+        assert_eq!(output, b"Hello\n.\nWorld\r\n");
+
+        let (output, end) = decode_byte_by_byte(b".\r\n");
+        assert_eq!(end, Some(3));
+        assert!(output.is_empty());
+
+        let (output, end) = decode_byte_by_byte(b".\rx\r\n.\r\n");
+        assert_eq!(end, Some(8));
+        assert_eq!(output, b"\rx\r\n");
+    }
+    #[test]
+    fn test_round_trip() {
+        let body = b"Subject: test\r\n\r\n.\r\n..\r\n. leading\r\nend".as_slice();
+        let encoded = encode(&[body]);
+        let (decoded, end) = decode_byte_by_byte(&encoded);
+        assert_eq!(end, Some(encoded.len()));
+        assert_eq!(decoded, [body, b"\r\n"].concat());
+    }
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_stream() {
+        use futures::{executor::block_on, io::Cursor, StreamExt};
+
+        use super::async_data::{DataBodyStream, DataBodyWriter};
+        block_on(async {
+            let mut writer = DataBodyWriter::new(Cursor::new(Vec::new()));
+            writer.write_chunk(b"Line one\r\n.dot").await.unwrap();
+            writer.write_chunk(b" line\r\n").await.unwrap();
+            let mut written = writer.finish().await.unwrap().into_inner();
+            written.extend_from_slice(b"QUIT\r\n");
+
+            let mut stream = DataBodyStream::new(Cursor::new(written));
+            let mut body = Vec::new();
+            while let Some(chunk) = stream.next().await {
+                body.extend_from_slice(&chunk.unwrap());
+            }
+            assert_eq!(body, b"Line one\r\n.dot line\r\n");
+            let reader = stream.into_inner();
+            let position = reader.position() as usize;
+            assert_eq!(&reader.into_inner()[position..], b"QUIT\r\n");
+
+            let input = b"too big\r\nand more\r\n.\r\nQUIT\r\n".to_vec();
+            let reader = futures::io::BufReader::with_capacity(4, Cursor::new(input));
+            let mut stream = DataBodyStream::new(reader).with_max_size(3);
+            assert!(stream.next().await.unwrap().is_err());
+            assert!(stream.next().await.is_none());
+            assert!(stream.is_finished());
+            let mut rest = String::new();
+            futures::AsyncReadExt::read_to_string(&mut stream.into_inner(), &mut rest)
+                .await
+                .unwrap();
+            assert_eq!(rest, "QUIT\r\n");
+        });
+    }
+}
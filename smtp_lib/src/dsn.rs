@@ -0,0 +1,892 @@
+/*!
+# Delivery Status Notifications
+
+The `message/delivery-status` content defined in [RFC 3464](https://datatracker.ietf.org/doc/html/rfc3464).
+It is sent as a part of a `multipart/report` message defined in [RFC 6522](https://datatracker.ietf.org/doc/html/rfc6522)
+
+```text
+Reporting-MTA: dns; mail.example.com
+
+Final-Recipient: rfc822; user@example.com
+Action: failed
+Status: 5.1.1
+Diagnostic-Code: smtp; 550 5.1.1 No such user
+```
+
+[DeliveryStatus] is the `message/delivery-status` part. [DeliveryReport] is the entire `multipart/report` message.
+[DeliveryReport::bounce] builds the report for recipients that could not be delivered to
+ */
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+use thiserror::Error;
+
+use crate::{
+    commands::command_impls::{DsnReturn, ForwardPath, NotifyCondition},
+    enhanced_status_code::{EnhancedStatusCode, EnhancedStatusCodeParseError, StatusClass},
+    server_response::MultilineServerResponse,
+    session::Envelope,
+    statement::SingleLineStatement,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum DsnParseError {
+    #[error("Missing field: {0}")]
+    MissingField(&'static str),
+    #[error("Invalid {name} field: {value}")]
+    InvalidField { name: String, value: String },
+    #[error("Invalid status: {0}")]
+    InvalidStatus(#[from] EnhancedStatusCodeParseError),
+    #[error("The report does not contain any recipients")]
+    NoRecipients,
+    #[error("Not a multipart/report message")]
+    NotAReport,
+    #[error("The report does not contain a message/delivery-status part")]
+    MissingDeliveryStatus,
+}
+/// A value with its type. `type ";" value`
+///
+/// Used for the MTA names, the recipients and the diagnostic code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypedValue {
+    /// Such as `dns`, `rfc822` or `smtp`
+    pub value_type: String,
+    pub value: String,
+}
+impl TypedValue {
+    pub fn new(value_type: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            value_type: value_type.into(),
+            value: value.into(),
+        }
+    }
+    /// A `dns` MTA name
+    pub fn dns(name: impl Into<String>) -> Self {
+        Self::new("dns", name)
+    }
+    /// An `rfc822` address
+    pub fn rfc822(address: impl Into<String>) -> Self {
+        Self::new("rfc822", address)
+    }
+    /// An `smtp` diagnostic code. The lines of the reply are joined with a space
+    pub fn smtp(reply: &MultilineServerResponse) -> Self {
+        Self::new("smtp", format_reply(reply))
+    }
+    /// Compares the type without case
+    pub fn is_type(&self, value_type: &str) -> bool {
+        self.value_type.eq_ignore_ascii_case(value_type)
+    }
+}
+impl Display for TypedValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}; {}", self.value_type, self.value)
+    }
+}
+impl FromStr for TypedValue {
+    type Err = DsnParseError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (value_type, typed_value) = value
+            .split_once(';')
+            .filter(|(value_type, _)| !value_type.trim().is_empty())
+            .ok_or_else(|| DsnParseError::InvalidField {
+                name: "typed value".to_owned(),
+                value: value.to_owned(),
+            })?;
+        Ok(Self::new(value_type.trim(), typed_value.trim()))
+    }
+}
+/// What happened to the message for a recipient
+///
+/// [RFC 3464 Section 2.3.3](https://datatracker.ietf.org/doc/html/rfc3464#section-2.3.3)
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, strum::EnumString, strum::Display, strum::EnumIs,
+)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum Action {
+    /// The message could not be delivered
+    Failed,
+    /// The message has not been delivered yet. The MTA will keep trying
+    Delayed,
+    /// The message was delivered to the recipient
+    Delivered,
+    /// The message was relayed to a system that does not send DSNs
+    Relayed,
+    /// The message was delivered and forwarded to multiple addresses
+    Expanded,
+}
+/// The fields about the message. The first group of the report
+///
+/// [RFC 3464 Section 2.2](https://datatracker.ietf.org/doc/html/rfc3464#section-2.2)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PerMessageFields {
+    /// The `ENVID=` of the MAIL FROM command
+    pub original_envelope_id: Option<String>,
+    /// The MTA that created the report
+    pub reporting_mta: TypedValue,
+    pub dsn_gateway: Option<TypedValue>,
+    /// The MTA the message was received from
+    pub received_from_mta: Option<TypedValue>,
+    /// RFC 5322 date-time
+    pub arrival_date: Option<String>,
+    /// Any other field
+    pub extensions: Vec<(String, String)>,
+}
+impl PerMessageFields {
+    pub fn new(reporting_mta: TypedValue) -> Self {
+        Self {
+            original_envelope_id: None,
+            reporting_mta,
+            dsn_gateway: None,
+            received_from_mta: None,
+            arrival_date: None,
+            extensions: Vec::new(),
+        }
+    }
+    fn from_fields(fields: Vec<(String, String)>) -> Result<Self, DsnParseError> {
+        let mut original_envelope_id = None;
+        let mut reporting_mta = None;
+        let mut dsn_gateway = None;
+        let mut received_from_mta = None;
+        let mut arrival_date = None;
+        let mut extensions = Vec::new();
+        for (name, value) in fields {
+            match name.to_ascii_lowercase().as_str() {
+                "original-envelope-id" => original_envelope_id = Some(value),
+                "reporting-mta" => reporting_mta = Some(parse_field(&name, &value)?),
+                "dsn-gateway" => dsn_gateway = Some(parse_field(&name, &value)?),
+                "received-from-mta" => received_from_mta = Some(parse_field(&name, &value)?),
+                "arrival-date" => arrival_date = Some(value),
+                _ => extensions.push((name, value)),
+            }
+        }
+        Ok(Self {
+            original_envelope_id,
+            reporting_mta: reporting_mta.ok_or(DsnParseError::MissingField("Reporting-MTA"))?,
+            dsn_gateway,
+            received_from_mta,
+            arrival_date,
+            extensions,
+        })
+    }
+}
+impl Display for PerMessageFields {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write_field(
+            f,
+            "Original-Envelope-Id",
+            self.original_envelope_id.as_ref(),
+        )?;
+        write_field(f, "Reporting-MTA", Some(&self.reporting_mta))?;
+        write_field(f, "DSN-Gateway", self.dsn_gateway.as_ref())?;
+        write_field(f, "Received-From-MTA", self.received_from_mta.as_ref())?;
+        write_field(f, "Arrival-Date", self.arrival_date.as_ref())?;
+        for (name, value) in &self.extensions {
+            write_field(f, name, Some(value))?;
+        }
+        Ok(())
+    }
+}
+/// The fields about a single recipient
+///
+/// [RFC 3464 Section 2.3](https://datatracker.ietf.org/doc/html/rfc3464#section-2.3)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PerRecipientFields {
+    /// The `ORCPT=` of the RCPT TO command
+    pub original_recipient: Option<TypedValue>,
+    /// The recipient the report is about
+    pub final_recipient: TypedValue,
+    pub action: Action,
+    pub status: EnhancedStatusCode,
+    /// The MTA that sent the diagnostic code
+    pub remote_mta: Option<TypedValue>,
+    /// The reply that caused the report
+    pub diagnostic_code: Option<TypedValue>,
+    /// RFC 5322 date-time
+    pub last_attempt_date: Option<String>,
+    pub final_log_id: Option<String>,
+    /// RFC 5322 date-time. Only for [Action::Delayed]
+    pub will_retry_until: Option<String>,
+    /// Any other field
+    pub extensions: Vec<(String, String)>,
+}
+impl PerRecipientFields {
+    pub fn new(final_recipient: TypedValue, action: Action, status: EnhancedStatusCode) -> Self {
+        Self {
+            original_recipient: None,
+            final_recipient,
+            action,
+            status,
+            remote_mta: None,
+            diagnostic_code: None,
+            last_attempt_date: None,
+            final_log_id: None,
+            will_retry_until: None,
+            extensions: Vec::new(),
+        }
+    }
+    fn from_fields(fields: Vec<(String, String)>) -> Result<Self, DsnParseError> {
+        let mut original_recipient = None;
+        let mut final_recipient = None;
+        let mut action = None;
+        let mut status = None;
+        let mut remote_mta = None;
+        let mut diagnostic_code = None;
+        let mut last_attempt_date = None;
+        let mut final_log_id = None;
+        let mut will_retry_until = None;
+        let mut extensions = Vec::new();
+        for (name, value) in fields {
+            match name.to_ascii_lowercase().as_str() {
+                "original-recipient" => original_recipient = Some(parse_field(&name, &value)?),
+                "final-recipient" => final_recipient = Some(parse_field(&name, &value)?),
+                "action" => action = Some(parse_field(&name, &value)?),
+                // The code may be followed by a comment
+                "status" => {
+                    let code = value.split_whitespace().next().unwrap_or_default();
+                    status = Some(EnhancedStatusCode::from_str(code)?);
+                }
+                "remote-mta" => remote_mta = Some(parse_field(&name, &value)?),
+                "diagnostic-code" => diagnostic_code = Some(parse_field(&name, &value)?),
+                "last-attempt-date" => last_attempt_date = Some(value),
+                "final-log-id" => final_log_id = Some(value),
+                "will-retry-until" => will_retry_until = Some(value),
+                _ => extensions.push((name, value)),
+            }
+        }
+        Ok(Self {
+            original_recipient,
+            final_recipient: final_recipient
+                .ok_or(DsnParseError::MissingField("Final-Recipient"))?,
+            action: action.ok_or(DsnParseError::MissingField("Action"))?,
+            status: status.ok_or(DsnParseError::MissingField("Status"))?,
+            remote_mta,
+            diagnostic_code,
+            last_attempt_date,
+            final_log_id,
+            will_retry_until,
+            extensions,
+        })
+    }
+}
+impl Display for PerRecipientFields {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write_field(f, "Original-Recipient", self.original_recipient.as_ref())?;
+        write_field(f, "Final-Recipient", Some(&self.final_recipient))?;
+        write_field(f, "Action", Some(&self.action))?;
+        write_field(f, "Status", Some(&self.status))?;
+        write_field(f, "Remote-MTA", self.remote_mta.as_ref())?;
+        write_field(f, "Diagnostic-Code", self.diagnostic_code.as_ref())?;
+        write_field(f, "Last-Attempt-Date", self.last_attempt_date.as_ref())?;
+        write_field(f, "Final-Log-ID", self.final_log_id.as_ref())?;
+        write_field(f, "Will-Retry-Until", self.will_retry_until.as_ref())?;
+        for (name, value) in &self.extensions {
+            write_field(f, name, Some(value))?;
+        }
+        Ok(())
+    }
+}
+/// The content of a `message/delivery-status` part
+///
+/// The groups of fields are separated by an empty line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeliveryStatus {
+    pub per_message: PerMessageFields,
+    /// At least one recipient is required
+    pub recipients: Vec<PerRecipientFields>,
+}
+impl DeliveryStatus {
+    pub fn new(per_message: PerMessageFields) -> Self {
+        Self {
+            per_message,
+            recipients: Vec::new(),
+        }
+    }
+    pub fn with_recipient(mut self, recipient: PerRecipientFields) -> Self {
+        self.recipients.push(recipient);
+        self
+    }
+}
+impl Display for DeliveryStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.per_message)?;
+        for recipient in &self.recipients {
+            write!(f, "\r\n{}", recipient)?;
+        }
+        Ok(())
+    }
+}
+impl FromStr for DeliveryStatus {
+    type Err = DsnParseError;
+
+    fn from_str(content: &str) -> Result<Self, Self::Err> {
+        let mut groups = field_groups(content).into_iter();
+        let per_message = PerMessageFields::from_fields(
+            groups
+                .next()
+                .ok_or(DsnParseError::MissingField("Reporting-MTA"))?,
+        )?;
+        let recipients = groups
+            .map(PerRecipientFields::from_fields)
+            .collect::<Result<Vec<_>, _>>()?;
+        if recipients.is_empty() {
+            return Err(DsnParseError::NoRecipients);
+        }
+        Ok(Self {
+            per_message,
+            recipients,
+        })
+    }
+}
+/// The part of the original message included in the report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReturnedContent {
+    /// `message/rfc822`. The entire message
+    Message(Vec<u8>),
+    /// `text/rfc822-headers`. Only the header section
+    Headers(Vec<u8>),
+}
+/// A recipient the message could not be delivered to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecipientFailure {
+    pub forward_path: ForwardPath,
+    /// The reply that rejected the recipient or the message
+    pub reply: MultilineServerResponse,
+    /// The host that sent the reply. None if the reply was created by this server
+    pub remote_mta: Option<String>,
+}
+impl RecipientFailure {
+    pub fn new(forward_path: impl Into<ForwardPath>, reply: MultilineServerResponse) -> Self {
+        Self {
+            forward_path: forward_path.into(),
+            reply,
+            remote_mta: None,
+        }
+    }
+    pub fn with_remote_mta(mut self, remote_mta: impl Into<String>) -> Self {
+        self.remote_mta = Some(remote_mta.into());
+        self
+    }
+    /// The Enhanced Status Code of the reply. Falls back to `X.0.0` using the class of the reply code
+    fn status(&self) -> EnhancedStatusCode {
+        self.reply.enhanced_status_code().unwrap_or_else(|| {
+            let class = match self.reply.code() {
+                Some(code) if code.is_transient_negative() => {
+                    StatusClass::PersistentTransientFailure
+                }
+                _ => StatusClass::PermanentFailure,
+            };
+            EnhancedStatusCode::new(class, 0, 0)
+        })
+    }
+}
+/// A `multipart/report` message with a `message/delivery-status` part
+///
+/// [RFC 6522](https://datatracker.ietf.org/doc/html/rfc6522)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeliveryReport {
+    /// The header fields of the report. `MIME-Version` and `Content-Type` are written by [DeliveryReport::to_bytes]
+    pub headers: Vec<(String, String)>,
+    pub boundary: String,
+    /// The first part. A description of the report for people
+    pub human_readable: String,
+    pub delivery_status: DeliveryStatus,
+    pub returned: Option<ReturnedContent>,
+}
+impl DeliveryReport {
+    pub fn new(human_readable: impl Into<String>, delivery_status: DeliveryStatus) -> Self {
+        Self {
+            headers: Vec::new(),
+            boundary: generate_boundary(),
+            human_readable: human_readable.into(),
+            delivery_status,
+            returned: None,
+        }
+    }
+    /// Adds a header field
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+    /// The value of the first header field with the name
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+    /// Builds the bounce for the recipients the message could not be delivered to.
+    ///
+    /// Recipients that sent `NOTIFY=` without `FAILURE` are left out.
+    /// Returns None if the envelope has the null sender or no recipient is left.
+    ///
+    /// With `RET=FULL` the entire message is returned. Otherwise only the header section.
+    /// The `Date` and `Message-ID` header fields must be added before sending the report
+    pub fn bounce(
+        reporting_mta: &str,
+        envelope: &Envelope,
+        failures: &[RecipientFailure],
+        message: &[u8],
+    ) -> Option<Self> {
+        let reverse_path = envelope.reverse_path()?;
+        let mut per_message = PerMessageFields::new(TypedValue::dns(reporting_mta));
+        per_message.original_envelope_id = envelope.mail_from.env_id().map(str::to_owned);
+        let mut delivery_status = DeliveryStatus::new(per_message);
+        let mut human_readable = format!(
+            "This is the mail system at {}.\r\n\r\nYour message could not be delivered to the following recipients.\r\n\r\n",
+            reporting_mta
+        );
+        for failure in failures {
+            let recipient = envelope
+                .recipients
+                .iter()
+                .find(|recipient| recipient.forward_path == failure.forward_path);
+            if recipient
+                .and_then(|recipient| recipient.notify())
+                .is_some_and(|notify| !notify.notify_on(NotifyCondition::Failure))
+            {
+                continue;
+            }
+            human_readable.push_str(&format!(
+                "<{}>: {}\r\n",
+                failure.forward_path,
+                format_reply(&failure.reply)
+            ));
+            let mut fields = PerRecipientFields::new(
+                TypedValue::rfc822(failure.forward_path.to_string()),
+                Action::Failed,
+                failure.status(),
+            );
+            fields.original_recipient = recipient
+                .and_then(|recipient| recipient.original_recipient())
+                .map(|original| TypedValue::new(&original.address_type, &original.address));
+            fields.remote_mta = failure.remote_mta.as_deref().map(TypedValue::dns);
+            fields.diagnostic_code = Some(TypedValue::smtp(&failure.reply));
+            delivery_status.recipients.push(fields);
+        }
+        if delivery_status.recipients.is_empty() {
+            return None;
+        }
+        let returned = match envelope.mail_from.ret() {
+            Some(DsnReturn::Full) => ReturnedContent::Message(message.to_vec()),
+            _ => ReturnedContent::Headers(split_header_section(message).0.to_vec()),
+        };
+        let mut report = Self::new(human_readable, delivery_status)
+            .with_header(
+                "From",
+                format!("Mail Delivery System <MAILER-DAEMON@{}>", reporting_mta),
+            )
+            .with_header("To", format!("<{}>", reverse_path))
+            .with_header("Subject", "Undelivered Mail Returned to Sender")
+            .with_header("Auto-Submitted", "auto-replied");
+        report.returned = Some(returned);
+        Some(report)
+    }
+    /// Parses a `multipart/report` message. Such as a bounce sent by another server
+    pub fn parse(message: &[u8]) -> Result<Self, DsnParseError> {
+        let (header_section, body) = split_header_section(message);
+        let headers = field_groups(&String::from_utf8_lossy(header_section))
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+        let content_type =
+            find_header(&headers, "Content-Type").ok_or(DsnParseError::NotAReport)?;
+        if !media_type(content_type).eq_ignore_ascii_case("multipart/report") {
+            return Err(DsnParseError::NotAReport);
+        }
+        let boundary =
+            content_type_parameter(content_type, "boundary").ok_or(DsnParseError::NotAReport)?;
+
+        let mut human_readable = None;
+        let mut delivery_status = None;
+        let mut returned = None;
+        for (index, part) in split_multipart(body, &boundary).into_iter().enumerate() {
+            let (part_header_section, content) = split_header_section(part);
+            let part_headers = field_groups(&String::from_utf8_lossy(part_header_section))
+                .into_iter()
+                .next()
+                .unwrap_or_default();
+            let part_type = find_header(&part_headers, "Content-Type")
+                .map(media_type)
+                .unwrap_or("text/plain")
+                .to_ascii_lowercase();
+            match part_type.as_str() {
+                "message/delivery-status" | "message/global-delivery-status" => {
+                    delivery_status =
+                        Some(DeliveryStatus::from_str(&String::from_utf8_lossy(content))?);
+                }
+                "message/rfc822" | "message/global" => {
+                    returned = Some(ReturnedContent::Message(content.to_vec()));
+                }
+                "text/rfc822-headers" | "message/global-headers" => {
+                    returned = Some(ReturnedContent::Headers(content.to_vec()));
+                }
+                _ if index == 0 => {
+                    human_readable = Some(String::from_utf8_lossy(content).into_owned());
+                }
+                _ => {}
+            }
+        }
+        Ok(Self {
+            headers: headers
+                .into_iter()
+                .filter(|(name, _)| {
+                    !name.eq_ignore_ascii_case("MIME-Version")
+                        && !name.eq_ignore_ascii_case("Content-Type")
+                })
+                .collect(),
+            boundary,
+            human_readable: human_readable.unwrap_or_default(),
+            delivery_status: delivery_status.ok_or(DsnParseError::MissingDeliveryStatus)?,
+            returned,
+        })
+    }
+    /// Writes the entire message
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut message = String::new();
+        for (name, value) in &self.headers {
+            message.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        message.push_str(&format!(
+            "MIME-Version: 1.0\r\nContent-Type: multipart/report; report-type=delivery-status;\r\n\tboundary=\"{}\"\r\n\r\n",
+            self.boundary
+        ));
+        message.push_str(&format!(
+            "--{}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{}\r\n",
+            self.boundary, self.human_readable
+        ));
+        message.push_str(&format!(
+            "--{}\r\nContent-Type: message/delivery-status\r\n\r\n{}\r\n",
+            self.boundary, self.delivery_status
+        ));
+        let mut message = message.into_bytes();
+        if let Some(returned) = &self.returned {
+            let (content_type, content) = match returned {
+                ReturnedContent::Message(content) => ("message/rfc822", content),
+                ReturnedContent::Headers(content) => ("text/rfc822-headers", content),
+            };
+            message.extend_from_slice(
+                format!(
+                    "--{}\r\nContent-Type: {}\r\n\r\n",
+                    self.boundary, content_type
+                )
+                .as_bytes(),
+            );
+            message.extend_from_slice(content);
+            message.extend_from_slice(b"\r\n");
+        }
+        message.extend_from_slice(format!("--{}--\r\n", self.boundary).as_bytes());
+        message
+    }
+}
+
+fn format_reply(reply: &MultilineServerResponse) -> String {
+    reply
+        .0
+        .iter()
+        .map(|line| line.to_spaced_line().trim_end().to_owned())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+fn generate_boundary() -> String {
+    format!("=_{:032x}", rand::random::<u128>())
+}
+fn write_field(
+    f: &mut Formatter<'_>,
+    name: &str,
+    value: Option<&impl Display>,
+) -> std::fmt::Result {
+    match value {
+        Some(value) => write!(f, "{}: {}\r\n", name, value),
+        None => Ok(()),
+    }
+}
+fn parse_field<T: FromStr>(name: &str, value: &str) -> Result<T, DsnParseError> {
+    T::from_str(value).map_err(|_| DsnParseError::InvalidField {
+        name: name.to_owned(),
+        value: value.to_owned(),
+    })
+}
+fn find_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(header, _)| header.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+/// Splits the content into groups of `name: value` fields. Groups are separated by empty lines.
+///
+/// Folded lines are unfolded
+fn field_groups(content: &str) -> Vec<Vec<(String, String)>> {
+    let mut groups = Vec::new();
+    let mut fields: Vec<(String, String)> = Vec::new();
+    for line in content.lines() {
+        if line.trim().is_empty() {
+            if !fields.is_empty() {
+                groups.push(std::mem::take(&mut fields));
+            }
+        } else if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = fields.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            fields.push((name.trim().to_owned(), value.trim().to_owned()));
+        }
+    }
+    if !fields.is_empty() {
+        groups.push(fields);
+    }
+    groups
+}
+/// Splits the header section from the body at the first empty line
+fn split_header_section(message: &[u8]) -> (&[u8], &[u8]) {
+    let mut offset = 0;
+    for line in message.split_inclusive(|byte| *byte == b'\n') {
+        if line == b"\r\n" || line == b"\n" {
+            return (&message[..offset], &message[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+    (message, &[])
+}
+/// The parts between the boundary delimiter lines. The preamble and epilogue are ignored
+fn split_multipart<'a>(body: &'a [u8], boundary: &str) -> Vec<&'a [u8]> {
+    let delimiter = format!("--{}", boundary);
+    let mut parts = Vec::new();
+    let mut start = None;
+    let mut offset = 0;
+    for line in body.split_inclusive(|byte| *byte == b'\n') {
+        if let Some(rest) = line.trim_ascii_end().strip_prefix(delimiter.as_bytes())
+            && (rest.is_empty() || rest == b"--")
+        {
+            if let Some(start) = start {
+                // The line break before the delimiter belongs to the delimiter
+                let part: &[u8] = &body[start..offset];
+                let part = part
+                    .strip_suffix(b"\n")
+                    .map(|part| part.strip_suffix(b"\r").unwrap_or(part))
+                    .unwrap_or(part);
+                parts.push(part);
+            }
+            if rest == b"--" {
+                return parts;
+            }
+            start = Some(offset + line.len());
+        }
+        offset += line.len();
+    }
+    parts
+}
+/// The `type/subtype` of a Content-Type value
+fn media_type(content_type: &str) -> &str {
+    content_type.split(';').next().unwrap_or_default().trim()
+}
+/// Finds a parameter of a Content-Type value. Quotes are removed
+fn content_type_parameter(content_type: &str, name: &str) -> Option<String> {
+    content_type.split(';').skip(1).find_map(|parameter| {
+        let (key, value) = parameter.split_once('=')?;
+        key.trim().eq_ignore_ascii_case(name).then(|| {
+            let value = value.trim();
+            value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .unwrap_or(value)
+                .to_owned()
+        })
+    })
+}
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use mail_lib_types::EmailAddress;
+
+    use super::{
+        Action, DeliveryReport, DeliveryStatus, RecipientFailure, ReturnedContent, TypedValue,
+    };
+    use crate::{
+        commands::command_impls::{
+            DsnNotify, DsnReturn, MailFromCommandData, MailParameter, OriginalRecipient,
+            RcptParameter, RcptToCommandData,
+        },
+        enhanced_status_code::EnhancedStatusCode,
+        server_response::MultilineServerResponse,
+        session::Envelope,
+    };
+
+    const STATUS: &str = "Original-Envelope-Id: QQ314159\r\n\
+        Reporting-MTA: dns; mail.example.com\r\n\
+        Arrival-Date: Thu, 15 Oct 2026 10:00:00 +0000\r\n\
+        \r\n\
+        Original-Recipient: rfc822; user+tag@example.com\r\n\
+        Final-Recipient: rfc822; user@example.com\r\n\
+        Action: failed\r\n\
+        Status: 5.1.1\r\n\
+        Remote-MTA: dns; mx.example.com\r\n\
+        Diagnostic-Code: smtp; 550 5.1.1 No such user\r\n\
+        \r\n\
+        Final-Recipient: rfc822; other@example.com\r\n\
+        Action: delayed\r\n\
+        Status: 4.2.2\r\n\
+        Will-Retry-Until: Fri, 16 Oct 2026 10:00:00 +0000\r\n";
+
+    fn address(address: &str) -> EmailAddress {
+        EmailAddress::new(address).unwrap()
+    }
+    #[test]
+    fn test_parse_status() {
+        let status = DeliveryStatus::from_str(STATUS).unwrap();
+        assert_eq!(
+            status.per_message.original_envelope_id.as_deref(),
+            Some("QQ314159")
+        );
+        assert_eq!(
+            status.per_message.reporting_mta,
+            TypedValue::dns("mail.example.com")
+        );
+        assert_eq!(status.recipients.len(), 2);
+        let first = &status.recipients[0];
+        assert_eq!(
+            first.original_recipient,
+            Some(TypedValue::rfc822("user+tag@example.com"))
+        );
+        assert_eq!(first.action, Action::Failed);
+        assert_eq!(first.status, EnhancedStatusCode::BAD_DESTINATION_MAILBOX);
+        assert_eq!(
+            first.diagnostic_code,
+            Some(TypedValue::new("smtp", "550 5.1.1 No such user"))
+        );
+        assert_eq!(status.recipients[1].action, Action::Delayed);
+        assert_eq!(
+            status.recipients[1].status,
+            EnhancedStatusCode::MAILBOX_FULL
+        );
+
+        assert_eq!(status.to_string(), STATUS);
+    }
+    #[test]
+    fn test_parse_status_lenient() {
+        let status = DeliveryStatus::from_str(
+            "reporting-mta: dns;\n mail.example.com\n\n\nfinal-recipient: RFC822;user@example.com\naction: FAILED\nstatus: 5.0.0 (unknown)\nX-Postfix-Sender: rfc822; a@example.com\n",
+        )
+        .unwrap();
+        assert_eq!(
+            status.per_message.reporting_mta,
+            TypedValue::dns("mail.example.com")
+        );
+        let recipient = &status.recipients[0];
+        assert!(recipient.final_recipient.is_type("rfc822"));
+        assert_eq!(recipient.action, Action::Failed);
+        assert_eq!(recipient.extensions.len(), 1);
+
+        assert!(DeliveryStatus::from_str("Reporting-MTA: dns; mail.example.com\r\n").is_err());
+        assert!(DeliveryStatus::from_str(
+            "Reporting-MTA: dns; a\r\n\r\nFinal-Recipient: rfc822; b@example.com\r\nAction: lost\r\nStatus: 5.0.0\r\n"
+        )
+        .is_err());
+    }
+    #[test]
+    fn test_bounce() {
+        let mut envelope = Envelope::new(
+            MailFromCommandData::new(Some(address("sender@example.com")))
+                .with_parameter(MailParameter::Ret(DsnReturn::Hdrs))
+                .with_parameter(MailParameter::EnvId("QQ314159".to_owned())),
+        );
+        envelope.recipients.push(
+            RcptToCommandData::new(address("user@example.com")).with_parameter(
+                RcptParameter::OriginalRecipient(OriginalRecipient::rfc822("user+tag@example.com")),
+            ),
+        );
+        envelope.recipients.push(
+            RcptToCommandData::new(address("quiet@example.com"))
+                .with_parameter(RcptParameter::Notify(DsnNotify::Never)),
+        );
+        let message = b"Subject: Hello\r\nFrom: sender@example.com\r\n\r\nHello World\r\n";
+        let failures = [
+            RecipientFailure::new(
+                address("user@example.com"),
+                MultilineServerResponse::from_str("550 5.1.1 No such user\r\n").unwrap(),
+            )
+            .with_remote_mta("mx.example.com"),
+            RecipientFailure::new(
+                address("quiet@example.com"),
+                MultilineServerResponse::from_str("550 No such user\r\n").unwrap(),
+            ),
+        ];
+        let report =
+            DeliveryReport::bounce("mail.example.com", &envelope, &failures, message).unwrap();
+        assert_eq!(report.header("to"), Some("<sender@example.com>"));
+        assert_eq!(report.delivery_status.recipients.len(), 1);
+        assert_eq!(
+            report.returned,
+            Some(ReturnedContent::Headers(
+                b"Subject: Hello\r\nFrom: sender@example.com\r\n".to_vec()
+            ))
+        );
+
+        let bytes = report.to_bytes();
+        let parsed = DeliveryReport::parse(&bytes).unwrap();
+        assert_eq!(parsed, report);
+        let recipient = &parsed.delivery_status.recipients[0];
+        assert_eq!(
+            recipient.original_recipient,
+            Some(TypedValue::rfc822("user+tag@example.com"))
+        );
+        assert_eq!(
+            recipient.remote_mta,
+            Some(TypedValue::dns("mx.example.com"))
+        );
+        assert_eq!(
+            parsed
+                .delivery_status
+                .per_message
+                .original_envelope_id
+                .as_deref(),
+            Some("QQ314159")
+        );
+
+        let null_sender = Envelope::new(MailFromCommandData::new(None));
+        assert!(
+            DeliveryReport::bounce("mail.example.com", &null_sender, &failures, message).is_none()
+        );
+    }
+    #[test]
+    fn test_parse_report() {
+        let message = "From: MAILER-DAEMON@mx.example.com\r\n\
+            Content-Type: multipart/report; report-type=delivery-status; boundary=XYZ\r\n\
+            \r\n\
+            Preamble\r\n\
+            --XYZ\r\n\
+            \r\n\
+            Delivery failed\r\n\
+            --XYZ\r\n\
+            Content-Type: message/delivery-status\r\n\
+            \r\n\
+            Reporting-MTA: dns; mx.example.com\r\n\
+            \r\n\
+            Final-Recipient: rfc822; user@example.com\r\n\
+            Action: failed\r\n\
+            Status: 5.2.1\r\n\
+            --XYZ\r\n\
+            Content-Type: message/rfc822\r\n\
+            \r\n\
+            Subject: Hello\r\n\
+            \r\n\
+            Body\r\n\
+            --XYZ--\r\n";
+        let report = DeliveryReport::parse(message.as_bytes()).unwrap();
+        assert_eq!(report.boundary, "XYZ");
+        assert_eq!(report.human_readable, "Delivery failed");
+        assert_eq!(
+            report.returned,
+            Some(ReturnedContent::Message(
+                b"Subject: Hello\r\n\r\nBody".to_vec()
+            ))
+        );
+        assert_eq!(
+            report.delivery_status.recipients[0].status.to_string(),
+            "5.2.1"
+        );
+
+        assert!(DeliveryReport::parse(b"Content-Type: text/plain\r\n\r\nHello\r\n").is_err());
+    }
+}
@@ -0,0 +1,309 @@
+/*!
+# Enhanced Mail System Status Codes
+
+Defined in [RFC 3463](https://datatracker.ietf.org/doc/html/rfc3463) and sent as part of a server response as defined in [RFC 2034](https://datatracker.ietf.org/doc/html/rfc2034)
+
+```text
+250 2.1.5 Destination address valid
+```
+ */
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum EnhancedStatusCodeParseError {
+    #[error("Invalid enhanced status code format: {0}")]
+    InvalidFormat(String),
+    #[error("Invalid enhanced status code class: {0}. Must be 2, 4, or 5")]
+    InvalidClass(u8),
+    #[error("Enhanced status code value out of range: {0}. Must be between 0 and 999")]
+    OutOfRange(u16),
+}
+/// The class of an Enhanced Status Code. The first number of the code.
+///
+/// [RFC 3463 Section 3.1](https://datatracker.ietf.org/doc/html/rfc3463#section-3.1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::EnumIs)]
+pub enum StatusClass {
+    /// 2.X.X Success
+    Success,
+    /// 4.X.X Persistent Transient Failure
+    PersistentTransientFailure,
+    /// 5.X.X Permanent Failure
+    PermanentFailure,
+}
+impl StatusClass {
+    /// The number representing the class
+    pub fn code(&self) -> u8 {
+        match self {
+            StatusClass::Success => 2,
+            StatusClass::PersistentTransientFailure => 4,
+            StatusClass::PermanentFailure => 5,
+        }
+    }
+}
+impl TryFrom<u8> for StatusClass {
+    type Error = EnhancedStatusCodeParseError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            2 => Ok(StatusClass::Success),
+            4 => Ok(StatusClass::PersistentTransientFailure),
+            5 => Ok(StatusClass::PermanentFailure),
+            other => Err(EnhancedStatusCodeParseError::InvalidClass(other)),
+        }
+    }
+}
+impl Display for StatusClass {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+/// The known subjects of an Enhanced Status Code. The second number of the code.
+///
+/// [RFC 3463 Section 3.2](https://datatracker.ietf.org/doc/html/rfc3463#section-3.2)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::Display)]
+pub enum StatusSubject {
+    /// X.0.XXX Other or Undefined Status
+    Other,
+    /// X.1.XXX Addressing Status
+    Addressing,
+    /// X.2.XXX Mailbox Status
+    Mailbox,
+    /// X.3.XXX Mail System Status
+    MailSystem,
+    /// X.4.XXX Network and Routing Status
+    NetworkAndRouting,
+    /// X.5.XXX Mail Delivery Protocol Status
+    MailDeliveryProtocol,
+    /// X.6.XXX Message Content or Media Status
+    MessageContent,
+    /// X.7.XXX Security or Policy Status
+    SecurityOrPolicy,
+}
+impl StatusSubject {
+    /// Gets the subject from its number
+    pub fn from_code(code: u16) -> Option<Self> {
+        match code {
+            0 => Some(StatusSubject::Other),
+            1 => Some(StatusSubject::Addressing),
+            2 => Some(StatusSubject::Mailbox),
+            3 => Some(StatusSubject::MailSystem),
+            4 => Some(StatusSubject::NetworkAndRouting),
+            5 => Some(StatusSubject::MailDeliveryProtocol),
+            6 => Some(StatusSubject::MessageContent),
+            7 => Some(StatusSubject::SecurityOrPolicy),
+            _ => None,
+        }
+    }
+}
+/// An Enhanced Status Code. `class "." subject "." detail`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EnhancedStatusCode {
+    pub class: StatusClass,
+    pub subject: u16,
+    pub detail: u16,
+}
+impl EnhancedStatusCode {
+    /// 2.0.0 Other undefined Status
+    pub const SUCCESS: Self = Self::new(StatusClass::Success, 0, 0);
+    /// 2.1.0 Other address status. Commonly used for an accepted sender
+    pub const SENDER_OK: Self = Self::new(StatusClass::Success, 1, 0);
+    /// 2.1.5 Destination address valid
+    pub const DESTINATION_VALID: Self = Self::new(StatusClass::Success, 1, 5);
+    /// 2.6.0 Other or undefined media error. Commonly used for message accepted
+    pub const MESSAGE_ACCEPTED: Self = Self::new(StatusClass::Success, 6, 0);
+    /// 4.2.2 Mailbox full
+    pub const MAILBOX_FULL: Self = Self::new(StatusClass::PersistentTransientFailure, 2, 2);
+    /// 5.1.1 Bad destination mailbox address
+    pub const BAD_DESTINATION_MAILBOX: Self = Self::new(StatusClass::PermanentFailure, 1, 1);
+    /// 5.5.1 Invalid command
+    pub const INVALID_COMMAND: Self = Self::new(StatusClass::PermanentFailure, 5, 1);
+    /// 5.5.2 Syntax error
+    pub const SYNTAX_ERROR: Self = Self::new(StatusClass::PermanentFailure, 5, 2);
+    /// 5.7.1 Delivery not authorized, message refused
+    pub const NOT_AUTHORIZED: Self = Self::new(StatusClass::PermanentFailure, 7, 1);
+
+    /// Creates a new Enhanced Status Code.
+    ///
+    /// Subject and Detail should be in the range 0-999
+    pub const fn new(class: StatusClass, subject: u16, detail: u16) -> Self {
+        Self {
+            class,
+            subject,
+            detail,
+        }
+    }
+    /// The subject if it is a known subject
+    pub fn subject_kind(&self) -> Option<StatusSubject> {
+        StatusSubject::from_code(self.subject)
+    }
+    /// The description of the `subject.detail` as registered in
+    /// [RFC 3463](https://datatracker.ietf.org/doc/html/rfc3463#section-3) and its updates.
+    ///
+    /// The meaning does not change based on the class.
+    pub fn description(&self) -> Option<&'static str> {
+        let description = match (self.subject, self.detail) {
+            (0, 0) => "Other undefined Status",
+            (1, 0) => "Other address status",
+            (1, 1) => "Bad destination mailbox address",
+            (1, 2) => "Bad destination system address",
+            (1, 3) => "Bad destination mailbox address syntax",
+            (1, 4) => "Destination mailbox address ambiguous",
+            (1, 5) => "Destination address valid",
+            (1, 6) => "Destination mailbox has moved, No forwarding address",
+            (1, 7) => "Bad sender's mailbox address syntax",
+            (1, 8) => "Bad sender's system address",
+            (1, 9) => "Message relayed to non-compliant mailer",
+            (1, 10) => "Recipient address has null MX",
+            (2, 0) => "Other or undefined mailbox status",
+            (2, 1) => "Mailbox disabled, not accepting messages",
+            (2, 2) => "Mailbox full",
+            (2, 3) => "Message length exceeds administrative limit",
+            (2, 4) => "Mailing list expansion problem",
+            (3, 0) => "Other or undefined mail system status",
+            (3, 1) => "Mail system full",
+            (3, 2) => "System not accepting network messages",
+            (3, 3) => "System not capable of selected features",
+            (3, 4) => "Message too big for system",
+            (3, 5) => "System incorrectly configured",
+            (3, 6) => "Requested priority was changed",
+            (4, 0) => "Other or undefined network or routing status",
+            (4, 1) => "No answer from host",
+            (4, 2) => "Bad connection",
+            (4, 3) => "Directory server failure",
+            (4, 4) => "Unable to route",
+            (4, 5) => "Mail system congestion",
+            (4, 6) => "Routing loop detected",
+            (4, 7) => "Delivery time expired",
+            (5, 0) => "Other or undefined protocol status",
+            (5, 1) => "Invalid command",
+            (5, 2) => "Syntax error",
+            (5, 3) => "Too many recipients",
+            (5, 4) => "Invalid command arguments",
+            (5, 5) => "Wrong protocol version",
+            (5, 6) => "Authentication Exchange line is too long",
+            (6, 0) => "Other or undefined media error",
+            (6, 1) => "Media not supported",
+            (6, 2) => "Conversion required and prohibited",
+            (6, 3) => "Conversion required but not supported",
+            (6, 4) => "Conversion with loss performed",
+            (6, 5) => "Conversion Failed",
+            (6, 6) => "Message content not available",
+            (6, 7) => "Non-ASCII addresses not permitted for that sender/recipient",
+            (6, 8) => "UTF-8 string reply is required, but not permitted by the SMTP client",
+            (6, 9) => "UTF-8 header message cannot be transferred to one or more recipients",
+            (7, 0) => "Other or undefined security status",
+            (7, 1) => "Delivery not authorized, message refused",
+            (7, 2) => "Mailing list expansion prohibited",
+            (7, 3) => "Security conversion required but not possible",
+            (7, 4) => "Security features not supported",
+            (7, 5) => "Cryptographic failure",
+            (7, 6) => "Cryptographic algorithm not supported",
+            (7, 7) => "Message integrity failure",
+            (7, 8) => "Authentication credentials invalid",
+            (7, 9) => "Authentication mechanism is too weak",
+            (7, 10) => "Encryption Needed",
+            (7, 11) => "Encryption required for requested authentication mechanism",
+            (7, 12) => "A password transition is needed",
+            (7, 13) => "User Account Disabled",
+            (7, 14) => "Trust relationship required",
+            _ => return None,
+        };
+        Some(description)
+    }
+    /// Attempts to parse an Enhanced Status Code from the start of a response message.
+    ///
+    /// Returns the code and the rest of the message with the separating space removed.
+    pub fn split_from_message(message: &str) -> Option<(Self, &str)> {
+        let (code, rest) = match message.split_once(' ') {
+            Some((code, rest)) => (code, rest),
+            None => (message, ""),
+        };
+        let code = Self::from_str(code).ok()?;
+        Some((code, rest))
+    }
+}
+impl Display for EnhancedStatusCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.class, self.subject, self.detail)
+    }
+}
+impl FromStr for EnhancedStatusCode {
+    type Err = EnhancedStatusCodeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid_format = || EnhancedStatusCodeParseError::InvalidFormat(s.to_owned());
+        let mut parts = s.split('.');
+        let (Some(class), Some(subject), Some(detail), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid_format());
+        };
+        let parse_part = |part: &str, max_len: usize| {
+            if part.is_empty() || part.len() > max_len || !part.bytes().all(|b| b.is_ascii_digit())
+            {
+                return Err(invalid_format());
+            }
+            u16::from_str(part).map_err(|_| invalid_format())
+        };
+        let class = StatusClass::try_from(parse_part(class, 1)? as u8)?;
+        let subject = parse_part(subject, 3)?;
+        let detail = parse_part(detail, 3)?;
+        Ok(Self::new(class, subject, detail))
+    }
+}
+impl TryFrom<(u8, u16, u16)> for EnhancedStatusCode {
+    type Error = EnhancedStatusCodeParseError;
+
+    fn try_from((class, subject, detail): (u8, u16, u16)) -> Result<Self, Self::Error> {
+        let class = StatusClass::try_from(class)?;
+        for value in [subject, detail] {
+            if value > 999 {
+                return Err(EnhancedStatusCodeParseError::OutOfRange(value));
+            }
+        }
+        Ok(Self::new(class, subject, detail))
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::{EnhancedStatusCode, StatusClass, StatusSubject};
+
+    #[test]
+    fn test_parse() {
+        let code = EnhancedStatusCode::from_str("5.1.1").unwrap();
+        assert_eq!(code, EnhancedStatusCode::BAD_DESTINATION_MAILBOX);
+        assert_eq!(code.subject_kind(), Some(StatusSubject::Addressing));
+        assert_eq!(code.description(), Some("Bad destination mailbox address"));
+        assert_eq!(code.to_string(), "5.1.1");
+
+        let code = EnhancedStatusCode::from_str("4.999.123").unwrap();
+        assert_eq!(code.class, StatusClass::PersistentTransientFailure);
+        assert_eq!(code.description(), None);
+
+        for invalid in [
+            "3.0.0", "5.1", "5.1.1.1", "5..1", "5.1000.0", "a.b.c", "25.0.0",
+        ] {
+            assert!(
+                EnhancedStatusCode::from_str(invalid).is_err(),
+                "{} should be invalid",
+                invalid
+            );
+        }
+    }
+    #[test]
+    fn test_split_from_message() {
+        let (code, message) =
+            EnhancedStatusCode::split_from_message("2.1.5 Destination address valid").unwrap();
+        assert_eq!(code, EnhancedStatusCode::DESTINATION_VALID);
+        assert_eq!(message, "Destination address valid");
+
+        assert!(EnhancedStatusCode::split_from_message("OK").is_none());
+    }
+}
@@ -2,7 +2,10 @@ use std::string::FromUtf8Error;
 
 use thiserror::Error;
 
-use crate::{smtp_server::ServerExtensionParseError, statement::StatementParseError};
+use crate::{
+    auth::AuthError, commands::CommandParseError, server_response::MultilineServerResponse,
+    smtp_server::ServerExtensionParseError, statement::StatementParseError,
+};
 
 #[derive(Debug, Error)]
 pub enum SMTPError {
@@ -18,11 +21,27 @@ pub enum SMTPError {
     InvalidExtension(#[from] ServerExtensionParseError),
     #[error("Invalid Response: {0}")]
     InvalidResponse(&'static str),
+    #[error("Unable to parse command: {0}")]
+    CommandParse(#[from] CommandParseError),
+    #[error("Message exceeds the maximum size of {0} bytes")]
+    MessageTooLarge(usize),
+    #[error("Server rejected the command with {}", .0.code().map(u16::from).unwrap_or_default())]
+    Rejected(MultilineServerResponse),
+    #[error("STARTTLS failed: {0}")]
+    StartTls(&'static str),
+    #[error("Authentication failed: {0}")]
+    Auth(#[from] AuthError),
+    #[error("The server does not support {0}")]
+    MissingExtension(&'static str),
 }
 impl SMTPError {
     pub fn get_error_code(&self) -> u16 {
         match self {
             SMTPError::InvalidCommand(_) => 502,
+            SMTPError::CommandParse(error) => error.error_code(),
+            SMTPError::MessageTooLarge(_) => 552,
+            SMTPError::Rejected(response) => response.code().map(u16::from).unwrap_or_default(),
+            SMTPError::Auth(error) => error.code().code(),
             _ => 0,
         }
     }
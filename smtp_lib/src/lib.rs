@@ -1,19 +1,43 @@
+pub mod auth;
+#[cfg(feature = "client")]
+pub mod client;
 pub mod commands;
+pub mod data;
+pub mod dsn;
+pub mod enhanced_status_code;
 pub mod error;
+#[cfg(feature = "server")]
+pub mod server;
 pub mod server_response;
+pub mod session;
 pub mod smtp_client;
 pub mod smtp_server;
 pub mod statement;
+#[cfg(any(feature = "client", feature = "server"))]
+pub mod tls;
 
 pub type Result<T> = std::result::Result<T, error::SMTPError>;
-/// By default, SMTP uses '.\n' as the end of a multiline command
-pub static END_OF_MULTILINE_COMMAND: &str = ".\n";
+/// The end of the data sent after the DATA command. `<CRLF>.<CRLF>`
+///
+/// See [data] for handling the body without buffering it
+pub static END_OF_MULTILINE_COMMAND: &str = "\r\n.\r\n";
 pub static END_OF_COMMAND: &str = "\n";
 pub static CRLF: &str = "\r\n";
 
 pub type BoxSendFuture<'a, T> = std::pin::Pin<Box<dyn std::future::Future<Output = T> + Send + 'a>>;
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Where a connection is in the SMTP session. See [session::Session]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::Display, strum::EnumIs)]
 pub enum SMTPConnectionState {
+    /// The greeting was sent. Waiting for EHLO or HELO
     Connected,
+    /// The client identified itself. No mail transaction has started
     Helo,
+    /// MAIL FROM was accepted
+    MailFrom,
+    /// At least one RCPT TO was accepted
+    RcptTo,
+    /// The server is receiving the message
+    Data,
+    /// QUIT was sent. No more commands are accepted
+    Closed,
 }
@@ -0,0 +1,976 @@
+use std::{
+    fmt::Debug,
+    io,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+
+use bytes::{Bytes, BytesMut};
+use futures::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncWriteExt, BufReader},
+    Stream, StreamExt,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    time::{Instant, Sleep},
+};
+use tokio_util::compat::{Compat, TokioAsyncReadCompatExt};
+use tracing::{debug, trace};
+
+use super::{
+    ConnectionInfo, HandlerResult, ServerConfig, SessionHandler, DEFAULT_MAX_CHUNKED_MESSAGE_SIZE,
+};
+#[cfg(feature = "rustls")]
+use crate::smtp_server::async_traits::AsyncStartTlsConnection;
+use crate::{
+    auth::{
+        decode_sasl, encode_sasl,
+        server::{SaslServer, ServerStep},
+        AuthError, AuthMechanism, Authenticator, MAX_AUTH_LINE_LENGTH,
+    },
+    commands::{
+        async_traits::AsyncSMTPCommand,
+        command_impls::{
+            AuthCommand, AuthCommandData, BdatCommandData, EhloCommand, EhloResponse, ForwardPath,
+            HeloCommand, LhloCommand, MailParameter,
+        },
+        ClientCommand, CommandParseError, SMTPCommand,
+    },
+    data::async_data::DataBodyStream,
+    enhanced_status_code::{EnhancedStatusCode, StatusClass},
+    error::SMTPError,
+    server_response::{MultilineServerResponse, ResponseCode, ServerResponseLine},
+    session::{Envelope, Session},
+    smtp_server::{
+        async_traits::AsyncSMTPConnection, SMTPConnection, SMTPServer, SMTPServerExtension,
+    },
+    statement::Statement,
+    tls::MaybeTlsStream,
+    SMTPConnectionState, END_OF_MULTILINE_COMMAND,
+};
+
+/// The body of a message sent with DATA or BDAT.
+///
+/// Yields the message with the dot-stuffing removed.
+/// If the message is larger than [ServerConfig::with_max_message_size] the stream returns [SMTPError::MessageTooLarge].
+/// If no data arrives within [ServerConfig::with_data_timeout] the stream returns a [io::ErrorKind::TimedOut] error
+pub struct MessageBody<'a> {
+    inner: MessageBodyInner<'a>,
+}
+enum MessageBodyInner<'a> {
+    Data {
+        stream: DataBodyStream<&'a mut (dyn AsyncBufRead + Send + Unpin)>,
+        timeout: Duration,
+        /// Reset every time data is read
+        deadline: Pin<Box<Sleep>>,
+    },
+    /// Every BDAT chunk. Taken once it is read
+    Chunks { message: Option<Bytes>, size: usize },
+}
+impl<'a> MessageBody<'a> {
+    pub(crate) fn new(
+        reader: &'a mut (dyn AsyncBufRead + Send + Unpin),
+        max_size: Option<usize>,
+        timeout: Duration,
+    ) -> Self {
+        let stream = DataBodyStream::new(reader);
+        let stream = match max_size {
+            Some(max_size) => stream.with_max_size(max_size),
+            None => stream,
+        };
+        Self {
+            inner: MessageBodyInner::Data {
+                stream,
+                timeout,
+                deadline: Box::pin(tokio::time::sleep(timeout)),
+            },
+        }
+    }
+    /// A message that was received with BDAT
+    pub(crate) fn from_chunks(message: Bytes) -> Self {
+        Self {
+            inner: MessageBodyInner::Chunks {
+                size: message.len(),
+                message: Some(message),
+            },
+        }
+    }
+    /// Reads the entire message
+    pub async fn bytes(&mut self) -> crate::Result<Bytes> {
+        let mut message = BytesMut::new();
+        while let Some(chunk) = self.next().await {
+            message.extend_from_slice(&chunk?);
+        }
+        Ok(message.freeze())
+    }
+    /// The number of bytes read so far
+    pub fn size(&self) -> usize {
+        match &self.inner {
+            MessageBodyInner::Data { stream, .. } => stream.size(),
+            MessageBodyInner::Chunks { size, .. } => *size,
+        }
+    }
+    /// If the message was larger than the max message size
+    pub fn is_too_large(&self) -> bool {
+        match &self.inner {
+            MessageBodyInner::Data { stream, .. } => stream.is_too_large(),
+            // Checked while the chunks are received
+            MessageBodyInner::Chunks { .. } => false,
+        }
+    }
+    /// Reads and drops the rest of the message
+    pub(crate) async fn discard(&mut self) -> crate::Result<()> {
+        while let Some(chunk) = self.next().await {
+            match chunk {
+                Ok(_) | Err(SMTPError::MessageTooLarge(_)) => {}
+                Err(error) => return Err(error),
+            }
+        }
+        if matches!(&self.inner, MessageBodyInner::Data { stream, .. } if !stream.is_finished()) {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Connection closed before the end of the data",
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+impl Stream for MessageBody<'_> {
+    type Item = crate::Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match &mut self.get_mut().inner {
+            MessageBodyInner::Data {
+                stream,
+                timeout,
+                deadline,
+            } => {
+                if let Poll::Ready(item) = Pin::new(stream).poll_next(cx) {
+                    deadline.as_mut().reset(Instant::now() + *timeout);
+                    return Poll::Ready(item);
+                }
+                ready!(deadline.as_mut().poll(cx));
+                // Re-arm so a caller polling again after the error gets a fresh timeout
+                deadline.as_mut().reset(Instant::now() + *timeout);
+                Poll::Ready(Some(Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "Timed out waiting for the message data",
+                )
+                .into())))
+            }
+            MessageBodyInner::Chunks { message, .. } => Poll::Ready(message.take().map(Ok)),
+        }
+    }
+}
+impl Debug for MessageBody<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MessageBody")
+            .field("size", &self.size())
+            .field("too_large", &self.is_too_large())
+            .finish()
+    }
+}
+
+/// A single client connected to a [Server](super::Server)
+pub struct ServerConnection<S> {
+    reader: BufReader<Compat<MaybeTlsStream<S>>>,
+    config: Arc<ServerConfig>,
+    info: ConnectionInfo,
+    session: Session,
+    authenticator: Option<Arc<dyn Authenticator>>,
+    /// The BDAT chunks of the current transaction
+    chunks: BytesMut,
+}
+impl<S> ServerConnection<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    pub fn new(stream: S, config: Arc<ServerConfig>, info: ConnectionInfo) -> Self {
+        Self {
+            reader: BufReader::new(MaybeTlsStream::Plain(stream).compat()),
+            session: config.new_session(),
+            config,
+            info,
+            authenticator: None,
+            chunks: BytesMut::new(),
+        }
+    }
+    /// Enables AUTH
+    pub fn with_authenticator(mut self, authenticator: Arc<dyn Authenticator>) -> Self {
+        self.authenticator = Some(authenticator);
+        self
+    }
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+    pub fn info(&self) -> &ConnectionInfo {
+        &self.info
+    }
+    /// Runs the session until the client sends QUIT or disconnects
+    pub async fn run<H: SessionHandler + ?Sized>(mut self, handler: &H) -> crate::Result<()> {
+        match handler.connect(&self.info).await {
+            HandlerResult::Reject(response) => return self.send(response).await,
+            HandlerResult::AcceptWith(response) => self.send(response).await?,
+            HandlerResult::Accept => {
+                let greeting = match self.config.get_greeting() {
+                    Some(greeting) => greeting.to_owned(),
+                    None if self.config.is_lmtp() => format!("LMTP {}", self.config.name()),
+                    None => format!("ESMTP {}", self.config.name()),
+                };
+                let greeting = format!("{} {}", self.config.get_hostname(), greeting);
+                self.send(MultilineServerResponse::new_with_code(
+                    ResponseCode::ServiceReady,
+                    Some(greeting),
+                    0,
+                ))
+                .await?;
+            }
+        }
+        loop {
+            let line = match tokio::time::timeout(
+                self.config.command_timeout,
+                self.read_command_line(self.read_limit()),
+            )
+            .await
+            {
+                Err(_) => {
+                    debug!(peer_addr = ?self.info.peer_addr, "Command timeout");
+                    let message =
+                        format!("{} Timeout; closing connection", self.config.get_hostname());
+                    return self
+                        .reply(
+                            ResponseCode::ServiceNotAvailable,
+                            EnhancedStatusCode::new(StatusClass::PersistentTransientFailure, 4, 2),
+                            message,
+                        )
+                        .await;
+                }
+                Ok(Ok(Some(line))) => line,
+                Ok(Ok(None)) => {
+                    debug!(peer_addr = ?self.info.peer_addr, "Client disconnected");
+                    return Ok(());
+                }
+                Ok(Err(SMTPError::CommandParse(error))) => {
+                    self.reply_parse_error(error).await?;
+                    continue;
+                }
+                Ok(Err(SMTPError::UTF8(_))) => {
+                    self.reply_parse_error(CommandParseError::InvalidCommand(
+                        "Invalid UTF-8".to_owned(),
+                    ))
+                    .await?;
+                    continue;
+                }
+                Ok(Err(error)) => return Err(error),
+            };
+            trace!(line = line.trim_end(), "Received command");
+            let max_length = if AuthCommand::can_handle(&line) {
+                self.read_limit()
+            } else {
+                self.config.max_line_length
+            };
+            let command = match ClientCommand::parse_with_limit(&line, max_length) {
+                Ok(command) => command,
+                Err(error) => {
+                    self.reply_parse_error(error).await?;
+                    continue;
+                }
+            };
+            if let ClientCommand::Bdat(data) = command {
+                if !self.chunking_available() {
+                    // The chunk size can not be trusted. Closing is the only way to stay in sync
+                    let DefaultResponse::Response(response) = self.default_response(&command)
+                    else {
+                        return Ok(());
+                    };
+                    return self.send(response).await;
+                }
+                // The chunk has to be read even if the command is rejected
+                self.receive_chunk(handler, data).await?;
+                continue;
+            }
+            if let Err(error) = self.session.check(&command) {
+                self.send(error.to_response()).await?;
+                continue;
+            }
+            if let ClientCommand::Auth(data) = command {
+                self.authenticate(data).await?;
+                continue;
+            }
+            let result = match &command {
+                ClientCommand::Ehlo(data)
+                | ClientCommand::Helo(data)
+                | ClientCommand::Lhlo(data) => handler.ehlo(&self.info, &self.session, data).await,
+                ClientCommand::MailFrom(data) => {
+                    match self.declared_size_too_large(&data.parameters) {
+                        Some(response) => HandlerResult::Reject(response),
+                        None => handler.mail_from(&self.info, &self.session, data).await,
+                    }
+                }
+                ClientCommand::RcptTo(data) => {
+                    handler.rcpt_to(&self.info, &self.session, data).await
+                }
+                ClientCommand::StartTls if !self.config.is_tls_available() => {
+                    HandlerResult::reject(
+                        ResponseCode::CommandNotImplemented,
+                        EnhancedStatusCode::INVALID_COMMAND,
+                        "STARTTLS not available",
+                    )
+                }
+                _ => HandlerResult::Accept,
+            };
+            let custom_response = match result {
+                HandlerResult::Reject(response) => {
+                    self.send(response).await?;
+                    continue;
+                }
+                HandlerResult::AcceptWith(response) => Some(response),
+                HandlerResult::Accept => None,
+            };
+            let is_quit = matches!(command, ClientCommand::Quit);
+            let is_data = matches!(command, ClientCommand::Data);
+            #[cfg(feature = "rustls")]
+            let is_start_tls = matches!(command, ClientCommand::StartTls);
+            let default_response = self.default_response(&command);
+            if let Err(error) = self.session.accept(command) {
+                self.send(error.to_response()).await?;
+                continue;
+            }
+            match (custom_response, default_response) {
+                (Some(response), _) => self.send(response).await?,
+                (None, DefaultResponse::Ehlo(response)) => {
+                    EhloCommand::server_send(response, &mut self).await?;
+                }
+                (None, DefaultResponse::Helo(response)) => {
+                    HeloCommand::server_send(response, &mut self).await?;
+                }
+                (None, DefaultResponse::Lhlo(response)) => {
+                    LhloCommand::server_send(response, &mut self).await?;
+                }
+                (None, DefaultResponse::Response(response)) => self.send(response).await?,
+            }
+            if is_quit {
+                return Ok(());
+            }
+            if is_data {
+                self.receive_message(handler).await?;
+            }
+            #[cfg(feature = "rustls")]
+            if is_start_tls {
+                self.upgrade().await?;
+            }
+        }
+    }
+    async fn receive_message<H: SessionHandler + ?Sized>(
+        &mut self,
+        handler: &H,
+    ) -> crate::Result<()> {
+        let Some(envelope) = self.session.finish_data() else {
+            return Ok(());
+        };
+        let mut body = MessageBody::new(
+            &mut self.reader,
+            self.config.max_message_size,
+            self.config.data_timeout,
+        );
+        let results = if self.config.is_lmtp() {
+            handler.lmtp_message(&self.info, &envelope, &mut body).await
+        } else {
+            vec![handler.message(&self.info, &envelope, &mut body).await]
+        };
+        body.discard().await?;
+        debug!(
+            peer_addr = ?self.info.peer_addr,
+            size = body.size(),
+            "Received message"
+        );
+        if body.is_too_large() {
+            let max_size = self.config.max_message_size.unwrap_or_default();
+            return self
+                .reply_too_large(max_size, self.reply_count(&envelope))
+                .await;
+        }
+        self.reply_to_message(&envelope, results).await
+    }
+    /// Reads a BDAT chunk. After the last chunk the message is passed to the handler
+    ///
+    /// [RFC 3030 Section 2](https://datatracker.ietf.org/doc/html/rfc3030#section-2)
+    async fn receive_chunk<H: SessionHandler + ?Sized>(
+        &mut self,
+        handler: &H,
+        data: BdatCommandData,
+    ) -> crate::Result<()> {
+        let command = ClientCommand::Bdat(data);
+        let rejected = self.session.check(&command).err();
+        let limit = self
+            .config
+            .max_message_size
+            .unwrap_or(DEFAULT_MAX_CHUNKED_MESSAGE_SIZE);
+        let max_size = match self.chunks.len().checked_add(data.size) {
+            Some(size) if size <= limit => None,
+            _ => Some(limit),
+        };
+        let keep = rejected.is_none() && max_size.is_none();
+        self.read_chunk(data.size, keep).await?;
+        if let Some(error) = rejected {
+            return self.send(error.to_response()).await;
+        }
+        if let Some(max_size) = max_size {
+            let replies = match self.session.envelope() {
+                Some(envelope) if data.last => self.reply_count(envelope),
+                _ => 1,
+            };
+            // The transaction failed. The client has to start over with MAIL
+            self.chunks.clear();
+            self.session.reset();
+            return self.reply_too_large(max_size, replies).await;
+        }
+        if let Err(error) = self.session.accept(command) {
+            return self.send(error.to_response()).await;
+        }
+        if !data.last {
+            return self
+                .reply(
+                    ResponseCode::Ok,
+                    EnhancedStatusCode::SUCCESS,
+                    format!("{} octets received", data.size),
+                )
+                .await;
+        }
+        let Some(envelope) = self.session.finish_data() else {
+            return Ok(());
+        };
+        let mut body = MessageBody::from_chunks(std::mem::take(&mut self.chunks).freeze());
+        let results = if self.config.is_lmtp() {
+            handler.lmtp_message(&self.info, &envelope, &mut body).await
+        } else {
+            vec![handler.message(&self.info, &envelope, &mut body).await]
+        };
+        debug!(
+            peer_addr = ?self.info.peer_addr,
+            size = body.size(),
+            "Received message"
+        );
+        self.reply_to_message(&envelope, results).await
+    }
+    /// Reads a chunk and appends it to the message if `keep` is true. The buffer only grows as the data arrives
+    async fn read_chunk(&mut self, size: usize, keep: bool) -> crate::Result<()> {
+        let mut remaining = size;
+        while remaining > 0 {
+            let buffer = tokio::time::timeout(self.config.data_timeout, self.reader.fill_buf())
+                .await
+                .map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "Timed out waiting for the chunk data",
+                    )
+                })??;
+            if buffer.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Connection closed before the end of the chunk",
+                )
+                .into());
+            }
+            let read = buffer.len().min(remaining);
+            if keep {
+                self.chunks.extend_from_slice(&buffer[..read]);
+            }
+            self.reader.consume_unpin(read);
+            remaining -= read;
+        }
+        Ok(())
+    }
+    async fn reply_too_large(&mut self, max_size: usize, replies: usize) -> crate::Result<()> {
+        for _ in 0..replies {
+            self.reply(
+                ResponseCode::ExceededStorageAllocation,
+                EnhancedStatusCode::new(StatusClass::PermanentFailure, 3, 4),
+                SMTPError::MessageTooLarge(max_size).to_string(),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+    /// The number of replies to the end of the message. LMTP replies once for every recipient
+    fn reply_count(&self, envelope: &Envelope) -> usize {
+        if self.config.is_lmtp() {
+            envelope.recipients.len()
+        } else {
+            1
+        }
+    }
+    /// Sends the replies to the end of the message
+    ///
+    /// [RFC 2033 Section 4.2](https://datatracker.ietf.org/doc/html/rfc2033#section-4.2)
+    async fn reply_to_message(
+        &mut self,
+        envelope: &Envelope,
+        results: Vec<HandlerResult>,
+    ) -> crate::Result<()> {
+        let recipients: Vec<Option<&ForwardPath>> = if self.config.is_lmtp() {
+            envelope.forward_paths().map(Some).collect()
+        } else {
+            vec![None]
+        };
+        let mut results = results.into_iter();
+        for recipient in recipients {
+            let result = results
+                .next()
+                .unwrap_or_else(|| HandlerResult::local_error("No result for the recipient"));
+            match (result, recipient) {
+                (HandlerResult::Accept, Some(recipient)) => {
+                    self.reply(
+                        ResponseCode::Ok,
+                        EnhancedStatusCode::MESSAGE_ACCEPTED,
+                        format!("<{}> Message accepted for delivery", recipient),
+                    )
+                    .await?
+                }
+                (HandlerResult::Accept, None) => {
+                    self.reply(
+                        ResponseCode::Ok,
+                        EnhancedStatusCode::MESSAGE_ACCEPTED,
+                        "Message accepted for delivery",
+                    )
+                    .await?
+                }
+                (HandlerResult::AcceptWith(response) | HandlerResult::Reject(response), _) => {
+                    self.send(response).await?
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Performs the TLS handshake after the `220` reply to STARTTLS
+    #[cfg(feature = "rustls")]
+    async fn upgrade(&mut self) -> crate::Result<()> {
+        let Some(config) = self.config.tls.clone() else {
+            return Err(SMTPError::StartTls("No TLS config"));
+        };
+        // RFC 3207 Section 5. Anything sent with the STARTTLS command could have been injected by an attacker
+        if !self.reader.buffer().is_empty() {
+            return Err(SMTPError::StartTls(
+                "The client sent data after the STARTTLS command",
+            ));
+        }
+        self.reader
+            .get_mut()
+            .get_mut()
+            .upgrade_server(config)
+            .await?;
+        self.session.start_tls();
+        debug!(peer_addr = ?self.info.peer_addr, "TLS handshake finished");
+        Ok(())
+    }
+    /// If CHUNKING is advertised
+    fn chunking_available(&self) -> bool {
+        self.config
+            .supported_extensions()
+            .contains(&SMTPServerExtension::Chunking)
+    }
+    /// If AUTH can be used in the current state of the connection
+    fn auth_available(&self) -> bool {
+        self.authenticator.is_some() && (self.session.is_tls() || !self.config.auth_requires_tls)
+    }
+    /// The mechanisms of the authenticator. The `-PLUS` mechanisms are only offered over TLS
+    fn auth_mechanisms(&self) -> Vec<AuthMechanism> {
+        let Some(authenticator) = &self.authenticator else {
+            return Vec::new();
+        };
+        let mut mechanisms = authenticator.mechanisms();
+        if !self.session.is_tls() {
+            mechanisms.retain(|mechanism| !mechanism.is_channel_binding());
+        }
+        mechanisms
+    }
+    /// Runs the `334` exchange and replies `235` or the error
+    async fn authenticate(&mut self, data: AuthCommandData) -> crate::Result<()> {
+        let Some(authenticator) = self.authenticator.clone() else {
+            return self
+                .reply(
+                    ResponseCode::CommandNotImplemented,
+                    EnhancedStatusCode::INVALID_COMMAND,
+                    "AUTH not available",
+                )
+                .await;
+        };
+        if !self.auth_available() {
+            return self.send(AuthError::EncryptionRequired.to_response()).await;
+        }
+        if !self.auth_mechanisms().contains(&data.mechanism) {
+            return self
+                .send(AuthError::UnsupportedMechanism(data.mechanism).to_response())
+                .await;
+        }
+        let mut sasl = match SaslServer::new(data.mechanism, self.config.get_hostname()) {
+            Ok(sasl) => {
+                sasl.with_channel_binding(self.reader.get_ref().get_ref().channel_binding())
+            }
+            Err(error) => return self.send(error.to_response()).await,
+        };
+        let mut response = data.initial_response;
+        let result = loop {
+            let challenge = match sasl.step(authenticator.as_ref(), response.as_deref()).await {
+                Ok(ServerStep::Success(identity)) => break Ok(identity),
+                Ok(ServerStep::Challenge(challenge)) => challenge,
+                Err(error) => break Err(error),
+            };
+            self.send(MultilineServerResponse::new_with_code(
+                ResponseCode::ServerChallenge,
+                Some(encode_sasl(&challenge)),
+                0,
+            ))
+            .await?;
+            let line = tokio::time::timeout(
+                self.config.command_timeout,
+                self.read_command_line(MAX_AUTH_LINE_LENGTH),
+            )
+            .await
+            .map_err(|_| io::Error::from(io::ErrorKind::TimedOut))?;
+            let line = match line {
+                Ok(Some(line)) => line,
+                Ok(None) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+                Err(SMTPError::CommandParse(_) | SMTPError::UTF8(_)) => {
+                    break Err(AuthError::Malformed("Invalid response line"));
+                }
+                Err(error) => return Err(error),
+            };
+            match decode_sasl(&line) {
+                Ok(decoded) => response = Some(decoded),
+                Err(error) => break Err(error),
+            }
+        };
+        match result {
+            Ok(identity) => {
+                debug!(peer_addr = ?self.info.peer_addr, identity, "Authenticated");
+                self.session.set_authenticated(identity);
+                self.reply(
+                    ResponseCode::AuthenticationSucceeded,
+                    EnhancedStatusCode::new(StatusClass::Success, 7, 0),
+                    "Authentication successful",
+                )
+                .await
+            }
+            Err(error) => {
+                debug!(peer_addr = ?self.info.peer_addr, %error, "Authentication failed");
+                self.send(error.to_response()).await
+            }
+        }
+    }
+    /// The longest line read before the command is known
+    fn read_limit(&self) -> usize {
+        if self.authenticator.is_some() {
+            self.config.max_line_length.max(MAX_AUTH_LINE_LENGTH)
+        } else {
+            self.config.max_line_length
+        }
+    }
+    /// Rejects MAIL FROM if the `SIZE=` parameter is larger than the max message size
+    fn declared_size_too_large(
+        &self,
+        parameters: &[MailParameter],
+    ) -> Option<MultilineServerResponse> {
+        let max_size = self.config.max_message_size?;
+        parameters.iter().find_map(|parameter| match parameter {
+            MailParameter::Size(size) if *size > max_size => {
+                Some(MultilineServerResponse::new(vec![
+                    ServerResponseLine::new_with_enhanced_status_code(
+                        ResponseCode::ExceededStorageAllocation,
+                        EnhancedStatusCode::new(StatusClass::PermanentFailure, 3, 4),
+                        Some(SMTPError::MessageTooLarge(max_size).to_string()),
+                    ),
+                ]))
+            }
+            _ => None,
+        })
+    }
+    fn default_response(&self, command: &ClientCommand) -> DefaultResponse {
+        let hostname = self.config.get_hostname();
+        let (code, enhanced_status_code, message) = match command {
+            ClientCommand::Ehlo(data) | ClientCommand::Helo(data) | ClientCommand::Lhlo(data) => {
+                let response = EhloResponse::Success {
+                    hello: (
+                        ResponseCode::Ok,
+                        format!("{} Hello {}", hostname, data.client_hostname),
+                    ),
+                    extensions: self
+                        .config
+                        .supported_extensions()
+                        .iter()
+                        .filter(|extension| {
+                            !(self.session.is_tls()
+                                && matches!(extension, SMTPServerExtension::StartTLS))
+                        })
+                        .cloned()
+                        .chain(
+                            self.auth_available()
+                                .then(|| SMTPServerExtension::Auth(self.auth_mechanisms())),
+                        )
+                        .collect(),
+                };
+                return match command {
+                    ClientCommand::Ehlo(_) => DefaultResponse::Ehlo(response),
+                    ClientCommand::Lhlo(_) => DefaultResponse::Lhlo(response),
+                    _ => DefaultResponse::Helo(response),
+                };
+            }
+            ClientCommand::MailFrom(_) => (
+                ResponseCode::Ok,
+                EnhancedStatusCode::SENDER_OK,
+                "OK".to_owned(),
+            ),
+            ClientCommand::RcptTo(_) => (
+                ResponseCode::Ok,
+                EnhancedStatusCode::DESTINATION_VALID,
+                "OK".to_owned(),
+            ),
+            ClientCommand::Data => {
+                return DefaultResponse::Response(MultilineServerResponse::new_with_code(
+                    ResponseCode::StartMailInput,
+                    Some("Start mail input; end with <CRLF>.<CRLF>".to_owned()),
+                    0,
+                ));
+            }
+            ClientCommand::Rset | ClientCommand::Noop(_) => (
+                ResponseCode::Ok,
+                EnhancedStatusCode::SUCCESS,
+                "OK".to_owned(),
+            ),
+            ClientCommand::Quit => (
+                ResponseCode::ServiceClosing,
+                EnhancedStatusCode::SUCCESS,
+                format!("{} Service closing transmission channel", hostname),
+            ),
+            ClientCommand::Vrfy(_) => (
+                ResponseCode::CannotVerifyUser,
+                EnhancedStatusCode::new(StatusClass::Success, 5, 0),
+                "Cannot VRFY user, but will accept message and attempt delivery".to_owned(),
+            ),
+            ClientCommand::Expn(_) => (
+                ResponseCode::CommandNotImplemented,
+                EnhancedStatusCode::new(StatusClass::PermanentFailure, 5, 1),
+                "EXPN not supported".to_owned(),
+            ),
+            ClientCommand::StartTls => (
+                ResponseCode::ServiceReady,
+                EnhancedStatusCode::SUCCESS,
+                "Ready to start TLS".to_owned(),
+            ),
+            // Handled before the default response
+            ClientCommand::Auth(_) => (
+                ResponseCode::CommandNotImplemented,
+                EnhancedStatusCode::INVALID_COMMAND,
+                "AUTH not available".to_owned(),
+            ),
+            // Only used when CHUNKING is not available
+            ClientCommand::Bdat(_) => (
+                ResponseCode::CommandNotImplemented,
+                EnhancedStatusCode::INVALID_COMMAND,
+                "BDAT not available".to_owned(),
+            ),
+            ClientCommand::Help(_) => (
+                ResponseCode::HelpMessage,
+                EnhancedStatusCode::SUCCESS,
+                "See https://datatracker.ietf.org/doc/html/rfc5321".to_owned(),
+            ),
+        };
+        DefaultResponse::Response(MultilineServerResponse::new(vec![
+            ServerResponseLine::new_with_enhanced_status_code(
+                code,
+                enhanced_status_code,
+                Some(message),
+            ),
+        ]))
+    }
+    async fn reply_parse_error(&mut self, error: CommandParseError) -> crate::Result<()> {
+        let enhanced_status_code = match error {
+            CommandParseError::UnknownCommand(_) | CommandParseError::LineTooLong(_) => {
+                EnhancedStatusCode::INVALID_COMMAND
+            }
+            _ => EnhancedStatusCode::SYNTAX_ERROR,
+        };
+        self.reply(
+            ResponseCode::from(error.error_code()),
+            enhanced_status_code,
+            error.to_string(),
+        )
+        .await
+    }
+    async fn reply(
+        &mut self,
+        code: ResponseCode,
+        enhanced_status_code: EnhancedStatusCode,
+        message: impl Into<String>,
+    ) -> crate::Result<()> {
+        self.send(MultilineServerResponse::new(vec![
+            ServerResponseLine::new_with_enhanced_status_code(
+                code,
+                enhanced_status_code,
+                Some(message.into()),
+            ),
+        ]))
+        .await
+    }
+    /// Sends the response. Enhanced Status Codes are removed if the client did not send EHLO
+    async fn send(&mut self, mut response: MultilineServerResponse) -> crate::Result<()> {
+        if !self.session.is_extended() {
+            for line in response.0.iter_mut() {
+                line.set_enhanced_status_code(None);
+            }
+        }
+        self.write_bytes(response.to_bytes()).await
+    }
+    async fn write_bytes(&mut self, bytes: Bytes) -> crate::Result<()> {
+        let stream = self.reader.get_mut();
+        stream.write_all(&bytes).await?;
+        stream.flush().await?;
+        Ok(())
+    }
+    /// Reads the next line. Lines longer than the max line length are discarded
+    ///
+    /// Returns None if the client closed the connection
+    async fn read_command_line(&mut self, max_length: usize) -> crate::Result<Option<String>> {
+        let mut line = Vec::new();
+        let mut length = 0;
+        loop {
+            let buffer = self.reader.fill_buf().await?;
+            if buffer.is_empty() {
+                if length == 0 {
+                    return Ok(None);
+                }
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+            let (chunk, found_end) = match buffer.iter().position(|byte| *byte == b'\n') {
+                Some(end) => (&buffer[..=end], true),
+                None => (buffer, false),
+            };
+            let chunk_length = chunk.len();
+            length += chunk_length;
+            if length <= max_length {
+                line.extend_from_slice(chunk);
+            }
+            self.reader.consume_unpin(chunk_length);
+            if found_end {
+                break;
+            }
+        }
+        if length > max_length {
+            return Err(CommandParseError::LineTooLong(length).into());
+        }
+        Ok(Some(String::from_utf8(line)?))
+    }
+}
+enum DefaultResponse {
+    Ehlo(EhloResponse),
+    Helo(EhloResponse),
+    Lhlo(EhloResponse),
+    Response(MultilineServerResponse),
+}
+impl<S> Debug for ServerConnection<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ServerConnection")
+            .field("info", &self.info)
+            .field("session", &self.session)
+            .field("auth", &self.authenticator.is_some())
+            .finish()
+    }
+}
+impl<S> SMTPConnection for ServerConnection<S> {
+    type Server = ServerConfig;
+
+    fn get_server(&self) -> &Self::Server {
+        &self.config
+    }
+
+    fn get_state(&self) -> &SMTPConnectionState {
+        self.session.state()
+    }
+
+    fn get_end_of_multiline_command(&self) -> &str {
+        END_OF_MULTILINE_COMMAND
+    }
+}
+impl<'a, S> AsyncSMTPConnection<'a> for ServerConnection<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'a,
+{
+    type ReadLineFuture = crate::BoxSendFuture<'a, crate::Result<String>>;
+    type WriteFuture = crate::BoxSendFuture<'a, crate::Result<()>>;
+    type ReadTilEndFuture = crate::BoxSendFuture<'a, crate::Result<String>>;
+
+    fn read_line(&'a mut self) -> Self::ReadLineFuture {
+        Box::pin(async move {
+            self.read_command_line(self.config.max_line_length)
+                .await?
+                .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof).into())
+        })
+    }
+
+    fn write(&'a mut self, command: Bytes) -> Self::WriteFuture {
+        Box::pin(self.write_bytes(command))
+    }
+
+    /// Reads the message body until the end of the DATA section
+    ///
+    /// 8BITMIME and BINARYMIME bodies are not required to be UTF-8. Invalid sequences are replaced with
+    /// `U+FFFD` rather than failing, use [`MessageBody`] to get the raw bytes
+    fn read_til_end(&'a mut self) -> Self::ReadTilEndFuture {
+        Box::pin(async move {
+            let mut body = MessageBody::new(
+                &mut self.reader,
+                self.config.max_message_size,
+                self.config.data_timeout,
+            );
+            let message = body.bytes().await?;
+            Ok(String::from_utf8_lossy(&message).into_owned())
+        })
+    }
+}
+#[cfg(feature = "rustls")]
+impl<'a, S> AsyncStartTlsConnection<'a> for ServerConnection<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'a,
+{
+    type UpgradeFuture = crate::BoxSendFuture<'a, crate::Result<()>>;
+
+    fn is_tls(&self) -> bool {
+        self.session.is_tls()
+    }
+
+    fn upgrade_to_tls(&'a mut self) -> Self::UpgradeFuture {
+        Box::pin(self.upgrade())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::{io, time::Duration};
+
+    use futures::{io::BufReader, StreamExt};
+    use tokio::io::AsyncWriteExt;
+    use tokio_util::compat::TokioAsyncReadCompatExt;
+
+    use super::MessageBody;
+    use crate::error::SMTPError;
+
+    #[tokio::test]
+    async fn test_message_body_timeout_rearms() {
+        let (mut client, server) = tokio::io::duplex(64);
+        let mut reader = BufReader::new(server.compat());
+        let timeout = Duration::from_millis(50);
+        let mut body = MessageBody::new(&mut reader, None, timeout);
+
+        let error = body.next().await.unwrap().unwrap_err();
+        assert!(matches!(error, SMTPError::IO(error) if error.kind() == io::ErrorKind::TimedOut));
+        // The next poll waits for a fresh timeout instead of failing at once
+        assert!(tokio::time::timeout(timeout / 2, body.next())
+            .await
+            .is_err());
+
+        client.write_all(b"Hello\r\n.\r\n").await.unwrap();
+        assert_eq!(body.bytes().await.unwrap().as_ref(), b"Hello\r\n");
+    }
+}
@@ -0,0 +1,398 @@
+/*!
+# Tokio SMTP Server
+
+A ready to use SMTP server. Requires the `server` feature.
+
+The server runs the protocol and calls a [SessionHandler] for every decision.
+
+```rust,no_run
+use smtp_lib::server::{HandlerResult, MessageBody, Server, ServerConfig, SessionHandler};
+use smtp_lib::{server::ConnectionInfo, session::Envelope};
+
+struct PrintHandler;
+#[async_trait::async_trait]
+impl SessionHandler for PrintHandler {
+    async fn message(
+        &self,
+        _: &ConnectionInfo,
+        envelope: &Envelope,
+        body: &mut MessageBody<'_>,
+    ) -> HandlerResult {
+        match body.bytes().await {
+            Ok(message) => {
+                println!("{:?} sent {} bytes", envelope.reverse_path(), message.len());
+                HandlerResult::Accept
+            }
+            Err(_) => HandlerResult::local_error("Unable to read the message"),
+        }
+    }
+}
+# async fn run() -> std::io::Result<()> {
+let config = ServerConfig::new("mail.example.com").with_max_message_size(10 * 1024 * 1024);
+Server::new(config, PrintHandler).listen("0.0.0.0:25").await
+# }
+```
+ */
+mod connection;
+
+use std::{fmt::Debug, net::SocketAddr, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+pub use connection::{MessageBody, ServerConnection};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, ToSocketAddrs},
+};
+use tracing::{debug, warn};
+
+use crate::{
+    auth::Authenticator,
+    commands::{
+        client_command::MAX_COMMAND_LINE_LENGTH,
+        command_impls::{EhloCommandData, MailFromCommandData, RcptToCommandData},
+    },
+    enhanced_status_code::{EnhancedStatusCode, StatusClass},
+    server_response::{MultilineServerResponse, ResponseCode, ServerResponseLine},
+    session::{Envelope, Session},
+    smtp_server::{SMTPServer, SMTPServerExtension},
+};
+
+/// How long [Server::serve] waits before accepting again after an error
+const ACCEPT_ERROR_DELAY: Duration = Duration::from_millis(100);
+/// The largest message accepted with BDAT when [ServerConfig::with_max_message_size] is not set
+pub const DEFAULT_MAX_CHUNKED_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+/// The configuration of a [Server]
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    hostname: String,
+    name: String,
+    greeting: Option<String>,
+    extensions: Vec<SMTPServerExtension>,
+    max_message_size: Option<usize>,
+    max_recipients: Option<usize>,
+    max_line_length: usize,
+    command_timeout: Duration,
+    data_timeout: Duration,
+    auth_requires_tls: bool,
+    lmtp: bool,
+    #[cfg(feature = "rustls")]
+    tls: Option<Arc<tokio_rustls::rustls::ServerConfig>>,
+}
+impl ServerConfig {
+    pub fn new(hostname: impl Into<String>) -> Self {
+        Self {
+            hostname: hostname.into(),
+            name: "smtp_lib".to_owned(),
+            greeting: None,
+            extensions: vec![SMTPServerExtension::EnhancedStatusCodes],
+            max_message_size: None,
+            max_recipients: None,
+            max_line_length: MAX_COMMAND_LINE_LENGTH,
+            // RFC 5321 Section 4.5.3.2.7
+            command_timeout: Duration::from_secs(5 * 60),
+            // RFC 5321 Section 4.5.3.2.6
+            data_timeout: Duration::from_secs(3 * 60),
+            auth_requires_tls: true,
+            lmtp: false,
+            #[cfg(feature = "rustls")]
+            tls: None,
+        }
+    }
+    /// The name of the server software. Used in the default greeting
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+    /// The text after the hostname in the `220` greeting
+    pub fn with_greeting(mut self, greeting: impl Into<String>) -> Self {
+        self.greeting = Some(greeting.into());
+        self
+    }
+    /// Advertises an extension in the EHLO response
+    pub fn with_extension(mut self, extension: SMTPServerExtension) -> Self {
+        self.extensions.push(extension);
+        self
+    }
+    /// Rejects messages larger than `max_message_size` and advertises `SIZE`
+    pub fn with_max_message_size(mut self, max_message_size: usize) -> Self {
+        self.extensions
+            .retain(|extension| !matches!(extension, SMTPServerExtension::Size(_)));
+        self.extensions
+            .push(SMTPServerExtension::Size(Some(max_message_size)));
+        self.max_message_size = Some(max_message_size);
+        self
+    }
+    /// See [Session::with_max_recipients]
+    pub fn with_max_recipients(mut self, max_recipients: usize) -> Self {
+        self.max_recipients = Some(max_recipients);
+        self
+    }
+    /// The longest command line accepted. Including the CRLF
+    pub fn with_max_line_length(mut self, max_line_length: usize) -> Self {
+        self.max_line_length = max_line_length;
+        self
+    }
+    /// How long to wait for the next command before closing the connection
+    pub fn with_command_timeout(mut self, command_timeout: Duration) -> Self {
+        self.command_timeout = command_timeout;
+        self
+    }
+    /// How long to wait for more of a message body sent with DATA or BDAT before closing the connection
+    pub fn with_data_timeout(mut self, data_timeout: Duration) -> Self {
+        self.data_timeout = data_timeout;
+        self
+    }
+    /// If AUTH is only offered after STARTTLS. Defaults to true
+    pub fn with_auth_requires_tls(mut self, auth_requires_tls: bool) -> Self {
+        self.auth_requires_tls = auth_requires_tls;
+        self
+    }
+    /// Speaks LMTP instead of SMTP. Clients greet with LHLO and every recipient gets its own reply to the message.
+    ///
+    /// See [SessionHandler::lmtp_message]. [RFC 2033](https://datatracker.ietf.org/doc/html/rfc2033)
+    pub fn with_lmtp(mut self) -> Self {
+        self.lmtp = true;
+        self
+    }
+    pub fn is_lmtp(&self) -> bool {
+        self.lmtp
+    }
+    /// Enables STARTTLS and advertises it
+    #[cfg(feature = "rustls")]
+    pub fn with_tls(mut self, config: Arc<tokio_rustls::rustls::ServerConfig>) -> Self {
+        if !self.extensions.contains(&SMTPServerExtension::StartTLS) {
+            self.extensions.push(SMTPServerExtension::StartTLS);
+        }
+        self.tls = Some(config);
+        self
+    }
+    /// If STARTTLS can be used
+    pub fn is_tls_available(&self) -> bool {
+        #[cfg(feature = "rustls")]
+        return self.tls.is_some();
+        #[cfg(not(feature = "rustls"))]
+        return false;
+    }
+    pub fn max_message_size(&self) -> Option<usize> {
+        self.max_message_size
+    }
+    pub(crate) fn new_session(&self) -> Session {
+        let session = match self.max_recipients {
+            Some(max_recipients) => Session::new().with_max_recipients(max_recipients),
+            None => Session::new(),
+        };
+        if self.lmtp {
+            session.with_lmtp()
+        } else {
+            session
+        }
+    }
+}
+impl SMTPServer for ServerConfig {
+    fn get_hostname(&self) -> &str {
+        &self.hostname
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_greeting(&self) -> Option<&str> {
+        self.greeting.as_deref()
+    }
+
+    fn supported_extensions(&self) -> &Vec<SMTPServerExtension> {
+        &self.extensions
+    }
+}
+/// Information about the client connection
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConnectionInfo {
+    pub peer_addr: Option<SocketAddr>,
+    pub local_addr: Option<SocketAddr>,
+}
+/// The decision of a [SessionHandler]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HandlerResult {
+    /// Accept with the default reply
+    Accept,
+    /// Accept with a custom reply. The reply should be a positive completion
+    AcceptWith(MultilineServerResponse),
+    /// Reject with the given reply
+    Reject(MultilineServerResponse),
+}
+impl HandlerResult {
+    /// Rejects with a single line reply
+    pub fn reject(
+        code: ResponseCode,
+        enhanced_status_code: EnhancedStatusCode,
+        message: impl Into<String>,
+    ) -> Self {
+        HandlerResult::Reject(MultilineServerResponse::new(vec![
+            ServerResponseLine::new_with_enhanced_status_code(
+                code,
+                enhanced_status_code,
+                Some(message.into()),
+            ),
+        ]))
+    }
+    /// `451 4.3.0` Requested action aborted: local error in processing
+    pub fn local_error(message: impl Into<String>) -> Self {
+        Self::reject(
+            ResponseCode::LocalError,
+            EnhancedStatusCode::new(StatusClass::PersistentTransientFailure, 3, 0),
+            message,
+        )
+    }
+    /// If the command was accepted
+    pub fn is_accept(&self) -> bool {
+        !matches!(self, HandlerResult::Reject(_))
+    }
+}
+/// Decides what the server does. Every method except [SessionHandler::message] accepts by default
+#[async_trait]
+pub trait SessionHandler: Send + Sync + 'static {
+    /// A client connected. Rejecting sends the reply and closes the connection
+    async fn connect(&self, _info: &ConnectionInfo) -> HandlerResult {
+        HandlerResult::Accept
+    }
+    /// The client sent EHLO, HELO or LHLO
+    async fn ehlo(
+        &self,
+        _info: &ConnectionInfo,
+        _session: &Session,
+        _data: &EhloCommandData,
+    ) -> HandlerResult {
+        HandlerResult::Accept
+    }
+    /// The client started a mail transaction
+    async fn mail_from(
+        &self,
+        _info: &ConnectionInfo,
+        _session: &Session,
+        _data: &MailFromCommandData,
+    ) -> HandlerResult {
+        HandlerResult::Accept
+    }
+    /// The client added a recipient
+    async fn rcpt_to(
+        &self,
+        _info: &ConnectionInfo,
+        _session: &Session,
+        _data: &RcptToCommandData,
+    ) -> HandlerResult {
+        HandlerResult::Accept
+    }
+    /// The client is sending a message.
+    ///
+    /// With DATA the body is read from the connection. With BDAT every chunk was already received.
+    /// The body does not need to be read completely. The server will discard anything left
+    async fn message(
+        &self,
+        info: &ConnectionInfo,
+        envelope: &Envelope,
+        body: &mut MessageBody<'_>,
+    ) -> HandlerResult;
+    /// Used instead of [SessionHandler::message] when the server speaks LMTP.
+    ///
+    /// Returns one result for every recipient in the order of [Envelope::recipients].
+    /// Defaults to the result of [SessionHandler::message] for every recipient
+    async fn lmtp_message(
+        &self,
+        info: &ConnectionInfo,
+        envelope: &Envelope,
+        body: &mut MessageBody<'_>,
+    ) -> Vec<HandlerResult> {
+        let result = self.message(info, envelope, body).await;
+        vec![result; envelope.recipients.len()]
+    }
+}
+
+/// A tokio SMTP server
+pub struct Server<H> {
+    config: Arc<ServerConfig>,
+    handler: Arc<H>,
+    authenticator: Option<Arc<dyn Authenticator>>,
+}
+impl<H> Clone for Server<H> {
+    fn clone(&self) -> Self {
+        Self {
+            config: self.config.clone(),
+            handler: self.handler.clone(),
+            authenticator: self.authenticator.clone(),
+        }
+    }
+}
+impl<H: SessionHandler> Server<H> {
+    pub fn new(config: ServerConfig, handler: H) -> Self {
+        Self {
+            config: Arc::new(config),
+            handler: Arc::new(handler),
+            authenticator: None,
+        }
+    }
+    /// Enables AUTH. The mechanisms are advertised once [ServerConfig::with_auth_requires_tls] allows it
+    pub fn with_authenticator(mut self, authenticator: impl Authenticator) -> Self {
+        self.authenticator = Some(Arc::new(authenticator));
+        self
+    }
+    pub fn config(&self) -> &ServerConfig {
+        &self.config
+    }
+    /// Binds to the address and serves connections
+    ///
+    /// Returns the error from binding the listener. Once bound, this runs until the future is dropped. See [`Server::serve`]
+    pub async fn listen(self, addr: impl ToSocketAddrs) -> std::io::Result<()> {
+        let listener = TcpListener::bind(addr).await?;
+        self.serve(listener).await
+    }
+    /// Serves connections from the listener. Each connection is handled in its own task
+    ///
+    /// Never returns on its own; drop the future to stop serving.
+    /// Errors accepting a connection, such as running out of file descriptors, are logged and the server keeps running.
+    /// Errors from individual connections are logged and do not affect other connections
+    pub async fn serve(self, listener: TcpListener) -> std::io::Result<()> {
+        loop {
+            let (stream, peer_addr) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(error) => {
+                    warn!(%error, "Failed to accept a connection");
+                    // Most accept errors persist for a moment. Avoids spinning on them
+                    tokio::time::sleep(ACCEPT_ERROR_DELAY).await;
+                    continue;
+                }
+            };
+            let info = ConnectionInfo {
+                peer_addr: Some(peer_addr),
+                local_addr: stream.local_addr().ok(),
+            };
+            let server = self.clone();
+            tokio::spawn(async move {
+                debug!(?peer_addr, "Accepted connection");
+                if let Err(error) = server.handle_connection(stream, info).await {
+                    warn!(?peer_addr, %error, "Connection failed");
+                }
+            });
+        }
+    }
+    /// Runs the SMTP protocol on an already established stream
+    pub async fn handle_connection<S>(&self, stream: S, info: ConnectionInfo) -> crate::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+    {
+        let mut connection = ServerConnection::new(stream, self.config.clone(), info);
+        if let Some(authenticator) = &self.authenticator {
+            connection = connection.with_authenticator(authenticator.clone());
+        }
+        connection.run(self.handler.as_ref()).await
+    }
+}
+impl<H> Debug for Server<H> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Server")
+            .field("config", &self.config)
+            .field("auth", &self.authenticator.is_some())
+            .finish()
+    }
+}
@@ -3,50 +3,255 @@ use std::{
     str::FromStr,
 };
 
-use bytes::{BufMut, Bytes, BytesMut};
+use bytes::{Bytes, BytesMut};
 
-use crate::statement::{
-    MultiLineStatement, SingleLineStatement, Statement, StatementParseError, StatementWriteError,
-    StatementWriter,
+use crate::{
+    enhanced_status_code::EnhancedStatusCode,
+    statement::{
+        MultiLineStatement, SingleLineStatement, Statement, StatementParseError,
+        StatementWriteError, StatementWriter,
+    },
 };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The category of a reply code. Determined by the first digit of the code.
+///
+/// [RFC 5321 Section 4.2.1](https://datatracker.ietf.org/doc/html/rfc5321#section-4.2.1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::Display, strum::EnumIs)]
+pub enum ResponseCategory {
+    /// `2yz` The requested action has been successfully completed.
+    PositiveCompletion,
+    /// `3yz` The command has been accepted, but the server is waiting for more information.
+    PositiveIntermediate,
+    /// `4yz` The command was not accepted, but the error condition is temporary.
+    TransientNegative,
+    /// `5yz` The command was not accepted and the error condition is permanent.
+    PermanentNegative,
+}
+impl ResponseCategory {
+    /// Gets the category from the first digit of a reply code.
+    ///
+    /// Returns None if the code is outside of 2xx-5xx
+    pub fn from_code(code: u16) -> Option<Self> {
+        match code / 100 {
+            2 => Some(Self::PositiveCompletion),
+            3 => Some(Self::PositiveIntermediate),
+            4 => Some(Self::TransientNegative),
+            5 => Some(Self::PermanentNegative),
+            _ => None,
+        }
+    }
+}
+/// SMTP Reply Codes as defined in [RFC 5321 Section 4.2](https://datatracker.ietf.org/doc/html/rfc5321#section-4.2)
+///
+/// Codes added by extensions such as [RFC 4954](https://datatracker.ietf.org/doc/html/rfc4954) are also included.
+/// Any code without a named variant is stored in [ResponseCode::Other]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ResponseCode {
+    /// 211 System status, or system help reply
+    SystemStatus,
+    /// 214 Help message
+    HelpMessage,
+    /// 220 `<domain>` Service ready
+    ServiceReady,
+    /// 221 `<domain>` Service closing transmission channel
+    ServiceClosing,
+    /// 235 Authentication Succeeded
+    AuthenticationSucceeded,
+    /// 250 Requested mail action okay, completed
     Ok,
-    // TODO
+    /// 251 User not local; will forward to `<forward-path>`
+    UserNotLocalWillForward,
+    /// 252 Cannot VRFY user, but will accept message and attempt delivery
+    CannotVerifyUser,
+    /// 334 Server challenge for the AUTH command
+    ServerChallenge,
+    /// 354 Start mail input; end with `<CRLF>.<CRLF>`
+    StartMailInput,
+    /// 421 `<domain>` Service not available, closing transmission channel
+    ServiceNotAvailable,
+    /// 450 Requested mail action not taken: mailbox unavailable (e.g., mailbox busy)
+    MailboxTemporarilyUnavailable,
+    /// 451 Requested action aborted: local error in processing
+    LocalError,
+    /// 452 Requested action not taken: insufficient system storage
+    InsufficientStorage,
+    /// 453 Requested action not taken. Used by some servers to signal there is no mail
+    ///
+    /// This code is not assigned by RFC 5321 but is commonly seen in the wild.
+    NoMail,
+    /// 454 Temporary authentication failure
+    TemporaryAuthenticationFailure,
+    /// 455 Server unable to accommodate parameters
+    UnableToAccommodateParameters,
+    /// 500 Syntax error, command unrecognized
+    SyntaxError,
+    /// 501 Syntax error in parameters or arguments
+    SyntaxErrorInParameters,
+    /// 502 Command not implemented
+    CommandNotImplemented,
+    /// 503 Bad sequence of commands
+    BadSequence,
+    /// 504 Command parameter not implemented
+    ParameterNotImplemented,
+    /// 530 Authentication required
+    AuthenticationRequired,
+    /// 535 Authentication credentials invalid
+    AuthenticationCredentialsInvalid,
+    /// 550 Requested action not taken: mailbox unavailable (e.g., mailbox not found, no access, or command rejected for policy reasons)
+    MailboxUnavailable,
+    /// 551 User not local; please try `<forward-path>`
+    UserNotLocal,
+    /// 552 Requested mail action aborted: exceeded storage allocation
+    ExceededStorageAllocation,
+    /// 553 Requested action not taken: mailbox name not allowed
+    MailboxNameNotAllowed,
+    /// 554 Transaction failed
+    TransactionFailed,
+    /// 555 MAIL FROM/RCPT TO parameters not recognized or not implemented
+    ParametersNotRecognized,
+    /// 556 Domain does not accept mail [RFC 7504](https://datatracker.ietf.org/doc/html/rfc7504)
+    DomainDoesNotAcceptMail,
+    /// Any other code.
     Other(u16),
 }
+impl ResponseCode {
+    /// The numeric value of the code
+    pub fn code(&self) -> u16 {
+        match self {
+            ResponseCode::SystemStatus => 211,
+            ResponseCode::HelpMessage => 214,
+            ResponseCode::ServiceReady => 220,
+            ResponseCode::ServiceClosing => 221,
+            ResponseCode::AuthenticationSucceeded => 235,
+            ResponseCode::Ok => 250,
+            ResponseCode::UserNotLocalWillForward => 251,
+            ResponseCode::CannotVerifyUser => 252,
+            ResponseCode::ServerChallenge => 334,
+            ResponseCode::StartMailInput => 354,
+            ResponseCode::ServiceNotAvailable => 421,
+            ResponseCode::MailboxTemporarilyUnavailable => 450,
+            ResponseCode::LocalError => 451,
+            ResponseCode::InsufficientStorage => 452,
+            ResponseCode::NoMail => 453,
+            ResponseCode::TemporaryAuthenticationFailure => 454,
+            ResponseCode::UnableToAccommodateParameters => 455,
+            ResponseCode::SyntaxError => 500,
+            ResponseCode::SyntaxErrorInParameters => 501,
+            ResponseCode::CommandNotImplemented => 502,
+            ResponseCode::BadSequence => 503,
+            ResponseCode::ParameterNotImplemented => 504,
+            ResponseCode::AuthenticationRequired => 530,
+            ResponseCode::AuthenticationCredentialsInvalid => 535,
+            ResponseCode::MailboxUnavailable => 550,
+            ResponseCode::UserNotLocal => 551,
+            ResponseCode::ExceededStorageAllocation => 552,
+            ResponseCode::MailboxNameNotAllowed => 553,
+            ResponseCode::TransactionFailed => 554,
+            ResponseCode::ParametersNotRecognized => 555,
+            ResponseCode::DomainDoesNotAcceptMail => 556,
+            ResponseCode::Other(code) => *code,
+        }
+    }
+    /// Creates a [ResponseCode] only if the code is within 2xx-5xx
+    pub fn new_checked(code: u16) -> Option<Self> {
+        if Self::is_valid_code(code) {
+            Some(Self::from(code))
+        } else {
+            None
+        }
+    }
+    /// Checks if the code is a valid reply code.
+    ///
+    /// The first digit must be 2-5 and the second digit must be 0-5
+    pub fn is_valid_code(code: u16) -> bool {
+        (200..600).contains(&code) && (code / 10) % 10 <= 5
+    }
+    /// The category of the code.
+    ///
+    /// Returns None if the code is outside of 2xx-5xx. Only possible with [ResponseCode::Other]
+    pub fn category(&self) -> Option<ResponseCategory> {
+        ResponseCategory::from_code(self.code())
+    }
+    /// 2xx
+    pub fn is_positive_completion(&self) -> bool {
+        matches!(self.category(), Some(ResponseCategory::PositiveCompletion))
+    }
+    /// 3xx
+    pub fn is_intermediate(&self) -> bool {
+        matches!(
+            self.category(),
+            Some(ResponseCategory::PositiveIntermediate)
+        )
+    }
+    /// 4xx
+    pub fn is_transient_negative(&self) -> bool {
+        matches!(self.category(), Some(ResponseCategory::TransientNegative))
+    }
+    /// 5xx
+    pub fn is_permanent_negative(&self) -> bool {
+        matches!(self.category(), Some(ResponseCategory::PermanentNegative))
+    }
+    /// 4xx or 5xx
+    pub fn is_negative(&self) -> bool {
+        self.is_transient_negative() || self.is_permanent_negative()
+    }
+}
 
 impl PartialEq<u16> for ResponseCode {
     fn eq(&self, other: &u16) -> bool {
-        match self {
-            ResponseCode::Other(code) => code == other,
-            ResponseCode::Ok => *other == 250,
-        }
+        self.code() == *other
     }
 }
 
 impl Display for ResponseCode {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            ResponseCode::Other(code) => {
-                write!(f, "{}", code)
-            }
-            ResponseCode::Ok => {
-                write!(f, "250")
-            }
-        }
+        write!(f, "{}", self.code())
     }
 }
 impl From<u16> for ResponseCode {
     fn from(code: u16) -> Self {
         match code {
+            211 => ResponseCode::SystemStatus,
+            214 => ResponseCode::HelpMessage,
+            220 => ResponseCode::ServiceReady,
+            221 => ResponseCode::ServiceClosing,
+            235 => ResponseCode::AuthenticationSucceeded,
             250 => ResponseCode::Ok,
+            251 => ResponseCode::UserNotLocalWillForward,
+            252 => ResponseCode::CannotVerifyUser,
+            334 => ResponseCode::ServerChallenge,
+            354 => ResponseCode::StartMailInput,
+            421 => ResponseCode::ServiceNotAvailable,
+            450 => ResponseCode::MailboxTemporarilyUnavailable,
+            451 => ResponseCode::LocalError,
+            452 => ResponseCode::InsufficientStorage,
+            453 => ResponseCode::NoMail,
+            454 => ResponseCode::TemporaryAuthenticationFailure,
+            455 => ResponseCode::UnableToAccommodateParameters,
+            500 => ResponseCode::SyntaxError,
+            501 => ResponseCode::SyntaxErrorInParameters,
+            502 => ResponseCode::CommandNotImplemented,
+            503 => ResponseCode::BadSequence,
+            504 => ResponseCode::ParameterNotImplemented,
+            530 => ResponseCode::AuthenticationRequired,
+            535 => ResponseCode::AuthenticationCredentialsInvalid,
+            550 => ResponseCode::MailboxUnavailable,
+            551 => ResponseCode::UserNotLocal,
+            552 => ResponseCode::ExceededStorageAllocation,
+            553 => ResponseCode::MailboxNameNotAllowed,
+            554 => ResponseCode::TransactionFailed,
+            555 => ResponseCode::ParametersNotRecognized,
+            556 => ResponseCode::DomainDoesNotAcceptMail,
             _ => ResponseCode::Other(code),
         }
     }
 }
-#[derive(Debug)]
+impl From<ResponseCode> for u16 {
+    fn from(code: ResponseCode) -> Self {
+        code.code()
+    }
+}
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MultilineServerResponse(pub Vec<ServerResponseLine>);
 
 impl MultilineServerResponse {
@@ -62,13 +267,27 @@ impl MultilineServerResponse {
     pub fn add_line(&mut self, code: ResponseCode, message: Option<String>) {
         self.0.push(ServerResponseLine::new(code, message));
     }
+    /// Adds a line that was already built
+    pub fn push_line(&mut self, line: ServerResponseLine) {
+        self.0.push(line);
+    }
+    /// The code of the response. Taken from the last line
+    pub fn code(&self) -> Option<ResponseCode> {
+        self.0.last().map(|line| line.code)
+    }
+    /// The Enhanced Status Code of the response.
+    ///
+    /// RFC 2034 requires every line to carry the same code so the first one found is returned
+    pub fn enhanced_status_code(&self) -> Option<EnhancedStatusCode> {
+        self.0.iter().find_map(|line| line.enhanced_status_code)
+    }
 }
 
 impl FromStr for MultilineServerResponse {
     type Err = StatementParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let lines = s.split("\r\n");
+        let lines = s.split("\r\n").filter(|line| !line.is_empty());
         let mut lines_result = Vec::with_capacity(lines.size_hint().0);
 
         for line in lines {
@@ -88,7 +307,15 @@ impl Statement for MultilineServerResponse {
     }
 
     fn to_bytes(&self) -> Bytes {
-        todo!()
+        let mut string = String::with_capacity(self.statement_size());
+        if let Some((last, lines)) = self.0.split_last() {
+            // Infallible
+            for line in lines {
+                let _ = line.write_hyphenated_line(&mut string);
+            }
+            let _ = last.write_spaced_line(&mut string);
+        }
+        Bytes::from(string)
     }
 }
 impl MultiLineStatement for MultilineServerResponse {
@@ -103,33 +330,93 @@ impl MultiLineStatement for MultilineServerResponse {
         Ok(MultilineServerResponse(lines_result))
     }
 }
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ServerResponseLine {
     pub(crate) code: ResponseCode,
+    pub(crate) enhanced_status_code: Option<EnhancedStatusCode>,
     pub(crate) message: Option<String>,
 }
 impl ServerResponseLine {
     pub fn new(code: ResponseCode, message: Option<String>) -> Self {
-        Self { code, message }
+        Self {
+            code,
+            enhanced_status_code: None,
+            message,
+        }
+    }
+    /// Creates a new line with an [EnhancedStatusCode]
+    pub fn new_with_enhanced_status_code(
+        code: ResponseCode,
+        enhanced_status_code: EnhancedStatusCode,
+        message: Option<String>,
+    ) -> Self {
+        Self {
+            code,
+            enhanced_status_code: Some(enhanced_status_code),
+            message,
+        }
+    }
+    pub fn code(&self) -> ResponseCode {
+        self.code
+    }
+    pub fn enhanced_status_code(&self) -> Option<EnhancedStatusCode> {
+        self.enhanced_status_code
+    }
+    /// The message of the line without the Enhanced Status Code
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+    /// Sets the Enhanced Status Code for this line
+    pub fn set_enhanced_status_code(&mut self, enhanced_status_code: Option<EnhancedStatusCode>) {
+        self.enhanced_status_code = enhanced_status_code;
+    }
+
+    fn write_line(
+        &self,
+        separator: &str,
+        buf: &mut impl StatementWriter,
+    ) -> Result<(), StatementWriteError> {
+        buf.write_str(self.code.to_string().as_str())?;
+        buf.write_str(separator)?;
+        if let Some(enhanced_status_code) = &self.enhanced_status_code {
+            buf.write_str(enhanced_status_code.to_string().as_str())?;
+            if self.message.is_some() {
+                buf.write_str(" ")?;
+            }
+        }
+        if let Some(message) = &self.message {
+            buf.write_str(message.as_str())?;
+        }
+        buf.write_str("\r\n")?;
+        Ok(())
     }
 }
 impl FromStr for ServerResponseLine {
     type Err = StatementParseError;
 
     fn from_str(string: &str) -> Result<Self, Self::Err> {
-        let code = u16::from_str(&string[0..3])
-            .map_err(|_| StatementParseError::InvalidResponseCode(string.to_string()))
-            .map(ResponseCode::from)?;
-        if string.len() == 3 {
-            Ok(Self {
-                code,
-                message: None,
-            })
-        } else {
-            Ok(Self {
+        let code = string
+            .get(0..3)
+            .and_then(|code| u16::from_str(code).ok())
+            .and_then(ResponseCode::new_checked)
+            .ok_or_else(|| StatementParseError::InvalidResponseCode(string.to_owned()))?;
+        // The code is followed by a space, a hyphen on all but the last line, or nothing
+        let message = match string.as_bytes().get(3) {
+            None => return Ok(Self::new(code, None)),
+            Some(b' ' | b'-') => &string[4..],
+            Some(_) => return Err(StatementParseError::InvalidResponseCode(string.to_owned())),
+        };
+        // RFC 3463 Section 2. The class has to match the first digit of the reply code.
+        // Otherwise it is ordinary text
+        match EnhancedStatusCode::split_from_message(message)
+            .filter(|(enhanced, _)| u16::from(enhanced.class.code()) == code.code() / 100)
+        {
+            Some((enhanced_status_code, message)) => Ok(Self {
                 code,
-                message: Some(string[4..].to_string()),
-            })
+                enhanced_status_code: Some(enhanced_status_code),
+                message: (!message.is_empty()).then(|| message.to_owned()),
+            }),
+            None => Ok(Self::new(code, Some(message.to_owned()))),
         }
     }
 }
@@ -145,13 +432,7 @@ impl SingleLineStatement for ServerResponseLine {
         &self,
         buf: &mut impl StatementWriter,
     ) -> Result<(), StatementWriteError> {
-        buf.write_str(self.code.to_string().as_str())?;
-        buf.write_str("-")?;
-        if let Some(message) = &self.message {
-            buf.write_str(message.as_str())?;
-        }
-        buf.write_str("\r\n")?;
-        Ok(())
+        self.write_line("-", buf)
     }
 
     fn to_spaced_line(&self) -> String {
@@ -162,34 +443,99 @@ impl SingleLineStatement for ServerResponseLine {
     }
 
     fn write_spaced_line(&self, buf: &mut impl StatementWriter) -> Result<(), StatementWriteError> {
-        buf.write_str(self.code.to_string().as_str())?;
-        buf.write_str(" ")?;
-        if let Some(message) = &self.message {
-            buf.write_str(message.as_str())?;
-        }
-        buf.write_str("\r\n")?;
-        Ok(())
+        self.write_line(" ", buf)
     }
 }
 impl Statement for ServerResponseLine {
     fn statement_size(&self) -> usize {
-        4 + self.message.as_ref().map(|s| s.len()).unwrap_or(0)
+        // An enhanced status code is at most `X.YYY.ZZZ `
+        4 + self.enhanced_status_code.map(|_| 10).unwrap_or(0)
+            + self.message.as_ref().map(|s| s.len()).unwrap_or(0)
     }
 
     fn to_bytes(&self) -> Bytes {
-        let mut bytes =
-            BytesMut::with_capacity(4 + self.message.as_ref().map(|s| s.len()).unwrap_or(0));
+        let mut bytes = BytesMut::with_capacity(self.statement_size() + 2);
         bytes
-            .write_str(self.code.to_string().as_str())
-            .expect("Failed to write code");
-        bytes.put_u8(b' ');
-        if let Some(message) = &self.message {
-            bytes
-                .write_str(message.as_str())
-                .expect("Failed to write message");
-        }
-        bytes.put_u8(b'\r');
-        bytes.put_u8(b'\n');
+            .write_str(self.to_spaced_line().as_str())
+            .expect("Failed to write line");
         bytes.freeze()
     }
 }
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::{MultilineServerResponse, ResponseCategory, ResponseCode, ServerResponseLine};
+    use crate::{
+        enhanced_status_code::EnhancedStatusCode,
+        statement::{SingleLineStatement, Statement},
+    };
+
+    #[test]
+    fn test_response_code_round_trip() {
+        for code in 200..600 {
+            if !ResponseCode::is_valid_code(code) {
+                continue;
+            }
+            let response_code = ResponseCode::from(code);
+            assert_eq!(response_code.code(), code);
+            assert_eq!(response_code, code);
+            assert_eq!(response_code.to_string(), code.to_string());
+        }
+    }
+    #[test]
+    fn test_categories() {
+        assert!(ResponseCode::Ok.is_positive_completion());
+        assert!(ResponseCode::StartMailInput.is_intermediate());
+        assert!(ResponseCode::ServiceNotAvailable.is_transient_negative());
+        assert!(ResponseCode::MailboxUnavailable.is_permanent_negative());
+        assert_eq!(
+            ResponseCode::Other(299).category(),
+            Some(ResponseCategory::PositiveCompletion)
+        );
+        assert_eq!(ResponseCode::Other(199).category(), None);
+    }
+    #[test]
+    fn test_parse_line() {
+        let line = ServerResponseLine::from_str("250 OK").unwrap();
+        assert_eq!(line.code, ResponseCode::Ok);
+        assert_eq!(line.message.as_deref(), Some("OK"));
+
+        let line = ServerResponseLine::from_str("354").unwrap();
+        assert_eq!(line.code, ResponseCode::StartMailInput);
+        assert_eq!(line.message, None);
+
+        let line = ServerResponseLine::from_str("550 5.1.1 User unknown").unwrap();
+        assert_eq!(line.code, ResponseCode::MailboxUnavailable);
+        assert_eq!(
+            line.enhanced_status_code,
+            Some(EnhancedStatusCode::BAD_DESTINATION_MAILBOX)
+        );
+        assert_eq!(line.message.as_deref(), Some("User unknown"));
+        assert_eq!(line.to_spaced_line(), "550 5.1.1 User unknown\r\n");
+
+        let line = ServerResponseLine::from_str("250 5.1.1 ok").unwrap();
+        assert_eq!(line.code, ResponseCode::Ok);
+        assert_eq!(line.enhanced_status_code, None);
+        assert_eq!(line.message.as_deref(), Some("5.1.1 ok"));
+
+        assert!(ServerResponseLine::from_str("199 Nope").is_err());
+        assert!(ServerResponseLine::from_str("600 Nope").is_err());
+        assert!(ServerResponseLine::from_str("2").is_err());
+        assert!(ServerResponseLine::from_str("ABC").is_err());
+        assert!(ServerResponseLine::from_str("250XHello").is_err());
+        assert!(ServerResponseLine::from_str("250?x").is_err());
+    }
+    #[test]
+    fn test_multiline_round_trip() {
+        let raw = "250-smtp.example.com\r\n250-2.0.0 SIZE 1000\r\n250 2.0.0 OK\r\n";
+        let response = MultilineServerResponse::from_str(raw).unwrap();
+        assert_eq!(response.0.len(), 3);
+        assert_eq!(response.code(), Some(ResponseCode::Ok));
+        assert_eq!(
+            response.enhanced_status_code(),
+            Some(EnhancedStatusCode::SUCCESS)
+        );
+        assert_eq!(response.to_bytes().as_ref(), raw.as_bytes());
+    }
+}
@@ -0,0 +1,525 @@
+/*!
+# SMTP Session
+
+Tracks the order of commands on the server side of a connection.
+
+```text
+greeting -> EHLO/HELO -> MAIL -> RCPT* -> DATA | BDAT* BDAT LAST -> (back to EHLO/HELO)
+```
+
+[Session] does not do any IO and does not decide if a command should be accepted.
+The server checks the sequence with [Session::check], applies its own policy, and then calls [Session::accept].
+
+RSET and a new EHLO/HELO abort the current transaction.
+After a successful STARTTLS handshake [Session::start_tls] resets the session to the state after the greeting.
+The AUTH exchange is run by the server. It calls [Session::set_authenticated] once the client is authenticated
+ */
+use mail_lib_types::EmailAddress;
+use thiserror::Error;
+
+use crate::{
+    commands::{
+        command_impls::{
+            BodyType, EhloCommandData, ForwardPath, MailFromCommandData, MailParameter,
+            RcptToCommandData,
+        },
+        ClientCommand,
+    },
+    enhanced_status_code::{EnhancedStatusCode, StatusClass},
+    server_response::{MultilineServerResponse, ResponseCode, ServerResponseLine},
+    SMTPConnectionState,
+};
+
+/// A command was sent at the wrong time
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum SessionError {
+    #[error("Send HELO/EHLO first")]
+    NoHelo,
+    #[error("Need MAIL command")]
+    NoMail,
+    #[error("Need RCPT command")]
+    NoRecipients,
+    #[error("Nested MAIL command")]
+    NestedMail,
+    #[error("Too many recipients. The limit is {0}")]
+    TooManyRecipients(usize),
+    #[error("Not accepting commands while receiving data")]
+    ReceivingData,
+    #[error("Connection is closed")]
+    Closed,
+    #[error("TLS already active")]
+    TlsActive,
+    #[error("MAIL transaction in progress")]
+    MailTransactionInProgress,
+    #[error("Already authenticated")]
+    AlreadyAuthenticated,
+    #[error("DATA can not be used after BDAT")]
+    DataAfterBdat,
+    #[error("BODY=BINARYMIME requires BDAT")]
+    BinaryMimeRequiresBdat,
+    #[error("LMTP requires LHLO")]
+    LhloRequired,
+    #[error("LHLO is only used with LMTP")]
+    NotLmtp,
+}
+impl SessionError {
+    /// `503` for everything except [SessionError::TooManyRecipients] which is `452`.
+    /// A greeting of the wrong protocol is `500`
+    pub fn code(&self) -> ResponseCode {
+        match self {
+            SessionError::TooManyRecipients(_) => ResponseCode::InsufficientStorage,
+            SessionError::LhloRequired | SessionError::NotLmtp => ResponseCode::SyntaxError,
+            _ => ResponseCode::BadSequence,
+        }
+    }
+    pub fn enhanced_status_code(&self) -> EnhancedStatusCode {
+        match self {
+            SessionError::TooManyRecipients(_) => {
+                EnhancedStatusCode::new(StatusClass::PersistentTransientFailure, 5, 3)
+            }
+            _ => EnhancedStatusCode::INVALID_COMMAND,
+        }
+    }
+    /// The reply that should be sent to the client
+    pub fn to_response(&self) -> MultilineServerResponse {
+        MultilineServerResponse::new(vec![ServerResponseLine::new_with_enhanced_status_code(
+            self.code(),
+            self.enhanced_status_code(),
+            Some(self.to_string()),
+        )])
+    }
+}
+/// The sender and recipients of the current mail transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Envelope {
+    pub mail_from: MailFromCommandData,
+    pub recipients: Vec<RcptToCommandData>,
+}
+impl Envelope {
+    pub fn new(mail_from: MailFromCommandData) -> Self {
+        Self {
+            mail_from,
+            recipients: Vec::new(),
+        }
+    }
+    /// None is the null sender `<>`
+    pub fn reverse_path(&self) -> Option<&EmailAddress> {
+        self.mail_from.reverse_path.as_ref()
+    }
+    /// The parameters sent with MAIL FROM
+    pub fn mail_parameters(&self) -> &[MailParameter] {
+        &self.mail_from.parameters
+    }
+    /// The paths of every accepted recipient
+    pub fn forward_paths(&self) -> impl Iterator<Item = &ForwardPath> {
+        self.recipients
+            .iter()
+            .map(|recipient| &recipient.forward_path)
+    }
+}
+
+/// The state of a server side SMTP session
+#[derive(Debug, Clone)]
+pub struct Session {
+    state: SMTPConnectionState,
+    client_hostname: Option<EhloCommandData>,
+    extended: bool,
+    envelope: Option<Envelope>,
+    max_recipients: Option<usize>,
+    tls: bool,
+    authenticated: Option<String>,
+    /// BDAT was used in the current transaction
+    chunking: bool,
+    lmtp: bool,
+}
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            state: SMTPConnectionState::Connected,
+            client_hostname: None,
+            extended: false,
+            envelope: None,
+            max_recipients: None,
+            tls: false,
+            authenticated: None,
+            chunking: false,
+            lmtp: false,
+        }
+    }
+}
+impl Session {
+    /// A new session. The greeting is expected to have been sent
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// RCPT TO will fail with [SessionError::TooManyRecipients] after `max_recipients` have been accepted
+    ///
+    /// RFC 5321 requires at least 100 recipients to be accepted
+    pub fn with_max_recipients(mut self, max_recipients: usize) -> Self {
+        self.max_recipients = Some(max_recipients);
+        self
+    }
+    /// The client has to greet with LHLO instead of EHLO or HELO
+    ///
+    /// [RFC 2033](https://datatracker.ietf.org/doc/html/rfc2033)
+    pub fn with_lmtp(mut self) -> Self {
+        self.lmtp = true;
+        self
+    }
+    pub fn is_lmtp(&self) -> bool {
+        self.lmtp
+    }
+    pub fn state(&self) -> &SMTPConnectionState {
+        &self.state
+    }
+    /// The hostname sent with EHLO, HELO or LHLO
+    pub fn client_hostname(&self) -> Option<&EhloCommandData> {
+        self.client_hostname.as_ref()
+    }
+    /// If the client used EHLO or LHLO instead of HELO
+    pub fn is_extended(&self) -> bool {
+        self.extended
+    }
+    /// If STARTTLS was completed
+    pub fn is_tls(&self) -> bool {
+        self.tls
+    }
+    /// The identity the client authenticated as with AUTH
+    pub fn authenticated_as(&self) -> Option<&str> {
+        self.authenticated.as_deref()
+    }
+    /// The AUTH exchange succeeded
+    pub fn set_authenticated(&mut self, identity: impl Into<String>) {
+        self.authenticated = Some(identity.into());
+    }
+    /// The envelope of the current mail transaction
+    pub fn envelope(&self) -> Option<&Envelope> {
+        self.envelope.as_ref()
+    }
+    /// Checks if the command can be sent in the current state
+    pub fn check(&self, command: &ClientCommand) -> Result<(), SessionError> {
+        match self.state {
+            SMTPConnectionState::Closed => return Err(SessionError::Closed),
+            SMTPConnectionState::Data => return Err(SessionError::ReceivingData),
+            _ => {}
+        }
+        match command {
+            ClientCommand::Ehlo(_) | ClientCommand::Helo(_) if self.lmtp => {
+                Err(SessionError::LhloRequired)
+            }
+            ClientCommand::Lhlo(_) if !self.lmtp => Err(SessionError::NotLmtp),
+            ClientCommand::MailFrom(_) => match self.state {
+                SMTPConnectionState::Connected => Err(SessionError::NoHelo),
+                SMTPConnectionState::Helo => Ok(()),
+                _ => Err(SessionError::NestedMail),
+            },
+            ClientCommand::RcptTo(_) => match self.state {
+                SMTPConnectionState::Connected => Err(SessionError::NoHelo),
+                SMTPConnectionState::Helo => Err(SessionError::NoMail),
+                _ => match (self.max_recipients, &self.envelope) {
+                    (Some(max), Some(envelope)) if envelope.recipients.len() >= max => {
+                        Err(SessionError::TooManyRecipients(max))
+                    }
+                    _ => Ok(()),
+                },
+            },
+            // RFC 3030 Section 2
+            ClientCommand::Data if self.chunking => Err(SessionError::DataAfterBdat),
+            // RFC 3030 Section 3
+            ClientCommand::Data
+                if self
+                    .envelope
+                    .as_ref()
+                    .and_then(|envelope| envelope.mail_from.body())
+                    == Some(BodyType::BinaryMime) =>
+            {
+                Err(SessionError::BinaryMimeRequiresBdat)
+            }
+            ClientCommand::Data | ClientCommand::Bdat(_) => match self.state {
+                SMTPConnectionState::Connected => Err(SessionError::NoHelo),
+                SMTPConnectionState::Helo => Err(SessionError::NoMail),
+                SMTPConnectionState::MailFrom => Err(SessionError::NoRecipients),
+                _ => Ok(()),
+            },
+            ClientCommand::StartTls if self.tls => Err(SessionError::TlsActive),
+            ClientCommand::StartTls => match self.state {
+                SMTPConnectionState::Connected => Err(SessionError::NoHelo),
+                SMTPConnectionState::Helo => Ok(()),
+                _ => Err(SessionError::MailTransactionInProgress),
+            },
+            // RFC 4954 Section 4. Only one successful AUTH per session
+            ClientCommand::Auth(_) if self.authenticated.is_some() => {
+                Err(SessionError::AlreadyAuthenticated)
+            }
+            ClientCommand::Auth(_) => match self.state {
+                SMTPConnectionState::Connected => Err(SessionError::NoHelo),
+                SMTPConnectionState::Helo => Ok(()),
+                _ => Err(SessionError::MailTransactionInProgress),
+            },
+            _ => Ok(()),
+        }
+    }
+    /// Applies a command that was accepted by the server.
+    ///
+    /// Only call this once the server has decided to accept the command.
+    /// The sequence is checked again so the state can never become invalid
+    pub fn accept(&mut self, command: ClientCommand) -> Result<(), SessionError> {
+        self.check(&command)?;
+        match command {
+            ClientCommand::Ehlo(data) | ClientCommand::Lhlo(data) => self.greet(data, true),
+            ClientCommand::Helo(data) => self.greet(data, false),
+            ClientCommand::MailFrom(data) => {
+                self.envelope = Some(Envelope::new(data));
+                self.state = SMTPConnectionState::MailFrom;
+            }
+            ClientCommand::RcptTo(data) => {
+                if let Some(envelope) = self.envelope.as_mut() {
+                    envelope.recipients.push(data);
+                }
+                self.state = SMTPConnectionState::RcptTo;
+            }
+            ClientCommand::Data => self.state = SMTPConnectionState::Data,
+            ClientCommand::Bdat(data) => {
+                self.chunking = true;
+                // The state only changes once the last chunk was received
+                if data.last {
+                    self.state = SMTPConnectionState::Data;
+                }
+            }
+            ClientCommand::Rset => self.reset(),
+            ClientCommand::Quit => {
+                self.envelope = None;
+                self.chunking = false;
+                self.state = SMTPConnectionState::Closed;
+            }
+            ClientCommand::Noop(_)
+            | ClientCommand::Vrfy(_)
+            | ClientCommand::Expn(_)
+            | ClientCommand::Help(_)
+            | ClientCommand::StartTls
+            | ClientCommand::Auth(_) => {}
+        }
+        Ok(())
+    }
+    /// Aborts the current mail transaction.
+    pub fn reset(&mut self) {
+        self.envelope = None;
+        self.chunking = false;
+        if self.client_hostname.is_some() {
+            self.state = SMTPConnectionState::Helo;
+        } else {
+            self.state = SMTPConnectionState::Connected;
+        }
+    }
+    /// The message was received or the DATA command failed. Also called after the last BDAT chunk.
+    ///
+    /// Returns the envelope of the finished transaction and waits for the next MAIL command
+    pub fn finish_data(&mut self) -> Option<Envelope> {
+        if self.state != SMTPConnectionState::Data {
+            return None;
+        }
+        self.state = SMTPConnectionState::Helo;
+        self.chunking = false;
+        self.envelope.take()
+    }
+    /// The TLS handshake after STARTTLS finished.
+    ///
+    /// Everything learned from the client is discarded. The client has to send EHLO again
+    ///
+    /// [RFC 3207 Section 4.2](https://datatracker.ietf.org/doc/html/rfc3207#section-4.2)
+    pub fn start_tls(&mut self) {
+        self.client_hostname = None;
+        self.extended = false;
+        self.envelope = None;
+        self.chunking = false;
+        self.authenticated = None;
+        self.state = SMTPConnectionState::Connected;
+        self.tls = true;
+    }
+    fn greet(&mut self, data: EhloCommandData, extended: bool) {
+        self.client_hostname = Some(data);
+        self.extended = extended;
+        self.envelope = None;
+        self.chunking = false;
+        self.state = SMTPConnectionState::Helo;
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::{Session, SessionError};
+    use crate::{commands::ClientCommand, SMTPConnectionState};
+
+    fn command(line: &str) -> ClientCommand {
+        ClientCommand::parse(line).unwrap()
+    }
+    #[test]
+    fn test_transaction() {
+        let mut session = Session::new();
+        assert_eq!(
+            session.check(&command("MAIL FROM:<a@example.com>")),
+            Err(SessionError::NoHelo)
+        );
+        session.accept(command("EHLO client.example.com")).unwrap();
+        assert!(session.is_extended());
+        assert_eq!(
+            session.accept(command("RCPT TO:<b@example.com>")),
+            Err(SessionError::NoMail)
+        );
+        session
+            .accept(command("MAIL FROM:<a@example.com>"))
+            .unwrap();
+        assert_eq!(
+            session.check(&command("MAIL FROM:<a@example.com>")),
+            Err(SessionError::NestedMail)
+        );
+        assert_eq!(
+            session.check(&command("DATA")),
+            Err(SessionError::NoRecipients)
+        );
+        session.accept(command("RCPT TO:<b@example.com>")).unwrap();
+        session.accept(command("RCPT TO:<c@example.com>")).unwrap();
+        session.accept(command("DATA")).unwrap();
+        assert_eq!(session.state(), &SMTPConnectionState::Data);
+        assert_eq!(
+            session.check(&command("NOOP")),
+            Err(SessionError::ReceivingData)
+        );
+
+        let envelope = session.finish_data().unwrap();
+        assert_eq!(envelope.reverse_path().unwrap(), &"a@example.com");
+        assert_eq!(envelope.forward_paths().count(), 2);
+        assert_eq!(session.state(), &SMTPConnectionState::Helo);
+        assert!(session.envelope().is_none());
+
+        session.accept(command("QUIT")).unwrap();
+        assert_eq!(session.check(&command("NOOP")), Err(SessionError::Closed));
+    }
+    #[test]
+    fn test_resets() {
+        let mut session = Session::new();
+        session.accept(command("RSET")).unwrap();
+        assert_eq!(session.state(), &SMTPConnectionState::Connected);
+
+        session.accept(command("HELO client.example.com")).unwrap();
+        session.accept(command("MAIL FROM:<>")).unwrap();
+        session.accept(command("RSET")).unwrap();
+        assert_eq!(session.state(), &SMTPConnectionState::Helo);
+        assert!(session.envelope().is_none());
+
+        session.accept(command("MAIL FROM:<>")).unwrap();
+        session.accept(command("RCPT TO:<b@example.com>")).unwrap();
+        session.accept(command("EHLO other.example.com")).unwrap();
+        assert_eq!(session.state(), &SMTPConnectionState::Helo);
+        assert!(session.envelope().is_none());
+        assert_eq!(
+            session.client_hostname().unwrap().client_hostname,
+            "other.example.com"
+        );
+    }
+    #[test]
+    fn test_start_tls() {
+        let mut session = Session::new();
+        assert_eq!(
+            session.check(&command("STARTTLS")),
+            Err(SessionError::NoHelo)
+        );
+        session.accept(command("EHLO client.example.com")).unwrap();
+        session.accept(command("MAIL FROM:<>")).unwrap();
+        assert_eq!(
+            session.check(&command("STARTTLS")),
+            Err(SessionError::MailTransactionInProgress)
+        );
+        session.accept(command("RSET")).unwrap();
+        session.accept(command("STARTTLS")).unwrap();
+        session.start_tls();
+        assert!(session.is_tls());
+        assert!(session.client_hostname().is_none());
+        assert_eq!(session.state(), &SMTPConnectionState::Connected);
+
+        session.accept(command("EHLO client.example.com")).unwrap();
+        assert_eq!(
+            session.check(&command("STARTTLS")),
+            Err(SessionError::TlsActive)
+        );
+    }
+    #[test]
+    fn test_auth() {
+        let mut session = Session::new();
+        let auth = command("AUTH PLAIN");
+        assert_eq!(session.check(&auth), Err(SessionError::NoHelo));
+        session.accept(command("EHLO client.example.com")).unwrap();
+        session.accept(command("MAIL FROM:<>")).unwrap();
+        assert_eq!(
+            session.check(&auth),
+            Err(SessionError::MailTransactionInProgress)
+        );
+        session.accept(command("RSET")).unwrap();
+        assert_eq!(session.check(&auth), Ok(()));
+        session.set_authenticated("tim");
+        assert_eq!(session.authenticated_as(), Some("tim"));
+        assert_eq!(
+            session.check(&auth),
+            Err(SessionError::AlreadyAuthenticated)
+        );
+    }
+    #[test]
+    fn test_bdat() {
+        let mut session = Session::new();
+        session.accept(command("EHLO client.example.com")).unwrap();
+        assert_eq!(
+            session.check(&command("BDAT 10")),
+            Err(SessionError::NoMail)
+        );
+        session.accept(command("MAIL FROM:<>")).unwrap();
+        session.accept(command("RCPT TO:<b@example.com>")).unwrap();
+        session.accept(command("BDAT 10")).unwrap();
+        assert_eq!(session.state(), &SMTPConnectionState::RcptTo);
+        assert_eq!(
+            session.check(&command("DATA")),
+            Err(SessionError::DataAfterBdat)
+        );
+        session.accept(command("BDAT 0 LAST")).unwrap();
+        assert_eq!(session.state(), &SMTPConnectionState::Data);
+        assert!(session.finish_data().is_some());
+
+        session
+            .accept(command("MAIL FROM:<> BODY=BINARYMIME"))
+            .unwrap();
+        session.accept(command("RCPT TO:<b@example.com>")).unwrap();
+        assert_eq!(
+            session.check(&command("DATA")),
+            Err(SessionError::BinaryMimeRequiresBdat)
+        );
+        assert_eq!(session.check(&command("BDAT 10 LAST")), Ok(()));
+    }
+    #[test]
+    fn test_lmtp() {
+        let mut session = Session::new().with_lmtp();
+        let error = session
+            .accept(command("EHLO client.example.com"))
+            .unwrap_err();
+        assert_eq!(error, SessionError::LhloRequired);
+        assert_eq!(error.to_response().code(), Some(500.into()));
+        session.accept(command("LHLO client.example.com")).unwrap();
+        assert!(session.is_extended());
+        assert_eq!(session.state(), &SMTPConnectionState::Helo);
+
+        assert_eq!(
+            Session::new().check(&command("LHLO client.example.com")),
+            Err(SessionError::NotLmtp)
+        );
+    }
+    #[test]
+    fn test_max_recipients() {
+        let mut session = Session::new().with_max_recipients(1);
+        session.accept(command("EHLO client.example.com")).unwrap();
+        session.accept(command("MAIL FROM:<>")).unwrap();
+        session.accept(command("RCPT TO:<b@example.com>")).unwrap();
+        let error = session
+            .accept(command("RCPT TO:<c@example.com>"))
+            .unwrap_err();
+        assert_eq!(error, SessionError::TooManyRecipients(1));
+        assert_eq!(error.to_response().code(), Some(452.into()));
+        assert_eq!(SessionError::NoMail.to_response().code(), Some(503.into()));
+    }
+}
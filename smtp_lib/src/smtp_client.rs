@@ -33,4 +33,18 @@ pub mod async_traits {
         /// Reads til
         fn read_til_end(&'a mut self) -> Self::ReadTilEndFuture;
     }
+    /// A client that can swap its stream for a TLS stream after STARTTLS
+    ///
+    /// [RFC 3207](https://datatracker.ietf.org/doc/html/rfc3207)
+    pub trait AsyncStartTlsClient<'a>: AsyncSMTPClient<'a> {
+        type UpgradeFuture: Future<Output = crate::Result<()>> + 'a + Send;
+
+        /// If the connection is already encrypted
+        fn is_tls(&self) -> bool;
+        /// Performs the TLS handshake. Called after the server replied `220` to STARTTLS
+        ///
+        /// Must fail if the server sent anything after the `220` reply.
+        /// The extensions from the previous EHLO must be discarded
+        fn upgrade_to_tls(&'a mut self) -> Self::UpgradeFuture;
+    }
 }
@@ -7,7 +7,7 @@ use auto_impl::auto_impl;
 use enum_helper::EnumOfKeys;
 use thiserror::Error;
 
-use crate::SMTPConnectionState;
+use crate::{auth::AuthMechanism, SMTPConnectionState};
 
 #[derive(Debug, Clone, PartialEq, Eq, Error)]
 pub enum ServerExtensionParseError {
@@ -16,6 +16,9 @@ pub enum ServerExtensionParseError {
     #[error("Invalid extension: {0}")]
     InvalidExtension(String),
 }
+/// An extension advertised in the EHLO response
+///
+/// Keywords are matched without case. Unknown keywords are kept in [SMTPServerExtension::Other]
 #[derive(Debug, Clone, PartialEq, Eq, EnumOfKeys)]
 #[enum_of_keys(SMTPServerExtensionKeys)]
 #[enum_attr(derive(
@@ -31,24 +34,83 @@ pub enum ServerExtensionParseError {
 ))]
 #[enum_attr(strum(serialize_all = "UPPERCASE"))]
 pub enum SMTPServerExtension {
-    Size(usize),
+    /// The max message size. `None` if the server did not send a value
+    ///
+    /// [RFC 1870](https://datatracker.ietf.org/doc/html/rfc1870)
+    Size(Option<usize>),
+    /// [RFC 3207](https://datatracker.ietf.org/doc/html/rfc3207)
     StartTLS,
-    //Auth(Vec<LoginMechanism>),
+    /// `BODY=8BITMIME`. [RFC 6152](https://datatracker.ietf.org/doc/html/rfc6152)
+    #[enum_attr(strum(serialize = "8BITMIME"))]
+    EightBitMime,
+    /// [RFC 6531](https://datatracker.ietf.org/doc/html/rfc6531)
+    SmtpUtf8,
+    /// Delivery Status Notifications. [RFC 3461](https://datatracker.ietf.org/doc/html/rfc3461)
+    Dsn,
+    /// [RFC 2034](https://datatracker.ietf.org/doc/html/rfc2034)
+    EnhancedStatusCodes,
+    /// [RFC 4954](https://datatracker.ietf.org/doc/html/rfc4954)
+    Auth(Vec<AuthMechanism>),
+    /// [RFC 2920](https://datatracker.ietf.org/doc/html/rfc2920)
+    Pipelining,
+    /// The BDAT command. [RFC 3030](https://datatracker.ietf.org/doc/html/rfc3030)
+    Chunking,
+    /// `BODY=BINARYMIME`. Requires [SMTPServerExtension::Chunking]
+    BinaryMime,
+    /// The minimum by-time in seconds. [RFC 2852](https://datatracker.ietf.org/doc/html/rfc2852)
+    DeliverBy(Option<u32>),
+    /// The priority profile. [RFC 6710](https://datatracker.ietf.org/doc/html/rfc6710)
+    #[enum_attr(strum(serialize = "MT-PRIORITY"))]
+    MtPriority(Option<String>),
+    /// [RFC 8689](https://datatracker.ietf.org/doc/html/rfc8689)
+    RequireTls,
+    /// [RFC 4865](https://datatracker.ietf.org/doc/html/rfc4865)
+    FutureRelease {
+        /// The max number of seconds a message can be held
+        max_interval: u64,
+        /// The latest date-time a message can be held until
+        max_datetime: String,
+    },
+    /// [RFC 9422](https://datatracker.ietf.org/doc/html/rfc9422)
+    Limits(Vec<Limit>),
     #[enum_of_keys(default=name)]
     #[enum_attr(strum(default))]
-    Other {
-        name: String,
-        value: Option<String>,
-    },
+    Other { name: String, value: Option<String> },
 }
 impl Display for SMTPServerExtension {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            SMTPServerExtension::Size(size) => write!(f, "SIZE {}", size),
+            SMTPServerExtension::Size(Some(size)) => write!(f, "SIZE {}", size),
+            SMTPServerExtension::Size(None) => write!(f, "SIZE"),
             SMTPServerExtension::StartTLS => write!(f, "STARTTLS"),
-            // SMTPServerExtension::Auth(value) => {
-            //    write!(f, "AUTH {}", LoginMechanism::format_iter(value.iter()))
-            //}
+            SMTPServerExtension::EightBitMime => write!(f, "8BITMIME"),
+            SMTPServerExtension::SmtpUtf8 => write!(f, "SMTPUTF8"),
+            SMTPServerExtension::Dsn => write!(f, "DSN"),
+            SMTPServerExtension::EnhancedStatusCodes => write!(f, "ENHANCEDSTATUSCODES"),
+            SMTPServerExtension::Auth(mechanisms) => {
+                write!(f, "AUTH {}", AuthMechanism::format_list(mechanisms))
+            }
+            SMTPServerExtension::Pipelining => write!(f, "PIPELINING"),
+            SMTPServerExtension::Chunking => write!(f, "CHUNKING"),
+            SMTPServerExtension::BinaryMime => write!(f, "BINARYMIME"),
+            SMTPServerExtension::DeliverBy(Some(min_by_time)) => {
+                write!(f, "DELIVERBY {}", min_by_time)
+            }
+            SMTPServerExtension::DeliverBy(None) => write!(f, "DELIVERBY"),
+            SMTPServerExtension::MtPriority(Some(profile)) => write!(f, "MT-PRIORITY {}", profile),
+            SMTPServerExtension::MtPriority(None) => write!(f, "MT-PRIORITY"),
+            SMTPServerExtension::RequireTls => write!(f, "REQUIRETLS"),
+            SMTPServerExtension::FutureRelease {
+                max_interval,
+                max_datetime,
+            } => write!(f, "FUTURERELEASE {} {}", max_interval, max_datetime),
+            SMTPServerExtension::Limits(limits) => {
+                write!(f, "LIMITS")?;
+                for limit in limits {
+                    write!(f, " {}", limit)?;
+                }
+                Ok(())
+            }
             SMTPServerExtension::Other { name, value } => {
                 if let Some(value) = value {
                     write!(f, "{} {}", name, value)
@@ -59,38 +121,267 @@ impl Display for SMTPServerExtension {
         }
     }
 }
+impl SMTPServerExtension {
+    /// Parses a line of the EHLO reply.
+    ///
+    /// A known keyword with parameters that do not parse is kept as [SMTPServerExtension::Other].
+    /// So one odd extension does not make the whole reply unusable
+    pub fn parse_lenient(value: String) -> Result<Self, ServerExtensionParseError> {
+        let (name, parameters) = match value.split_once(' ') {
+            Some((name, parameters)) => (name, Some(parameters.trim())),
+            None => (value.as_str(), None),
+        };
+        if name.is_empty() {
+            return Err(ServerExtensionParseError::InvalidExtension(value));
+        }
+        let other = Self::Other {
+            name: name.to_owned(),
+            value: parameters
+                .filter(|parameters| !parameters.is_empty())
+                .map(str::to_owned),
+        };
+        Ok(Self::try_from(value).unwrap_or(other))
+    }
+}
 impl TryFrom<String> for SMTPServerExtension {
     type Error = ServerExtensionParseError;
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
-        let mut split = value.split(" ");
-        match split
-            .next()
-            .ok_or(ServerExtensionParseError::InvalidSize(value.clone()))?
-        {
-            "SIZE" => {
-                let size = usize::from_str(
-                    split
-                        .next()
-                        .ok_or(ServerExtensionParseError::InvalidSize(value.clone()))?,
-                )
-                .map_err(|_| ServerExtensionParseError::InvalidSize(value.clone()))?;
-                Ok(Self::Size(size))
-            }
-            "STARTLS" => Ok(Self::StartTLS),
-            //"AUTH" => Ok(Self::Auth(LoginMechanism::from_iter(split))),
-            other_key => {
-                let other_key = other_key.to_string();
-                let other_data = value.splitn(2, " ").nth(1).map(|s| s.to_string());
-                Ok(Self::Other {
-                    name: other_key,
-                    value: other_data,
+        let (keyword, parameters) = match value.split_once(' ') {
+            Some((keyword, parameters)) => (keyword, Some(parameters.trim())),
+            None => (value.as_str(), None),
+        };
+        if keyword.is_empty() {
+            return Err(ServerExtensionParseError::InvalidExtension(value));
+        }
+        let parameters = parameters.filter(|parameters| !parameters.is_empty());
+        let invalid = || ServerExtensionParseError::InvalidExtension(value.clone());
+        match keyword.to_ascii_uppercase().as_str() {
+            "SIZE" => match parameters {
+                None => Ok(Self::Size(None)),
+                Some(size) => usize::from_str(size)
+                    .map(|size| Self::Size(Some(size)))
+                    .map_err(|_| ServerExtensionParseError::InvalidSize(value.clone())),
+            },
+            "STARTTLS" => Ok(Self::StartTLS),
+            "8BITMIME" => Ok(Self::EightBitMime),
+            "SMTPUTF8" => Ok(Self::SmtpUtf8),
+            "DSN" => Ok(Self::Dsn),
+            "ENHANCEDSTATUSCODES" => Ok(Self::EnhancedStatusCodes),
+            "AUTH" => Ok(Self::Auth(AuthMechanism::parse_list(
+                parameters.unwrap_or_default(),
+            ))),
+            "PIPELINING" => Ok(Self::Pipelining),
+            "CHUNKING" => Ok(Self::Chunking),
+            "BINARYMIME" => Ok(Self::BinaryMime),
+            "DELIVERBY" => match parameters {
+                None => Ok(Self::DeliverBy(None)),
+                Some(min_by_time) => u32::from_str(min_by_time)
+                    .map(|min_by_time| Self::DeliverBy(Some(min_by_time)))
+                    .map_err(|_| invalid()),
+            },
+            "MT-PRIORITY" => Ok(Self::MtPriority(parameters.map(str::to_owned))),
+            "REQUIRETLS" => Ok(Self::RequireTls),
+            "FUTURERELEASE" => {
+                let (max_interval, max_datetime) = parameters
+                    .and_then(|parameters| parameters.split_once(' '))
+                    .ok_or_else(invalid)?;
+                Ok(Self::FutureRelease {
+                    max_interval: u64::from_str(max_interval).map_err(|_| invalid())?,
+                    max_datetime: max_datetime.trim().to_owned(),
                 })
             }
+            "LIMITS" => parameters
+                .unwrap_or_default()
+                .split_ascii_whitespace()
+                .map(|limit| Limit::from_str(limit).map_err(|_| invalid()))
+                .collect::<Result<_, _>>()
+                .map(Self::Limits),
+            _ => Ok(Self::Other {
+                name: keyword.to_owned(),
+                value: parameters.map(str::to_owned),
+            }),
+        }
+    }
+}
+/// A limit of the `LIMITS` extension
+///
+/// [RFC 9422](https://datatracker.ietf.org/doc/html/rfc9422#section-4)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Limit {
+    /// The max number of transactions per connection
+    MailMax(usize),
+    /// The max number of recipients per transaction
+    RcptMax(usize),
+    /// The max number of recipient domains per transaction
+    RcptDomainMax(usize),
+    Other {
+        name: String,
+        value: String,
+    },
+}
+impl Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Limit::MailMax(value) => write!(f, "MAILMAX={}", value),
+            Limit::RcptMax(value) => write!(f, "RCPTMAX={}", value),
+            Limit::RcptDomainMax(value) => write!(f, "RCPTDOMAINMAX={}", value),
+            Limit::Other { name, value } => write!(f, "{}={}", name, value),
+        }
+    }
+}
+impl FromStr for Limit {
+    type Err = ServerExtensionParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ServerExtensionParseError::InvalidExtension(s.to_owned());
+        let (name, value) = s.split_once('=').ok_or_else(invalid)?;
+        let number = || usize::from_str(value).map_err(|_| invalid());
+        match name.to_ascii_uppercase().as_str() {
+            "MAILMAX" => number().map(Limit::MailMax),
+            "RCPTMAX" => number().map(Limit::RcptMax),
+            "RCPTDOMAINMAX" => number().map(Limit::RcptDomainMax),
+            _ => Ok(Limit::Other {
+                name: name.to_owned(),
+                value: value.to_owned(),
+            }),
         }
     }
 }
 
+/// The extensions a server advertised in its EHLO response
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Capabilities(Vec<SMTPServerExtension>);
+impl Capabilities {
+    pub fn new(extensions: Vec<SMTPServerExtension>) -> Self {
+        Self(extensions)
+    }
+    pub fn iter(&self) -> std::slice::Iter<'_, SMTPServerExtension> {
+        self.0.iter()
+    }
+    pub fn as_slice(&self) -> &[SMTPServerExtension] {
+        &self.0
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    pub fn contains(&self, extension: &SMTPServerExtension) -> bool {
+        self.0.contains(extension)
+    }
+    /// Removes all extensions. The extensions must be discarded after STARTTLS
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+    fn supports(&self, is: impl Fn(&SMTPServerExtension) -> bool) -> bool {
+        self.0.iter().any(is)
+    }
+    pub fn supports_start_tls(&self) -> bool {
+        self.supports(|extension| matches!(extension, SMTPServerExtension::StartTLS))
+    }
+    pub fn supports_8bitmime(&self) -> bool {
+        self.supports(|extension| matches!(extension, SMTPServerExtension::EightBitMime))
+    }
+    pub fn supports_smtputf8(&self) -> bool {
+        self.supports(|extension| matches!(extension, SMTPServerExtension::SmtpUtf8))
+    }
+    pub fn supports_dsn(&self) -> bool {
+        self.supports(|extension| matches!(extension, SMTPServerExtension::Dsn))
+    }
+    pub fn supports_enhanced_status_codes(&self) -> bool {
+        self.supports(|extension| matches!(extension, SMTPServerExtension::EnhancedStatusCodes))
+    }
+    pub fn supports_pipelining(&self) -> bool {
+        self.supports(|extension| matches!(extension, SMTPServerExtension::Pipelining))
+    }
+    pub fn supports_chunking(&self) -> bool {
+        self.supports(|extension| matches!(extension, SMTPServerExtension::Chunking))
+    }
+    /// Requires both `CHUNKING` and `BINARYMIME`
+    pub fn supports_binary_mime(&self) -> bool {
+        self.supports_chunking()
+            && self.supports(|extension| matches!(extension, SMTPServerExtension::BinaryMime))
+    }
+    pub fn supports_deliver_by(&self) -> bool {
+        self.supports(|extension| matches!(extension, SMTPServerExtension::DeliverBy(_)))
+    }
+    pub fn supports_mt_priority(&self) -> bool {
+        self.supports(|extension| matches!(extension, SMTPServerExtension::MtPriority(_)))
+    }
+    pub fn supports_require_tls(&self) -> bool {
+        self.supports(|extension| matches!(extension, SMTPServerExtension::RequireTls))
+    }
+    pub fn supports_future_release(&self) -> bool {
+        self.supports(|extension| matches!(extension, SMTPServerExtension::FutureRelease { .. }))
+    }
+    /// The value of the `SIZE` extension. `None` if missing, without a value or `0`
+    pub fn max_size(&self) -> Option<usize> {
+        self.0.iter().find_map(|extension| match extension {
+            SMTPServerExtension::Size(Some(size)) if *size > 0 => Some(*size),
+            _ => None,
+        })
+    }
+    /// The mechanisms of the `AUTH` extension
+    pub fn auth_mechanisms(&self) -> &[AuthMechanism] {
+        self.0
+            .iter()
+            .find_map(|extension| match extension {
+                SMTPServerExtension::Auth(mechanisms) => Some(mechanisms.as_slice()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+    /// The minimum by-time of the `DELIVERBY` extension
+    pub fn min_by_time(&self) -> Option<u32> {
+        self.0.iter().find_map(|extension| match extension {
+            SMTPServerExtension::DeliverBy(min_by_time) => *min_by_time,
+            _ => None,
+        })
+    }
+    /// The limits of the `LIMITS` extension
+    pub fn limits(&self) -> &[Limit] {
+        self.0
+            .iter()
+            .find_map(|extension| match extension {
+                SMTPServerExtension::Limits(limits) => Some(limits.as_slice()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+    /// The value of `RCPTMAX`
+    pub fn max_recipients(&self) -> Option<usize> {
+        self.limits().iter().find_map(|limit| match limit {
+            Limit::RcptMax(max) => Some(*max),
+            _ => None,
+        })
+    }
+}
+impl From<Vec<SMTPServerExtension>> for Capabilities {
+    fn from(extensions: Vec<SMTPServerExtension>) -> Self {
+        Self(extensions)
+    }
+}
+impl FromIterator<SMTPServerExtension> for Capabilities {
+    fn from_iter<T: IntoIterator<Item = SMTPServerExtension>>(iter: T) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+impl IntoIterator for Capabilities {
+    type Item = SMTPServerExtension;
+    type IntoIter = std::vec::IntoIter<SMTPServerExtension>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+impl<'a> IntoIterator for &'a Capabilities {
+    type Item = &'a SMTPServerExtension;
+    type IntoIter = std::slice::Iter<'a, SMTPServerExtension>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
 #[auto_impl(&,&mut, Box, Arc)]
 pub trait SMTPServer: Debug {
     fn get_hostname(&self) -> &str;
@@ -141,4 +432,143 @@ pub mod async_traits {
         /// Reads til
         fn read_til_end(&'a mut self) -> Self::ReadTilEndFuture;
     }
+    /// A connection that can swap its stream for a TLS stream after STARTTLS
+    ///
+    /// [RFC 3207](https://datatracker.ietf.org/doc/html/rfc3207)
+    pub trait AsyncStartTlsConnection<'a>: AsyncSMTPConnection<'a> {
+        type UpgradeFuture: Future<Output = Result<(), SMTPError>> + Send + 'a;
+
+        /// If the connection is already encrypted
+        fn is_tls(&self) -> bool;
+        /// Performs the TLS handshake. Called after the `220` reply to STARTTLS was sent
+        ///
+        /// Must fail if the client sent anything after the STARTTLS command.
+        /// The session must be reset once the handshake is done
+        fn upgrade_to_tls(&'a mut self) -> Self::UpgradeFuture;
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::{Capabilities, Limit, SMTPServerExtension};
+    use crate::auth::AuthMechanism;
+
+    fn parse(line: &str) -> SMTPServerExtension {
+        SMTPServerExtension::try_from(line.to_owned()).unwrap()
+    }
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("SIZE 1000"), SMTPServerExtension::Size(Some(1000)));
+        assert_eq!(parse("SIZE"), SMTPServerExtension::Size(None));
+        assert_eq!(parse("8bitmime"), SMTPServerExtension::EightBitMime);
+        assert_eq!(parse("SmtpUtf8"), SMTPServerExtension::SmtpUtf8);
+        assert_eq!(parse("dsn"), SMTPServerExtension::Dsn);
+        assert_eq!(parse("Pipelining"), SMTPServerExtension::Pipelining);
+        assert_eq!(
+            parse("DELIVERBY 240"),
+            SMTPServerExtension::DeliverBy(Some(240))
+        );
+        assert_eq!(
+            parse("MT-PRIORITY MIXER"),
+            SMTPServerExtension::MtPriority(Some("MIXER".to_owned()))
+        );
+        assert_eq!(parse("requiretls"), SMTPServerExtension::RequireTls);
+        assert_eq!(
+            parse("FUTURERELEASE 604800 2026-10-24T00:00:00Z"),
+            SMTPServerExtension::FutureRelease {
+                max_interval: 604800,
+                max_datetime: "2026-10-24T00:00:00Z".to_owned(),
+            }
+        );
+        assert_eq!(
+            parse("LIMITS RCPTMAX=20 mailmax=5 X-OTHER=1"),
+            SMTPServerExtension::Limits(vec![
+                Limit::RcptMax(20),
+                Limit::MailMax(5),
+                Limit::Other {
+                    name: "X-OTHER".to_owned(),
+                    value: "1".to_owned()
+                }
+            ])
+        );
+        assert_eq!(
+            parse("X-Custom value"),
+            SMTPServerExtension::Other {
+                name: "X-Custom".to_owned(),
+                value: Some("value".to_owned())
+            }
+        );
+
+        assert!(SMTPServerExtension::try_from("SIZE big".to_owned()).is_err());
+        assert!(SMTPServerExtension::try_from("DELIVERBY soon".to_owned()).is_err());
+        assert!(SMTPServerExtension::try_from("FUTURERELEASE 100".to_owned()).is_err());
+        assert!(SMTPServerExtension::try_from("LIMITS RCPTMAX".to_owned()).is_err());
+    }
+    #[test]
+    fn test_parse_lenient() {
+        assert_eq!(
+            SMTPServerExtension::parse_lenient("SIZE big".to_owned()),
+            Ok(SMTPServerExtension::Other {
+                name: "SIZE".to_owned(),
+                value: Some("big".to_owned())
+            })
+        );
+        assert_eq!(
+            SMTPServerExtension::parse_lenient("FUTURERELEASE 100".to_owned()),
+            Ok(SMTPServerExtension::Other {
+                name: "FUTURERELEASE".to_owned(),
+                value: Some("100".to_owned())
+            })
+        );
+        assert_eq!(
+            SMTPServerExtension::parse_lenient("DELIVERBY 240".to_owned()),
+            Ok(SMTPServerExtension::DeliverBy(Some(240)))
+        );
+        assert!(SMTPServerExtension::parse_lenient(" SIZE".to_owned()).is_err());
+    }
+    #[test]
+    fn test_round_trip() {
+        for line in [
+            "SIZE 1000",
+            "SIZE",
+            "8BITMIME",
+            "SMTPUTF8",
+            "DSN",
+            "DELIVERBY 240",
+            "DELIVERBY",
+            "MT-PRIORITY MIXER",
+            "REQUIRETLS",
+            "FUTURERELEASE 604800 2026-10-24T00:00:00Z",
+            "LIMITS RCPTMAX=20 MAILMAX=5",
+            "X-CUSTOM value",
+        ] {
+            assert_eq!(parse(line).to_string(), line);
+        }
+    }
+    #[test]
+    fn test_capabilities() {
+        let capabilities: Capabilities = [
+            "SIZE 0",
+            "PIPELINING",
+            "CHUNKING",
+            "AUTH PLAIN LOGIN",
+            "LIMITS RCPTMAX=20",
+        ]
+        .into_iter()
+        .map(parse)
+        .collect();
+        assert!(capabilities.supports_pipelining());
+        assert!(capabilities.supports_chunking());
+        assert!(!capabilities.supports_binary_mime());
+        assert!(!capabilities.supports_dsn());
+        assert_eq!(capabilities.max_size(), None);
+        assert_eq!(
+            capabilities.auth_mechanisms(),
+            &[AuthMechanism::Plain, AuthMechanism::Login]
+        );
+        assert_eq!(capabilities.max_recipients(), Some(20));
+
+        let capabilities = Capabilities::new(vec![parse("size 1000"), parse("BINARYMIME")]);
+        assert_eq!(capabilities.max_size(), Some(1000));
+        assert!(!capabilities.supports_binary_mime());
+    }
 }
@@ -0,0 +1,241 @@
+/*!
+# Stream that can be upgraded to TLS
+
+Used by the [client](crate::client) and [server](crate::server) for STARTTLS.
+The TLS support requires the `rustls` feature.
+ */
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::auth::scram::ChannelBinding;
+
+/// A stream that is either plain text or TLS
+#[derive(Debug)]
+pub enum MaybeTlsStream<S> {
+    Plain(S),
+    #[cfg(feature = "rustls")]
+    Tls(Box<tokio_rustls::TlsStream<S>>),
+    /// The stream was taken for the TLS handshake and the handshake failed
+    #[cfg(feature = "rustls")]
+    Closed,
+}
+impl<S> MaybeTlsStream<S> {
+    /// If the stream is encrypted
+    pub fn is_tls(&self) -> bool {
+        match self {
+            MaybeTlsStream::Plain(_) => false,
+            #[cfg(feature = "rustls")]
+            MaybeTlsStream::Tls(_) => true,
+            #[cfg(feature = "rustls")]
+            MaybeTlsStream::Closed => false,
+        }
+    }
+    /// The `tls-exporter` channel binding of the connection. Used by the SCRAM `-PLUS` mechanisms
+    ///
+    /// None unless the connection uses TLS 1.3. With TLS 1.2 the exporter is only safe with the extended master secret.
+    /// [RFC 9266 Section 3](https://datatracker.ietf.org/doc/html/rfc9266#section-3)
+    pub fn channel_binding(&self) -> Option<ChannelBinding> {
+        match self {
+            #[cfg(feature = "rustls")]
+            MaybeTlsStream::Tls(stream) => {
+                use tokio_rustls::rustls::ProtocolVersion;
+
+                if stream.get_ref().1.protocol_version() != Some(ProtocolVersion::TLSv1_3) {
+                    return None;
+                }
+                let output = [0u8; 32];
+                let exported = match stream.as_ref() {
+                    tokio_rustls::TlsStream::Client(stream) => stream
+                        .get_ref()
+                        .1
+                        .export_keying_material(output, ChannelBinding::EXPORTER_LABEL, None),
+                    tokio_rustls::TlsStream::Server(stream) => stream
+                        .get_ref()
+                        .1
+                        .export_keying_material(output, ChannelBinding::EXPORTER_LABEL, None),
+                };
+                exported
+                    .ok()
+                    .map(|data| ChannelBinding::TlsExporter(data.to_vec()))
+            }
+            _ => None,
+        }
+    }
+}
+#[cfg(feature = "rustls")]
+mod rustls_impl {
+    use std::{io, sync::Arc};
+
+    use tokio::io::{AsyncRead, AsyncWrite};
+    use tokio_rustls::{
+        rustls::{pki_types::ServerName, ClientConfig, ServerConfig},
+        TlsAcceptor, TlsConnector, TlsStream,
+    };
+
+    use super::MaybeTlsStream;
+
+    impl<S> MaybeTlsStream<S>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        fn take_plain(&mut self) -> io::Result<S> {
+            match std::mem::replace(self, MaybeTlsStream::Closed) {
+                MaybeTlsStream::Plain(stream) => Ok(stream),
+                other => {
+                    *self = other;
+                    Err(io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        "The stream is not plain text",
+                    ))
+                }
+            }
+        }
+        /// Performs the client side of the TLS handshake
+        pub async fn upgrade_client(
+            &mut self,
+            config: Arc<ClientConfig>,
+            server_name: ServerName<'static>,
+        ) -> io::Result<()> {
+            let stream = self.take_plain()?;
+            let stream = TlsConnector::from(config)
+                .connect(server_name, stream)
+                .await?;
+            *self = MaybeTlsStream::Tls(Box::new(TlsStream::Client(stream)));
+            Ok(())
+        }
+        /// Performs the server side of the TLS handshake
+        pub async fn upgrade_server(&mut self, config: Arc<ServerConfig>) -> io::Result<()> {
+            let stream = self.take_plain()?;
+            let stream = TlsAcceptor::from(config).accept(stream).await?;
+            *self = MaybeTlsStream::Tls(Box::new(TlsStream::Server(stream)));
+            Ok(())
+        }
+    }
+}
+#[cfg(feature = "rustls")]
+fn closed() -> io::Error {
+    io::Error::new(io::ErrorKind::NotConnected, "TLS handshake failed")
+}
+impl<S> AsyncRead for MaybeTlsStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            MaybeTlsStream::Plain(stream) => Pin::new(stream).poll_read(cx, buf),
+            #[cfg(feature = "rustls")]
+            MaybeTlsStream::Tls(stream) => Pin::new(stream).poll_read(cx, buf),
+            #[cfg(feature = "rustls")]
+            MaybeTlsStream::Closed => Poll::Ready(Err(closed())),
+        }
+    }
+}
+impl<S> AsyncWrite for MaybeTlsStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            MaybeTlsStream::Plain(stream) => Pin::new(stream).poll_write(cx, buf),
+            #[cfg(feature = "rustls")]
+            MaybeTlsStream::Tls(stream) => Pin::new(stream).poll_write(cx, buf),
+            #[cfg(feature = "rustls")]
+            MaybeTlsStream::Closed => Poll::Ready(Err(closed())),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            MaybeTlsStream::Plain(stream) => Pin::new(stream).poll_flush(cx),
+            #[cfg(feature = "rustls")]
+            MaybeTlsStream::Tls(stream) => Pin::new(stream).poll_flush(cx),
+            #[cfg(feature = "rustls")]
+            MaybeTlsStream::Closed => Poll::Ready(Err(closed())),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            MaybeTlsStream::Plain(stream) => Pin::new(stream).poll_shutdown(cx),
+            #[cfg(feature = "rustls")]
+            MaybeTlsStream::Tls(stream) => Pin::new(stream).poll_shutdown(cx),
+            #[cfg(feature = "rustls")]
+            MaybeTlsStream::Closed => Poll::Ready(Ok(())),
+        }
+    }
+}
+#[cfg(all(test, feature = "rustls"))]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio_rustls::rustls::{
+        pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName},
+        version::{TLS12, TLS13},
+        ClientConfig, RootCertStore, ServerConfig, SupportedProtocolVersion,
+    };
+
+    use super::MaybeTlsStream;
+
+    /// Connects a client and server with only the given TLS version enabled
+    async fn connect(
+        version: &'static SupportedProtocolVersion,
+    ) -> (
+        MaybeTlsStream<tokio::io::DuplexStream>,
+        MaybeTlsStream<tokio::io::DuplexStream>,
+    ) {
+        let certificate =
+            CertificateDer::from(include_bytes!("../tests/data/localhost.der").to_vec());
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(
+            include_bytes!("../tests/data/localhost.key.der").to_vec(),
+        ));
+        let server_config = ServerConfig::builder_with_protocol_versions(&[version])
+            .with_no_client_auth()
+            .with_single_cert(vec![certificate], key)
+            .unwrap();
+        let mut roots = RootCertStore::empty();
+        roots
+            .add(CertificateDer::from(
+                include_bytes!("../tests/data/ca.der").to_vec(),
+            ))
+            .unwrap();
+        let client_config = ClientConfig::builder_with_protocol_versions(&[version])
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+        let (client_stream, server_stream) = tokio::io::duplex(16 * 1024);
+        let mut client = MaybeTlsStream::Plain(client_stream);
+        let mut server = MaybeTlsStream::Plain(server_stream);
+        let server_name = ServerName::try_from("localhost").unwrap();
+        let (client_result, server_result) = tokio::join!(
+            client.upgrade_client(Arc::new(client_config), server_name),
+            server.upgrade_server(Arc::new(server_config))
+        );
+        client_result.unwrap();
+        server_result.unwrap();
+        (client, server)
+    }
+    #[tokio::test]
+    async fn test_channel_binding() {
+        let (client, server) = connect(&TLS13).await;
+        let binding = client.channel_binding().unwrap();
+        assert_eq!(server.channel_binding(), Some(binding));
+
+        // tls-exporter is not safe with TLS 1.2
+        let (client, server) = connect(&TLS12).await;
+        assert_eq!(client.channel_binding(), None);
+        assert_eq!(server.channel_binding(), None);
+    }
+}
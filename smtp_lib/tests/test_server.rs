@@ -1,74 +1,357 @@
-use std::sync::Arc;
+#![cfg(feature = "server")]
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use bytes::Bytes;
 use smtp_lib::{
-    smtp_server::{
-        async_traits::AsyncSMTPConnection, SMTPConnection, SMTPServer, SMTPServerExtension,
-    },
-    SMTPConnectionState,
+    auth::{AuthCredentials, AuthError, Authenticator},
+    commands::command_impls::MailFromCommandData,
+    enhanced_status_code::EnhancedStatusCode,
+    server::{ConnectionInfo, HandlerResult, MessageBody, Server, ServerConfig, SessionHandler},
+    server_response::ResponseCode,
+    session::{Envelope, Session},
+    smtp_server::SMTPServerExtension,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
 };
-use tokio::net::{TcpListener, TcpStream};
-
-#[derive(Debug)]
-pub struct TestSMTPServer {}
 
-impl SMTPServer for TestSMTPServer {
-    fn get_hostname(&self) -> &str {
-        "test.com"
+#[derive(Debug, Default)]
+struct TestHandler {
+    messages: Arc<Mutex<Vec<(Envelope, Vec<u8>)>>>,
+}
+#[async_trait::async_trait]
+impl SessionHandler for TestHandler {
+    async fn mail_from(
+        &self,
+        _: &ConnectionInfo,
+        _: &Session,
+        data: &MailFromCommandData,
+    ) -> HandlerResult {
+        match &data.reverse_path {
+            Some(address) if address.to_string().ends_with("@blocked.example.com") => {
+                HandlerResult::reject(
+                    ResponseCode::MailboxUnavailable,
+                    EnhancedStatusCode::NOT_AUTHORIZED,
+                    "Sender rejected",
+                )
+            }
+            _ => HandlerResult::Accept,
+        }
     }
-
-    fn name(&self) -> &str {
-        "Test Server"
+    async fn message(
+        &self,
+        _: &ConnectionInfo,
+        envelope: &Envelope,
+        body: &mut MessageBody<'_>,
+    ) -> HandlerResult {
+        let Ok(message) = body.bytes().await else {
+            return HandlerResult::Accept;
+        };
+        self.messages
+            .lock()
+            .unwrap()
+            .push((envelope.clone(), message.to_vec()));
+        HandlerResult::Accept
     }
+}
 
-    fn get_greeting(&self) -> Option<&str> {
-        None
+struct TestAuthenticator;
+#[async_trait::async_trait]
+impl Authenticator for TestAuthenticator {
+    async fn authenticate(&self, credentials: AuthCredentials) -> Result<String, AuthError> {
+        if credentials.username() == "tim" && credentials.verify_password("tanstaaf") {
+            Ok("tim".to_owned())
+        } else {
+            Err(AuthError::InvalidCredentials)
+        }
     }
+}
 
-    fn supported_extensions(&self) -> &Vec<SMTPServerExtension> {
-        todo!()
+struct TestClient {
+    reader: BufReader<TcpStream>,
+}
+impl TestClient {
+    async fn connect(addr: std::net::SocketAddr) -> Self {
+        let stream = TcpStream::connect(addr).await.unwrap();
+        Self {
+            reader: BufReader::new(stream),
+        }
+    }
+    /// Reads a reply and returns the last line
+    async fn read_reply(&mut self) -> String {
+        loop {
+            let mut line = String::new();
+            self.reader.read_line(&mut line).await.unwrap();
+            if line.as_bytes().get(3) != Some(&b'-') {
+                return line.trim_end().to_owned();
+            }
+        }
+    }
+    async fn write(&mut self, data: &str) {
+        self.reader
+            .get_mut()
+            .write_all(data.as_bytes())
+            .await
+            .unwrap();
+    }
+    async fn send(&mut self, data: &str) -> String {
+        self.write(data).await;
+        self.read_reply().await
+    }
+    /// Waits for the server to close the connection without replying
+    async fn assert_closed(&mut self) {
+        let mut line = String::new();
+        let read = tokio::time::timeout(Duration::from_secs(5), self.reader.read_line(&mut line))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(read, 0, "Unexpected reply {line:?}");
     }
 }
-#[derive(Debug)]
-pub struct TestSMTPConnection {
-    stream: TcpStream,
-    server: Arc<TestSMTPServer>,
+#[tokio::test]
+async fn test_session() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let handler = TestHandler::default();
+    let messages = handler.messages.clone();
+    let config = ServerConfig::new("mail.example.com").with_max_message_size(64);
+    let server = Server::new(config, handler);
+    tokio::spawn(server.serve(listener));
+
+    let mut client = TestClient::connect(addr).await;
+    assert!(client
+        .read_reply()
+        .await
+        .starts_with("220 mail.example.com"));
+    assert_eq!(
+        client.send("MAIL FROM:<a@example.com>\r\n").await,
+        "503 Send HELO/EHLO first"
+    );
+    assert!(client
+        .send("EHLO client.example.com\r\n")
+        .await
+        .starts_with("250 "));
+    assert!(client
+        .send("MAIL FROM:<a@blocked.example.com>\r\n")
+        .await
+        .starts_with("550 5.7.1"));
+    assert!(client
+        .send("MAIL FROM:<a@example.com> SIZE=100\r\n")
+        .await
+        .starts_with("552 5.3.4"));
+    assert!(client
+        .send("MAIL FROM:<a@example.com>\r\n")
+        .await
+        .starts_with("250 2.1.0"));
+    assert!(client
+        .send("RCPT TO:<b@example.com>\r\n")
+        .await
+        .starts_with("250 2.1.5"));
+    assert!(client.send("DATA\r\n").await.starts_with("354"));
+    assert!(client
+        .send("Subject: Test\r\n\r\n..Hello\r\n.\r\n")
+        .await
+        .starts_with("250 2.6.0"));
+
+    client.send("MAIL FROM:<>\r\n").await;
+    client.send("RCPT TO:<b@example.com>\r\n").await;
+    client.send("DATA\r\n").await;
+    let too_large = format!("{}\r\n.\r\n", "a".repeat(100));
+    assert!(client.send(&too_large).await.starts_with("552 5.3.4"));
+
+    assert!(client.send("FOO\r\n").await.starts_with("500 5.5.1"));
+    assert!(client.send("STARTTLS\r\n").await.starts_with("502 5.5.1"));
+    assert!(client.send("QUIT\r\n").await.starts_with("221"));
+
+    // CHUNKING was not advertised. The chunk is not read and the connection is closed
+    let mut client = TestClient::connect(addr).await;
+    client.read_reply().await;
+    client.send("EHLO client.example.com\r\n").await;
+    assert!(client
+        .send("BDAT 18446744073709551615 LAST\r\n")
+        .await
+        .starts_with("502 5.5.1"));
+    client.assert_closed().await;
+
+    let messages = messages.lock().unwrap();
+    assert_eq!(messages.len(), 1);
+    let (envelope, message) = &messages[0];
+    assert_eq!(envelope.reverse_path().unwrap(), &"a@example.com");
+    assert_eq!(message, b"Subject: Test\r\n\r\n.Hello\r\n");
 }
-impl SMTPConnection for TestSMTPConnection {
-    type Server = TestSMTPServer;
+#[tokio::test]
+async fn test_bdat() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let handler = TestHandler::default();
+    let messages = handler.messages.clone();
+    let config = ServerConfig::new("mail.example.com")
+        .with_max_message_size(64)
+        .with_extension(SMTPServerExtension::Chunking);
+    let server = Server::new(config, handler);
+    tokio::spawn(server.serve(listener));
 
-    fn get_server(&self) -> &Self::Server {
-        self.server.as_ref()
-    }
+    let mut client = TestClient::connect(addr).await;
+    client.read_reply().await;
+    assert_eq!(
+        client.send("EHLO client.example.com\r\n").await,
+        "250 CHUNKING"
+    );
+    // The chunk is read even if the command is rejected
+    assert!(client.send("BDAT 5\r\nHello").await.starts_with("503"));
+    assert!(client.send("NOOP\r\n").await.starts_with("250"));
 
-    fn get_state(&self) -> &SMTPConnectionState {
-        todo!()
-    }
+    client.send("MAIL FROM:<a@example.com>\r\n").await;
+    client.send("RCPT TO:<b@example.com>\r\n").await;
+    assert_eq!(
+        client.send("BDAT 7\r\nSubject").await,
+        "250 2.0.0 7 octets received"
+    );
+    assert!(client.send("DATA\r\n").await.starts_with("503"));
+    assert!(client
+        .send("BDAT 13 LAST\r\n: Test\r\n\r\n.\r\n")
+        .await
+        .starts_with("250 2.6.0"));
 
-    fn get_end_of_multiline_command(&self) -> &str {
-        todo!()
-    }
+    client.send("MAIL FROM:<>\r\n").await;
+    client.send("RCPT TO:<b@example.com>\r\n").await;
+    let too_large = format!("BDAT 100 LAST\r\n{}", "a".repeat(100));
+    assert!(client.send(&too_large).await.starts_with("552 5.3.4"));
+    // The transaction was aborted
+    assert!(client
+        .send("RCPT TO:<b@example.com>\r\n")
+        .await
+        .starts_with("503"));
+
+    let messages = messages.lock().unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].1, b"Subject: Test\r\n\r\n.\r\n");
 }
+#[tokio::test]
+async fn test_data_timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let handler = TestHandler::default();
+    let messages = handler.messages.clone();
+    let config = ServerConfig::new("mail.example.com")
+        .with_data_timeout(Duration::from_millis(100))
+        .with_extension(SMTPServerExtension::Chunking);
+    let server = Server::new(config, handler);
+    tokio::spawn(server.serve(listener));
 
-impl<'b> AsyncSMTPConnection<'b> for TestSMTPConnection {
-    type ReadLineFuture = smtp_lib::BoxSendFuture<'b, smtp_lib::Result<String>>;
-    type WriteFuture = smtp_lib::BoxSendFuture<'b, smtp_lib::Result<()>>;
-    type ReadTilEndFuture = smtp_lib::BoxSendFuture<'b, smtp_lib::Result<String>>;
+    let mut client = TestClient::connect(addr).await;
+    client.read_reply().await;
+    client.send("EHLO client.example.com\r\n").await;
+    client.send("MAIL FROM:<a@example.com>\r\n").await;
+    client.send("RCPT TO:<b@example.com>\r\n").await;
+    assert!(client.send("DATA\r\n").await.starts_with("354"));
+    client.write("Subject").await;
+    // The server gives up on the rest of the body and closes the connection
+    client.assert_closed().await;
 
-    fn read_line(&mut self) -> Self::ReadLineFuture {
-        todo!()
-    }
+    let mut client = TestClient::connect(addr).await;
+    client.read_reply().await;
+    client.send("EHLO client.example.com\r\n").await;
+    client.send("MAIL FROM:<a@example.com>\r\n").await;
+    client.send("RCPT TO:<b@example.com>\r\n").await;
+    client.write("BDAT 100 LAST\r\nSubject").await;
+    client.assert_closed().await;
 
-    fn write(&mut self, command: Bytes) -> Self::WriteFuture {
-        todo!()
-    }
+    assert!(messages.lock().unwrap().is_empty());
+}
+#[tokio::test]
+async fn test_auth() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let config = ServerConfig::new("mail.example.com").with_auth_requires_tls(false);
+    let server = Server::new(config, TestHandler::default()).with_authenticator(TestAuthenticator);
+    tokio::spawn(server.serve(listener));
 
-    fn read_til_end(&mut self) -> Self::ReadTilEndFuture {
-        todo!()
-    }
+    let mut client = TestClient::connect(addr).await;
+    client.read_reply().await;
+    assert_eq!(
+        client.send("EHLO client.example.com\r\n").await,
+        "250 AUTH PLAIN LOGIN"
+    );
+    assert!(client
+        .send("AUTH CRAM-MD5\r\n")
+        .await
+        .starts_with("504 5.5.4"));
+    // Cancelled
+    assert_eq!(client.send("AUTH PLAIN\r\n").await, "334");
+    assert!(client.send("*\r\n").await.starts_with("501"));
+    // Wrong password
+    assert!(client
+        .send("AUTH PLAIN AHRpbQB3cm9uZw==\r\n")
+        .await
+        .starts_with("535 5.7.8"));
+    assert_eq!(client.send("AUTH LOGIN\r\n").await, "334 VXNlcm5hbWU6");
+    assert_eq!(client.send("dGlt\r\n").await, "334 UGFzc3dvcmQ6");
+    assert!(client
+        .send("dGFuc3RhYWY=\r\n")
+        .await
+        .starts_with("235 2.7.0"));
+    assert!(client
+        .send("AUTH PLAIN AHRpbQB0YW5zdGFhZg==\r\n")
+        .await
+        .starts_with("503"));
+}
+#[tokio::test]
+async fn test_auth_requires_tls() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let config = ServerConfig::new("mail.example.com");
+    let server = Server::new(config, TestHandler::default()).with_authenticator(TestAuthenticator);
+    tokio::spawn(server.serve(listener));
+
+    let mut client = TestClient::connect(addr).await;
+    client.read_reply().await;
+    assert_eq!(
+        client.send("EHLO client.example.com\r\n").await,
+        "250 ENHANCEDSTATUSCODES"
+    );
+    assert!(client
+        .send("AUTH PLAIN AHRpbQB0YW5zdGFhZg==\r\n")
+        .await
+        .starts_with("538 5.7.11"));
 }
 #[tokio::test]
-async fn test() {
-    let server = TestSMTPServer {};
-    let stream = TcpListener::bind("0.0.0.0:2525").await.unwrap();
+async fn test_lmtp() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let handler = TestHandler::default();
+    let messages = handler.messages.clone();
+    let config = ServerConfig::new("mail.example.com").with_lmtp();
+    let server = Server::new(config, handler);
+    tokio::spawn(server.serve(listener));
+
+    let mut client = TestClient::connect(addr).await;
+    assert!(client.read_reply().await.contains(" LMTP "));
+    assert!(client
+        .send("EHLO client.example.com\r\n")
+        .await
+        .starts_with("500"));
+    assert!(client
+        .send("LHLO client.example.com\r\n")
+        .await
+        .starts_with("250 "));
+    client.send("MAIL FROM:<a@example.com>\r\n").await;
+    client.send("RCPT TO:<b@example.com>\r\n").await;
+    client.send("RCPT TO:<c@example.com>\r\n").await;
+    assert!(client.send("DATA\r\n").await.starts_with("354"));
+    // One reply for every recipient
+    assert_eq!(
+        client.send("Subject: Test\r\n\r\nHello\r\n.\r\n").await,
+        "250 2.6.0 <b@example.com> Message accepted for delivery"
+    );
+    assert_eq!(
+        client.read_reply().await,
+        "250 2.6.0 <c@example.com> Message accepted for delivery"
+    );
+    assert!(client.send("NOOP\r\n").await.starts_with("250"));
+
+    assert_eq!(messages.lock().unwrap().len(), 1);
 }